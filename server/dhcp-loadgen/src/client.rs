@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::net::UdpSocket;
+use tokio::sync::oneshot;
+use tokio::time::{sleep, sleep_until, timeout, Instant};
+
+use dhcp_proto::options::*;
+use dhcp_proto::DHCPMessage;
+
+use crate::stats::{Exchange, Stats};
+
+//ONE SOCKET ON THE CLIENT PORT SHARED BY EVERY SIMULATED CLIENT
+//REPLIES ARE HANDED TO THE CLIENT WAITING ON THEIR TRANSACTION ID
+pub struct Transport {
+    socket: UdpSocket,
+    server: SocketAddrV4,
+    pending: Mutex<HashMap<u32, oneshot::Sender<DHCPMessage>>>,
+    next_xid: AtomicU32,
+    stats: Arc<Stats>,
+}
+
+impl Transport {
+    pub async fn bind(client_port: u16, server: SocketAddrV4, stats: Arc<Stats>) -> std::io::Result<Arc<Self>> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, client_port)).await?;
+        socket.set_broadcast(true)?;
+        //START SOMEWHERE DIFFERENT EVERY RUN SO LATE REPLIES FROM AN EARLIER RUN ARE NOT MISTAKEN FOR OURS
+        let seed = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().subsec_nanos();
+        Ok(Arc::new(Transport {
+            socket,
+            server,
+            pending: Mutex::new(HashMap::new()),
+            next_xid: AtomicU32::new(seed),
+            stats,
+        }))
+    }
+
+    //DELIVER REPLIES UNTIL THE SOCKET FAILS
+    pub async fn receive(self: Arc<Self>) {
+        let mut buf = vec![0; 1500];
+        loop {
+            let size = match self.socket.recv_from(&mut buf).await {
+                Ok((size, _)) => size,
+                Err(e) => {
+                    eprintln!("Failed to receive: {}", e);
+                    return;
+                }
+            };
+            let message = match DHCPMessage::from_buffer(&buf[..size]) {
+                Ok(message) if message.op == 2 => message,
+                _ => continue,
+            };
+            Stats::count(&self.stats.replies);
+            if let Some(waiting) = self.pending.lock().unwrap().remove(&message.xid) {
+                let _ = waiting.send(message);
+            }
+        }
+    }
+
+    pub fn xid(&self) -> u32 {
+        self.next_xid.fetch_add(1, Ordering::Relaxed)
+    }
+
+    async fn send(&self, message: &DHCPMessage) {
+        Stats::count(&self.stats.sent);
+        if let Err(e) = self.socket.send_to(&message.to_buffer(), self.server).await {
+            eprintln!("Failed to send to {}: {}", self.server, e);
+        }
+    }
+
+    //SEND AND WAIT FOR THE REPLY WITH THE SAME TRANSACTION ID
+    async fn exchange(&self, message: &DHCPMessage, exchange: Exchange, wait: Duration) -> Option<DHCPMessage> {
+        let (reply, response) = oneshot::channel();
+        self.pending.lock().unwrap().insert(message.xid, reply);
+        let sent = Instant::now();
+        self.send(message).await;
+        match timeout(wait, response).await {
+            Ok(Ok(reply)) => {
+                self.stats.latency(exchange, sent.elapsed());
+                Some(reply)
+            }
+            _ => {
+                self.pending.lock().unwrap().remove(&message.xid);
+                self.stats.timeout(exchange);
+                None
+            }
+        }
+    }
+}
+
+//HOW A SIMULATED CLIENT BEHAVES
+#[derive(Clone, Copy)]
+pub struct Behaviour {
+    pub reply_timeout: Duration,
+    //SERVER LEASE TIMERS ARE DIVIDED BY THIS SO LONG LEASES CAN BE EXERCISED IN A SHORT RUN
+    pub time_scale: f64,
+    pub release_at_end: bool,
+}
+
+pub struct SimulatedClient {
+    transport: Arc<Transport>,
+    stats: Arc<Stats>,
+    mac: [u8; 6],
+    behaviour: Behaviour,
+    pub ever_bound: bool,
+}
+
+fn message_type(message: &DHCPMessage) -> Option<u8> {
+    message.options_map.get(&MESSAGE_TYPE).and_then(|v| v.first().copied())
+}
+
+fn option_u32(message: &DHCPMessage, code: u8) -> Option<u32> {
+    message.options_map.get(&code)
+        .and_then(|v| <[u8; 4]>::try_from(v.as_slice()).ok())
+        .map(u32::from_be_bytes)
+}
+
+impl SimulatedClient {
+    pub fn new(index: u32, transport: Arc<Transport>, stats: Arc<Stats>, behaviour: Behaviour) -> Self {
+        let index = index.to_be_bytes();
+        SimulatedClient {
+            transport,
+            stats,
+            mac: [0x02, 0x4c, index[0], index[1], index[2], index[3]],
+            behaviour,
+            ever_bound: false,
+        }
+    }
+
+    fn message(&self, xid: u32, message_type: u8, ciaddr: Ipv4Addr, mut options: Vec<u8>) -> DHCPMessage {
+        let mut chaddr = [0u8; 16];
+        chaddr[..6].copy_from_slice(&self.mac);
+        let mut options_buf = vec![MESSAGE_TYPE, 1, message_type];
+        options_buf.append(&mut options);
+        DHCPMessage::new(1, 1, 6, 0, xid, 0, 0, ciaddr, Ipv4Addr::UNSPECIFIED,
+            Ipv4Addr::UNSPECIFIED, Ipv4Addr::UNSPECIFIED, chaddr, [0; 64], [0; 128], options_buf)
+    }
+
+    fn scaled(&self, seconds: u32) -> Duration {
+        Duration::from_secs_f64(seconds as f64 / self.behaviour.time_scale)
+    }
+
+    //DISCOVER AND REQUEST UNTIL A LEASE IS GRANTED OR THE RUN ENDS
+    //RETURNS THE ADDRESS, SERVER IDENTIFIER AND ACK
+    async fn acquire(&mut self, deadline: Instant) -> Option<(Ipv4Addr, Vec<u8>, DHCPMessage)> {
+        let wait = self.behaviour.reply_timeout;
+        while Instant::now() < deadline {
+            let xid = self.transport.xid();
+            let discover = self.message(xid, DHCPDISCOVER, Ipv4Addr::UNSPECIFIED, Vec::new());
+            let offer = match self.transport.exchange(&discover, Exchange::Discover, wait).await {
+                Some(offer) if message_type(&offer) == Some(DHCPOFFER) => offer,
+                //NO OFFER, USUALLY AN EMPTY POOL - BACK OFF LIKE A REAL CLIENT
+                _ => {
+                    sleep(wait).await;
+                    continue;
+                }
+            };
+            let server_id = offer.options_map.get(&SERVER_IDENTIFIER).cloned().unwrap_or_default();
+            let mut options = Vec::new();
+            push_ip(&mut options, REQUESTED_IP, offer.yiaddr);
+            push_bytes(&mut options, SERVER_IDENTIFIER, &server_id);
+            let request = self.message(xid, DHCPREQUEST, Ipv4Addr::UNSPECIFIED, options);
+            match self.transport.exchange(&request, Exchange::Request, wait).await {
+                Some(ack) if message_type(&ack) == Some(DHCPACK) => return Some((offer.yiaddr, server_id, ack)),
+                Some(_) => Stats::count(&self.stats.naks),
+                None => {}
+            }
+        }
+        None
+    }
+
+    //LEASE, RENEW AT T1 FOR AS LONG AS THE RUN LASTS, START OVER ON A NAK OR A LOST LEASE
+    pub async fn run(&mut self, deadline: Instant) {
+        while let Some((address, server_id, mut ack)) = self.acquire(deadline).await {
+            Stats::count(&self.stats.leases);
+            self.stats.bound();
+            self.ever_bound = true;
+            loop {
+                let lease_time = option_u32(&ack, LEASE_TIME).unwrap_or(3600);
+                let renewal_time = option_u32(&ack, RENEWAL_TIME).unwrap_or(lease_time / 2);
+                let renew_at = Instant::now() + self.scaled(renewal_time);
+                if renew_at >= deadline {
+                    sleep_until(deadline).await;
+                    if self.behaviour.release_at_end {
+                        self.release(address, &server_id).await;
+                    }
+                    return;
+                }
+                sleep_until(renew_at).await;
+                let renew = self.message(self.transport.xid(), DHCPREQUEST, address, Vec::new());
+                match self.transport.exchange(&renew, Exchange::Renew, self.behaviour.reply_timeout).await {
+                    Some(reply) if message_type(&reply) == Some(DHCPACK) => {
+                        Stats::count(&self.stats.renewals);
+                        ack = reply;
+                    }
+                    Some(_) => {
+                        Stats::count(&self.stats.naks);
+                        break;
+                    }
+                    None => break,
+                }
+            }
+            self.stats.unbound();
+        }
+    }
+
+    async fn release(&self, address: Ipv4Addr, server_id: &[u8]) {
+        let mut options = Vec::new();
+        //THE RELEASED ADDRESS GOES IN CIADDR, RFC 2131 FORBIDS OPTION 50 IN A RELEASE
+        push_bytes(&mut options, SERVER_IDENTIFIER, server_id);
+        self.transport.send(&self.message(self.transport.xid(), DHCPRELEASE, address, options)).await;
+        Stats::count(&self.stats.releases);
+        self.stats.unbound();
+    }
+}
@@ -0,0 +1,96 @@
+//LOAD GENERATOR FOR THE DHCP SERVER
+//SIMULATES MANY CLIENTS GOING THROUGH DORA AND RENEWING AT T1, THEN PRINTS THROUGHPUT AND LATENCIES
+mod client;
+mod stats;
+
+use std::error::Error;
+use std::net::SocketAddrV4;
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::Parser;
+use tokio::time::{sleep, Instant};
+
+use client::{Behaviour, SimulatedClient, Transport};
+use stats::Stats;
+
+#[derive(Parser)]
+#[command(name = "dhcp-loadgen", version, about = "Simulate DHCP clients against a local server and report how it copes")]
+struct Options {
+    /// Address and port of the server under test
+    #[arg(long, default_value = "127.0.0.1:67")]
+    server: SocketAddrV4,
+
+    /// Port to receive replies on, the server has to send replies there (its --client-port)
+    #[arg(long, default_value_t = 68)]
+    client_port: u16,
+
+    /// Number of simulated clients
+    #[arg(long, default_value_t = 1000)]
+    clients: u32,
+
+    /// Length of the run in seconds
+    #[arg(long, default_value_t = 60)]
+    duration: u64,
+
+    /// Seconds over which client start times are spread
+    #[arg(long, default_value_t = 10)]
+    ramp_up: u64,
+
+    /// Milliseconds to wait for a reply before counting a timeout
+    #[arg(long, default_value_t = 2000)]
+    timeout: u64,
+
+    /// Divide the server's lease timers by this, e.g. 60 renews a one hour lease every 30 seconds
+    #[arg(long, default_value_t = 1.0)]
+    time_scale: f64,
+
+    /// Release every lease when the run ends
+    #[arg(long)]
+    release: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let options = Options::parse();
+    if options.time_scale <= 0.0 {
+        return Err("--time-scale must be greater than 0".into());
+    }
+
+    let stats = Arc::new(Stats::default());
+    let transport = Transport::bind(options.client_port, options.server, Arc::clone(&stats)).await
+        .map_err(|e| format!("Failed to bind the client port {}: {}", options.client_port, e))?;
+    tokio::spawn(Arc::clone(&transport).receive());
+
+    let behaviour = Behaviour {
+        reply_timeout: Duration::from_millis(options.timeout),
+        time_scale: options.time_scale,
+        release_at_end: options.release,
+    };
+    println!("Simulating {} clients against {} for {}s", options.clients, options.server, options.duration);
+
+    let started = Instant::now();
+    let deadline = started + Duration::from_secs(options.duration);
+    let ramp_up = Duration::from_secs(options.ramp_up);
+    let clients: Vec<_> = (0..options.clients).map(|index| {
+        let mut client = SimulatedClient::new(index, Arc::clone(&transport), Arc::clone(&stats), behaviour);
+        let start_after = ramp_up.mul_f64(index as f64 / options.clients as f64);
+        tokio::spawn(async move {
+            sleep(start_after).await;
+            client.run(deadline).await;
+            client.ever_bound
+        })
+    }).collect();
+
+    let mut never_bound = 0;
+    for client in clients {
+        if !client.await? {
+            never_bound += 1;
+        }
+    }
+    //LET RELEASES LEAVE THE SOCKET BEFORE THE PROCESS EXITS
+    sleep(Duration::from_millis(100)).await;
+
+    print!("{}", stats.report(started.elapsed(), options.clients as usize, never_bound));
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+//WHICH EXCHANGE A LATENCY OR TIMEOUT BELONGS TO
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Exchange {
+    Discover,
+    Request,
+    Renew,
+}
+
+impl fmt::Display for Exchange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Exchange::Discover => write!(f, "discover -> offer"),
+            Exchange::Request => write!(f, "request -> ack"),
+            Exchange::Renew => write!(f, "renew -> ack"),
+        }
+    }
+}
+
+//COUNTERS SHARED BY ALL SIMULATED CLIENTS
+#[derive(Default)]
+pub struct Stats {
+    pub sent: AtomicU64,
+    pub replies: AtomicU64,
+    pub leases: AtomicU64,
+    pub renewals: AtomicU64,
+    pub naks: AtomicU64,
+    pub releases: AtomicU64,
+    bound: AtomicUsize,
+    peak_bound: AtomicUsize,
+    timeouts: Mutex<BTreeMap<Exchange, u64>>,
+    latencies: Mutex<BTreeMap<Exchange, Vec<Duration>>>,
+}
+
+impl Stats {
+    pub fn count(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn latency(&self, exchange: Exchange, latency: Duration) {
+        self.latencies.lock().unwrap().entry(exchange).or_default().push(latency);
+    }
+
+    pub fn timeout(&self, exchange: Exchange) {
+        *self.timeouts.lock().unwrap().entry(exchange).or_default() += 1;
+    }
+
+    pub fn bound(&self) {
+        let bound = self.bound.fetch_add(1, Ordering::Relaxed) + 1;
+        self.peak_bound.fetch_max(bound, Ordering::Relaxed);
+    }
+
+    pub fn unbound(&self) {
+        self.bound.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn report(&self, elapsed: Duration, clients: usize, never_bound: usize) -> String {
+        let seconds = elapsed.as_secs_f64();
+        let per_second = |counter: &AtomicU64| counter.load(Ordering::Relaxed) as f64 / seconds;
+        let timeouts = self.timeouts.lock().unwrap();
+        let timeouts_of = |exchange| timeouts.get(&exchange).copied().unwrap_or(0);
+
+        let mut report = String::new();
+        report += &format!("Ran {:.1}s with {} clients\n", seconds, clients);
+        report += &format!("Messages: {} sent ({:.1}/s), {} replies\n",
+            self.sent.load(Ordering::Relaxed), per_second(&self.sent), self.replies.load(Ordering::Relaxed));
+        report += &format!("Leases: {} acquired ({:.1}/s), {} renewed, {} released, {} NAKs\n",
+            self.leases.load(Ordering::Relaxed), per_second(&self.leases),
+            self.renewals.load(Ordering::Relaxed), self.releases.load(Ordering::Relaxed),
+            self.naks.load(Ordering::Relaxed));
+        report += &format!("Timeouts: {} discover, {} request, {} renew\n",
+            timeouts_of(Exchange::Discover), timeouts_of(Exchange::Request), timeouts_of(Exchange::Renew));
+        //THE SERVER STAYS SILENT ON A DISCOVER WHEN THE POOL IS EMPTY, SO EXHAUSTION SHOWS AS DISCOVER TIMEOUTS
+        report += &format!("Pool: {} clients bound at the end, {} at peak, {} never got a lease\n",
+            self.bound.load(Ordering::Relaxed), self.peak_bound.load(Ordering::Relaxed), never_bound);
+
+        report += &format!("{:<18} {:>8} {:>10} {:>10} {:>10} {:>10}\n", "LATENCY", "COUNT", "P50", "P90", "P99", "MAX");
+        for (exchange, latencies) in self.latencies.lock().unwrap().iter_mut() {
+            latencies.sort();
+            let percentile = |p: f64| format_duration(latencies[((latencies.len() - 1) as f64 * p).round() as usize]);
+            report += &format!("{:<18} {:>8} {:>10} {:>10} {:>10} {:>10}\n", exchange.to_string(), latencies.len(),
+                percentile(0.50), percentile(0.90), percentile(0.99), percentile(1.0));
+        }
+        report
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.2}ms", duration.as_secs_f64() * 1000.0)
+}
@@ -0,0 +1,53 @@
+use std::hint::black_box;
+use std::net::Ipv4Addr;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use dhcp_proto::options::*;
+use dhcp_proto::DHCPMessage;
+
+fn request(options: Vec<u8>) -> DHCPMessage {
+    let mut chaddr = [0u8; 16];
+    chaddr[..6].copy_from_slice(&[0x02, 0x00, 0x00, 0x00, 0x00, 0x01]);
+    DHCPMessage::new(1, 1, 6, 0, 0x1234_5678, 0, 0, Ipv4Addr::UNSPECIFIED, Ipv4Addr::UNSPECIFIED,
+        Ipv4Addr::UNSPECIFIED, Ipv4Addr::UNSPECIFIED, chaddr, [0; 64], [0; 128], options)
+}
+
+//REPLY OPTIONS THE SIZE OF A FULLY CONFIGURED ACK
+fn reply_options() -> Vec<u8> {
+    let mut options = vec![MESSAGE_TYPE, 1, DHCPACK];
+    push_ip(&mut options, SERVER_IDENTIFIER, Ipv4Addr::new(192, 168, 10, 30));
+    push_ip(&mut options, SUBNET_MASK, Ipv4Addr::new(255, 255, 255, 0));
+    push_ip_list(&mut options, ROUTER, &[Ipv4Addr::new(192, 168, 10, 1); 8]);
+    push_ip_list(&mut options, DNS_SERVER, &[Ipv4Addr::new(192, 168, 10, 2); 8]);
+    push_bytes(&mut options, DOMAIN_NAME, &[b'x'; 200]);
+    push_bytes(&mut options, ROOT_PATH, &[b'r'; 200]);
+    push_bytes(&mut options, EXTENSIONS_PATH, &[b'e'; 200]);
+    options
+}
+
+fn codec(c: &mut Criterion) {
+    let mut options = vec![];
+    push_ip(&mut options, REQUESTED_IP, Ipv4Addr::new(192, 168, 10, 100));
+    push_ip(&mut options, SERVER_IDENTIFIER, Ipv4Addr::new(192, 168, 10, 30));
+    push_bytes(&mut options, PARAMETER_REQUEST_LIST, &[1, 3, 6, 15, 28, 42, 51, 58, 59]);
+    let mut typed = vec![MESSAGE_TYPE, 1, DHCPREQUEST];
+    typed.extend_from_slice(&options);
+    let message = request(typed);
+    let buffer = message.to_buffer();
+
+    c.bench_function("from_buffer request", |b| b.iter(|| DHCPMessage::from_buffer(black_box(&buffer))));
+    c.bench_function("to_buffer request", |b| b.iter(|| black_box(&message).to_buffer()));
+
+    let reply_options = reply_options();
+    c.bench_function("pack_options fits", |b| {
+        b.iter(|| pack_options(black_box(&reply_options), 1500, black_box(&message)))
+    });
+    //MINIMUM SIZE FORCES OVERLOAD INTO FILE AND SNAME AND DROPS THE REST
+    c.bench_function("pack_options overloaded", |b| {
+        b.iter(|| pack_options(black_box(&reply_options), MIN_MAX_MESSAGE_SIZE - 28, black_box(&message)))
+    });
+}
+
+criterion_group!(benches, codec);
+criterion_main!(benches);
@@ -0,0 +1,13 @@
+#![no_main]
+
+use dhcp_proto::types::DHCPMessage;
+use libfuzzer_sys::fuzz_target;
+
+//ANY INPUT EITHER FAILS TO PARSE OR SERIALIZES BACK TO THE SAME BYTES AND THE SAME MESSAGE
+fuzz_target!(|data: &[u8]| {
+    if let Ok(message) = DHCPMessage::from_buffer(data) {
+        let buf = message.to_buffer();
+        assert_eq!(buf, data);
+        assert_eq!(DHCPMessage::from_buffer(&buf).unwrap(), message);
+    }
+});
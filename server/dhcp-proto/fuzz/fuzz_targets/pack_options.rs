@@ -0,0 +1,21 @@
+#![no_main]
+
+use dhcp_proto::options::{max_reply_size, pack_options};
+use dhcp_proto::types::DHCPMessage;
+use libfuzzer_sys::fuzz_target;
+
+//THE FUZZED BYTES ARE A REQUEST FOLLOWED BY THE OPTIONS BUFFER OF A REPLY
+//THE PACKED REPLY MUST STAY WITHIN THE CLIENT'S LIMIT AND PARSE AGAIN
+fuzz_target!(|data: &[u8]| {
+    let Some(split) = data.first().map(|&b| (b as usize * 4).min(data.len() - 1) + 1) else { return };
+    let Ok(request) = DHCPMessage::from_buffer(&data[1..split]) else { return };
+    let options_buf = &data[split..];
+
+    let limit = max_reply_size(&request);
+    let packed = pack_options(options_buf, limit, &request);
+    let reply = DHCPMessage::new(2, 1, 6, 0, request.xid, 0, 0, request.ciaddr, request.yiaddr,
+        request.siaddr, request.giaddr, request.chaddr, packed.sname, packed.file, packed.options);
+    let buf = reply.to_buffer();
+    assert!(buf.len() <= limit);
+    let _ = DHCPMessage::from_buffer(&buf);
+});
@@ -0,0 +1,5 @@
+//DHCP MESSAGE CODEC AND OPTION ENCODING, NO NETWORKING OR STORAGE
+pub mod options;
+pub mod types;
+
+pub use types::{DHCPMessage, ParseError};
@@ -0,0 +1,241 @@
+use crate::types::DHCPMessage;
+use std::cmp::Reverse;
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+//DHCP OPTIONS
+pub const SUBNET_MASK: u8 = 1;
+pub const TIME_OFFSET: u8 = 2;
+pub const ROUTER: u8 = 3;
+pub const TIME_SERVER: u8 = 4;
+pub const NAME_SERVER: u8 = 5;
+pub const DNS_SERVER: u8 = 6;
+pub const LOG_SERVER: u8 = 7;
+pub const COOKIE_SERVER: u8 = 8;
+pub const LPR_SERVER: u8 = 9;
+pub const IMPRESS_SERVER: u8 = 10;
+pub const RESOURCE_LOCATION_SERVER: u8 = 11;
+pub const HOST_NAME: u8 = 12;
+pub const BOOT_FILE_SIZE: u8 = 13;
+pub const MERIT_DUMP_FILE: u8 = 14;
+pub const DOMAIN_NAME: u8 = 15;
+pub const SWAP_SERVER: u8 = 16;
+pub const ROOT_PATH: u8 = 17;
+pub const EXTENSIONS_PATH: u8 = 18;
+
+pub const BROADCAST_ADDRESS: u8 = 28;
+
+pub const NETWORK_TIME_PROTOCOL_SERVERS: u8 = 42;
+
+pub const REQUESTED_IP: u8 = 50;
+pub const LEASE_TIME: u8 = 51;
+pub const OPTION_OVERLOAD: u8 = 52;
+pub const MESSAGE_TYPE: u8 = 53;
+pub const SERVER_IDENTIFIER: u8 = 54;
+pub const PARAMETER_REQUEST_LIST: u8 = 55;
+
+pub const MAXIMUM_DHCP_MESSAGE_SIZE: u8 = 57;
+pub const RENEWAL_TIME: u8 = 58;
+pub const REBINDING_TIME: u8 = 59;
+pub const CLIENT_IDENTIFIER: u8 = 61;
+pub const CLIENT_FQDN: u8 = 81;
+
+pub const END: u8 = 255;
+
+//DHCP MESSAGE TYPE
+pub const DHCPDISCOVER: u8 = 1;
+pub const DHCPOFFER: u8 = 2;
+pub const DHCPREQUEST: u8 = 3;
+pub const DHCPDECLINE: u8 = 4;
+pub const DHCPACK: u8 = 5;
+pub const DHCPNAK: u8 = 6;
+pub const DHCPRELEASE: u8 = 7;
+pub const DHCPINFORM: u8 = 8;
+
+//LOWERCASE NAME OF A MESSAGE TYPE, FOR LOGS AND METRIC LABELS
+pub fn message_type_name(message_type: u8) -> &'static str {
+    match message_type {
+        DHCPDISCOVER => "discover",
+        DHCPOFFER => "offer",
+        DHCPREQUEST => "request",
+        DHCPDECLINE => "decline",
+        DHCPACK => "ack",
+        DHCPNAK => "nak",
+        DHCPRELEASE => "release",
+        DHCPINFORM => "inform",
+        _ => "unknown",
+    }
+}
+
+ /*
+ * FUNCTIONS FOR HANDLING OPTIONS VEC<U8> BUFFER
+ */
+pub fn push_ip(options_buf: &mut Vec<u8>, code: u8, ip: Ipv4Addr) {
+    options_buf.push(code);
+    options_buf.push(4);
+    options_buf.extend_from_slice(&ip.octets());
+}
+
+//LIST LENGTH IS CHECKED WHEN THE CONFIG IS LOADED
+pub fn push_ip_list(options_buf: &mut Vec<u8>, code: u8, ips: &[Ipv4Addr]) {
+    options_buf.push(code);
+    options_buf.push(4 * ips.len() as u8);
+    for ip in ips {
+        options_buf.extend_from_slice(&ip.octets());
+    }
+}
+
+//STRING LENGTH IS CHECKED WHEN THE CONFIG IS LOADED
+pub fn push_bytes(options_buf: &mut Vec<u8>, code: u8, bytes: &[u8]) {
+    options_buf.push(code);
+    options_buf.push(bytes.len() as u8);
+    options_buf.extend_from_slice(bytes);
+}
+
+pub fn push_seconds(options_buf: &mut Vec<u8>, code: u8, duration: Duration) {
+    push_bytes(options_buf, code, &(duration.as_secs() as u32).to_be_bytes());
+}
+
+//SMALLEST MAXIMUM MESSAGE SIZE A CLIENT MAY ASK FOR (RFC 2132 SECTION 9.10)
+//ALSO WHAT EVERY CLIENT MUST ACCEPT WHEN IT DID NOT SEND OPTION 57
+pub const MIN_MAX_MESSAGE_SIZE: usize = 576;
+const IP_UDP_HEADER_SIZE: usize = 28;
+//OP THROUGH FILE, EVERYTHING BEFORE THE MAGIC COOKIE
+const FIXED_HEADER_SIZE: usize = 236;
+const MAGIC_COOKIE_SIZE: usize = 4;
+const SNAME_SIZE: usize = 64;
+const FILE_SIZE: usize = 128;
+
+//LARGEST DHCP MESSAGE (WITHOUT IP AND UDP HEADERS) THE CLIENT ACCEPTS
+//OPTION 57 BELOW 576 IS NOT LEGAL AND IS RAISED TO 576
+pub fn max_reply_size(message: &DHCPMessage) -> usize {
+    let requested = match message.options_map.get(&MAXIMUM_DHCP_MESSAGE_SIZE) {
+        Some(v) if v.len() == 2 => u16::from_be_bytes([v[0], v[1]]) as usize,
+        _ => MIN_MAX_MESSAGE_SIZE,
+    };
+    requested.max(MIN_MAX_MESSAGE_SIZE) - IP_UDP_HEADER_SIZE
+}
+
+//LOWER NUMBERS ARE KEPT FIRST WHEN NOT EVERYTHING FITS
+fn option_priority(code: u8) -> u8 {
+    match code {
+        MESSAGE_TYPE | SERVER_IDENTIFIER | LEASE_TIME => 0,
+        SUBNET_MASK | ROUTER | DNS_SERVER | RENEWAL_TIME | REBINDING_TIME => 1,
+        DOMAIN_NAME | BROADCAST_ADDRESS => 2,
+        _ => 3,
+    }
+}
+
+//OPTIONS SPLIT OVER THE OPTIONS FIELD AND, WITH OPTION OVERLOAD, THE SNAME AND FILE FIELDS
+pub struct PackedOptions {
+    //WITHOUT END, DHCPMessage::new ADDS IT
+    pub options: Vec<u8>,
+    pub sname: [u8; SNAME_SIZE],
+    pub file: [u8; FILE_SIZE],
+    //CODES OF OPTIONS THAT DID NOT FIT ANYWHERE
+    pub dropped: Vec<u8>,
+}
+
+//AREA OPTIONS CAN BE PLACED IN, CAPACITY LEAVES ROOM FOR THE END OPTION
+struct OptionArea {
+    capacity: usize,
+    used: usize,
+    //INDEXES INTO THE OPTION LIST, IN PLACEMENT ORDER
+    placed: Vec<usize>,
+}
+
+impl OptionArea {
+    fn new(capacity: usize) -> Self {
+        OptionArea { capacity, used: 0, placed: Vec::new() }
+    }
+
+    fn try_place(&mut self, index: usize, len: usize) -> bool {
+        if self.used + len > self.capacity {
+            return false;
+        }
+        self.used += len;
+        self.placed.push(index);
+        true
+    }
+
+    //OPTIONS IN THE ORDER THEY WERE BUILT IN, NOT THE ORDER THEY WERE PLACED IN
+    fn write(&mut self, entries: &[&[u8]], out: &mut [u8]) -> usize {
+        self.placed.sort_unstable();
+        let mut position = 0;
+        for &index in &self.placed {
+            out[position..position + entries[index].len()].copy_from_slice(entries[index]);
+            position += entries[index].len();
+        }
+        position
+    }
+}
+
+//FIT AN OPTIONS BUFFER INTO A REPLY OF AT MOST max_message_size BYTES
+//OPTIONS ARE NEVER CUT, ONES THAT DO NOT FIT ARE DROPPED LOWEST PRIORITY FIRST
+//SNAME AND FILE ARE ONLY BORROWED (OPTION 52) WHEN THE CLIENT LEFT THEM EMPTY
+pub fn pack_options(options_buf: &[u8], max_message_size: usize, request: &DHCPMessage) -> PackedOptions {
+    let mut entries: Vec<&[u8]> = Vec::new();
+    let mut i = 0;
+    while i < options_buf.len() {
+        match options_buf[i] {
+            0 => i += 1,
+            END => break,
+            _ => {
+                let end = (i + 2 + *options_buf.get(i + 1).unwrap_or(&0) as usize).min(options_buf.len());
+                entries.push(&options_buf[i..end]);
+                i = end;
+            }
+        }
+    }
+
+    //OPTIONS FIELD, MINUS THE COOKIE AND THE END OPTION
+    let main_capacity = max_message_size.saturating_sub(FIXED_HEADER_SIZE + MAGIC_COOKIE_SIZE + 1);
+    let mut packed = PackedOptions {
+        options: Vec::new(),
+        sname: [0; SNAME_SIZE],
+        file: [0; FILE_SIZE],
+        dropped: Vec::new(),
+    };
+
+    let total: usize = entries.iter().map(|entry| entry.len()).sum();
+    if total <= main_capacity {
+        packed.options = entries.concat();
+        return packed;
+    }
+
+    //BIGGEST FIRST WITHIN A PRIORITY, SMALL OPTIONS FILL THE GAPS LEFT IN SNAME AND FILE
+    let mut order: Vec<usize> = (0..entries.len()).collect();
+    order.sort_by_key(|&index| (option_priority(entries[index][0]), Reverse(entries[index].len())));
+
+    //OPTION 52 ITSELF TAKES 3 BYTES OF THE OPTIONS FIELD
+    let mut main = OptionArea::new(main_capacity.saturating_sub(3));
+    let mut file = OptionArea::new(if request.file.iter().all(|&b| b == 0) {FILE_SIZE - 1} else {0});
+    let mut sname = OptionArea::new(if request.sname.iter().all(|&b| b == 0) {SNAME_SIZE - 1} else {0});
+    for index in order {
+        let len = entries[index].len();
+        //RFC 2131 ORDER: OPTIONS FIELD, THEN FILE, THEN SNAME
+        if !main.try_place(index, len) && !file.try_place(index, len) && !sname.try_place(index, len) {
+            packed.dropped.push(entries[index][0]);
+        }
+    }
+
+    let mut main_buf = vec![0; main.used];
+    main.write(&entries, &mut main_buf);
+    packed.options = main_buf;
+
+    let mut overload = 0;
+    if !file.placed.is_empty() {
+        let used = file.write(&entries, &mut packed.file);
+        packed.file[used] = END;
+        overload |= 1;
+    }
+    if !sname.placed.is_empty() {
+        let used = sname.write(&entries, &mut packed.sname);
+        packed.sname[used] = END;
+        overload |= 2;
+    }
+    if overload > 0 {
+        packed.options.extend_from_slice(&[OPTION_OVERLOAD, 1, overload]);
+    }
+    packed
+}
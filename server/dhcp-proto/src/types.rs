@@ -0,0 +1,260 @@
+use std::net::Ipv4Addr;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+const BOOT_FILENAME_SIZE: usize = 128;
+const SERVER_NAME_SIZE: usize = 64;
+const MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+const PAD: u8 = 0;
+const END: u8 = 255;
+const OPTION_OVERLOAD: u8 = 52;
+
+//PART OF THE MESSAGE AN OPTION WAS READ FROM
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionField {
+    Options,
+    File,
+    Sname,
+}
+
+impl OptionField {
+    //WHERE THE FIELD STARTS IN THE MESSAGE
+    pub fn start(&self) -> usize {
+        match self {
+            OptionField::Options => 240,
+            OptionField::File => 108,
+            OptionField::Sname => 44,
+        }
+    }
+}
+
+impl fmt::Display for OptionField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OptionField::Options => write!(f, "options"),
+            OptionField::File => write!(f, "file"),
+            OptionField::Sname => write!(f, "sname"),
+        }
+    }
+}
+
+//WHY A RECEIVED PACKET IS NOT A DHCP MESSAGE
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    TooShort { length: usize },
+    BadMagicCookie([u8; 4]),
+    //OFFSETS ARE FROM THE START OF THE MESSAGE
+    //OPTION CODE AT THE VERY END OF A FIELD WITHOUT A LENGTH BYTE
+    MissingLength { field: OptionField, offset: usize, code: u8 },
+    //OPTION LENGTH RUNS PAST THE END OF THE FIELD
+    Truncated { field: OptionField, offset: usize, code: u8, length: usize, available: usize },
+    //OPTION 52 THAT IS NOT ONE BYTE OF 1, 2 OR 3
+    BadOverload(Vec<u8>),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::TooShort { length } =>
+                write!(f, "message is {} bytes, at least 240 are needed", length),
+            ParseError::BadMagicCookie(cookie) =>
+                write!(f, "invalid magic cookie {:?}", cookie),
+            ParseError::MissingLength { field, offset, code } =>
+                write!(f, "option {} at offset {} ({} field) has no length byte", code, offset, field),
+            ParseError::Truncated { field, offset, code, length, available } =>
+                write!(f, "option {} at offset {} ({} field) is {} bytes long but only {} remain", code, offset, field, length, available),
+            ParseError::BadOverload(value) =>
+                write!(f, "invalid option overload value {:?}", value),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+//READ THE OPTIONS OF ONE FIELD INTO THE MAP
+//PAD IS SKIPPED, END STOPS THE FIELD
+//A CODE SEEN AGAIN IS APPENDED TO THE EARLIER VALUE (RFC 3396 LONG OPTIONS)
+pub fn parse_option_field(field: &[u8], kind: OptionField, options_map: &mut HashMap<u8, Vec<u8>>) -> Result<(), ParseError> {
+    let mut i = 0;
+    while i < field.len() {
+        let code = field[i];
+        match code {
+            PAD => {
+                i += 1;
+                continue;
+            }
+            END => break,
+            _ => {}
+        }
+        let length = *field.get(i + 1).ok_or(ParseError::MissingLength { field: kind, offset: kind.start() + i, code })? as usize;
+        let value_start = i + 2;
+        if value_start + length > field.len() {
+            return Err(ParseError::Truncated {
+                field: kind,
+                offset: kind.start() + i,
+                code,
+                length,
+                available: field.len() - value_start,
+            });
+        }
+        options_map.entry(code).or_default().extend_from_slice(&field[value_start..value_start + length]);
+        i = value_start + length;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DHCPMessage {
+    pub op: u8,
+    pub htype: u8,
+    pub hlen: u8,
+    pub hops: u8,
+    pub xid: u32,
+    pub secs: u16,
+    pub flags: u16,
+    pub ciaddr: Ipv4Addr,
+    pub yiaddr: Ipv4Addr,
+    pub siaddr: Ipv4Addr,
+    pub giaddr: Ipv4Addr,
+    pub chaddr: [u8; 16],
+    pub sname: [u8; SERVER_NAME_SIZE],
+    pub file: [u8; BOOT_FILENAME_SIZE],
+    pub options: Vec<u8>,
+    pub options_map: HashMap<u8, Vec<u8>>,
+}
+
+impl DHCPMessage {
+    //CREATE NEW DHCP MESSAGE FROM BUFFER
+    pub fn from_buffer(buf: &[u8]) -> Result<Self, ParseError> {
+        if buf.len() < 240 {
+            return Err(ParseError::TooShort { length: buf.len() });
+        }
+        
+        if buf[236..240] != MAGIC_COOKIE {
+            return Err(ParseError::BadMagicCookie([buf[236], buf[237], buf[238], buf[239]]));
+        }
+
+        let options = buf[236..].to_vec();
+
+        let mut options_map = HashMap::new();
+        parse_option_field(&options[4..], OptionField::Options, &mut options_map)?;
+
+        //OPTION 52 SAYS SNAME AND/OR FILE CARRY MORE OPTIONS
+        //RFC 3396: THE OPTIONS FIELD IS READ FIRST, THEN FILE, THEN SNAME
+        let overload = match options_map.get(&OPTION_OVERLOAD) {
+            Some(value) if value.len() == 1 && (1..=3).contains(&value[0]) => value[0],
+            Some(value) => return Err(ParseError::BadOverload(value.clone())),
+            None => 0,
+        };
+        if overload & 1 != 0 {
+            parse_option_field(&buf[108..108 + BOOT_FILENAME_SIZE], OptionField::File, &mut options_map)?;
+        }
+        if overload & 2 != 0 {
+            parse_option_field(&buf[44..44 + SERVER_NAME_SIZE], OptionField::Sname, &mut options_map)?;
+        }
+        options_map.insert(255, Vec::new());
+
+        Ok(DHCPMessage {
+            op: buf[0],
+            htype: buf[1],
+            hlen: buf[2],
+            hops: buf[3],
+            xid: u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]),
+            secs: u16::from_be_bytes([buf[8], buf[9]]),
+            flags: u16::from_be_bytes([buf[10], buf[11]]),
+            ciaddr: Ipv4Addr::new(buf[12], buf[13], buf[14], buf[15]),
+            yiaddr: Ipv4Addr::new(buf[16], buf[17], buf[18], buf[19]),
+            siaddr: Ipv4Addr::new(buf[20], buf[21], buf[22], buf[23]),
+            giaddr: Ipv4Addr::new(buf[24], buf[25], buf[26], buf[27]),
+            chaddr: {
+                let mut chaddr = [0u8; 16];
+                chaddr.copy_from_slice(&buf[28..44]);
+                chaddr
+            },
+            sname: {
+                let mut sname = [0u8; SERVER_NAME_SIZE];
+                sname.copy_from_slice(&buf[44..44 + SERVER_NAME_SIZE]);
+                sname
+            },
+            file: {
+                let mut file = [0u8; BOOT_FILENAME_SIZE];
+                file.copy_from_slice(&buf[108..108 + BOOT_FILENAME_SIZE]);
+                file
+            },
+            options,
+            options_map,
+        })
+    }
+
+    //CREATE NEW DHCP MESSAGE FROM PARAMETERS
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        op: u8,
+        htype: u8,
+        hlen: u8,
+        hops: u8,
+        xid: u32,
+        secs: u16,
+        flags: u16,
+        ciaddr: Ipv4Addr,
+        yiaddr: Ipv4Addr,
+        siaddr: Ipv4Addr,
+        giaddr: Ipv4Addr,
+        chaddr: [u8; 16],
+        sname: [u8; SERVER_NAME_SIZE],
+        file: [u8; BOOT_FILENAME_SIZE],
+        options: Vec<u8>,
+    ) -> Self {
+        let mut full_options = MAGIC_COOKIE.to_vec();
+        full_options.extend_from_slice(&options);
+
+        //OPTIONS ARE BUILT BY THE SERVER ITSELF, A MALFORMED TAIL IS SIMPLY LEFT OUT OF THE MAP
+        let mut options_map = HashMap::new();
+        let _ = parse_option_field(&options, OptionField::Options, &mut options_map);
+        options_map.insert(255, Vec::new());
+        full_options.push(255);
+
+        DHCPMessage {
+            op,
+            htype,
+            hlen,
+            hops,
+            xid,
+            secs,
+            flags,
+            ciaddr,
+            yiaddr,
+            siaddr,
+            giaddr,
+            chaddr,
+            sname,
+            file,
+            options: full_options,
+            options_map,
+        }
+    }
+
+    //FUNCTION TO CONVERT DHCP MESSAGE TO VEC<U8> BUFFER
+    pub fn to_buffer(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(236 + self.options.len());
+
+        buf.push(self.op);
+        buf.push(self.htype);
+        buf.push(self.hlen);
+        buf.push(self.hops);
+        buf.extend_from_slice(&self.xid.to_be_bytes());
+        buf.extend_from_slice(&self.secs.to_be_bytes());
+        buf.extend_from_slice(&self.flags.to_be_bytes());
+        buf.extend_from_slice(&self.ciaddr.octets());
+        buf.extend_from_slice(&self.yiaddr.octets());
+        buf.extend_from_slice(&self.siaddr.octets());
+        buf.extend_from_slice(&self.giaddr.octets());
+        buf.extend_from_slice(&self.chaddr);
+        buf.extend_from_slice(&self.sname);
+        buf.extend_from_slice(&self.file);
+        buf.extend_from_slice(&self.options);
+
+        buf
+    }
+}
@@ -0,0 +1,283 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::net::Ipv4Addr;
+use std::path::Path;
+
+use proptest::prelude::*;
+
+use dhcp_proto::options::*;
+use dhcp_proto::types::{DHCPMessage, OptionField, ParseError};
+
+//OPTION CODES A CLIENT MAY SEND, PAD, OVERLOAD AND END HAVE A MEANING OF THEIR OWN
+fn option_code() -> impl Strategy<Value = u8> {
+    (1u8..=254).prop_filter("not option overload", |code| *code != OPTION_OVERLOAD)
+}
+
+fn encode_options(options: &[(u8, Vec<u8>)]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    for (code, value) in options {
+        buf.push(*code);
+        buf.push(value.len() as u8);
+        buf.extend_from_slice(value);
+    }
+    buf
+}
+
+fn ipv4() -> impl Strategy<Value = Ipv4Addr> {
+    any::<u32>().prop_map(Ipv4Addr::from)
+}
+
+prop_compose! {
+    fn message_with(options: impl Strategy<Value = Vec<u8>>)(
+        op in 1u8..=2,
+        htype in any::<u8>(),
+        hlen in any::<u8>(),
+        hops in any::<u8>(),
+        xid in any::<u32>(),
+        secs in any::<u16>(),
+        flags in any::<u16>(),
+        ciaddr in ipv4(),
+        yiaddr in ipv4(),
+        siaddr in ipv4(),
+        giaddr in ipv4(),
+        chaddr in any::<[u8; 16]>(),
+        sname in proptest::collection::vec(any::<u8>(), 64),
+        file in proptest::collection::vec(any::<u8>(), 128),
+        options in options,
+    ) -> DHCPMessage {
+        DHCPMessage::new(op, htype, hlen, hops, xid, secs, flags, ciaddr, yiaddr, siaddr, giaddr, chaddr,
+            sname.try_into().unwrap(), file.try_into().unwrap(), options)
+    }
+}
+
+fn options_list() -> impl Strategy<Value = Vec<(u8, Vec<u8>)>> {
+    proptest::collection::vec((option_code(), proptest::collection::vec(any::<u8>(), 0..=255)), 0..12)
+}
+
+//A REQUEST WITH EMPTY SNAME AND FILE THAT ASKS FOR A GIVEN MAXIMUM MESSAGE SIZE
+fn request(max_message_size: u16) -> DHCPMessage {
+    let mut options = vec![MESSAGE_TYPE, 1, DHCPDISCOVER, MAXIMUM_DHCP_MESSAGE_SIZE, 2];
+    options.extend_from_slice(&max_message_size.to_be_bytes());
+    DHCPMessage::new(1, 1, 6, 0, 1, 0, 0, Ipv4Addr::UNSPECIFIED, Ipv4Addr::UNSPECIFIED,
+        Ipv4Addr::UNSPECIFIED, Ipv4Addr::UNSPECIFIED, [0; 16], [0; 64], [0; 128], options)
+}
+
+proptest! {
+    #[test]
+    fn serialized_messages_parse_back(message in message_with(options_list().prop_map(|o| encode_options(&o)))) {
+        let parsed = DHCPMessage::from_buffer(&message.to_buffer()).unwrap();
+        prop_assert_eq!(parsed, message);
+    }
+
+    #[test]
+    fn split_options_are_concatenated(code in option_code(),
+                                      parts in proptest::collection::vec(proptest::collection::vec(any::<u8>(), 0..=255), 1..4)) {
+        let options: Vec<(u8, Vec<u8>)> = parts.iter().map(|part| (code, part.clone())).collect();
+        let message = request(576);
+        let mut buf = message.to_buffer();
+        buf.truncate(240);
+        buf.extend_from_slice(&encode_options(&options));
+        buf.push(END);
+        let parsed = DHCPMessage::from_buffer(&buf).unwrap();
+        prop_assert_eq!(parsed.options_map.get(&code), Some(&parts.concat()));
+    }
+
+    #[test]
+    fn arbitrary_bytes_never_panic(buf in proptest::collection::vec(any::<u8>(), 0..700)) {
+        let _ = DHCPMessage::from_buffer(&buf);
+    }
+
+    #[test]
+    fn arbitrary_options_never_panic(header in proptest::collection::vec(any::<u8>(), 236),
+                                     options in proptest::collection::vec(any::<u8>(), 0..400)) {
+        let mut buf = header;
+        buf.extend_from_slice(&[99, 130, 83, 99]);
+        buf.extend_from_slice(&options);
+        if let Ok(message) = DHCPMessage::from_buffer(&buf) {
+            prop_assert_eq!(message.to_buffer(), buf);
+        }
+    }
+
+    #[test]
+    fn packed_options_fit_and_survive(options in proptest::collection::btree_map(option_code(), proptest::collection::vec(any::<u8>(), 0..=255), 0..16),
+                                      max_message_size in 0u16..1500) {
+        let request = request(max_message_size);
+        let limit = max_reply_size(&request);
+        let options: BTreeMap<u8, Vec<u8>> = options;
+        let options_buf = encode_options(&options.iter().map(|(code, value)| (*code, value.clone())).collect::<Vec<_>>());
+
+        let packed = pack_options(&options_buf, limit, &request);
+        let reply = DHCPMessage::new(2, 1, 6, 0, 1, 0, 0, Ipv4Addr::UNSPECIFIED, Ipv4Addr::UNSPECIFIED,
+            Ipv4Addr::UNSPECIFIED, Ipv4Addr::UNSPECIFIED, [0; 16], packed.sname, packed.file, packed.options);
+        let buf = reply.to_buffer();
+        prop_assert!(buf.len() <= limit, "{} byte reply for a {} byte limit", buf.len(), limit);
+
+        let parsed = DHCPMessage::from_buffer(&buf).unwrap();
+        for (code, value) in &options {
+            if packed.dropped.contains(code) {
+                prop_assert!(!parsed.options_map.contains_key(code));
+            } else {
+                prop_assert_eq!(parsed.options_map.get(code), Some(value));
+            }
+        }
+    }
+}
+
+//EVERY PACKET IN THE CORPUS PARSES AND SERIALIZES BACK TO THE SAME BYTES
+#[test]
+fn corpus_round_trips() {
+    let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    let mut count = 0;
+    for entry in fs::read_dir(&corpus).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|e| e.to_str()) != Some("bin") {
+            continue;
+        }
+        let bytes = fs::read(&path).unwrap();
+        let message = DHCPMessage::from_buffer(&bytes)
+            .unwrap_or_else(|e| panic!("{} does not parse: {}", path.display(), e));
+        assert!(message.options_map.contains_key(&MESSAGE_TYPE), "{} has no message type", path.display());
+        assert_eq!(message.to_buffer(), bytes, "{} changed on a round trip", path.display());
+        count += 1;
+    }
+    assert!(count > 0, "no packets in {}", corpus.display());
+}
+
+//OPTION 57 BELOW 576 IS RAISED TO 576, MISSING OR MALFORMED IT MEANS 576, LARGER VALUES ARE KEPT
+#[test]
+fn max_reply_size_is_never_below_576() {
+    assert_eq!(max_reply_size(&request(0)), 576 - 28);
+    assert_eq!(max_reply_size(&request(300)), 576 - 28);
+    assert_eq!(max_reply_size(&request(576)), 576 - 28);
+    assert_eq!(max_reply_size(&request(1500)), 1500 - 28);
+
+    let mut without = request(1500);
+    without.options_map.remove(&MAXIMUM_DHCP_MESSAGE_SIZE);
+    assert_eq!(max_reply_size(&without), 576 - 28);
+    without.options_map.insert(MAXIMUM_DHCP_MESSAGE_SIZE, vec![5, 220, 0]);
+    assert_eq!(max_reply_size(&without), 576 - 28);
+
+    //OPTION 56 IS A MESSAGE FOR THE USER, NOT A SIZE
+    let mut buf = request(576).to_buffer();
+    buf.truncate(240);
+    buf.extend_from_slice(&[MESSAGE_TYPE, 1, DHCPDISCOVER, 56, 2, 5, 220, 57, 2, 4, 0, END]);
+    assert_eq!(max_reply_size(&DHCPMessage::from_buffer(&buf).unwrap()), 1024 - 28);
+}
+
+//WHAT DOES NOT FIT GOES INTO FILE AND SNAME, THEN THE LOWEST PRIORITY OPTIONS ARE DROPPED WHOLE
+#[test]
+fn low_priority_options_are_dropped_first() {
+    let mut options_buf = vec![MESSAGE_TYPE, 1, DHCPOFFER];
+    push_ip(&mut options_buf, SERVER_IDENTIFIER, Ipv4Addr::new(192, 168, 10, 1));
+    push_bytes(&mut options_buf, LEASE_TIME, &3600u32.to_be_bytes());
+    push_ip(&mut options_buf, SUBNET_MASK, Ipv4Addr::new(255, 255, 255, 0));
+    push_bytes(&mut options_buf, DOMAIN_NAME, &[b'd'; 200]);
+    push_bytes(&mut options_buf, 43, &[1; 120]);
+    push_bytes(&mut options_buf, 224, &[2; 250]);
+
+    let request = request(576);
+    let packed = pack_options(&options_buf, max_reply_size(&request), &request);
+    assert_eq!(packed.dropped, vec![224]);
+
+    let reply = DHCPMessage::new(2, 1, 6, 0, 1, 0, 0, Ipv4Addr::UNSPECIFIED, Ipv4Addr::UNSPECIFIED,
+        Ipv4Addr::UNSPECIFIED, Ipv4Addr::UNSPECIFIED, [0; 16], packed.sname, packed.file, packed.options);
+    let buf = reply.to_buffer();
+    assert!(buf.len() <= 576 - 28);
+    let parsed = DHCPMessage::from_buffer(&buf).unwrap();
+    for code in [MESSAGE_TYPE, SERVER_IDENTIFIER, LEASE_TIME, SUBNET_MASK, DOMAIN_NAME] {
+        assert!(parsed.options_map.contains_key(&code), "option {} was lost", code);
+    }
+    assert_eq!(parsed.options_map[&DOMAIN_NAME], vec![b'd'; 200]);
+    //ONLY FITS IN THE BORROWED FILE FIELD
+    assert_eq!(parsed.options_map[&43], vec![1; 120]);
+    assert_eq!(parsed.options_map[&OPTION_OVERLOAD], vec![1]);
+}
+
+//A CLIENT THAT PUT A BOOT FILE NAME IN FILE KEEPS IT, OPTIONS THAT WOULD NEED IT ARE DROPPED INSTEAD
+#[test]
+fn used_file_and_sname_fields_are_not_borrowed() {
+    let mut options_buf = vec![MESSAGE_TYPE, 1, DHCPOFFER];
+    push_bytes(&mut options_buf, 43, &[1; 250]);
+    push_bytes(&mut options_buf, 224, &[2; 100]);
+
+    let mut request = request(576);
+    request.file[..8].copy_from_slice(b"pxelinux");
+    request.sname[..4].copy_from_slice(b"boot");
+    let packed = pack_options(&options_buf, max_reply_size(&request), &request);
+    assert_eq!(packed.dropped, vec![224]);
+    assert_eq!(packed.file, [0; 128]);
+    assert_eq!(packed.sname, [0; 64]);
+    assert!(!packed.options.windows(3).any(|window| window[0] == OPTION_OVERLOAD && window[1] == 1));
+}
+
+//A MESSAGE WITH options IN THE OPTIONS FIELD AND file AND sname WRITTEN INTO THOSE FIELDS
+fn overloaded(options: &[u8], file: &[u8], sname: &[u8]) -> Vec<u8> {
+    let mut buf = request(576).to_buffer();
+    buf.truncate(240);
+    buf[108..108 + file.len()].copy_from_slice(file);
+    buf[44..44 + sname.len()].copy_from_slice(sname);
+    buf.extend_from_slice(options);
+    buf.push(END);
+    buf
+}
+
+//RFC 3396: THE PARTS OF A LONG OPTION ARE JOINED OPTIONS FIELD FIRST, THEN FILE, THEN SNAME
+#[test]
+fn overloaded_fields_are_read_options_then_file_then_sname() {
+    let buf = overloaded(&[MESSAGE_TYPE, 1, DHCPREQUEST, OPTION_OVERLOAD, 1, 3, 12, 2, b'o', b'p'],
+                         &[12, 2, b'f', b'i', 0, 61, 2, 1, 2, END],
+                         &[12, 2, b's', b'n', END]);
+    let message = DHCPMessage::from_buffer(&buf).unwrap();
+    assert_eq!(message.options_map[&12], b"opfisn");
+    assert_eq!(message.options_map[&61], [1, 2]);
+    assert_eq!(message.to_buffer(), buf);
+}
+
+//ONLY THE FIELDS OPTION 52 NAMES ARE READ, THE OTHER ONE IS A PLAIN NAME
+#[test]
+fn only_the_named_field_is_overloaded() {
+    let file_only = overloaded(&[MESSAGE_TYPE, 1, DHCPREQUEST, OPTION_OVERLOAD, 1, 1],
+                               &[12, 1, b'f', END], &[12, 1, b's', END]);
+    assert_eq!(DHCPMessage::from_buffer(&file_only).unwrap().options_map[&12], b"f");
+
+    let sname_only = overloaded(&[MESSAGE_TYPE, 1, DHCPREQUEST, OPTION_OVERLOAD, 1, 2],
+                                &[12, 1, b'f', END], &[12, 1, b's', END]);
+    assert_eq!(DHCPMessage::from_buffer(&sname_only).unwrap().options_map[&12], b"s");
+
+    let neither = overloaded(&[MESSAGE_TYPE, 1, DHCPREQUEST], &[12, 1, b'f', END], &[12, 1, b's', END]);
+    assert!(!DHCPMessage::from_buffer(&neither).unwrap().options_map.contains_key(&12));
+}
+
+#[test]
+fn broken_overloaded_fields_are_reported_where_they_are() {
+    let bad_value = overloaded(&[MESSAGE_TYPE, 1, DHCPREQUEST, OPTION_OVERLOAD, 1, 4], &[], &[]);
+    assert_eq!(DHCPMessage::from_buffer(&bad_value), Err(ParseError::BadOverload(vec![4])));
+
+    let mut file = [0u8; 128];
+    file[126] = 12;
+    file[127] = 5;
+    let truncated = overloaded(&[MESSAGE_TYPE, 1, DHCPREQUEST, OPTION_OVERLOAD, 1, 1], &file, &[]);
+    assert_eq!(DHCPMessage::from_buffer(&truncated), Err(ParseError::Truncated {
+        field: OptionField::File, offset: 108 + 126, code: 12, length: 5, available: 0 }));
+
+    let mut sname = [0u8; 64];
+    sname[63] = 12;
+    let missing_length = overloaded(&[MESSAGE_TYPE, 1, DHCPREQUEST, OPTION_OVERLOAD, 1, 2], &[], &sname);
+    assert_eq!(DHCPMessage::from_buffer(&missing_length), Err(ParseError::MissingLength {
+        field: OptionField::Sname, offset: 44 + 63, code: 12 }));
+}
+
+//THE REQUEST systemd-networkd 252 SENT AFTER AN OFFER OF 10.99.0.50 FROM 10.99.0.1, SEE tests/corpus/README.md
+#[test]
+fn captured_request_decodes() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus/systemd-networkd-252-request.bin");
+    let message = DHCPMessage::from_buffer(&fs::read(path).unwrap()).unwrap();
+    assert_eq!(message.options_map[&MESSAGE_TYPE], [DHCPREQUEST]);
+    assert_eq!(message.options_map[&REQUESTED_IP], [10, 99, 0, 50]);
+    assert_eq!(message.options_map[&SERVER_IDENTIFIER], [10, 99, 0, 1]);
+    assert_eq!(message.options_map[&HOST_NAME], b"corpus-host");
+    assert_eq!(max_reply_size(&message), 1472 - 28);
+    //CLIENT IDENTIFIER TYPE 1 (ETHERNET) FOLLOWED BY THE HARDWARE ADDRESS
+    assert_eq!(message.options_map[&CLIENT_IDENTIFIER][0], 1);
+    assert_eq!(message.options_map[&CLIENT_IDENTIFIER][1..], message.chaddr[..6]);
+}
@@ -0,0 +1,27 @@
+use std::hint::black_box;
+use std::net::Ipv4Addr;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use dhcp_proto::DHCPMessage;
+use dhcp_server::server_config::server_config::parse_config;
+use dhcp_server::utility::options::*;
+
+fn options(c: &mut Criterion) {
+    let config = parse_config(&serde_json::from_str(include_str!("../../../server-config.json")).unwrap()).unwrap();
+    let server_id = Ipv4Addr::new(192, 168, 10, 30);
+    let request = DHCPMessage::new(1, 1, 6, 0, 1, 0, 0, Ipv4Addr::UNSPECIFIED, Ipv4Addr::UNSPECIFIED,
+        Ipv4Addr::UNSPECIFIED, Ipv4Addr::UNSPECIFIED, [0; 16], [0; 64], [0; 128], vec![MESSAGE_TYPE, 1, DHCPREQUEST]);
+    //EVERY OPTION THE CONFIGURATION CAN ANSWER
+    let parameter_request_list: Vec<u8> = (1..=60).collect();
+
+    c.bench_function("create_options_buffer ack", |b| {
+        b.iter(|| create_options_buffer(black_box(&request), black_box(&config), DHCPACK, server_id, None))
+    });
+    c.bench_function("inform_options_buf all", |b| {
+        b.iter(|| inform_options_buf(black_box(parameter_request_list.clone()), black_box(&config), "client", server_id))
+    });
+}
+
+criterion_group!(benches, options);
+criterion_main!(benches);
@@ -0,0 +1,80 @@
+//PRINTS THE LEASE EVENTS A notify HOOK PUBLISHES, ONE JSON OBJECT PER LINE, UNTIL IT IS STOPPED
+//
+//  POSTGRES_URI="host=localhost user=postgres" cargo run --example tail_lease_events -- [--channel NAME] [--after ID]
+//
+//WITH --after THE EVENTS KEPT IN THE OUTBOX AFTER ID ARE PRINTED FIRST, SO A CONSUMER THAT REMEMBERS THE LAST id IT
+//HANDLED MISSES NOTHING WHILE IT WAS AWAY. IT LISTENS BEFORE READING THE OUTBOX AND SKIPS WHAT IT ALREADY PRINTED
+use std::error::Error;
+use std::future::poll_fn;
+
+use tokio::sync::mpsc;
+use tokio_postgres::{AsyncMessage, NoTls};
+
+use dhcp_server::server_config::server_config::DEFAULT_NOTIFY_CHANNEL;
+
+struct Options {
+    channel: String,
+    after: Option<i64>,
+}
+
+fn options() -> Result<Options, String> {
+    let mut options = Options { channel: DEFAULT_NOTIFY_CHANNEL.to_string(), after: None };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or(format!("{} needs a value", arg))?;
+        match arg.as_str() {
+            "--channel" => options.channel = value,
+            "--after" => options.after = Some(value.parse().map_err(|_| format!("{:?} is not an outbox id", value))?),
+            other => return Err(format!("unknown argument {:?}, expected --channel or --after", other)),
+        }
+    }
+    Ok(options)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let options = options()?;
+    let uri = std::env::var("POSTGRES_URI").map_err(|_| "POSTGRES_URI is not set")?;
+    let (client, mut connection) = tokio_postgres::connect(&uri, NoTls).await?;
+
+    //NOTIFICATIONS ONLY ARRIVE THROUGH THE CONNECTION, SO ITS TASK PASSES THEM ON
+    let (sender, mut notifications) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Some(message) = poll_fn(|cx| connection.poll_message(cx)).await {
+            match message {
+                Ok(AsyncMessage::Notification(notification)) => {
+                    if sender.send(notification.payload().to_string()).is_err() {
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("Database connection error: {}", e);
+                    break;
+                }
+            }
+        }
+    });
+
+    client.batch_execute(&format!("LISTEN \"{}\"", options.channel.replace('"', "\"\""))).await?;
+
+    let mut last = options.after;
+    if let Some(after) = options.after {
+        let query = "SELECT id, (payload || jsonb_build_object('id', id))::TEXT FROM lease_events WHERE id > $1 ORDER BY id";
+        for row in client.query(query, &[&after]).await? {
+            last = Some(row.get(0));
+            println!("{}", row.get::<_, String>(1));
+        }
+    }
+
+    while let Some(payload) = notifications.recv().await {
+        let id = serde_json::from_str::<serde_json::Value>(&payload).ok().and_then(|event| event["id"].as_i64());
+        if let (Some(id), Some(last)) = (id, last) {
+            if id <= last {
+                continue;
+            }
+        }
+        println!("{}", payload);
+    }
+    Err("the database closed the connection".into())
+}
@@ -0,0 +1,38 @@
+-- SCHEMA AS CREATED BY SERVERS BEFORE MIGRATIONS WERE INTRODUCED
+-- EVERY STATEMENT IS GUARDED SO EXISTING DEPLOYMENTS CAN ADOPT IT IN PLACE
+
+CREATE TABLE IF NOT EXISTS ip_addresses (
+    ip_address INET PRIMARY KEY,
+    allocated BOOLEAN NOT NULL DEFAULT FALSE,
+    client_id VARCHAR(32) UNIQUE,
+    lease_start TIMESTAMP,
+    lease_end TIMESTAMP
+);
+
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'server_response') THEN
+        CREATE TYPE server_response AS ENUM (
+            'ACK',
+            'NAK'
+        );
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'lease_type') THEN
+        CREATE TYPE lease_type AS ENUM (
+            'RENEWING',
+            'INITIAL',
+            'DECLINED'
+        );
+    END IF;
+END
+$$;
+
+CREATE TABLE IF NOT EXISTS lease_history (
+    id SERIAL PRIMARY KEY,
+    ip_address INET NOT NULL,
+    client_id VARCHAR(32) NOT NULL,
+    lease_start TIMESTAMP,
+    lease_end TIMESTAMP,
+    server_response server_response NOT NULL,
+    lease_type lease_type NOT NULL
+);
@@ -0,0 +1,4 @@
+-- ADDRESSES REMOVED FROM THE POOL WHILE LEASED ARE KEPT UNTIL THEIR LEASE ENDS
+
+ALTER TABLE ip_addresses
+ADD COLUMN IF NOT EXISTS retired BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,10 @@
+-- LEASE TIMES WERE WRITTEN WITH NOW() IN THE SESSION TIME ZONE
+-- CONVERT THEM TO ABSOLUTE INSTANTS SO TIME ZONE CHANGES CANNOT SHIFT EXPIRY
+
+ALTER TABLE ip_addresses
+    ALTER COLUMN lease_start TYPE TIMESTAMPTZ USING lease_start AT TIME ZONE current_setting('TimeZone'),
+    ALTER COLUMN lease_end TYPE TIMESTAMPTZ USING lease_end AT TIME ZONE current_setting('TimeZone');
+
+ALTER TABLE lease_history
+    ALTER COLUMN lease_start TYPE TIMESTAMPTZ USING lease_start AT TIME ZONE current_setting('TimeZone'),
+    ALTER COLUMN lease_end TYPE TIMESTAMPTZ USING lease_end AT TIME ZONE current_setting('TimeZone');
@@ -0,0 +1,9 @@
+-- ADDRESSES AN ADMINISTRATOR TOOK OUT OF SERVICE AND ADDRESSES KEPT FOR ONE CLIENT
+
+ALTER TABLE ip_addresses
+ADD COLUMN IF NOT EXISTS blocked BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE IF NOT EXISTS reservations (
+    client_id VARCHAR(32) PRIMARY KEY,
+    ip_address INET NOT NULL UNIQUE
+);
@@ -0,0 +1,12 @@
+-- LEASE EVENTS KEPT BY notify HOOKS WITH outbox, SO A CONSUMER THAT WAS NOT LISTENING CAN CATCH UP FROM THE LAST id IT SAW
+
+CREATE TABLE IF NOT EXISTS lease_events (
+    id BIGSERIAL PRIMARY KEY,
+    event VARCHAR(16) NOT NULL,
+    ip_address INET NOT NULL,
+    client_id VARCHAR(32) NOT NULL,
+    payload JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS lease_events_created_at ON lease_events (created_at);
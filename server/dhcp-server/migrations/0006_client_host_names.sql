@@ -0,0 +1,9 @@
+-- THE NAME A CLIENT SENT IN OPTION 12 OR 81 AFTER THE HOST NAME POLICY, AND WHICH OF ITS DNS RECORDS THE SERVER UPDATES
+
+ALTER TABLE ip_addresses
+ADD COLUMN IF NOT EXISTS hostname VARCHAR(63);
+
+ALTER TABLE ip_addresses
+ADD COLUMN IF NOT EXISTS dns_update VARCHAR(8) NOT NULL DEFAULT 'both';
+
+CREATE INDEX IF NOT EXISTS ip_addresses_hostname ON ip_addresses (hostname) WHERE hostname IS NOT NULL;
@@ -0,0 +1,11 @@
+-- THE SERVER HOLDING THE ADVISORY LOCK lock_id AND WHEN IT LAST SAID IT IS ALIVE
+-- backend_pid IS ITS LOCK CONNECTION, A STANDBY ENDS THAT CONNECTION WHEN THE HEARTBEAT LAPSES SO THE LOCK IS FREED
+
+CREATE TABLE IF NOT EXISTS ha_leader (
+    lock_id BIGINT PRIMARY KEY,
+    node TEXT NOT NULL,
+    backend_pid INTEGER NOT NULL,
+    term BIGINT NOT NULL,
+    elected_at TIMESTAMPTZ NOT NULL,
+    heartbeat TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+
+use axum::extract::rejection::{JsonRejection, QueryRejection};
+use axum::extract::{Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
+use axum::Json;
+use serde::Deserialize;
+use tracing::info;
+
+use super::views::*;
+use super::{ApiError, ApiState, SCHEMA};
+use crate::failover::Binding;
+use crate::hooks::{LeaseEvent, LeaseEventKind};
+use crate::store::{AddressRecord, HistoryFilter, Reservation};
+
+const DEFAULT_HISTORY_LIMIT: u32 = 100;
+const MAX_HISTORY_LIMIT: u32 = 1000;
+
+//CLIENT IDS ARE STORED AS THE HEX OF THE WHOLE 16 BYTE chaddr FIELD
+const CLIENT_ID_LENGTH: usize = 32;
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+#[derive(Deserialize)]
+pub struct LeaseQuery {
+    //PART OF AN ADDRESS OR CLIENT ID
+    search: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct AddressQuery {
+    state: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    client_id: Option<String>,
+    ip_address: Option<String>,
+    limit: Option<u32>,
+}
+
+pub async fn list_leases(State(state): State<ApiState>,
+                         query: Result<Query<LeaseQuery>, QueryRejection>) -> ApiResult<Vec<AddressView>> {
+    let Query(query) = query.map_err(|e| ApiError::BadRequest(e.body_text()))?;
+    let search = query.search.map(|search| search.to_lowercase());
+    let views = address_views(&state).await?.into_iter()
+        .filter(|view| view.state == AddressState::Leased)
+        .filter(|view| search.as_ref().is_none_or(|search| matches_search(view, search)))
+        .collect();
+    Ok(Json(views))
+}
+
+pub async fn get_lease(State(state): State<ApiState>, Path(ip): Path<String>) -> ApiResult<AddressView> {
+    let ip = parse_ip(&ip)?;
+    address_views(&state).await?.into_iter()
+        .find(|view| view.ip_address == ip)
+        .map(Json)
+        .ok_or_else(|| not_in_table(ip))
+}
+
+//FREE THE ADDRESS NOW, THE CLIENT IS TOLD WITH A NAK WHEN IT NEXT RENEWS
+pub async fn release_lease(State(state): State<ApiState>, Path(ip): Path<String>) -> ApiResult<AddressView> {
+    let ip = parse_ip(&ip)?;
+    let record = find_address(&state, ip).await?;
+    if AddressState::of(&record, state.server.now()) != AddressState::Leased {
+        return Err(ApiError::NotFound(format!("{} is not leased", ip)));
+    }
+    //THE EVENT DESCRIBES THE LEASE THE STORE ENDED, WHICH MAY NOT BE THE ONE READ ABOVE
+    let Some(released) = state.server.store().release(ip, None).await? else {
+        return Err(ApiError::NotFound(format!("{} is not leased", ip)));
+    };
+    if let Some(failover) = state.server.failover() {
+        failover.record(Binding::free(&released));
+    }
+    info!(address = %ip, client_id = %released.client_id, "Lease released through the API");
+    state.server.hooks().emit(LeaseEvent::new(LeaseEventKind::Released, ip, &released.client_id, state.server.now())
+        .name(&released.name));
+    get_lease(State(state), Path(ip.to_string())).await
+}
+
+//TAKE THE LEASE AWAY BUT KEEP THE ADDRESS OUT OF THE POOL UNTIL THE LEASE WOULD HAVE ENDED
+pub async fn revoke_lease(State(state): State<ApiState>, Path(ip): Path<String>) -> ApiResult<AddressView> {
+    let ip = parse_ip(&ip)?;
+    let record = find_address(&state, ip).await?;
+    if AddressState::of(&record, state.server.now()) != AddressState::Leased {
+        return Err(ApiError::NotFound(format!("{} is not leased", ip)));
+    }
+    let Some(revoked) = state.server.store().revoke(ip, state.server.now()).await? else {
+        return Err(ApiError::NotFound(format!("{} is not leased", ip)));
+    };
+    if let Some(failover) = state.server.failover() {
+        failover.record(Binding::held(ip, state.server.now(), revoked.lease_end));
+    }
+    info!(address = %ip, client_id = %revoked.client_id, "Lease revoked through the API");
+    //FOR A HOOK THE CLIENT NO LONGER HOLDS THE ADDRESS, SAME AS A RELEASE
+    state.server.hooks().emit(LeaseEvent::new(LeaseEventKind::Released, ip, &revoked.client_id, state.server.now())
+        .name(&revoked.name));
+    get_lease(State(state), Path(ip.to_string())).await
+}
+
+pub async fn history(State(state): State<ApiState>,
+                     query: Result<Query<HistoryQuery>, QueryRejection>) -> ApiResult<Vec<HistoryView>> {
+    let Query(query) = query.map_err(|e| ApiError::BadRequest(e.body_text()))?;
+    let limit = query.limit.unwrap_or(DEFAULT_HISTORY_LIMIT);
+    if limit == 0 || limit > MAX_HISTORY_LIMIT {
+        return Err(ApiError::BadRequest(format!("limit must be between 1 and {}", MAX_HISTORY_LIMIT)));
+    }
+    let filter = HistoryFilter {
+        client_id: query.client_id.as_deref().map(parse_client_id).transpose()?,
+        ip_address: query.ip_address.as_deref().map(parse_ip).transpose()?,
+        limit,
+    };
+    let entries = state.server.store().history(&filter).await?;
+    Ok(Json(entries.into_iter().map(HistoryView::from).collect()))
+}
+
+pub async fn list_reservations(State(state): State<ApiState>) -> ApiResult<Vec<ReservationView>> {
+    let reservations = state.server.store().reservations().await?;
+    Ok(Json(reservations.into_iter().map(ReservationView::from).collect()))
+}
+
+//RESERVE AN ADDRESS OF THE POOL FOR A CLIENT, MOVING ANY RESERVATION IT ALREADY HAD
+//WHOEVER HOLDS THE ADDRESS NOW GETS A NAK WHEN IT RENEWS
+pub async fn reserve(State(state): State<ApiState>, Path(client_id): Path<String>,
+                     body: Result<Json<ReservationRequest>, JsonRejection>) -> ApiResult<ReservationView> {
+    let client_id = parse_client_id(&client_id)?;
+    let Json(body) = body.map_err(|e| ApiError::BadRequest(e.body_text()))?;
+    let config = state.server.config();
+    if !config.ip_pool.contains(body.ip_address) || config.restricted_ips.contains(&body.ip_address) {
+        return Err(ApiError::BadRequest(format!("{} is not an address of the pool", body.ip_address)));
+    }
+    let reservation = Reservation { client_id, ip_address: body.ip_address };
+    state.server.store().reserve(&reservation).await?;
+    info!(address = %reservation.ip_address, client_id = %reservation.client_id, "Address reserved through the API");
+    Ok(Json(reservation.into()))
+}
+
+pub async fn unreserve(State(state): State<ApiState>, Path(client_id): Path<String>) -> Result<StatusCode, ApiError> {
+    let client_id = parse_client_id(&client_id)?;
+    if !state.server.store().unreserve(&client_id).await? {
+        return Err(ApiError::NotFound(format!("no reservation for {}", client_id)));
+    }
+    info!(client_id = %client_id, "Reservation removed through the API");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn list_addresses(State(state): State<ApiState>,
+                            query: Result<Query<AddressQuery>, QueryRejection>) -> ApiResult<Vec<AddressView>> {
+    let Query(query) = query.map_err(|e| ApiError::BadRequest(e.body_text()))?;
+    let wanted = match query.state.as_deref() {
+        Some(name) => Some(AddressState::parse(name)
+            .ok_or_else(|| ApiError::BadRequest(format!("unknown address state {:?}", name)))?),
+        None => None,
+    };
+    let views = address_views(&state).await?.into_iter()
+        .filter(|view| wanted.is_none_or(|wanted| view.state == wanted))
+        .collect();
+    Ok(Json(views))
+}
+
+//A BLOCKED ADDRESS IS NEVER OFFERED, A CLIENT HOLDING IT GETS A NAK WHEN IT RENEWS
+pub async fn block(State(state): State<ApiState>, Path(ip): Path<String>) -> ApiResult<AddressView> {
+    set_blocked(state, ip, true).await
+}
+
+pub async fn unblock(State(state): State<ApiState>, Path(ip): Path<String>) -> ApiResult<AddressView> {
+    set_blocked(state, ip, false).await
+}
+
+async fn set_blocked(state: ApiState, ip: String, blocked: bool) -> ApiResult<AddressView> {
+    let ip = parse_ip(&ip)?;
+    if !state.server.store().set_blocked(ip, blocked).await? {
+        return Err(not_in_table(ip));
+    }
+    info!(address = %ip, blocked, "Address block changed through the API");
+    get_lease(State(state), Path(ip.to_string())).await
+}
+
+pub async fn pool(State(state): State<ApiState>) -> ApiResult<PoolView> {
+    let config = state.server.config();
+    let now = state.server.now();
+    let store = state.server.store();
+    let addresses = store.addresses().await?;
+    let reserved = store.reservations().await?.len();
+
+    let mut view = PoolView {
+        range_start: config.ip_pool.range_start,
+        range_end: config.ip_pool.range_end,
+        size: 0, leased: 0, held: 0, blocked: 0, free: 0, reserved, retired: 0,
+        utilization: 0.0,
+    };
+    for record in &addresses {
+        //A RETIRED ADDRESS STILL LEASED IS ON ITS WAY OUT OF THE POOL
+        if record.retired {
+            view.retired += 1;
+            continue;
+        }
+        view.size += 1;
+        match AddressState::of(record, now) {
+            AddressState::Leased => view.leased += 1,
+            AddressState::Held => view.held += 1,
+            AddressState::Blocked => view.blocked += 1,
+            AddressState::Retired | AddressState::Free => view.free += 1,
+        }
+    }
+    if view.size > 0 {
+        view.utilization = (view.leased + view.held) as f64 / view.size as f64;
+    }
+    Ok(Json(view))
+}
+
+pub async fn reload(State(state): State<ApiState>) -> ApiResult<ReloadView> {
+    let reload = state.reload.as_ref()
+        .ok_or_else(|| ApiError::Unavailable("configuration reloads are not enabled".to_string()))?;
+    match reload.reload().await {
+        Ok(summary) => Ok(Json(summary.into())),
+        Err(e) => Err(ApiError::Unavailable(e)),
+    }
+}
+
+pub async fn failover(State(state): State<ApiState>) -> ApiResult<FailoverView> {
+    let failover = state.server.failover().ok_or_else(no_failover)?;
+    Ok(Json(FailoverView::new(failover)))
+}
+
+//THE PARTNER IS KNOWN TO BE DOWN, ITS FREE ADDRESSES ARE OURS ONE MCLT FROM NOW
+pub async fn partner_down(State(state): State<ApiState>) -> ApiResult<FailoverView> {
+    let failover = state.server.failover().ok_or_else(no_failover)?;
+    failover.partner_down().map_err(ApiError::Conflict)?;
+    Ok(Json(FailoverView::new(failover)))
+}
+
+fn no_failover() -> ApiError {
+    ApiError::NotFound("failover is not configured".to_string())
+}
+
+pub async fn ha(State(state): State<ApiState>) -> ApiResult<HaView> {
+    let election = state.server.election()
+        .ok_or_else(|| ApiError::NotFound("high availability is not configured".to_string()))?;
+    Ok(Json(HaView::new(election)))
+}
+
+pub async fn schema() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "application/schema+json")], SCHEMA)
+}
+
+pub async fn not_found() -> ApiError {
+    ApiError::NotFound("no such endpoint".to_string())
+}
+
+async fn address_views(state: &ApiState) -> Result<Vec<AddressView>, ApiError> {
+    let store = state.server.store();
+    let now = state.server.now();
+    let mut reserved_for: HashMap<Ipv4Addr, String> = store.reservations().await?.into_iter()
+        .map(|reservation| (reservation.ip_address, reservation.client_id))
+        .collect();
+    Ok(store.addresses().await?.iter()
+        .map(|record| AddressView::new(record, reserved_for.remove(&record.ip_address), now))
+        .collect())
+}
+
+async fn find_address(state: &ApiState, ip: Ipv4Addr) -> Result<AddressRecord, ApiError> {
+    state.server.store().addresses().await?.into_iter()
+        .find(|record| record.ip_address == ip)
+        .ok_or_else(|| not_in_table(ip))
+}
+
+//A SEARCH WITH DOTS IS PART OF AN ADDRESS, ANYTHING ELSE MAY ALSO BE PART OF A HARDWARE ADDRESS
+fn matches_search(view: &AddressView, search: &str) -> bool {
+    if view.ip_address.to_string().contains(search) {
+        return true;
+    }
+    let hex: String = search.chars().filter(|c| *c != ':' && *c != '-').collect();
+    !search.contains('.') && !hex.is_empty()
+        && view.client_id.as_ref().is_some_and(|client_id| client_id.contains(&hex))
+}
+
+fn not_in_table(ip: Ipv4Addr) -> ApiError {
+    ApiError::NotFound(format!("{} is not an address of the pool", ip))
+}
+
+fn parse_ip(ip: &str) -> Result<Ipv4Addr, ApiError> {
+    ip.parse().map_err(|_| ApiError::BadRequest(format!("{:?} is not an IPv4 address", ip)))
+}
+
+//ACCEPTS A MAC ADDRESS (02:00:00:00:00:0a OR 02-00-...) OR THE STORED HEX FORM
+//AND PADS IT THE WAY THE SERVER PADS chaddr
+fn parse_client_id(client_id: &str) -> Result<String, ApiError> {
+    let hex: String = client_id.chars().filter(|c| *c != ':' && *c != '-').collect::<String>().to_lowercase();
+    if hex.is_empty() || hex.len() > CLIENT_ID_LENGTH || !hex.len().is_multiple_of(2) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(ApiError::BadRequest(format!("{:?} is not a client id or hardware address", client_id)));
+    }
+    Ok(format!("{:0<width$}", hex, width = CLIENT_ID_LENGTH))
+}
@@ -0,0 +1,134 @@
+//HTTP/JSON MANAGEMENT API, SERVED ON ITS OWN PORT NEXT TO THE DHCP LISTENERS
+//EVERY REQUEST NEEDS THE BEARER TOKEN, RESPONSE BODIES ARE DESCRIBED BY schema.json
+mod handlers;
+pub mod views;
+
+use std::fmt;
+use std::sync::Arc;
+
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post, put};
+use axum::{Json, Router};
+use tokio::net::TcpListener;
+
+use crate::server::Server;
+use crate::server_config::reload::ReloadHandle;
+use crate::set_up::create_db_tables::CustomError;
+use views::ErrorView;
+
+//JSON SCHEMA OF EVERY RESPONSE BODY, ALSO SERVED AT /api/schema
+pub const SCHEMA: &str = include_str!("schema.json");
+
+#[derive(Clone)]
+pub struct ApiState {
+    server: Arc<Server>,
+    reload: Option<ReloadHandle>,
+    token: Arc<str>,
+}
+
+impl ApiState {
+    pub fn new(server: Arc<Server>, token: impl Into<String>) -> Self {
+        ApiState { server, reload: None, token: token.into().into() }
+    }
+
+    //WITHOUT ONE /api/reload ANSWERS 503
+    pub fn reload_handle(mut self, reload: ReloadHandle) -> Self {
+        self.reload = Some(reload);
+        self
+    }
+}
+
+pub fn router(state: ApiState) -> Router {
+    Router::new()
+        .route("/api/leases", get(handlers::list_leases))
+        .route("/api/leases/{ip}", get(handlers::get_lease))
+        .route("/api/leases/{ip}/release", post(handlers::release_lease))
+        .route("/api/leases/{ip}/revoke", post(handlers::revoke_lease))
+        .route("/api/history", get(handlers::history))
+        .route("/api/reservations", get(handlers::list_reservations))
+        .route("/api/reservations/{client_id}", put(handlers::reserve).delete(handlers::unreserve))
+        .route("/api/addresses", get(handlers::list_addresses))
+        .route("/api/addresses/{ip}/block", put(handlers::block).delete(handlers::unblock))
+        .route("/api/pool", get(handlers::pool))
+        .route("/api/reload", post(handlers::reload))
+        .route("/api/failover", get(handlers::failover))
+        .route("/api/failover/partner-down", post(handlers::partner_down))
+        .route("/api/ha", get(handlers::ha))
+        .route("/api/schema", get(handlers::schema))
+        .fallback(handlers::not_found)
+        .layer(middleware::from_fn_with_state(state.clone(), authorize))
+        .with_state(state)
+}
+
+pub async fn serve(listener: TcpListener, state: ApiState) -> std::io::Result<()> {
+    axum::serve(listener, router(state)).await
+}
+
+async fn authorize(State(state): State<ApiState>, request: Request, next: Next) -> Response {
+    let token = request.headers().get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match token {
+        Some(token) if same_token(token.as_bytes(), state.token.as_bytes()) => next.run(request).await,
+        _ => ApiError::Unauthorized.into_response(),
+    }
+}
+
+//COMPARE WITHOUT STOPPING AT THE FIRST DIFFERENCE SO THE TIMING GIVES NOTHING AWAY
+fn same_token(given: &[u8], expected: &[u8]) -> bool {
+    given.len() == expected.len() && given.iter().zip(expected).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+//WHY A REQUEST FAILED, SENT AS {"error": ...} WITH THE MATCHING STATUS
+#[derive(Debug)]
+pub enum ApiError {
+    BadRequest(String),
+    Unauthorized,
+    NotFound(String),
+    Conflict(String),
+    Unavailable(String),
+    Store(CustomError),
+}
+
+impl From<CustomError> for ApiError {
+    fn from(err: CustomError) -> Self {
+        match err {
+            CustomError::Conflict(e) => ApiError::Conflict(e),
+            e => ApiError::Store(e),
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ApiError::BadRequest(e) | ApiError::NotFound(e) | ApiError::Conflict(e) | ApiError::Unavailable(e) => write!(f, "{}", e),
+            ApiError::Unauthorized => write!(f, "missing or wrong bearer token"),
+            ApiError::Store(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::Store(e) => {
+                tracing::error!(error = %e, "Lease store error in API request");
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        };
+        let mut response = (status, Json(ErrorView { error: self.to_string() })).into_response();
+        if status == StatusCode::UNAUTHORIZED {
+            response.headers_mut().insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+        }
+        response
+    }
+}
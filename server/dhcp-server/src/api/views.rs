@@ -0,0 +1,186 @@
+//JSON BODIES THE API SENDS AND RECEIVES, KEEP IN LINE WITH schema.json
+//TIMESTAMPS ARE RFC 3339 STRINGS IN UTC
+use std::net::Ipv4Addr;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::failover::{Failover, FailoverState};
+use crate::ha::{Election, HaRole};
+use crate::server_config::server_config::{FailoverMode, FailoverRole};
+use crate::set_up::create_db_tables::ReconcileSummary;
+pub use crate::store::AddressState;
+use crate::store::{AddressRecord, HistoryEntry, Reservation};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ErrorView {
+    pub error: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AddressView {
+    pub ip_address: Ipv4Addr,
+    pub state: AddressState,
+    pub client_id: Option<String>,
+    //NAME THE CLIENT GOES BY AFTER THE HOST NAME POLICY
+    pub hostname: Option<String>,
+    pub lease_start: Option<String>,
+    pub lease_end: Option<String>,
+    pub blocked: bool,
+    pub retired: bool,
+    //CLIENT THE ADDRESS IS RESERVED FOR
+    pub reserved_for: Option<String>,
+}
+
+impl AddressView {
+    pub fn new(record: &AddressRecord, reserved_for: Option<String>, now: DateTime<Utc>) -> Self {
+        let state = AddressState::of(record, now);
+        AddressView {
+            ip_address: record.ip_address,
+            state,
+            //AN EXPIRED LEASE STILL NAMES ITS CLIENT UNTIL THE NEXT CLEANUP, THAT IS NOT WHO HOLDS IT
+            client_id: record.client_id.clone().filter(|_| state == AddressState::Leased),
+            hostname: record.name.hostname.clone().filter(|_| state == AddressState::Leased),
+            lease_start: record.lease_start.map(timestamp),
+            lease_end: record.lease_end.map(timestamp),
+            blocked: record.blocked,
+            retired: record.retired,
+            reserved_for,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoryView {
+    pub ip_address: Ipv4Addr,
+    pub client_id: String,
+    pub lease_start: Option<String>,
+    pub lease_end: Option<String>,
+    pub server_response: String,
+    pub lease_type: String,
+}
+
+impl From<HistoryEntry> for HistoryView {
+    fn from(entry: HistoryEntry) -> Self {
+        HistoryView {
+            ip_address: entry.ip_address,
+            client_id: entry.client_id,
+            lease_start: entry.lease_start.map(timestamp),
+            lease_end: entry.lease_end.map(timestamp),
+            server_response: entry.server_response.as_str().to_string(),
+            lease_type: entry.lease_type.as_str().to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReservationView {
+    pub client_id: String,
+    pub ip_address: Ipv4Addr,
+}
+
+impl From<Reservation> for ReservationView {
+    fn from(reservation: Reservation) -> Self {
+        ReservationView { client_id: reservation.client_id, ip_address: reservation.ip_address }
+    }
+}
+
+//BODY OF PUT /api/reservations/{client_id}
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReservationRequest {
+    pub ip_address: Ipv4Addr,
+}
+
+//COUNTS OVER THE ADDRESSES STILL IN THE POOL, leased + held + blocked + free = size
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PoolView {
+    pub range_start: Ipv4Addr,
+    pub range_end: Ipv4Addr,
+    pub size: usize,
+    pub leased: usize,
+    pub held: usize,
+    pub blocked: usize,
+    pub free: usize,
+    //RESERVATIONS, WHATEVER STATE THEIR ADDRESS IS IN
+    pub reserved: usize,
+    //OUT OF THE POOL BUT STILL IN THE TABLE, NOT PART OF size
+    pub retired: usize,
+    //SHARE OF THE POOL LEASED OR HELD, 0 TO 1
+    pub utilization: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReloadView {
+    pub added: usize,
+    pub removed: usize,
+    pub retired: usize,
+    pub reinstated: usize,
+    pub restricted: usize,
+}
+
+impl From<ReconcileSummary> for ReloadView {
+    fn from(summary: ReconcileSummary) -> Self {
+        ReloadView {
+            added: summary.added,
+            removed: summary.removed,
+            retired: summary.retired,
+            reinstated: summary.reinstated,
+            restricted: summary.restricted,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FailoverView {
+    pub role: FailoverRole,
+    pub mode: FailoverMode,
+    pub state: FailoverState,
+    pub since: String,
+    pub peer: String,
+    pub split: u16,
+    pub mclt: u64,
+    pub pending_updates: usize,
+}
+
+impl FailoverView {
+    pub fn new(failover: &Failover) -> Self {
+        let (state, since) = failover.state();
+        let config = failover.config();
+        FailoverView {
+            role: config.role,
+            mode: config.mode,
+            state,
+            since: timestamp(since),
+            peer: config.peer.to_string(),
+            split: config.split,
+            mclt: config.mclt.as_secs(),
+            pending_updates: failover.pending_updates(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HaView {
+    pub node: String,
+    pub role: HaRole,
+    pub since: String,
+    pub term: Option<i64>,
+    pub leader: Option<String>,
+}
+
+impl HaView {
+    pub fn new(election: &Election) -> Self {
+        let (role, since) = election.role();
+        HaView {
+            node: election.config().node.clone(),
+            role,
+            since: timestamp(since),
+            term: election.term(),
+            leader: election.leader(),
+        }
+    }
+}
+
+pub fn timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
@@ -0,0 +1,43 @@
+use chrono::{DateTime, Duration, Utc};
+use std::sync::Mutex;
+
+//SOURCE OF THE CURRENT TIME FOR EVERYTHING LEASE RELATED
+//ALL LEASE INSTANTS ARE UTC SO HOST OR CONTAINER TIME ZONES DO NOT MATTER
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+//WALL CLOCK USED BY THE RUNNING SERVER
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+//CLOCK THAT ONLY MOVES WHEN TOLD TO, FOR DETERMINISTIC TESTS
+pub struct FakeClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl FakeClock {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        FakeClock { now: Mutex::new(start) }
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.lock().unwrap() = now;
+    }
+
+    pub fn advance(&self, by: Duration) {
+        let mut now = self.now.lock().unwrap();
+        *now += by;
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
+}
@@ -0,0 +1,334 @@
+//DNS UPDATE MESSAGES (RFC 2136) ON THE WIRE
+//THE FOUR SECTIONS OF AN UPDATE ARE ZONE, PREREQUISITE, UPDATE AND ADDITIONAL, IN A QUERY THEY ARE QUESTION, ANSWER, AUTHORITY AND ADDITIONAL
+//NAMES ARE WRITTEN WITHOUT COMPRESSION AND READ WITH IT
+use std::net::Ipv4Addr;
+
+pub const TYPE_A: u16 = 1;
+pub const TYPE_SOA: u16 = 6;
+pub const TYPE_PTR: u16 = 12;
+pub const TYPE_AAAA: u16 = 28;
+pub const TYPE_DHCID: u16 = 49;
+pub const TYPE_TSIG: u16 = 250;
+pub const TYPE_ANY: u16 = 255;
+
+pub const CLASS_IN: u16 = 1;
+//IN A PREREQUISITE: RRSET OR NAME DOES NOT EXIST, IN AN UPDATE: DELETE ONE RECORD
+pub const CLASS_NONE: u16 = 254;
+//IN A PREREQUISITE: RRSET OR NAME EXISTS, IN AN UPDATE: DELETE THE RRSET
+pub const CLASS_ANY: u16 = 255;
+
+pub const OPCODE_UPDATE: u8 = 5;
+
+pub const NOERROR: u8 = 0;
+pub const FORMERR: u8 = 1;
+pub const SERVFAIL: u8 = 2;
+pub const NXDOMAIN: u8 = 3;
+pub const NOTIMP: u8 = 4;
+pub const REFUSED: u8 = 5;
+pub const YXDOMAIN: u8 = 6;
+pub const YXRRSET: u8 = 7;
+pub const NXRRSET: u8 = 8;
+pub const NOTAUTH: u8 = 9;
+pub const NOTZONE: u8 = 10;
+
+const HEADER_LENGTH: usize = 12;
+const MAX_NAME_LENGTH: usize = 255;
+const MAX_LABEL_LENGTH: usize = 63;
+//A NAME POINTING BACK MORE OFTEN THAN THIS IS A LOOP
+const MAX_POINTERS: usize = 64;
+
+pub fn rcode_name(rcode: u8) -> &'static str {
+    match rcode {
+        NOERROR => "NOERROR",
+        FORMERR => "FORMERR",
+        SERVFAIL => "SERVFAIL",
+        NXDOMAIN => "NXDOMAIN",
+        NOTIMP => "NOTIMP",
+        REFUSED => "REFUSED",
+        YXDOMAIN => "YXDOMAIN",
+        YXRRSET => "YXRRSET",
+        NXRRSET => "NXRRSET",
+        NOTAUTH => "NOTAUTH",
+        NOTZONE => "NOTZONE",
+        _ => "unknown rcode",
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    //LOWERCASE, WITHOUT THE TRAILING DOT
+    pub name: String,
+    pub rtype: u16,
+    pub class: u16,
+    pub ttl: u32,
+    pub data: Vec<u8>,
+}
+
+impl Record {
+    pub fn new(name: &str, rtype: u16, class: u16, ttl: u32, data: Vec<u8>) -> Self {
+        Record { name: name.to_ascii_lowercase(), rtype, class, ttl, data }
+    }
+
+    pub fn a(name: &str, ttl: u32, ip: Ipv4Addr) -> Self {
+        Record::new(name, TYPE_A, CLASS_IN, ttl, ip.octets().to_vec())
+    }
+
+    pub fn ptr(name: &str, ttl: u32, target: &str) -> Self {
+        Record::new(name, TYPE_PTR, CLASS_IN, ttl, encode_name(target))
+    }
+
+    //PREREQUISITE THAT NOTHING AT ALL IS STORED UNDER name
+    pub fn name_not_in_use(name: &str) -> Self {
+        Record::new(name, TYPE_ANY, CLASS_NONE, 0, Vec::new())
+    }
+
+    //PREREQUISITE THAT NO rtype RECORD IS STORED UNDER name
+    pub fn rrset_absent(name: &str, rtype: u16) -> Self {
+        Record::new(name, rtype, CLASS_NONE, 0, Vec::new())
+    }
+
+    //PREREQUISITE THAT THE rtype RECORDS UNDER name ARE EXACTLY THIS ONE
+    pub fn rrset_is(name: &str, rtype: u16, data: Vec<u8>) -> Self {
+        Record::new(name, rtype, CLASS_IN, 0, data)
+    }
+
+    //UPDATE DELETING EVERY rtype RECORD UNDER name
+    pub fn delete_rrset(name: &str, rtype: u16) -> Self {
+        Record::new(name, rtype, CLASS_ANY, 0, Vec::new())
+    }
+
+    //UPDATE DELETING THE ONE RECORD WITH THIS DATA
+    pub fn delete_record(name: &str, rtype: u16, data: Vec<u8>) -> Self {
+        Record::new(name, rtype, CLASS_NONE, 0, data)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub id: u16,
+    pub response: bool,
+    pub opcode: u8,
+    pub rcode: u8,
+    //THE ZONE SECTION OF AN UPDATE HOLDS ONE ENTRY: THE ZONE NAME, TYPE SOA, CLASS IN
+    pub zone: Option<String>,
+    pub prerequisites: Vec<Record>,
+    pub updates: Vec<Record>,
+    pub additional: Vec<Record>,
+}
+
+impl Message {
+    pub fn update(id: u16, zone: &str) -> Self {
+        Message {
+            id,
+            response: false,
+            opcode: OPCODE_UPDATE,
+            rcode: NOERROR,
+            zone: Some(zone.to_ascii_lowercase()),
+            prerequisites: Vec::new(),
+            updates: Vec::new(),
+            additional: Vec::new(),
+        }
+    }
+
+    //THE ANSWER TO request, WITH ITS ZONE SECTION ECHOED
+    pub fn reply(request: &Message, rcode: u8) -> Self {
+        Message {
+            id: request.id,
+            response: true,
+            opcode: request.opcode,
+            rcode,
+            zone: request.zone.clone(),
+            prerequisites: Vec::new(),
+            updates: Vec::new(),
+            additional: Vec::new(),
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut wire = Vec::with_capacity(512);
+        wire.extend_from_slice(&self.id.to_be_bytes());
+        let flags = ((self.response as u16) << 15) | (((self.opcode & 0x0f) as u16) << 11) | (self.rcode & 0x0f) as u16;
+        wire.extend_from_slice(&flags.to_be_bytes());
+        for count in [self.zone.is_some() as usize, self.prerequisites.len(), self.updates.len(), self.additional.len()] {
+            wire.extend_from_slice(&(count as u16).to_be_bytes());
+        }
+        if let Some(zone) = &self.zone {
+            wire.extend_from_slice(&encode_name(zone));
+            wire.extend_from_slice(&TYPE_SOA.to_be_bytes());
+            wire.extend_from_slice(&CLASS_IN.to_be_bytes());
+        }
+        for record in self.prerequisites.iter().chain(&self.updates).chain(&self.additional) {
+            encode_record(&mut wire, record);
+        }
+        wire
+    }
+
+    pub fn decode(wire: &[u8]) -> Result<Message, String> {
+        let mut reader = Reader { wire, position: HEADER_LENGTH };
+        let header = reader.header()?;
+        let mut zone = None;
+        for _ in 0..header.counts[0] {
+            let name = reader.name()?;
+            reader.take(4)?;
+            zone.get_or_insert(name);
+        }
+        let mut sections = [Vec::new(), Vec::new(), Vec::new()];
+        for (section, count) in sections.iter_mut().zip(&header.counts[1..]) {
+            for _ in 0..*count {
+                section.push(reader.record()?);
+            }
+        }
+        let [prerequisites, updates, additional] = sections;
+        Ok(Message {
+            id: header.id,
+            response: header.flags & 0x8000 != 0,
+            opcode: ((header.flags >> 11) & 0x0f) as u8,
+            rcode: (header.flags & 0x0f) as u8,
+            zone,
+            prerequisites,
+            updates,
+            additional,
+        })
+    }
+}
+
+//WHERE THE LAST RECORD OF THE MESSAGE STARTS, IF IT IS A TSIG RECORD, AND THAT RECORD
+pub fn find_tsig(wire: &[u8]) -> Result<Option<(usize, Record)>, String> {
+    let mut reader = Reader { wire, position: HEADER_LENGTH };
+    let header = reader.header()?;
+    for _ in 0..header.counts[0] {
+        reader.name()?;
+        reader.take(4)?;
+    }
+    let records: usize = header.counts[1..].iter().sum();
+    let mut last = None;
+    for _ in 0..records {
+        let start = reader.position;
+        last = Some((start, reader.record()?));
+    }
+    Ok(last.filter(|(_, record)| record.rtype == TYPE_TSIG && header.counts[3] > 0))
+}
+
+//NAME AS LABELS, LOWERCASE AS DNSSEC AND TSIG CANONICAL FORM WANTS IT
+pub fn encode_name(name: &str) -> Vec<u8> {
+    let mut wire = Vec::with_capacity(name.len() + 2);
+    for label in name.trim_end_matches('.').split('.').filter(|label| !label.is_empty()) {
+        wire.push(label.len() as u8);
+        wire.extend(label.bytes().map(|b| b.to_ascii_lowercase()));
+    }
+    wire.push(0);
+    wire
+}
+
+//A NAME THAT FITS THE WIRE FORMAT: LABELS OF 1 TO 63 BYTES, 255 BYTES IN ALL
+pub fn valid_name(name: &str) -> bool {
+    let name = name.trim_end_matches('.');
+    !name.is_empty()
+        && name.len() + 2 <= MAX_NAME_LENGTH
+        && name.split('.').all(|label| !label.is_empty() && label.len() <= MAX_LABEL_LENGTH && label.is_ascii())
+}
+
+//WHERE PTR RECORDS FOR ip LIVE
+pub fn reverse_name(ip: Ipv4Addr) -> String {
+    let [a, b, c, d] = ip.octets();
+    format!("{}.{}.{}.{}.in-addr.arpa", d, c, b, a)
+}
+
+fn encode_record(wire: &mut Vec<u8>, record: &Record) {
+    wire.extend_from_slice(&encode_name(&record.name));
+    wire.extend_from_slice(&record.rtype.to_be_bytes());
+    wire.extend_from_slice(&record.class.to_be_bytes());
+    wire.extend_from_slice(&record.ttl.to_be_bytes());
+    wire.extend_from_slice(&(record.data.len() as u16).to_be_bytes());
+    wire.extend_from_slice(&record.data);
+}
+
+struct Header {
+    id: u16,
+    flags: u16,
+    counts: [usize; 4],
+}
+
+struct Reader<'a> {
+    wire: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn header(&mut self) -> Result<Header, String> {
+        if self.wire.len() < HEADER_LENGTH {
+            return Err(format!("message of {} bytes is shorter than a header", self.wire.len()));
+        }
+        let word = |index: usize| u16::from_be_bytes([self.wire[index * 2], self.wire[index * 2 + 1]]);
+        Ok(Header {
+            id: word(0),
+            flags: word(1),
+            counts: [word(2) as usize, word(3) as usize, word(4) as usize, word(5) as usize],
+        })
+    }
+
+    fn take(&mut self, length: usize) -> Result<&'a [u8], String> {
+        let bytes = self.wire.get(self.position..self.position + length).ok_or("message ends inside a record")?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        let (name, end) = read_name(self.wire, self.position)?;
+        self.position = end;
+        Ok(name)
+    }
+
+    fn record(&mut self) -> Result<Record, String> {
+        let name = self.name()?;
+        let rtype = self.u16()?;
+        let class = self.u16()?;
+        let ttl = self.u32()?;
+        let length = self.u16()? as usize;
+        let data = self.take(length)?.to_vec();
+        Ok(Record { name, rtype, class, ttl, data })
+    }
+}
+
+//THE NAME AT start AND WHERE THE BYTES AFTER IT BEGIN, FOLLOWING COMPRESSION POINTERS
+pub fn read_name(wire: &[u8], start: usize) -> Result<(String, usize), String> {
+    let mut labels = Vec::new();
+    let mut position = start;
+    let mut end = None;
+    let mut pointers = 0;
+    loop {
+        let length = *wire.get(position).ok_or("message ends inside a name")? as usize;
+        match length {
+            0 => {
+                position += 1;
+                break;
+            }
+            _ if length & 0xc0 == 0xc0 => {
+                let low = *wire.get(position + 1).ok_or("message ends inside a name")? as usize;
+                end.get_or_insert(position + 2);
+                pointers += 1;
+                if pointers > MAX_POINTERS {
+                    return Err("name compression loops".to_string());
+                }
+                position = ((length & 0x3f) << 8) | low;
+            }
+            _ if length > MAX_LABEL_LENGTH => return Err(format!("label of {} bytes", length)),
+            _ => {
+                let label = wire.get(position + 1..position + 1 + length).ok_or("message ends inside a name")?;
+                labels.push(String::from_utf8_lossy(label).to_ascii_lowercase());
+                position += 1 + length;
+            }
+        }
+    }
+    Ok((labels.join("."), end.unwrap_or(position)))
+}
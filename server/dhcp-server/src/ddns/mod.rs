@@ -0,0 +1,263 @@
+//DYNAMIC DNS UPDATES (RFC 2136) FOR LEASED ADDRESSES, CALLED BY ddns HOOKS
+//A GRANTED OR RENEWED LEASE ADDS <host name>.<zone> A AND DHCID RECORDS AND THE PTR RECORD, A RELEASED, DECLINED
+//OR EXPIRED ONE REMOVES THEM. DHCID RECORDS (RFC 4701) MARK WHICH CLIENT A NAME BELONGS TO, SO A NAME SOMEBODY
+//ELSE HOLDS IS NEVER TAKEN OVER OR DELETED (RFC 4703)
+//A CLIENT THAT UPDATES ITS OWN A RECORD THROUGH OPTION 81 ONLY GETS ITS PTR RECORD, ONE THAT ASKED FOR NO UPDATES NOTHING
+//A CLIENT WITHOUT A HOST NAME GETS NOTHING EITHER, UNLESS generated_names GIVES IT ONE
+//WHEN A RENEWAL CHANGES THE HOST NAME THE RECORDS OF THE OLD NAME ARE REMOVED BEFORE THE NEW ONES ARE ADDED
+pub mod message;
+pub mod tsig;
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sha2::{Digest, Sha256};
+use tokio::net::UdpSocket;
+use tracing::{debug, info, warn};
+
+use crate::hooks::{LeaseEvent, LeaseEventKind};
+use crate::store::DnsUpdate;
+use crate::server_config::server_config::DdnsConfig;
+use crate::utility::options::host_name;
+use message::{encode_name, rcode_name, reverse_name, Message, Record, NOERROR, NXRRSET, TYPE_A, TYPE_AAAA, TYPE_DHCID,
+              TYPE_PTR, YXDOMAIN, YXRRSET};
+
+//DHCID IDENTIFIER TYPE FOR htype AND chaddr, AND DIGEST TYPE SHA-256
+const IDENTIFIER_CHADDR: u16 = 0x0000;
+const DIGEST_SHA256: u8 = 1;
+const HTYPE_ETHERNET: u8 = 1;
+const ETHERNET_LENGTH: usize = 6;
+//LARGEST ANSWER OVER UDP WITH EDNS, UPDATE ANSWERS ARE FAR SMALLER
+const MAX_ANSWER: usize = 4096;
+
+//DHCID RDATA (RFC 4701): IDENTIFIER TYPE, DIGEST TYPE AND SHA-256 OF THE IDENTIFIER FOLLOWED BY THE NAME ON THE WIRE
+pub fn dhcid(htype: u8, chaddr: &[u8], fqdn: &str) -> Vec<u8> {
+    let mut digest = Sha256::new();
+    digest.update([htype]);
+    digest.update(chaddr);
+    digest.update(encode_name(fqdn));
+    let mut data = IDENTIFIER_CHADDR.to_be_bytes().to_vec();
+    data.push(DIGEST_SHA256);
+    data.extend_from_slice(&digest.finalize());
+    data
+}
+
+//THE NAME A CLIENT IS REGISTERED UNDER, ITS OWN HOST NAME WHEN IT SENT ONE
+//NONE FOR A CLIENT WITHOUT ONE UNLESS generated_names IS SET, A MADE-UP NAME IS NOT PUBLISHED BY DEFAULT
+pub fn fqdn(config: &DdnsConfig, event: &LeaseEvent) -> Option<String> {
+    name_in_zone(config, &event.client_id, event.hostname.as_deref())
+}
+
+fn name_in_zone(config: &DdnsConfig, client_id: &str, hostname: Option<&str>) -> Option<String> {
+    let hostname = match hostname {
+        Some(hostname) => hostname.to_string(),
+        None if config.generated_names => host_name(client_id),
+        None => return None,
+    };
+    Some(format!("{}.{}", hostname, config.zone))
+}
+
+pub async fn update(config: &DdnsConfig, event: &LeaseEvent) -> Result<(), String> {
+    if event.renamed {
+        remove_previous(config, event).await?;
+    }
+    let forward = match event.dns_update {
+        DnsUpdate::Both => true,
+        DnsUpdate::Ptr => false,
+        DnsUpdate::Neither => {
+            debug!(address = %event.ip_address, "Client asked for no DNS updates");
+            return Ok(());
+        }
+    };
+    //WITHOUT A NAME THERE IS NO A RECORD, AND NOTHING FOR A PTR RECORD TO POINT TO
+    let Some(fqdn) = fqdn(config, event) else {
+        debug!(address = %event.ip_address, "Client has no host name, no DNS records");
+        return Ok(());
+    };
+    let chaddr = hardware_address(&event.client_id)?;
+    let dhcid = dhcid(HTYPE_ETHERNET, &chaddr, &fqdn);
+    let updater = Updater { config, forward };
+    match event.event {
+        LeaseEventKind::Granted | LeaseEventKind::Renewed => updater.add(&fqdn, event.ip_address, dhcid).await,
+        LeaseEventKind::Released | LeaseEventKind::Declined | LeaseEventKind::Expired => {
+            updater.remove(&fqdn, event.ip_address, dhcid).await
+        }
+    }
+}
+
+//THE OLD NAME'S A, DHCID AND PTR RECORDS, WHATEVER dns_update SAYS NOW
+//THE A RECORDS GO ONLY WHERE THE DHCID SAYS THEY ARE OURS, SO ONES THE CLIENT KEPT ITSELF STAY
+async fn remove_previous(config: &DdnsConfig, event: &LeaseEvent) -> Result<(), String> {
+    let Some(previous) = name_in_zone(config, &event.client_id, event.previous_hostname.as_deref()) else {
+        return Ok(());
+    };
+    if fqdn(config, event).as_ref() == Some(&previous) {
+        return Ok(());
+    }
+    let chaddr = hardware_address(&event.client_id)?;
+    let dhcid = dhcid(HTYPE_ETHERNET, &chaddr, &previous);
+    Updater { config, forward: true }.remove(&previous, event.ip_address, dhcid).await
+}
+
+//THE CLIENT ID IS chaddr IN HEX, ITS FIRST SIX BYTES ARE THE ETHERNET ADDRESS
+fn hardware_address(client_id: &str) -> Result<Vec<u8>, String> {
+    (0..ETHERNET_LENGTH)
+        .map(|index| client_id.get(index * 2..index * 2 + 2).and_then(|hex| u8::from_str_radix(hex, 16).ok()))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or(format!("client id {:?} does not start with a hardware address", client_id))
+}
+
+struct Updater<'a> {
+    config: &'a DdnsConfig,
+    //THE A AND DHCID RECORDS ARE OURS TO KEEP, OTHERWISE ONLY THE PTR RECORD IS
+    forward: bool,
+}
+
+impl Updater<'_> {
+    async fn add(&self, fqdn: &str, ip: Ipv4Addr, dhcid: Vec<u8>) -> Result<(), String> {
+        if self.forward && !self.add_forward(fqdn, ip, dhcid).await? {
+            return Ok(());
+        }
+        if let Some(reverse_zone) = self.reverse_zone(ip) {
+            let reverse = reverse_name(ip);
+            let mut pointer = Message::update(0, reverse_zone);
+            pointer.updates.push(Record::delete_rrset(&reverse, TYPE_PTR));
+            pointer.updates.push(Record::ptr(&reverse, self.config.ttl, fqdn));
+            match self.exchange(pointer).await? {
+                NOERROR => {}
+                rcode => return Err(format!("adding PTR {} failed with {}", reverse, rcode_name(rcode))),
+            }
+        }
+        info!(name = fqdn, address = %ip, forward = self.forward, "DNS records added");
+        Ok(())
+    }
+
+    //FALSE WHEN THE NAME BELONGS TO ANOTHER CLIENT, THEN NOTHING IS ADDED
+    async fn add_forward(&self, fqdn: &str, ip: Ipv4Addr, dhcid: Vec<u8>) -> Result<bool, String> {
+        let ttl = self.config.ttl;
+        let zone = &self.config.zone;
+        //A NAME NOBODY USES YET
+        let mut claim = Message::update(0, zone);
+        claim.prerequisites.push(Record::name_not_in_use(fqdn));
+        claim.updates.push(Record::a(fqdn, ttl, ip));
+        claim.updates.push(Record::new(fqdn, TYPE_DHCID, message::CLASS_IN, ttl, dhcid.clone()));
+        match self.exchange(claim).await? {
+            NOERROR => Ok(true),
+            //IN USE, WHICH IS FINE IF THE DHCID SAYS IT IS OURS
+            YXDOMAIN => {
+                let mut replace = Message::update(0, zone);
+                replace.prerequisites.push(Record::rrset_is(fqdn, TYPE_DHCID, dhcid));
+                replace.updates.push(Record::delete_rrset(fqdn, TYPE_A));
+                replace.updates.push(Record::a(fqdn, ttl, ip));
+                match self.exchange(replace).await? {
+                    NOERROR => Ok(true),
+                    NXRRSET => {
+                        //RETRYING WOULD NOT CHANGE WHO HOLDS THE NAME
+                        warn!(name = fqdn, address = %ip, "DNS name belongs to another client, records not added");
+                        Ok(false)
+                    }
+                    rcode => Err(format!("updating {} failed with {}", fqdn, rcode_name(rcode))),
+                }
+            }
+            rcode => Err(format!("adding {} failed with {}", fqdn, rcode_name(rcode))),
+        }
+    }
+
+    async fn remove(&self, fqdn: &str, ip: Ipv4Addr, dhcid: Vec<u8>) -> Result<(), String> {
+        if self.forward {
+            self.remove_forward(fqdn, ip, dhcid).await?;
+        }
+        if let Some(reverse_zone) = self.reverse_zone(ip) {
+            let reverse = reverse_name(ip);
+            let mut pointer = Message::update(0, reverse_zone);
+            pointer.updates.push(Record::delete_record(&reverse, TYPE_PTR, encode_name(fqdn)));
+            match self.exchange(pointer).await? {
+                NOERROR => {}
+                rcode => return Err(format!("removing PTR {} failed with {}", reverse, rcode_name(rcode))),
+            }
+        }
+        info!(name = fqdn, address = %ip, forward = self.forward, "DNS records removed");
+        Ok(())
+    }
+
+    async fn remove_forward(&self, fqdn: &str, ip: Ipv4Addr, dhcid: Vec<u8>) -> Result<(), String> {
+        let zone = &self.config.zone;
+        //ONLY THE ADDRESS OF THIS LEASE, AND ONLY WHILE THE NAME IS STILL OURS
+        let mut address = Message::update(0, zone);
+        address.prerequisites.push(Record::rrset_is(fqdn, TYPE_DHCID, dhcid.clone()));
+        address.updates.push(Record::delete_record(fqdn, TYPE_A, ip.octets().to_vec()));
+        match self.exchange(address).await? {
+            NOERROR => {
+                //THE DHCID GOES WITH THE LAST ADDRESS RECORD
+                let mut marker = Message::update(0, zone);
+                marker.prerequisites.push(Record::rrset_is(fqdn, TYPE_DHCID, dhcid));
+                marker.prerequisites.push(Record::rrset_absent(fqdn, TYPE_A));
+                marker.prerequisites.push(Record::rrset_absent(fqdn, TYPE_AAAA));
+                marker.updates.push(Record::delete_rrset(fqdn, TYPE_DHCID));
+                match self.exchange(marker).await? {
+                    NOERROR | NXRRSET | YXRRSET => {}
+                    rcode => return Err(format!("removing the DHCID of {} failed with {}", fqdn, rcode_name(rcode))),
+                }
+            }
+            NXRRSET => debug!(name = fqdn, "DNS name is not ours, address record left alone"),
+            rcode => return Err(format!("removing {} failed with {}", fqdn, rcode_name(rcode))),
+        }
+        Ok(())
+    }
+
+    //PTR RECORDS ARE ONLY WRITTEN FOR ADDRESSES INSIDE THE REVERSE ZONE
+    fn reverse_zone(&self, ip: Ipv4Addr) -> Option<&str> {
+        let zone = self.config.reverse_zone.as_deref()?;
+        let reverse = reverse_name(ip);
+        if reverse.ends_with(&format!(".{}", zone)) {
+            Some(zone)
+        } else {
+            warn!(address = %ip, zone, "Address is outside the reverse zone, PTR record skipped");
+            None
+        }
+    }
+
+    //SEND ONE UPDATE, SIGNED WHEN A KEY IS CONFIGURED, AND RETURN THE RCODE OF THE ANSWER
+    async fn exchange(&self, mut request: Message) -> Result<u8, String> {
+        request.id = random_id();
+        let mut wire = request.encode();
+        let mut request_mac = None;
+        if let Some(key) = &self.config.key {
+            let (signed, mac) = tsig::sign(&wire, key, unix_time(), None);
+            wire = signed;
+            request_mac = Some(mac);
+        }
+        let local: SocketAddr = if self.config.server.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }.parse().unwrap();
+        let socket = UdpSocket::bind(local).await.map_err(|e| e.to_string())?;
+        socket.connect(self.config.server).await.map_err(|e| e.to_string())?;
+        socket.send(&wire).await.map_err(|e| format!("sending to {} failed: {}", self.config.server, e))?;
+        let mut buffer = vec![0u8; MAX_ANSWER];
+        //THE HOOK'S TIMEOUT ENDS THE WAIT WHEN NO ANSWER COMES
+        loop {
+            let length = socket.recv(&mut buffer).await.map_err(|e| format!("{} did not answer: {}", self.config.server, e))?;
+            let answer = &buffer[..length];
+            let reply = match Message::decode(answer) {
+                Ok(reply) if reply.response && reply.id == request.id => reply,
+                _ => continue,
+            };
+            if let Some(key) = &self.config.key {
+                tsig::verify(answer, key, unix_time(), request_mac.as_deref())
+                    .map_err(|e| format!("answer from {} rejected: {}", self.config.server, e))?;
+            }
+            return Ok(reply.rcode);
+        }
+    }
+}
+
+//SO A STRAY ANSWER TO AN EARLIER UPDATE IS NOT TAKEN FOR THIS ONE
+fn random_id() -> u16 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_nanos()).unwrap_or(0));
+    hasher.finish() as u16
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0)
+}
@@ -0,0 +1,177 @@
+//TRANSACTION SIGNATURES (RFC 8945): AN HMAC OVER THE MESSAGE WITH A SECRET SHARED WITH THE DNS SERVER
+//THE SIGNATURE TRAVELS AS THE LAST RECORD OF THE ADDITIONAL SECTION, AN ANSWER IS SIGNED OVER THE REQUEST'S MAC TOO
+use hmac::{Hmac, KeyInit, Mac};
+use sha2::{Sha256, Sha512};
+
+use super::message::{encode_name, find_tsig, read_name, Record, CLASS_ANY, TYPE_TSIG};
+use crate::server_config::server_config::{TsigAlgorithm, TsigKey};
+
+//SECONDS THE TWO CLOCKS MAY DIFFER, THE VALUE RFC 8945 RECOMMENDS
+pub const FUDGE: u16 = 300;
+
+//ERRORS IN THE TSIG RECORD OF A NOTAUTH ANSWER
+pub const BADSIG: u16 = 16;
+pub const BADKEY: u16 = 17;
+pub const BADTIME: u16 = 18;
+
+//THE RDATA OF A TSIG RECORD
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tsig {
+    pub algorithm: String,
+    //SECONDS SINCE THE EPOCH, 48 BITS ON THE WIRE
+    pub time_signed: u64,
+    pub fudge: u16,
+    pub mac: Vec<u8>,
+    pub original_id: u16,
+    pub error: u16,
+    pub other: Vec<u8>,
+}
+
+impl Tsig {
+    fn encode(&self) -> Vec<u8> {
+        let mut data = encode_name(&self.algorithm);
+        data.extend_from_slice(&self.time_signed.to_be_bytes()[2..]);
+        data.extend_from_slice(&self.fudge.to_be_bytes());
+        data.extend_from_slice(&(self.mac.len() as u16).to_be_bytes());
+        data.extend_from_slice(&self.mac);
+        data.extend_from_slice(&self.original_id.to_be_bytes());
+        data.extend_from_slice(&self.error.to_be_bytes());
+        data.extend_from_slice(&(self.other.len() as u16).to_be_bytes());
+        data.extend_from_slice(&self.other);
+        data
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Tsig, String> {
+        let (algorithm, mut position) = read_name(data, 0)?;
+        let mut take = |length: usize| {
+            let bytes = data.get(position..position + length).ok_or("TSIG record is cut short");
+            position += length;
+            bytes
+        };
+        let mut time = [0u8; 8];
+        time[2..].copy_from_slice(take(6)?);
+        let fudge = u16::from_be_bytes(take(2)?.try_into().unwrap());
+        let mac_length = u16::from_be_bytes(take(2)?.try_into().unwrap()) as usize;
+        let mac = take(mac_length)?.to_vec();
+        let original_id = u16::from_be_bytes(take(2)?.try_into().unwrap());
+        let error = u16::from_be_bytes(take(2)?.try_into().unwrap());
+        let other_length = u16::from_be_bytes(take(2)?.try_into().unwrap()) as usize;
+        let other = take(other_length)?.to_vec();
+        Ok(Tsig { algorithm, time_signed: u64::from_be_bytes(time), fudge, mac, original_id, error, other })
+    }
+}
+
+pub fn error_name(error: u16) -> String {
+    match error {
+        BADSIG => "BADSIG".to_string(),
+        BADKEY => "BADKEY".to_string(),
+        BADTIME => "BADTIME".to_string(),
+        other => format!("TSIG error {}", other),
+    }
+}
+
+//wire WITH A TSIG RECORD ADDED, AND THE MAC THE ANSWER WILL BE SIGNED OVER
+//request_mac IS THE MAC OF THE REQUEST WHEN SIGNING AN ANSWER
+pub fn sign(wire: &[u8], key: &TsigKey, time_signed: u64, request_mac: Option<&[u8]>) -> (Vec<u8>, Vec<u8>) {
+    let mut tsig = Tsig {
+        algorithm: key.algorithm.name().to_string(),
+        time_signed,
+        fudge: FUDGE,
+        mac: Vec::new(),
+        original_id: u16::from_be_bytes([wire[0], wire[1]]),
+        error: 0,
+        other: Vec::new(),
+    };
+    tsig.mac = mac(key, request_mac, wire, &tsig);
+    (append(wire, key, &tsig), tsig.mac)
+}
+
+//ANSWER TO A REQUEST WHOSE SIGNATURE DID NOT CHECK OUT: THE ERROR IN A TSIG RECORD WITHOUT A MAC
+pub fn sign_error(wire: &[u8], key: &TsigKey, time_signed: u64, error: u16) -> Vec<u8> {
+    let tsig = Tsig {
+        algorithm: key.algorithm.name().to_string(),
+        time_signed,
+        fudge: FUDGE,
+        mac: Vec::new(),
+        original_id: u16::from_be_bytes([wire[0], wire[1]]),
+        error,
+        other: Vec::new(),
+    };
+    append(wire, key, &tsig)
+}
+
+//CHECK THE SIGNATURE OF wire AND RETURN ITS MAC
+//request_mac IS THE MAC OF THE REQUEST WHEN CHECKING AN ANSWER
+pub fn verify(wire: &[u8], key: &TsigKey, now: u64, request_mac: Option<&[u8]>) -> Result<Vec<u8>, String> {
+    let (start, record) = find_tsig(wire)?.ok_or("the message is not signed")?;
+    if record.name != key.name.to_ascii_lowercase() {
+        return Err(format!("signed with key {:?} instead of {:?}", record.name, key.name));
+    }
+    let tsig = Tsig::decode(&record.data)?;
+    if tsig.error != 0 {
+        return Err(format!("the signature was refused with {}", error_name(tsig.error)));
+    }
+    if tsig.algorithm != key.algorithm.name() {
+        return Err(format!("signed with {} instead of {}", tsig.algorithm, key.algorithm.name()));
+    }
+    //THE MAC COVERS THE MESSAGE AS IT WAS BEFORE THE TSIG RECORD WAS ADDED
+    let mut unsigned = wire[..start].to_vec();
+    unsigned[..2].copy_from_slice(&tsig.original_id.to_be_bytes());
+    let additional = u16::from_be_bytes([unsigned[10], unsigned[11]]) - 1;
+    unsigned[10..12].copy_from_slice(&additional.to_be_bytes());
+    let expected = mac(key, request_mac, &unsigned, &tsig);
+    if !same(&expected, &tsig.mac) {
+        return Err("the signature does not match, is the secret right?".to_string());
+    }
+    if now.abs_diff(tsig.time_signed) > tsig.fudge as u64 {
+        return Err(format!("signed {} seconds away from our clock", now.abs_diff(tsig.time_signed)));
+    }
+    Ok(tsig.mac)
+}
+
+fn append(wire: &[u8], key: &TsigKey, tsig: &Tsig) -> Vec<u8> {
+    let record = Record::new(&key.name, TYPE_TSIG, CLASS_ANY, 0, tsig.encode());
+    let mut signed = wire.to_vec();
+    signed.extend_from_slice(&encode_name(&record.name));
+    signed.extend_from_slice(&record.rtype.to_be_bytes());
+    signed.extend_from_slice(&record.class.to_be_bytes());
+    signed.extend_from_slice(&record.ttl.to_be_bytes());
+    signed.extend_from_slice(&(record.data.len() as u16).to_be_bytes());
+    signed.extend_from_slice(&record.data);
+    let additional = u16::from_be_bytes([signed[10], signed[11]]) + 1;
+    signed[10..12].copy_from_slice(&additional.to_be_bytes());
+    signed
+}
+
+fn mac(key: &TsigKey, request_mac: Option<&[u8]>, message: &[u8], tsig: &Tsig) -> Vec<u8> {
+    let mut input = Vec::with_capacity(message.len() + 128);
+    if let Some(request_mac) = request_mac {
+        input.extend_from_slice(&(request_mac.len() as u16).to_be_bytes());
+        input.extend_from_slice(request_mac);
+    }
+    input.extend_from_slice(message);
+    input.extend_from_slice(&encode_name(&key.name));
+    input.extend_from_slice(&CLASS_ANY.to_be_bytes());
+    input.extend_from_slice(&0u32.to_be_bytes());
+    input.extend_from_slice(&encode_name(&tsig.algorithm));
+    input.extend_from_slice(&tsig.time_signed.to_be_bytes()[2..]);
+    input.extend_from_slice(&tsig.fudge.to_be_bytes());
+    input.extend_from_slice(&tsig.error.to_be_bytes());
+    input.extend_from_slice(&(tsig.other.len() as u16).to_be_bytes());
+    input.extend_from_slice(&tsig.other);
+    match key.algorithm {
+        TsigAlgorithm::HmacSha256 => hmac::<Hmac<Sha256>>(&key.secret, &input),
+        TsigAlgorithm::HmacSha512 => hmac::<Hmac<Sha512>>(&key.secret, &input),
+    }
+}
+
+fn hmac<M: Mac + KeyInit>(secret: &[u8], input: &[u8]) -> Vec<u8> {
+    let mut mac = <M as KeyInit>::new_from_slice(secret).expect("HMAC takes keys of any length");
+    mac.update(input);
+    mac.finalize().into_bytes().to_vec()
+}
+
+//COMPARED WITHOUT STOPPING AT THE FIRST DIFFERENCE
+fn same(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |difference, (x, y)| difference | (x ^ y)) == 0
+}
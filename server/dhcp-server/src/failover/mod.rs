@@ -0,0 +1,361 @@
+//DHCP FAILOVER BETWEEN TWO SERVERS SHARING ONE POOL, ALONG THE LINES OF THE IETF DHCP FAILOVER PROTOCOL
+//THE PAIR TELLS EACH OTHER ABOUT EVERY LEASE OVER A TCP CONNECTION (peer) AND SPLITS THE CLIENTS BY THE RFC 3074
+//HASH OF THEIR IDENTIFIER (hash). EACH HANDS OUT ITS OWN SHARE OF THE FREE ADDRESSES, SO BOTH CAN GIVE OUT
+//NEW LEASES WITHOUT ASKING THE OTHER
+//NO CLIENT IS GIVEN MORE THAN THE MCLT PAST WHAT THE PARTNER KNOWS ABOUT ITS LEASE. WHILE THE PAIR IS NOT TALKING
+//EITHER SERVER RENEWS ANY CLIENT, AND ONCE ONE OF THEM IS IN PARTNER-DOWN IT TAKES THE PARTNER'S FREE
+//ADDRESSES TOO AFTER ONE MCLT, WHEN EVERY LEASE THE PARTNER COULD HAVE GIVEN OUT ALONE HAS RUN OUT
+//WHEN THE PAIR TALKS AGAIN BOTH SEND EVERY LEASE THEY HOLD AND EVERY CHANGE THE OTHER HAS NOT ACKNOWLEDGED,
+//THE NEWER LEASE OF AN ADDRESS WINS
+pub mod hash;
+mod peer;
+
+use std::collections::{BTreeMap, HashMap};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tracing::{info, warn};
+
+use crate::clock::Clock;
+use crate::server_config::server_config::{FailoverConfig, FailoverMode, FailoverRole, HASH_BUCKETS};
+use crate::server_config::Config;
+use crate::set_up::create_db_tables::CustomError;
+use crate::store::{ClientName, DnsUpdate, Lease, LeaseStore};
+use crate::utility::types::DHCPMessage;
+
+//IN HOT STANDBY THE SECONDARY STILL OWNS THE TOP TENTH OF THE POOL, FOR NEW CLIENTS WHILE THE PAIR IS NOT TALKING
+const STANDBY_RESERVE_BUCKETS: u16 = HASH_BUCKETS / 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailoverState {
+    //TALKING TO THE PARTNER
+    Normal,
+    //NOT TALKING TO IT, IT MAY STILL BE ANSWERING CLIENTS
+    CommunicationsInterrupted,
+    //KNOWN TO BE DOWN, SAID BY AN ADMINISTRATOR OR failover.partner_down_after AFTER LOSING IT
+    PartnerDown,
+}
+
+impl FailoverState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailoverState::Normal => "normal",
+            FailoverState::CommunicationsInterrupted => "communications_interrupted",
+            FailoverState::PartnerDown => "partner_down",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BindingState {
+    Active,
+    Free,
+    //DECLINED OR REVOKED, OUT OF THE POOL UNTIL end
+    Held,
+}
+
+//WHAT ONE SERVER TELLS THE OTHER ABOUT AN ADDRESS
+//TIMES ARE MICROSECONDS SINCE THE EPOCH, AS PRECISE AS POSTGRESQL KEEPS THEM
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Binding {
+    pub ip_address: Ipv4Addr,
+    pub state: BindingState,
+    //NONE FOR A HELD ADDRESS
+    pub client_id: Option<String>,
+    //START OF THE LEASE, FOR A FREED ADDRESS THE START OF THE LEASE THAT ENDED
+    pub start: i64,
+    //ACTIVE: THE POTENTIAL EXPIRATION, AS LONG AS THE CLIENT MAY BE GIVEN AT ITS NEXT RENEWAL
+    //HELD: WHEN THE ADDRESS GOES BACK TO THE POOL
+    pub end: i64,
+    pub hostname: Option<String>,
+    pub dns_update: DnsUpdate,
+    //NUMBERS THE SENDER'S CHANGES, AN ACK NAMES IT SO A NEWER CHANGE OF THE SAME ADDRESS STAYS QUEUED
+    pub sequence: u64,
+}
+
+impl Binding {
+    //potential_end IS WHAT THE LEASE RUNS TO AT FULL LENGTH, THE CLIENT MAY HAVE BEEN GIVEN LESS
+    pub fn active(lease: &Lease, potential_end: DateTime<Utc>) -> Self {
+        Binding {
+            ip_address: lease.ip_address,
+            state: BindingState::Active,
+            client_id: Some(lease.client_id.clone()),
+            start: lease.lease_start.timestamp_micros(),
+            end: potential_end.timestamp_micros(),
+            hostname: lease.name.hostname.clone(),
+            dns_update: lease.name.dns_update,
+            sequence: 0,
+        }
+    }
+
+    pub fn free(lease: &Lease) -> Self {
+        Binding { state: BindingState::Free, ..Binding::active(lease, lease.lease_end) }
+    }
+
+    pub fn held(ip_address: Ipv4Addr, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        Binding {
+            ip_address,
+            state: BindingState::Held,
+            client_id: None,
+            start: start.timestamp_micros(),
+            end: end.timestamp_micros(),
+            hostname: None,
+            dns_update: DnsUpdate::default(),
+            sequence: 0,
+        }
+    }
+
+    fn lease(&self) -> Lease {
+        Lease {
+            ip_address: self.ip_address,
+            client_id: self.client_id.clone().unwrap_or_default(),
+            lease_start: from_micros(self.start),
+            lease_end: from_micros(self.end),
+            name: ClientName { hostname: self.hostname.clone(), dns_update: self.dns_update },
+        }
+    }
+}
+
+fn from_micros(micros: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp_micros(micros).unwrap_or_default()
+}
+
+struct Shared {
+    state: FailoverState,
+    since: DateTime<Utc>,
+    //CHANGES THE PARTNER HAS NOT ACKNOWLEDGED, THE LATEST PER ADDRESS
+    pending: BTreeMap<Ipv4Addr, Binding>,
+    //HOW FAR THE PARTNER KNOWS EACH LEASE RUNS
+    partner_knows: HashMap<Ipv4Addr, DateTime<Utc>>,
+}
+
+//ONE SERVER OF A FAILOVER PAIR, SHARED BY THE DHCP HANDLERS, THE PEER CONNECTION AND THE MANAGEMENT API
+pub struct Failover {
+    config: FailoverConfig,
+    store: Arc<dyn LeaseStore>,
+    clock: Arc<dyn Clock>,
+    shared: Mutex<Shared>,
+    sequence: AtomicU64,
+    updates: UnboundedSender<Binding>,
+    //TAKEN BY start
+    peer: Mutex<Option<(Option<TcpListener>, UnboundedReceiver<Binding>)>>,
+    listen_address: Option<SocketAddr>,
+}
+
+impl Failover {
+    //THE SECONDARY STARTS LISTENING FOR ITS PARTNER RIGHT AWAY, SO A BAD ADDRESS STOPS THE SERVER FROM BUILDING
+    pub async fn bind(config: FailoverConfig, store: Arc<dyn LeaseStore>, clock: Arc<dyn Clock>) -> std::io::Result<Failover> {
+        let listener = match config.role {
+            FailoverRole::Primary => None,
+            FailoverRole::Secondary => Some(TcpListener::bind(config.listen).await?),
+        };
+        let listen_address = listener.as_ref().map(TcpListener::local_addr).transpose()?;
+        let (updates, receiver) = mpsc::unbounded_channel();
+        let since = clock.now();
+        Ok(Failover {
+            config,
+            store,
+            clock,
+            shared: Mutex::new(Shared {
+                state: FailoverState::CommunicationsInterrupted,
+                since,
+                pending: BTreeMap::new(),
+                partner_knows: HashMap::new(),
+            }),
+            sequence: AtomicU64::new(1),
+            updates,
+            peer: Mutex::new(Some((listener, receiver))),
+            listen_address,
+        })
+    }
+
+    //CONNECT TO OR WAIT FOR THE PARTNER, AGAIN AND AGAIN FOR AS LONG AS THE SERVER RUNS
+    pub fn start(self: &Arc<Self>) {
+        if let Some((listener, receiver)) = self.peer.lock().unwrap().take() {
+            tokio::spawn(peer::run(Arc::clone(self), listener, receiver));
+        }
+    }
+
+    pub fn config(&self) -> &FailoverConfig {
+        &self.config
+    }
+
+    //WHERE THE SECONDARY LISTENS, NONE FOR THE PRIMARY
+    pub fn listen_address(&self) -> Option<SocketAddr> {
+        self.listen_address
+    }
+
+    //CURRENT STATE AND SINCE WHEN
+    pub fn state(&self) -> (FailoverState, DateTime<Utc>) {
+        let mut shared = self.shared.lock().unwrap();
+        //partner_down_after IS CHECKED WHENEVER SOMEBODY ASKS, THERE IS NO TIMER FOR IT
+        if let (FailoverState::CommunicationsInterrupted, Some(after)) = (shared.state, self.config.partner_down_after) {
+            let at = shared.since + chrono::Duration::seconds(after.as_secs() as i64);
+            if self.clock.now() >= at {
+                warn!(since = %at.to_rfc3339(), "Failover partner lost for too long, going to partner-down");
+                shared.state = FailoverState::PartnerDown;
+                shared.since = at;
+            }
+        }
+        (shared.state, shared.since)
+    }
+
+    pub fn pending_updates(&self) -> usize {
+        self.shared.lock().unwrap().pending.len()
+    }
+
+    //AN ADMINISTRATOR SAYS THE PARTNER IS DOWN, NOT WHILE IT IS STILL TALKING TO US
+    pub fn partner_down(&self) -> Result<(), String> {
+        let (state, _) = self.state();
+        match state {
+            FailoverState::Normal => Err("the failover partner is connected".to_string()),
+            FailoverState::PartnerDown => Ok(()),
+            FailoverState::CommunicationsInterrupted => {
+                let mut shared = self.shared.lock().unwrap();
+                shared.state = FailoverState::PartnerDown;
+                shared.since = self.clock.now();
+                warn!("Failover partner declared down");
+                Ok(())
+            }
+        }
+    }
+
+    //WHETHER THIS SERVER ANSWERS A CLIENT LOOKING FOR A SERVER
+    //WHILE THE PAIR TALKS ONLY THE ONE WHOSE HASH BUCKETS THE CLIENT FALLS IN, OTHERWISE EITHER
+    pub fn serves(&self, message: &DHCPMessage) -> bool {
+        if self.state().0 != FailoverState::Normal {
+            return true;
+        }
+        let primary = (hash::bucket(message) as u16) < self.config.split;
+        primary == (self.config.role == FailoverRole::Primary)
+    }
+
+    //WHETHER THIS SERVER MAY GIVE A FREE ADDRESS TO A NEW CLIENT
+    //THE POOL IS CUT IN TWO BY POSITION, THE PARTNER'S SHARE BECOMES OURS ONE MCLT INTO PARTNER-DOWN
+    pub fn may_allocate(&self, ip: Ipv4Addr, config: &Config) -> bool {
+        let (state, since) = self.state();
+        if state == FailoverState::PartnerDown && self.clock.now() >= since + self.mclt() {
+            return true;
+        }
+        let start = u32::from(config.ip_pool.range_start) as u64;
+        let size = u32::from(config.ip_pool.range_end) as u64 - start + 1;
+        let position = (u32::from(ip) as u64).saturating_sub(start);
+        let bucket = (position * HASH_BUCKETS as u64 / size) as u16;
+        let primary_share = match self.config.mode {
+            FailoverMode::LoadBalance => self.config.split,
+            FailoverMode::HotStandby => HASH_BUCKETS - STANDBY_RESERVE_BUCKETS,
+        };
+        (bucket < primary_share) == (self.config.role == FailoverRole::Primary)
+    }
+
+    //HOW LONG THE CLIENT'S LEASE MAY RUN: NO MORE THAN ONE MCLT PAST WHAT THE PARTNER KNOWS
+    pub fn lease_end(&self, ip: Ipv4Addr, wanted: DateTime<Utc>, now: DateTime<Utc>) -> DateTime<Utc> {
+        let known = self.shared.lock().unwrap().partner_knows.get(&ip).copied().unwrap_or(now);
+        wanted.min(known.max(now) + self.mclt())
+    }
+
+    //LEASES END ONE MCLT LATE WHILE THE PAIR IS NOT TALKING, THE PARTNER MAY HAVE RENEWED THEM
+    pub fn expiry_cutoff(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        match self.state().0 {
+            FailoverState::Normal => now,
+            _ => now - self.mclt(),
+        }
+    }
+
+    //QUEUE A CHANGE FOR THE PARTNER, IT IS SENT AGAIN ON EVERY CONNECTION UNTIL ACKNOWLEDGED
+    pub fn record(&self, mut binding: Binding) {
+        binding.sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+        self.shared.lock().unwrap().pending.insert(binding.ip_address, binding.clone());
+        let _ = self.updates.send(binding);
+    }
+
+    fn mclt(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.config.mclt.as_secs() as i64)
+    }
+
+    fn connected(&self) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.state = FailoverState::Normal;
+        shared.since = self.clock.now();
+        info!(peer = %self.config.peer, "Failover partner connected");
+    }
+
+    fn disconnected(&self) {
+        let mut shared = self.shared.lock().unwrap();
+        if shared.state == FailoverState::Normal {
+            shared.state = FailoverState::CommunicationsInterrupted;
+            shared.since = self.clock.now();
+            warn!(peer = %self.config.peer, "Failover partner lost");
+        }
+    }
+
+    fn acknowledged(&self, ip_address: Ipv4Addr, sequence: u64) {
+        let mut shared = self.shared.lock().unwrap();
+        let Some(binding) = shared.pending.get(&ip_address).filter(|binding| binding.sequence == sequence).cloned() else {
+            return;
+        };
+        shared.pending.remove(&ip_address);
+        match binding.state {
+            BindingState::Active => {
+                shared.partner_knows.insert(ip_address, from_micros(binding.end));
+            }
+            BindingState::Free | BindingState::Held => {
+                shared.partner_knows.remove(&ip_address);
+            }
+        }
+    }
+
+    //EVERY LEASE WE HOLD AND THEN EVERY UNACKNOWLEDGED CHANGE, SENT WHEN THE PAIR STARTS TALKING
+    async fn snapshot(&self) -> Result<Vec<Binding>, CustomError> {
+        let leases = self.store.active_leases(self.clock.now()).await?;
+        let shared = self.shared.lock().unwrap();
+        let mut bindings: Vec<Binding> = leases.iter()
+            .filter(|lease| !shared.pending.contains_key(&lease.ip_address))
+            .map(|lease| Binding::active(lease, lease.lease_end))
+            .collect();
+        bindings.extend(shared.pending.values().cloned());
+        Ok(bindings)
+    }
+
+    //TAKE IN A CHANGE THE PARTNER MADE
+    async fn apply(&self, binding: &Binding) -> Result<(), CustomError> {
+        let Some(local) = self.store.address(binding.ip_address).await? else {
+            return Ok(());
+        };
+        let now = self.clock.now();
+        let local_start = local.lease_start.map(|start| start.timestamp_micros());
+        match binding.state {
+            BindingState::Active => {
+                //A NEWER LEASE WE GAVE OUT WINS, AND THE PARTNER IS TOLD ABOUT IT
+                if let (true, Some(client_id), Some(start), Some(end)) = (local.allocated, &local.client_id, local.lease_start, local.lease_end) {
+                    if local_start > Some(binding.start) && end > now {
+                        let lease = Lease { ip_address: local.ip_address, client_id: client_id.clone(), lease_start: start, lease_end: end, name: local.name };
+                        self.record(Binding::active(&lease, end));
+                        return Ok(());
+                    }
+                }
+                self.store.put_lease(&binding.lease()).await?;
+                self.shared.lock().unwrap().partner_knows.insert(binding.ip_address, from_micros(binding.end));
+            }
+            BindingState::Free => {
+                //ONLY THE LEASE THAT ENDED, NOT ONE GIVEN OUT SINCE
+                if local.client_id.is_some() && local.client_id == binding.client_id && local_start <= Some(binding.start) {
+                    self.store.release(binding.ip_address, binding.client_id.as_deref()).await?;
+                }
+                self.shared.lock().unwrap().partner_knows.remove(&binding.ip_address);
+            }
+            BindingState::Held => {
+                self.store.decline(binding.ip_address, None, from_micros(binding.start), from_micros(binding.end)).await?;
+                self.shared.lock().unwrap().partner_knows.remove(&binding.ip_address);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,131 @@
+//THE CONNECTION BETWEEN THE TWO SERVERS OF A PAIR, ONE JSON MESSAGE PER LINE
+//THE PRIMARY CONNECTS, THE SECONDARY LISTENS AND ONLY TALKS TO ITS PARTNER'S ADDRESS
+//BOTH SIDES SEND A HEARTBEAT THREE TIMES PER peer_timeout AND GIVE UP ON A PEER THAT STAYS SILENT FOR A WHOLE ONE
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::time::{interval, sleep, sleep_until, timeout, Duration, Instant};
+use tracing::{debug, error, warn};
+
+use super::{Binding, Failover};
+use crate::server_config::server_config::{FailoverConfig, FailoverMode, FailoverRole};
+
+//BETWEEN ATTEMPTS TO REACH THE SECONDARY
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    //FIRST THING BOTH SIDES SEND, TWO SERVERS SET UP DIFFERENTLY ARE NOT A PAIR
+    Hello { role: FailoverRole, mode: FailoverMode, split: u16, mclt: u64 },
+    Update { binding: Binding },
+    Ack { ip_address: Ipv4Addr, sequence: u64 },
+    Heartbeat,
+}
+
+impl Message {
+    fn hello(config: &FailoverConfig) -> Self {
+        Message::Hello { role: config.role, mode: config.mode, split: config.split, mclt: config.mclt.as_secs() }
+    }
+}
+
+pub(super) async fn run(failover: Arc<Failover>, listener: Option<TcpListener>, mut updates: UnboundedReceiver<Binding>) {
+    loop {
+        let stream = match &listener {
+            Some(listener) => accept(listener, failover.config.peer.ip()).await,
+            None => connect(failover.config.peer).await,
+        };
+        if let Err(e) = session(&failover, stream, &mut updates).await {
+            warn!(peer = %failover.config.peer, error = %e, "Failover connection ended");
+        }
+        failover.disconnected();
+        if listener.is_none() {
+            sleep(RECONNECT_DELAY).await;
+        }
+    }
+}
+
+async fn accept(listener: &TcpListener, peer: IpAddr) -> TcpStream {
+    loop {
+        match listener.accept().await {
+            Ok((stream, from)) if from.ip() == peer => return stream,
+            Ok((_, from)) => warn!(from = %from, "Refused failover connection from an address that is not the partner"),
+            Err(e) => {
+                error!(error = %e, "Failed to accept failover connection");
+                sleep(RECONNECT_DELAY).await;
+            }
+        }
+    }
+}
+
+async fn connect(peer: SocketAddr) -> TcpStream {
+    loop {
+        match TcpStream::connect(peer).await {
+            Ok(stream) => return stream,
+            Err(e) => debug!(peer = %peer, error = %e, "Failover partner not reachable"),
+        }
+        sleep(RECONNECT_DELAY).await;
+    }
+}
+
+async fn send(write: &mut OwnedWriteHalf, message: &Message) -> Result<(), String> {
+    let mut line = serde_json::to_string(message).map_err(|e| e.to_string())?;
+    line.push('\n');
+    write.write_all(line.as_bytes()).await.map_err(|e| e.to_string())
+}
+
+async fn session(failover: &Failover, stream: TcpStream, updates: &mut UnboundedReceiver<Binding>) -> Result<(), String> {
+    let config = &failover.config;
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+
+    send(&mut write, &Message::hello(config)).await?;
+    let hello = match timeout(config.peer_timeout, lines.next_line()).await {
+        Ok(Ok(Some(line))) => serde_json::from_str::<Message>(&line).map_err(|e| format!("bad hello: {}", e))?,
+        Ok(Ok(None)) => return Err("closed before saying hello".to_string()),
+        Ok(Err(e)) => return Err(e.to_string()),
+        Err(_) => return Err("no hello in time".to_string()),
+    };
+    match hello {
+        Message::Hello { role, mode, split, mclt }
+            if role != config.role && mode == config.mode && split == config.split && mclt == config.mclt.as_secs() => {}
+        Message::Hello { .. } => return Err("the partner's role, mode, split or mclt does not match ours".to_string()),
+        _ => return Err("the partner did not start with a hello".to_string()),
+    }
+    failover.connected();
+
+    //WHAT WAS QUEUED WHILE APART IS IN THE SNAPSHOT, NEWER THAN ANY COPY IN THE CHANNEL
+    while updates.try_recv().is_ok() {}
+    for binding in failover.snapshot().await.map_err(|e| e.to_string())? {
+        send(&mut write, &Message::Update { binding }).await?;
+    }
+
+    let mut heartbeat = interval(config.peer_timeout / 3);
+    let mut deadline = Instant::now() + config.peer_timeout;
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                let line = line.map_err(|e| e.to_string())?.ok_or("closed by the partner")?;
+                deadline = Instant::now() + config.peer_timeout;
+                match serde_json::from_str::<Message>(&line) {
+                    Ok(Message::Update { binding }) => match failover.apply(&binding).await {
+                        //LEFT UNACKNOWLEDGED IT COMES AGAIN WITH THE NEXT CONNECTION
+                        Err(e) => error!(address = %binding.ip_address, error = %e, "Failed to store the failover partner's update"),
+                        Ok(()) => send(&mut write, &Message::Ack { ip_address: binding.ip_address, sequence: binding.sequence }).await?,
+                    },
+                    Ok(Message::Ack { ip_address, sequence }) => failover.acknowledged(ip_address, sequence),
+                    Ok(Message::Heartbeat | Message::Hello { .. }) => {}
+                    Err(e) => warn!(error = %e, "Ignored a failover message that does not parse"),
+                }
+            }
+            Some(binding) = updates.recv() => send(&mut write, &Message::Update { binding }).await?,
+            _ = heartbeat.tick() => send(&mut write, &Message::Heartbeat).await?,
+            _ = sleep_until(deadline) => return Err("the partner went silent".to_string()),
+        }
+    }
+}
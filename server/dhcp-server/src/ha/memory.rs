@@ -0,0 +1,128 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::ha::{LeaderLock, LeaderRecord};
+use crate::set_up::create_db_tables::CustomError;
+
+struct Leader {
+    node: String,
+    session: i32,
+    term: i64,
+    elected_at: DateTime<Utc>,
+    heartbeat: Instant,
+}
+
+#[derive(Default)]
+struct Table {
+    //SESSION HOLDING THE LOCK
+    holder: Option<i32>,
+    leader: Option<Leader>,
+    sessions: i32,
+    //SESSIONS THAT CANNOT REACH THE LOCK, THEY KEEP WHAT THEY HOLD
+    cut: HashSet<i32>,
+}
+
+//LEADER LOCK IN PROCESS MEMORY, FOR TESTS AND SERVERS EMBEDDED NEXT TO EACH OTHER IN ONE PROCESS
+//EVERY SERVER OF A GROUP NEEDS ITS OWN SESSION, TAKEN WITH session
+pub struct MemoryLock {
+    table: Arc<Mutex<Table>>,
+    session: i32,
+}
+
+impl MemoryLock {
+    pub fn new() -> Self {
+        MemoryLock::joining(Arc::default())
+    }
+
+    fn joining(table: Arc<Mutex<Table>>) -> Self {
+        let session = {
+            let mut table = table.lock().unwrap();
+            table.sessions += 1;
+            table.sessions
+        };
+        MemoryLock { table, session }
+    }
+
+    //ANOTHER SESSION ON THE SAME LOCK, LIKE ANOTHER SERVER CONNECTING TO THE SAME DATABASE
+    pub fn session(&self) -> MemoryLock {
+        MemoryLock::joining(Arc::clone(&self.table))
+    }
+
+    //THIS SESSION STOPS REACHING THE LOCK WITHOUT LETTING GO OF IT, LIKE A SERVER CUT OFF FROM ITS DATABASE
+    pub fn cut(&self) {
+        self.table.lock().unwrap().cut.insert(self.session);
+    }
+
+    pub fn heal(&self) {
+        self.table.lock().unwrap().cut.remove(&self.session);
+    }
+
+    fn reach(&self) -> Result<std::sync::MutexGuard<'_, Table>, CustomError> {
+        let table = self.table.lock().unwrap();
+        if table.cut.contains(&self.session) {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "leader lock unreachable").into());
+        }
+        Ok(table)
+    }
+}
+
+impl Default for MemoryLock {
+    fn default() -> Self {
+        MemoryLock::new()
+    }
+}
+
+#[async_trait]
+impl LeaderLock for MemoryLock {
+    async fn try_acquire(&self, node: &str) -> Result<Option<i64>, CustomError> {
+        let mut table = self.reach()?;
+        if table.holder.is_some_and(|holder| holder != self.session) {
+            return Ok(None);
+        }
+        table.holder = Some(self.session);
+        let term = table.leader.as_ref().map_or(1, |leader| leader.term + 1);
+        table.leader = Some(Leader { node: node.to_string(), session: self.session, term, elected_at: Utc::now(), heartbeat: Instant::now() });
+        Ok(Some(term))
+    }
+
+    async fn heartbeat(&self) -> Result<bool, CustomError> {
+        let mut table = self.reach()?;
+        match table.leader.as_mut().filter(|leader| leader.session == self.session) {
+            Some(leader) => {
+                leader.heartbeat = Instant::now();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn leader(&self) -> Result<Option<LeaderRecord>, CustomError> {
+        let table = self.reach()?;
+        Ok(table.leader.as_ref().map(|leader| LeaderRecord {
+            node: leader.node.clone(),
+            session: leader.session,
+            term: leader.term,
+            elected_at: leader.elected_at,
+            heartbeat_age: leader.heartbeat.elapsed(),
+        }))
+    }
+
+    async fn depose(&self, leader: &LeaderRecord) -> Result<(), CustomError> {
+        let mut table = self.reach()?;
+        if table.holder == Some(leader.session) {
+            table.holder = None;
+        }
+        Ok(())
+    }
+
+    async fn release(&self) {
+        let mut table = self.table.lock().unwrap();
+        if table.holder == Some(self.session) && !table.cut.contains(&self.session) {
+            table.holder = None;
+        }
+    }
+}
@@ -0,0 +1,235 @@
+//HIGH AVAILABILITY FOR SERVERS SHARING ONE DATABASE: THEY COMPETE FOR ONE LOCK AND ONLY ITS HOLDER, THE LEADER,
+//ANSWERS CLIENTS. THE OTHERS STAND BY AND TRY FOR THE LOCK EVERY HEARTBEAT
+//THE LOCK IS FREED WHEN THE LEADER'S CONNECTION ENDS. A LEADER THAT HANGS OR LOSES THE DATABASE WITHOUT THE
+//CONNECTION ENDING STOPS RECORDING HEARTBEATS, AND A STANDBY ENDS ITS SESSION ONCE THE LAST ONE IS ha.takeover_after OLD
+//THE LEADER GOES SILENT AFTER HALF OF THAT WITHOUT A HEARTBEAT, BUT FOR THE FEW MOMENTS TWO SERVERS MAY STILL BOTH
+//ANSWER EVERY LEASE IS WRITTEN WITH A STATEMENT THAT ONLY TAKES A FREE ADDRESS, SO NO ADDRESS IS GIVEN OUT TWICE
+pub mod memory;
+pub mod postgres;
+
+pub use memory::MemoryLock;
+pub use postgres::PostgresLock;
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::time::{interval, timeout, MissedTickBehavior};
+use tracing::{debug, info, warn};
+
+use crate::server_config::server_config::HaConfig;
+use crate::set_up::create_db_tables::CustomError;
+
+//THE LEADER AS THE LOCK RECORDS IT
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeaderRecord {
+    pub node: String,
+    //THE CONNECTION HOLDING THE LOCK, A BACKEND PROCESS ID IN POSTGRESQL
+    pub session: i32,
+    //COUNTS ELECTIONS, EVERY NEW LEADER GETS THE NEXT ONE
+    pub term: i64,
+    pub elected_at: DateTime<Utc>,
+    //TIME SINCE THE LAST HEARTBEAT, BY THE CLOCK OF THE LOCK
+    pub heartbeat_age: Duration,
+}
+
+//WHAT THE SERVERS OF A GROUP ELECT THEIR LEADER WITH, A PostgresLock OR A MemoryLock
+#[async_trait]
+pub trait LeaderLock: Send + Sync {
+    //TAKE THE LOCK IF NO SERVER HOLDS IT AND RECORD node AS LEADER, RETURNS THE NEW TERM
+    async fn try_acquire(&self, node: &str) -> Result<Option<i64>, CustomError>;
+
+    //RECORD THAT THE LEADER IS ALIVE, FALSE IF THIS SESSION IS NOT THE RECORDED LEADER ANY MORE
+    async fn heartbeat(&self) -> Result<bool, CustomError>;
+
+    //NONE BEFORE THE FIRST ELECTION
+    async fn leader(&self) -> Result<Option<LeaderRecord>, CustomError>;
+
+    //END THE SESSION OF A LEADER WHOSE HEARTBEAT LAPSED, WHICH FREES THE LOCK
+    async fn depose(&self, leader: &LeaderRecord) -> Result<(), CustomError>;
+
+    //LET GO OF THE LOCK IF THIS SESSION HOLDS IT
+    async fn release(&self);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HaRole {
+    Leader,
+    Standby,
+}
+
+impl HaRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HaRole::Leader => "leader",
+            HaRole::Standby => "standby",
+        }
+    }
+}
+
+struct Shared {
+    role: HaRole,
+    since: DateTime<Utc>,
+    //OUR TERM WHILE LEADER
+    term: Option<i64>,
+    //LAST HEARTBEAT THAT WAS RECORDED, THE LEADER ANSWERS CLIENTS UNTIL HALF OF takeover_after PAST IT
+    heartbeat: Instant,
+    //THE LEADER LAST SEEN WHILE STANDING BY
+    leader: Option<LeaderRecord>,
+}
+
+//THIS SERVER'S PART IN THE ELECTION, SHARED BY THE DHCP HANDLERS AND THE MANAGEMENT API
+pub struct Election {
+    config: HaConfig,
+    lock: Arc<dyn LeaderLock>,
+    shared: Mutex<Shared>,
+}
+
+impl Election {
+    //EVERY SERVER STARTS AS A STANDBY, THE FIRST TRY FOR THE LOCK IS MADE BY start
+    pub fn new(config: HaConfig, lock: Arc<dyn LeaderLock>) -> Election {
+        Election {
+            config,
+            lock,
+            shared: Mutex::new(Shared {
+                role: HaRole::Standby,
+                since: Utc::now(),
+                term: None,
+                heartbeat: Instant::now(),
+                leader: None,
+            }),
+        }
+    }
+
+    //TAKE PART IN THE ELECTION FOR AS LONG AS THE SERVER RUNS
+    pub fn start(self: &Arc<Self>) {
+        tokio::spawn(Arc::clone(self).run());
+    }
+
+    pub fn config(&self) -> &HaConfig {
+        &self.config
+    }
+
+    //CURRENT ROLE AND SINCE WHEN
+    pub fn role(&self) -> (HaRole, DateTime<Utc>) {
+        let shared = self.shared.lock().unwrap();
+        (shared.role, shared.since)
+    }
+
+    pub fn term(&self) -> Option<i64> {
+        self.shared.lock().unwrap().term
+    }
+
+    //THE LEADER AS LAST SEEN, THIS SERVER WHILE IT LEADS
+    pub fn leader(&self) -> Option<String> {
+        let shared = self.shared.lock().unwrap();
+        match shared.role {
+            HaRole::Leader => Some(self.config.node.clone()),
+            HaRole::Standby => shared.leader.as_ref().map(|leader| leader.node.clone()),
+        }
+    }
+
+    //WHETHER THIS SERVER ANSWERS CLIENTS
+    //ALSO FALSE FOR A LEADER WHOSE HEARTBEATS STOPPED, EVEN BEFORE THE ELECTION TASK GETS ROUND TO STEPPING DOWN
+    pub fn is_leader(&self) -> bool {
+        let shared = self.shared.lock().unwrap();
+        shared.role == HaRole::Leader && shared.heartbeat.elapsed() < self.grace()
+    }
+
+    //HOW LONG A LEADER GOES ON WITHOUT RECORDING A HEARTBEAT
+    fn grace(&self) -> Duration {
+        self.config.takeover_after / 2
+    }
+
+    async fn run(self: Arc<Self>) {
+        let mut ticks = interval(self.config.heartbeat);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticks.tick().await;
+            let role = self.shared.lock().unwrap().role;
+            match role {
+                HaRole::Leader => self.heartbeat().await,
+                HaRole::Standby => self.campaign().await,
+            }
+        }
+    }
+
+    async fn heartbeat(&self) {
+        let last = self.shared.lock().unwrap().heartbeat;
+        let started = Instant::now();
+        let remaining = self.grace().saturating_sub(last.elapsed());
+        match timeout(remaining, self.lock.heartbeat()).await {
+            Ok(Ok(true)) => {
+                self.shared.lock().unwrap().heartbeat = started;
+                return;
+            }
+            Ok(Ok(false)) => warn!(node = %self.config.node, "Another server holds the leader lock"),
+            Ok(Err(e)) => warn!(node = %self.config.node, error = %e, "Failed to record the leader heartbeat"),
+            Err(_) => warn!(node = %self.config.node, "No leader heartbeat recorded in time"),
+        }
+        self.step_down().await;
+    }
+
+    async fn step_down(&self) {
+        {
+            let mut shared = self.shared.lock().unwrap();
+            shared.role = HaRole::Standby;
+            shared.since = Utc::now();
+            shared.term = None;
+            shared.leader = None;
+        }
+        warn!(node = %self.config.node, "Stepped down, standing by");
+        //A LOCK THAT CANNOT BE REACHED IS FREED BY THE STANDBY THAT TAKES OVER
+        let _ = timeout(self.grace(), self.lock.release()).await;
+    }
+
+    async fn campaign(&self) {
+        let started = Instant::now();
+        match timeout(self.grace(), self.lock.try_acquire(&self.config.node)).await {
+            Ok(Ok(Some(term))) => {
+                let mut shared = self.shared.lock().unwrap();
+                shared.role = HaRole::Leader;
+                shared.since = Utc::now();
+                shared.term = Some(term);
+                shared.heartbeat = started;
+                shared.leader = None;
+                info!(node = %self.config.node, term, "Elected leader, answering clients");
+            }
+            Ok(Ok(None)) => self.watch_leader().await,
+            Ok(Err(e)) => {
+                warn!(node = %self.config.node, error = %e, "Failed to reach the leader lock");
+                self.lock.release().await;
+            }
+            Err(_) => {
+                warn!(node = %self.config.node, "Leader lock did not answer in time");
+                //IT MAY HAVE BEEN TAKEN AFTER ALL, LET GO SO IT IS NOT HELD BY A SERVER THAT IS NOT ANSWERING
+                self.lock.release().await;
+            }
+        }
+    }
+
+    //TAKE OVER FROM A LEADER THAT STOPPED RECORDING HEARTBEATS
+    async fn watch_leader(&self) {
+        let leader = match self.lock.leader().await {
+            Ok(Some(leader)) => leader,
+            Ok(None) => return,
+            Err(e) => {
+                warn!(error = %e, "Failed to look up the leader");
+                return;
+            }
+        };
+        if leader.heartbeat_age > self.config.takeover_after {
+            warn!(leader = %leader.node, term = leader.term, heartbeat_age = ?leader.heartbeat_age,
+                "Leader heartbeat lapsed, ending its session to take over");
+            if let Err(e) = self.lock.depose(&leader).await {
+                warn!(leader = %leader.node, error = %e, "Failed to end the leader's session");
+            }
+        } else {
+            debug!(leader = %leader.node, term = leader.term, "Standing by");
+        }
+        self.shared.lock().unwrap().leader = Some(leader);
+    }
+}
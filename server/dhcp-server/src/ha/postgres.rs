@@ -0,0 +1,115 @@
+use async_trait::async_trait;
+use std::time::Duration;
+use tokio::sync::{Mutex, MutexGuard};
+use tokio_postgres::{Client, NoTls};
+use tracing::debug;
+
+use crate::ha::{LeaderLock, LeaderRecord};
+use crate::set_up::create_db_tables::CustomError;
+
+//A SESSION-LEVEL ADVISORY LOCK ON ITS OWN CONNECTION, HELD UNTIL THE CONNECTION ENDS, AND THE ha_leader ROW OF lock_id
+//THE CONNECTION IS MADE AGAIN WHEN IT FAILS, WHICH ALSO LETS GO OF THE LOCK
+//ENDING ANOTHER SERVER'S SESSION NEEDS THE SAME DATABASE ROLE OR pg_signal_backend
+pub struct PostgresLock {
+    db_url: String,
+    lock_id: i64,
+    client: Mutex<Option<Client>>,
+}
+
+impl PostgresLock {
+    pub fn new(db_url: impl Into<String>, lock_id: i64) -> Self {
+        PostgresLock { db_url: db_url.into(), lock_id, client: Mutex::new(None) }
+    }
+
+    async fn session(&self) -> Result<MutexGuard<'_, Option<Client>>, CustomError> {
+        let mut client = self.client.lock().await;
+        if client.as_ref().is_none_or(Client::is_closed) {
+            let (connected, connection) = tokio_postgres::connect(&self.db_url, NoTls).await?;
+            tokio::spawn(async move {
+                if let Err(e) = connection.await {
+                    debug!(error = %e, "Leader lock connection ended");
+                }
+            });
+            *client = Some(connected);
+        }
+        Ok(client)
+    }
+}
+
+//A CONNECTION THAT FAILED A QUERY IS DROPPED, WHATEVER LOCK IT HELD GOES WITH IT
+fn forget_on_error<T>(client: &mut Option<Client>, result: Result<T, tokio_postgres::Error>) -> Result<T, CustomError> {
+    if result.is_err() {
+        *client = None;
+    }
+    Ok(result?)
+}
+
+#[async_trait]
+impl LeaderLock for PostgresLock {
+    async fn try_acquire(&self, node: &str) -> Result<Option<i64>, CustomError> {
+        let mut session = self.session().await?;
+        let client = session.as_ref().unwrap();
+        let result = client.query_one("SELECT pg_try_advisory_lock($1)", &[&self.lock_id]).await;
+        if !forget_on_error(&mut session, result)?.get::<usize, bool>(0) {
+            return Ok(None);
+        }
+        let query = "INSERT INTO ha_leader (lock_id, node, backend_pid, term, elected_at, heartbeat)
+                     VALUES ($1, $2, pg_backend_pid(), 1, now(), now())
+                     ON CONFLICT (lock_id) DO UPDATE
+                     SET node = EXCLUDED.node,
+                         backend_pid = EXCLUDED.backend_pid,
+                         term = ha_leader.term + 1,
+                         elected_at = EXCLUDED.elected_at,
+                         heartbeat = EXCLUDED.heartbeat
+                     RETURNING term";
+        let client = session.as_ref().unwrap();
+        let result = client.query_one(query, &[&self.lock_id, &node]).await;
+        Ok(Some(forget_on_error(&mut session, result)?.get(0)))
+    }
+
+    async fn heartbeat(&self) -> Result<bool, CustomError> {
+        let mut session = self.session().await?;
+        let query = "UPDATE ha_leader
+                     SET heartbeat = now()
+                     WHERE lock_id = $1
+                     AND backend_pid = pg_backend_pid()";
+        let result = session.as_ref().unwrap().execute(query, &[&self.lock_id]).await;
+        Ok(forget_on_error(&mut session, result)? == 1)
+    }
+
+    async fn leader(&self) -> Result<Option<LeaderRecord>, CustomError> {
+        let mut session = self.session().await?;
+        //THE AGE IS TAKEN BY THE DATABASE CLOCK, THE SAME ONE THE HEARTBEAT WAS WRITTEN WITH
+        let query = "SELECT node, backend_pid, term, elected_at, GREATEST(EXTRACT(EPOCH FROM now() - heartbeat), 0)::float8
+                     FROM ha_leader
+                     WHERE lock_id = $1";
+        let result = session.as_ref().unwrap().query_opt(query, &[&self.lock_id]).await;
+        Ok(forget_on_error(&mut session, result)?.map(|row| LeaderRecord {
+            node: row.get(0),
+            session: row.get(1),
+            term: row.get(2),
+            elected_at: row.get(3),
+            heartbeat_age: Duration::from_secs_f64(row.get(4)),
+        }))
+    }
+
+    async fn depose(&self, leader: &LeaderRecord) -> Result<(), CustomError> {
+        let mut session = self.session().await?;
+        //ONLY IF THAT BACKEND STILL HOLDS OUR LOCK, ITS PROCESS ID MAY HAVE BEEN REUSED BY NOW
+        let query = "SELECT pg_terminate_backend(pid)
+                     FROM pg_locks
+                     WHERE locktype = 'advisory'
+                     AND granted
+                     AND objsubid = 1
+                     AND ((classid::bigint << 32) | objid::bigint) = $1
+                     AND pid = $2";
+        let result = session.as_ref().unwrap().query(query, &[&self.lock_id, &leader.session]).await;
+        forget_on_error(&mut session, result)?;
+        Ok(())
+    }
+
+    async fn release(&self) {
+        //CLOSING THE CONNECTION LETS GO OF THE LOCK EVEN IF THE DATABASE DOES NOT ANSWER
+        *self.client.lock().await = None;
+    }
+}
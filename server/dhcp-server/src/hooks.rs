@@ -0,0 +1,268 @@
+//LEASE EVENT HOOKS, FOR IPAM AND NAC SYSTEMS THAT NEED TO KNOW WHO HOLDS WHICH ADDRESS
+//EVERY HOOK HAS A QUEUE AND A TASK OF ITS OWN THAT CALLS IT ONE EVENT AT A TIME, IN ORDER,
+//SO A SLOW OR FAILING HOOK NEVER DELAYS A REPLY. WHEN ITS QUEUE IS FULL NEW EVENTS ARE DROPPED AND COUNTED
+//A notify HOOK PUBLISHES TO A POSTGRESQL CHANNEL THROUGH THE LEASE STORE, SO CONSUMERS CAN LISTEN INSTEAD OF POLLING lease_history
+//A ddns HOOK KEEPS THE CLIENT'S DNS RECORDS IN STEP WITH ITS LEASE
+use std::net::Ipv4Addr;
+use std::process::Stdio;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::mpsc::{self, error::TrySendError, Receiver, Sender};
+use tokio::time::timeout;
+use tracing::{debug, error, warn};
+
+use crate::api::views::timestamp;
+use crate::metrics::HookMetrics;
+use crate::server_config::server_config::{HookAction, HookConfig, HooksConfig};
+use crate::store::{ClientName, DnsUpdate, LeaseStore};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LeaseEventKind {
+    //FIRST ACK FOR AN ADDRESS
+    Granted,
+    Renewed,
+    //BY THE CLIENT OR THROUGH THE MANAGEMENT API
+    Released,
+    Declined,
+    //NOTICED WHEN THE NEXT MESSAGE IS HANDLED, NOT THE MOMENT lease_end PASSES
+    Expired,
+}
+
+impl LeaseEventKind {
+    pub const ALL: [LeaseEventKind; 5] = [LeaseEventKind::Granted, LeaseEventKind::Renewed, LeaseEventKind::Released,
+                                          LeaseEventKind::Declined, LeaseEventKind::Expired];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LeaseEventKind::Granted => "granted",
+            LeaseEventKind::Renewed => "renewed",
+            LeaseEventKind::Released => "released",
+            LeaseEventKind::Declined => "declined",
+            LeaseEventKind::Expired => "expired",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<Self> {
+        LeaseEventKind::ALL.into_iter().find(|known| known.as_str() == kind)
+    }
+}
+
+//WHAT A HOOK IS TOLD, SENT AS JSON AND AS DHCP_<FIELD> ENVIRONMENT VARIABLES
+//TIMESTAMPS ARE RFC 3339 STRINGS IN UTC LIKE IN THE MANAGEMENT API
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LeaseEvent {
+    pub event: LeaseEventKind,
+    pub ip_address: Ipv4Addr,
+    pub client_id: String,
+    //HARDWARE ADDRESS FROM THE MESSAGE, UNKNOWN FOR EXPIRED LEASES AND API RELEASES
+    pub mac: Option<String>,
+    pub lease_start: Option<String>,
+    pub lease_end: Option<String>,
+    //THE NAME THE CLIENT GOES BY AND WHICH DNS RECORDS THE SERVER KEEPS FOR IT
+    #[serde(default)]
+    pub hostname: Option<String>,
+    #[serde(default)]
+    pub dns_update: DnsUpdate,
+    //SET WHEN A RENEWAL CHANGED THE HOST NAME, WITH THE ONE IT REPLACED, NULL IF THE CLIENT HAD NONE
+    #[serde(default)]
+    pub renamed: bool,
+    #[serde(default)]
+    pub previous_hostname: Option<String>,
+    //WHEN IT HAPPENED
+    pub time: String,
+}
+
+impl LeaseEvent {
+    pub fn new(event: LeaseEventKind, ip_address: Ipv4Addr, client_id: &str, time: DateTime<Utc>) -> Self {
+        LeaseEvent {
+            event,
+            ip_address,
+            client_id: client_id.to_string(),
+            mac: None,
+            lease_start: None,
+            lease_end: None,
+            hostname: None,
+            dns_update: DnsUpdate::default(),
+            renamed: false,
+            previous_hostname: None,
+            time: timestamp(time),
+        }
+    }
+
+    pub fn mac(mut self, mac: String) -> Self {
+        self.mac = Some(mac);
+        self
+    }
+
+    pub fn lease(mut self, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        self.lease_start = Some(timestamp(start));
+        self.lease_end = Some(timestamp(end));
+        self
+    }
+
+    pub fn name(mut self, name: &ClientName) -> Self {
+        self.hostname = name.hostname.clone();
+        self.dns_update = name.dns_update;
+        self
+    }
+
+    //THE NAME THE LEASE HAD BEFORE, AFTER name, ONLY KEPT WHEN THE HOST NAME CHANGED
+    pub fn previous_name(mut self, previous: &ClientName) -> Self {
+        self.renamed = previous.hostname != self.hostname;
+        self.previous_hostname = previous.hostname.clone().filter(|_| self.renamed);
+        self
+    }
+
+    //THE EVENT AS JSON, WITH THE OUTBOX id WHEN IT WAS KEPT THERE
+    pub fn payload(&self, id: Option<i64>) -> String {
+        let mut payload = serde_json::to_value(self).expect("lease events are plain data");
+        if let Some(id) = id {
+            payload["id"] = id.into();
+        }
+        payload.to_string()
+    }
+
+    fn environment(&self) -> Vec<(&'static str, String)> {
+        let mut environment = vec![
+            ("DHCP_EVENT", self.event.as_str().to_string()),
+            ("DHCP_IP_ADDRESS", self.ip_address.to_string()),
+            ("DHCP_CLIENT_ID", self.client_id.clone()),
+            ("DHCP_DNS_UPDATE", self.dns_update.as_str().to_string()),
+            ("DHCP_RENAMED", self.renamed.to_string()),
+            ("DHCP_TIME", self.time.clone()),
+        ];
+        for (name, value) in [("DHCP_MAC", &self.mac), ("DHCP_LEASE_START", &self.lease_start), ("DHCP_LEASE_END", &self.lease_end),
+                              ("DHCP_HOSTNAME", &self.hostname), ("DHCP_PREVIOUS_HOSTNAME", &self.previous_hostname)] {
+            if let Some(value) = value {
+                environment.push((name, value.clone()));
+            }
+        }
+        environment
+    }
+}
+
+struct Target {
+    name: String,
+    events: Vec<LeaseEventKind>,
+    sender: Sender<Arc<LeaseEvent>>,
+}
+
+pub struct Hooks {
+    targets: Vec<Target>,
+    metrics: HookMetrics,
+}
+
+impl Hooks {
+    //STARTS A WORKER TASK PER HOOK, SO IT NEEDS A TOKIO RUNTIME
+    pub fn start(config: &HooksConfig, metrics: HookMetrics, store: Arc<dyn LeaseStore>) -> Result<Hooks, reqwest::Error> {
+        let client = reqwest::Client::builder().build()?;
+        let targets = config.targets.iter().map(|hook| {
+            let (sender, receiver) = mpsc::channel(config.queue_size);
+            let callers = Callers { client: client.clone(), store: Arc::clone(&store) };
+            tokio::spawn(run(hook.clone(), receiver, callers, metrics.clone()));
+            Target { name: hook.name.clone(), events: hook.events.clone(), sender }
+        }).collect();
+        Ok(Hooks { targets, metrics })
+    }
+
+    pub fn emit(&self, event: LeaseEvent) {
+        let event = Arc::new(event);
+        for target in self.targets.iter().filter(|target| target.events.contains(&event.event)) {
+            match target.sender.try_send(Arc::clone(&event)) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => {
+                    self.metrics.dropped(&target.name);
+                    warn!(hook = %target.name, event = event.event.as_str(), address = %event.ip_address,
+                        "Hook queue full, lease event dropped");
+                }
+                //THE WORKER ONLY STOPS WHEN THE RUNTIME SHUTS DOWN
+                Err(TrySendError::Closed(_)) => {}
+            }
+        }
+    }
+}
+
+//WHAT THE WORKERS CALL HOOKS WITH
+struct Callers {
+    client: reqwest::Client,
+    store: Arc<dyn LeaseStore>,
+}
+
+async fn run(hook: HookConfig, mut receiver: Receiver<Arc<LeaseEvent>>, callers: Callers, metrics: HookMetrics) {
+    while let Some(event) = receiver.recv().await {
+        let mut delay = hook.retry_delay;
+        for attempt in 0..=hook.retries {
+            let result = match timeout(hook.timeout, call(&hook.action, &event, &callers)).await {
+                Ok(result) => result,
+                Err(_) => Err(format!("no answer within {} seconds", hook.timeout.as_secs())),
+            };
+            match result {
+                Ok(()) => {
+                    debug!(hook = %hook.name, event = event.event.as_str(), address = %event.ip_address, "Hook called");
+                    break;
+                }
+                Err(e) if attempt < hook.retries => {
+                    warn!(hook = %hook.name, event = event.event.as_str(), address = %event.ip_address, attempt = attempt + 1,
+                        error = %e, retry_in = delay.as_secs(), "Hook failed, retrying");
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                }
+                Err(e) => {
+                    metrics.failed(&hook.name);
+                    error!(hook = %hook.name, event = event.event.as_str(), address = %event.ip_address, attempts = attempt + 1,
+                        error = %e, "Hook failed, giving up on the event");
+                }
+            }
+        }
+    }
+}
+
+async fn call(action: &HookAction, event: &LeaseEvent, callers: &Callers) -> Result<(), String> {
+    let body = serde_json::to_vec(event).map_err(|e| e.to_string())?;
+    match action {
+        HookAction::Command { program, args } => {
+            //kill_on_drop ENDS A COMMAND THAT RAN INTO THE TIMEOUT
+            let mut child = Command::new(program)
+                .args(args)
+                .envs(event.environment())
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()
+                .map_err(|e| format!("failed to run {}: {}", program, e))?;
+            if let Some(mut stdin) = child.stdin.take() {
+                //A COMMAND THAT ONLY LOOKS AT THE ENVIRONMENT MAY EXIT WITHOUT READING STDIN
+                let _ = stdin.write_all(&body).await;
+            }
+            let output = child.wait_with_output().await.map_err(|e| e.to_string())?;
+            if output.status.success() {
+                Ok(())
+            } else {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                Err(format!("{} exited with {}: {}", program, output.status, stderr.trim()))
+            }
+        }
+        HookAction::Webhook { url, headers } => {
+            let mut request = callers.client.post(url).header(reqwest::header::CONTENT_TYPE, "application/json").body(body);
+            for (name, value) in headers {
+                request = request.header(name, value);
+            }
+            let response = request.send().await.map_err(|e| e.to_string())?;
+            if response.status().is_success() {
+                Ok(())
+            } else {
+                Err(format!("{} answered {}", url, response.status()))
+            }
+        }
+        HookAction::Notify { channel, outbox } => {
+            callers.store.publish(channel, event, *outbox).await.map_err(|e| e.to_string())
+        }
+        HookAction::Ddns(config) => crate::ddns::update(config, event).await,
+    }
+}
@@ -0,0 +1,20 @@
+//DHCP SERVER WITH POSTGRESQL OR IN-MEMORY LEASE STORAGE, FOR EMBEDDING OR THE SERVER BINARY
+//START WITH Server::builder
+pub mod utility;
+pub mod server_config;
+pub mod set_up;
+pub mod clock;
+pub mod listener;
+pub mod raw_sender;
+pub mod logging;
+pub mod netlink;
+pub mod store;
+pub mod api;
+pub mod metrics;
+pub mod hooks;
+pub mod ddns;
+pub mod failover;
+pub mod ha;
+pub mod server;
+
+pub use server::{BuildError, NakReason, Server, ServerBuilder};
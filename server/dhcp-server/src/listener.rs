@@ -0,0 +1,190 @@
+use ipnet::Ipv4Net;
+use nix::ifaddrs::getifaddrs;
+use nix::net::if_::if_nametoindex;
+use nix::sys::socket::{recvmsg, sendmsg, setsockopt, sockopt, ControlMessage, ControlMessageOwned, MsgFlags, SockaddrIn};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::collections::HashMap;
+use std::io::{self, IoSlice, IoSliceMut};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::os::fd::AsRawFd;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::io::Interest;
+use tokio::net::UdpSocket;
+
+//NETWORK INTERFACE AND ITS IPV4 ADDRESSES
+#[derive(Debug, Clone)]
+pub struct Interface {
+    pub name: String,
+    pub index: u32,
+    pub addresses: Vec<Ipv4Net>,
+}
+
+impl Interface {
+    pub fn has_address(&self, ip: Ipv4Addr) -> bool {
+        self.addresses.iter().any(|net| net.addr() == ip)
+    }
+}
+
+//WHERE A PACKET ARRIVED, LEARNED FROM IP_PKTINFO
+#[derive(Debug, Clone)]
+pub struct Received {
+    pub source: SocketAddr,
+    pub interface: Interface,
+    //ADDRESS THE KERNEL PICKS AS SOURCE FOR REPLIES ON THIS INTERFACE
+    pub local_address: Ipv4Addr,
+    //LISTENER THE PACKET CAME IN ON, REPLIES LEAVE THROUGH THE SAME ONE
+    pub listener: usize,
+}
+
+//ONE DHCP SERVER SOCKET
+//WITH AN INTERFACE NAME THE SOCKET ONLY SEES TRAFFIC OF THAT INTERFACE (SO_BINDTODEVICE)
+pub struct Listener {
+    socket: UdpSocket,
+    pub interface: Option<String>,
+}
+
+impl Listener {
+    pub fn bind(address: Ipv4Addr, port: u16, interface: Option<&str>) -> io::Result<Self> {
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_reuse_address(true)?;
+        socket.set_broadcast(true)?;
+        if let Some(interface) = interface {
+            socket.bind_device(Some(interface.as_bytes()))?;
+        }
+        setsockopt(&socket, sockopt::Ipv4PacketInfo, &true)?;
+        socket.set_nonblocking(true)?;
+        socket.bind(&SocketAddr::from((address, port)).into())?;
+        Ok(Listener {
+            socket: UdpSocket::from_std(socket.into())?,
+            interface: interface.map(str::to_string),
+        })
+    }
+
+    pub fn local_port(&self) -> u16 {
+        self.socket.local_addr().map(|address| address.port()).unwrap_or(0)
+    }
+
+    //RECEIVE ONE DATAGRAM TOGETHER WITH THE INTERFACE INDEX AND LOCAL ADDRESS IT ARRIVED ON
+    pub async fn recv(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr, u32, Ipv4Addr)> {
+        let fd = self.socket.as_raw_fd();
+        self.socket.async_io(Interest::READABLE, || {
+            let mut iov = [IoSliceMut::new(buf)];
+            let mut cmsg_buffer = nix::cmsg_space!(libc::in_pktinfo);
+            let message = recvmsg::<SockaddrIn>(fd, &mut iov, Some(&mut cmsg_buffer), MsgFlags::empty())?;
+
+            let mut interface_index = 0;
+            let mut local_address = Ipv4Addr::UNSPECIFIED;
+            for cmsg in message.cmsgs()? {
+                if let ControlMessageOwned::Ipv4PacketInfo(info) = cmsg {
+                    interface_index = info.ipi_ifindex as u32;
+                    local_address = Ipv4Addr::from(u32::from_be(info.ipi_spec_dst.s_addr));
+                }
+            }
+            let source = message.address
+                .map(|a| SocketAddr::V4(SocketAddrV4::new(a.ip(), a.port())))
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Datagram without source address"))?;
+            Ok((message.bytes, source, interface_index, local_address))
+        }).await
+    }
+
+    //SEND OUT OF THE GIVEN INTERFACE WITH THE GIVEN SOURCE ADDRESS
+    pub async fn send(&self, buf: &[u8], dest: SocketAddrV4, interface_index: u32, source: Ipv4Addr) -> io::Result<usize> {
+        let fd = self.socket.as_raw_fd();
+        let info = libc::in_pktinfo {
+            ipi_ifindex: interface_index as libc::c_int,
+            ipi_spec_dst: libc::in_addr { s_addr: u32::from(source).to_be() },
+            ipi_addr: libc::in_addr { s_addr: 0 },
+        };
+        let dest = SockaddrIn::from(dest);
+        self.socket.async_io(Interest::WRITABLE, || {
+            let iov = [IoSlice::new(buf)];
+            let cmsgs = [ControlMessage::Ipv4PacketInfo(&info)];
+            Ok(sendmsg(fd, &iov, &cmsgs, MsgFlags::empty(), Some(&dest))?)
+        }).await
+    }
+}
+
+//LIST INTERFACES WITH AT LEAST ONE IPV4 ADDRESS
+pub fn interfaces() -> io::Result<Vec<Interface>> {
+    let mut by_name: HashMap<String, Interface> = HashMap::new();
+    for ifaddr in getifaddrs()? {
+        let address = match ifaddr.address.as_ref().and_then(|a| a.as_sockaddr_in()) {
+            Some(address) => address.ip(),
+            None => continue,
+        };
+        let netmask = ifaddr.netmask.as_ref().and_then(|a| a.as_sockaddr_in())
+            .map(|mask| mask.ip())
+            .unwrap_or(Ipv4Addr::BROADCAST);
+        let net = match Ipv4Net::with_netmask(address, netmask) {
+            Ok(net) => net,
+            Err(_) => continue,
+        };
+        if !by_name.contains_key(&ifaddr.interface_name) {
+            let index = if_nametoindex(ifaddr.interface_name.as_str())?;
+            by_name.insert(ifaddr.interface_name.clone(), Interface {
+                name: ifaddr.interface_name.clone(),
+                index,
+                addresses: Vec::new(),
+            });
+        }
+        by_name.get_mut(&ifaddr.interface_name).unwrap().addresses.push(net);
+    }
+    Ok(by_name.into_values().collect())
+}
+
+//HOW OFTEN AN UNKNOWN INDEX MAY HAVE THE INTERFACES RE-READ, EVERY DATAGRAM LOOKS ITS INTERFACE UP
+const UNKNOWN_INDEX_REFRESH: Duration = Duration::from_secs(1);
+
+//INTERFACES BY INDEX, RE-READ FROM THE KERNEL WHEN AN UNKNOWN INDEX SHOWS UP
+pub struct InterfaceTable {
+    interfaces: RwLock<HashMap<u32, Interface>>,
+    //LAST RE-READ FOR AN UNKNOWN INDEX
+    looked_up: Mutex<Option<Instant>>,
+}
+
+impl Default for InterfaceTable {
+    fn default() -> Self {
+        InterfaceTable::new()
+    }
+}
+
+impl InterfaceTable {
+    pub fn new() -> Self {
+        let table = InterfaceTable { interfaces: RwLock::new(HashMap::new()), looked_up: Mutex::new(None) };
+        table.refresh();
+        table
+    }
+
+    pub fn refresh(&self) {
+        match interfaces() {
+            Ok(interfaces) => {
+                *self.interfaces.write().unwrap() = interfaces.into_iter().map(|i| (i.index, i)).collect();
+            }
+            Err(e) => tracing::warn!(error = %e, "Failed to list network interfaces"),
+        }
+    }
+
+    pub fn all(&self) -> Vec<Interface> {
+        self.interfaces.read().unwrap().values().cloned().collect()
+    }
+
+    //NONE FOR INDEX 0, THE KERNEL DID NOT SAY, AND FOR AN INDEX STILL UNKNOWN AFTER A RECENT RE-READ
+    pub fn get(&self, index: u32) -> Option<Interface> {
+        if index == 0 {
+            return None;
+        }
+        if let Some(interface) = self.interfaces.read().unwrap().get(&index) {
+            return Some(interface.clone());
+        }
+        {
+            let mut looked_up = self.looked_up.lock().unwrap();
+            if looked_up.is_some_and(|at| at.elapsed() < UNKNOWN_INDEX_REFRESH) {
+                return None;
+            }
+            *looked_up = Some(Instant::now());
+        }
+        self.refresh();
+        self.interfaces.read().unwrap().get(&index).cloned()
+    }
+}
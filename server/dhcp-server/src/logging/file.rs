@@ -0,0 +1,131 @@
+//LOG FILE THAT IS ROTATED BY SIZE AND/OR EVERY HOUR OR DAY (UTC)
+//THE CURRENT FILE IS RENAMED TO <path>.<YYYYMMDD-HHMMSS.mmm> AND ONLY THE NEWEST keep OF THOSE STAY
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Duration, Utc};
+
+use super::queue::Sink;
+use crate::server_config::server_config::{FileSink, RotateEvery};
+
+const ROTATED_SUFFIX_FORMAT: &str = "%Y%m%d-%H%M%S%.3f";
+
+pub struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    //WHEN THE CURRENT FILE WAS STARTED, DECIDES WHEN IT IS ROTATED BY TIME
+    started: DateTime<Utc>,
+    max_size: Option<u64>,
+    rotate: Option<RotateEvery>,
+    keep: usize,
+}
+
+impl RotatingFile {
+    pub fn open(sink: &FileSink) -> io::Result<Self> {
+        let path = PathBuf::from(&sink.path);
+        let file = append(&path)?;
+        let metadata = file.metadata()?;
+        //A FILE LEFT FROM YESTERDAY IS ROTATED ON THE FIRST LINE WRITTEN TODAY
+        let started = metadata.modified().map(DateTime::<Utc>::from).unwrap_or_else(|_| Utc::now());
+        Ok(RotatingFile {
+            path,
+            file,
+            size: metadata.len(),
+            started,
+            max_size: sink.max_size,
+            rotate: sink.rotate,
+            keep: sink.keep,
+        })
+    }
+
+    fn due(&self, line: usize, now: DateTime<Utc>) -> bool {
+        //A LINE LONGER THAN max_size STILL GOES INTO A FILE OF ITS OWN
+        let too_big = self.max_size.is_some_and(|max| self.size > 0 && self.size + line as u64 > max);
+        let too_old = self.rotate.is_some_and(|every| period(every, self.started) != period(every, now));
+        too_big || too_old
+    }
+
+    fn rotate(&mut self, now: DateTime<Utc>) -> io::Result<()> {
+        let mut stamp = now;
+        let mut rotated = self.rotated_path(stamp);
+        while rotated.exists() {
+            stamp += Duration::milliseconds(1);
+            rotated = self.rotated_path(stamp);
+        }
+        fs::rename(&self.path, &rotated)?;
+        self.file = append(&self.path)?;
+        self.size = 0;
+        self.started = now;
+        Ok(())
+    }
+
+    fn rotated_path(&self, stamp: DateTime<Utc>) -> PathBuf {
+        let mut rotated = self.path.clone().into_os_string();
+        rotated.push(format!(".{}", stamp.format(ROTATED_SUFFIX_FORMAT)));
+        PathBuf::from(rotated)
+    }
+
+    //THE SUFFIX SORTS BY TIME, SO THE OLDEST FILES COME FIRST
+    fn prune(&self) -> io::Result<()> {
+        let mut rotated = rotated_files(&self.path)?;
+        rotated.sort();
+        let excess = rotated.len().saturating_sub(self.keep);
+        for old in &rotated[..excess] {
+            fs::remove_file(old)?;
+        }
+        Ok(())
+    }
+}
+
+impl Sink for RotatingFile {
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        let now = Utc::now();
+        let rotated = self.due(line.len(), now);
+        if rotated {
+            self.rotate(now)?;
+        }
+        self.file.write_all(line)?;
+        self.size += line.len() as u64;
+        //PRUNING LAST, A FILE THAT CANNOT BE DELETED DOES NOT COST THE LINE
+        if rotated {
+            self.prune()?;
+        }
+        Ok(())
+    }
+}
+
+fn append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn period(every: RotateEvery, time: DateTime<Utc>) -> i64 {
+    let seconds = match every {
+        RotateEvery::Hourly => 3600,
+        RotateEvery::Daily => 86400,
+    };
+    time.timestamp().div_euclid(seconds)
+}
+
+//FILES NEXT TO path NAMED path.<rotation suffix>, OTHER FILES IN THE DIRECTORY ARE LEFT ALONE
+fn rotated_files(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let name = match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) => format!("{}.", name),
+        None => return Ok(Vec::new()),
+    };
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let mut rotated = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let suffix = file_name.to_str().and_then(|file_name| file_name.strip_prefix(&name));
+        if suffix.is_some_and(|suffix| chrono::NaiveDateTime::parse_from_str(suffix, ROTATED_SUFFIX_FORMAT).is_ok()) {
+            rotated.push(entry.path());
+        }
+    }
+    Ok(rotated)
+}
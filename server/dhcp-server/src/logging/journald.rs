@@ -0,0 +1,54 @@
+//SYSTEMD JOURNAL NATIVE PROTOCOL, EVERY FIELD BECOMES A JOURNAL FIELD
+//SO journalctl SYSLOG_IDENTIFIER=dhcp-server CLIENT_ID=... FINDS ONE CLIENT'S TRANSACTIONS
+use super::record::{Format, Record};
+use super::syslog::severity;
+
+const IDENTIFIER: &str = "dhcp-server";
+//JOURNAL FIELD NAMES ARE AT MOST 64 CHARACTERS
+const MAX_FIELD_NAME: usize = 64;
+
+pub struct Journald;
+
+impl Format for Journald {
+    fn format(&self, record: &Record) -> Vec<u8> {
+        let mut datagram = Vec::new();
+        field(&mut datagram, "MESSAGE", &record.message);
+        field(&mut datagram, "PRIORITY", &severity(record.level).to_string());
+        field(&mut datagram, "SYSLOG_IDENTIFIER", IDENTIFIER);
+        field(&mut datagram, "TARGET", record.target);
+        for (name, value) in &record.fields {
+            if let Some(name) = field_name(name) {
+                field(&mut datagram, &name, value);
+            }
+        }
+        datagram
+    }
+}
+
+//NAME=value, OR THE LENGTH PREFIXED FORM WHEN THE VALUE SPANS LINES
+fn field(datagram: &mut Vec<u8>, name: &str, value: &str) {
+    datagram.extend_from_slice(name.as_bytes());
+    if value.contains('\n') {
+        datagram.push(b'\n');
+        datagram.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        datagram.push(b'=');
+    }
+    datagram.extend_from_slice(value.as_bytes());
+    datagram.push(b'\n');
+}
+
+//UPPERCASE LETTERS, DIGITS AND '_', NOT STARTING WITH '_' WHICH IS KEPT FOR TRUSTED FIELDS
+//NAMES THE EVENT COULD OVERWRITE (MESSAGE, PRIORITY...) GET A PREFIX
+fn field_name(name: &str) -> Option<String> {
+    let name: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .skip_while(|&c| c == '_' || c.is_ascii_digit())
+        .take(MAX_FIELD_NAME)
+        .collect();
+    match name.as_str() {
+        "" => None,
+        "MESSAGE" | "PRIORITY" | "SYSLOG_IDENTIFIER" | "TARGET" => Some(format!("DHCP_{}", name)),
+        _ => Some(name),
+    }
+}
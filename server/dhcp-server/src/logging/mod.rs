@@ -0,0 +1,137 @@
+//STRUCTURED LOGGING FOR THE SERVER BINARY, OR AN EMBEDDER THAT WANTS THE SAME OUTPUT
+//EVERY EVENT GOES TO EACH SINK OF THE logging CONFIG: STDOUT AND FILES AS TEXT OR ONE JSON OBJECT PER LINE,
+//SYSLOG AND JOURNALD WITH THE FIELDS KEPT SEPARATE
+//LEVELS COME FROM RUST_LOG (E.G. info OR dhcp_server=debug,tokio_postgres=warn), info WHEN IT IS NOT SET
+mod file;
+mod journald;
+mod queue;
+mod record;
+mod syslog;
+
+use std::error::Error;
+use std::fmt;
+use std::io::IsTerminal;
+use std::str::FromStr;
+use std::time::Duration;
+
+use tracing::Subscriber;
+use tracing_subscriber::filter::EnvFilter;
+use tracing_subscriber::layer::{Layer, SubscriberExt};
+use tracing_subscriber::registry::Registry;
+use tracing_subscriber::util::SubscriberInitExt;
+
+use crate::server_config::server_config::{LogSink, LoggingConfig};
+use queue::Queue;
+use record::RecordLayer;
+
+//HOW LONG A FLUSH WAITS FOR EACH SINK TO WRITE WHAT IS QUEUED
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    //SPAN FIELDS (xid, mac, client_id...) ARE REPEATED ON EVERY LINE SO EACH ONE STANDS ON ITS OWN
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("unknown log format {:?}, expected text or json", format)),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LogFormat::Text => write!(f, "text"),
+            LogFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+//INSTALL THE GLOBAL SUBSCRIBER, FAILS IF ONE IS ALREADY SET OR A SINK CANNOT BE OPENED
+//KEEP THE GUARD UNTIL THE PROCESS EXITS SO THE LAST LINES ARE WRITTEN
+pub fn init(format: LogFormat, config: &LoggingConfig) -> Result<LogGuard, Box<dyn Error>> {
+    let (subscriber, guard) = subscriber(format, config)?;
+    subscriber.try_init()?;
+    Ok(guard)
+}
+
+//THE SUBSCRIBER init INSTALLS, FOR USE WITH tracing::subscriber::with_default
+pub fn subscriber(format: LogFormat, config: &LoggingConfig) -> Result<(impl Subscriber + Send + Sync, LogGuard), Box<dyn Error>> {
+    let mut layers = Vec::new();
+    let mut queues = Vec::new();
+    for sink in &config.sinks {
+        let (layer, queue) = match sink {
+            LogSink::Stdout => {
+                let queue = Queue::spawn("stdout", queue::Stdout, config.queue_size)?;
+                //NO COLOURS WHEN STDOUT IS PIPED INTO A FILE OR ANOTHER PROGRAM
+                (lines(format, queue.clone(), std::io::stdout().is_terminal()), queue)
+            }
+            LogSink::File(file) => {
+                let rotating = file::RotatingFile::open(file)
+                    .map_err(|e| format!("Failed to open log file {}: {}", file.path, e))?;
+                let queue = Queue::spawn("file", rotating, config.queue_size)?;
+                (lines(format, queue.clone(), false), queue)
+            }
+            LogSink::Syslog(syslog) => {
+                let transport = syslog::transport(&syslog.address)
+                    .map_err(|e| format!("Failed to open syslog socket {:?}: {}", syslog.address, e))?;
+                let queue = Queue::spawn("syslog", transport, config.queue_size)?;
+                (RecordLayer::new(syslog::Syslog::new(syslog), queue.clone()).boxed(), queue)
+            }
+            LogSink::Journald { socket } => {
+                let transport = queue::UnixDatagramSink::connect(socket)
+                    .map_err(|e| format!("Failed to open journald socket {}: {}", socket, e))?;
+                let queue = Queue::spawn("journald", transport, config.queue_size)?;
+                (RecordLayer::new(journald::Journald, queue.clone()).boxed(), queue)
+            }
+        };
+        layers.push(layer);
+        queues.push(queue);
+    }
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let subscriber = tracing_subscriber::registry()
+        .with(layers)
+        .with(filter);
+    Ok((subscriber, LogGuard { queues }))
+}
+
+//ONE FORMATTED LINE PER EVENT, FOR STDOUT AND FILES
+fn lines(format: LogFormat, queue: Queue, ansi: bool) -> BoxedLayer {
+    let layer = tracing_subscriber::fmt::layer().with_writer(queue);
+    match format {
+        LogFormat::Text => layer.with_ansi(ansi).boxed(),
+        LogFormat::Json => layer.json().with_current_span(false).with_span_list(true).boxed(),
+    }
+}
+
+//WRITES OUT WHAT THE SINKS STILL HAVE QUEUED WHEN DROPPED
+#[must_use = "queued log lines are only written out while the guard is kept"]
+pub struct LogGuard {
+    queues: Vec<Queue>,
+}
+
+impl LogGuard {
+    //WAITS AT MOST A SECOND PER SINK, A SINK THAT IS STUCK DOES NOT HOLD UP THE REST FOREVER
+    pub fn flush(&self) {
+        for queue in &self.queues {
+            queue.flush(FLUSH_TIMEOUT);
+        }
+    }
+}
+
+impl Drop for LogGuard {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
@@ -0,0 +1,183 @@
+//EVERY SINK WRITES FROM ITS OWN THREAD, AN EVENT ONLY HANDS OVER THE FINISHED LINE
+//WHEN A SINK FALLS queue_size LINES BEHIND NEW LINES ARE DROPPED AND COUNTED
+//SO A STALLED DISK OR SYSLOG DAEMON NEVER MAKES THE PACKET HANDLERS WAIT
+use std::io::{self, Write};
+use std::os::unix::net::UnixDatagram;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tracing_subscriber::fmt::MakeWriter;
+
+pub trait Sink: Send + 'static {
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()>;
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Sink for Box<dyn Sink> {
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        (**self).write_line(line)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (**self).flush()
+    }
+}
+
+enum Message {
+    Line(Vec<u8>),
+    //ANSWERED ONCE EVERY LINE QUEUED BEFORE IT IS WRITTEN
+    Flush(SyncSender<()>),
+}
+
+#[derive(Clone)]
+pub struct Queue {
+    sender: SyncSender<Message>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl Queue {
+    pub fn spawn(name: &str, sink: impl Sink, capacity: usize) -> io::Result<Queue> {
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let name = name.to_string();
+        let counter = Arc::clone(&dropped);
+        thread::Builder::new()
+            .name(format!("log-{}", name))
+            .spawn(move || run(&name, sink, receiver, &counter))?;
+        Ok(Queue { sender, dropped })
+    }
+
+    pub fn push(&self, line: Vec<u8>) {
+        if let Err(TrySendError::Full(_)) = self.sender.try_send(Message::Line(line)) {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    //WAIT FOR THE LINES QUEUED SO FAR, FALSE WHEN THE SINK DID NOT CATCH UP IN TIME
+    pub fn flush(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let (done, wait) = mpsc::sync_channel(1);
+        let mut message = Message::Flush(done);
+        loop {
+            match self.sender.try_send(message) {
+                Ok(()) => break,
+                Err(TrySendError::Full(returned)) if Instant::now() < deadline => {
+                    message = returned;
+                    thread::sleep(Duration::from_millis(5));
+                }
+                Err(_) => return false,
+            }
+        }
+        wait.recv_timeout(deadline.saturating_duration_since(Instant::now())).is_ok()
+    }
+}
+
+fn run(name: &str, mut sink: impl Sink, receiver: Receiver<Message>, dropped: &AtomicU64) {
+    //ONLY THE FIRST ERROR OF AN OUTAGE IS REPORTED
+    let mut failing = false;
+    for message in receiver {
+        match message {
+            Message::Line(line) => match sink.write_line(&line) {
+                Ok(()) => failing = false,
+                Err(e) => {
+                    if !failing {
+                        //THE SINK ITSELF IS BROKEN, LOGGING THIS THROUGH tracing MIGHT NOT GET ANYWHERE EITHER
+                        eprintln!("Log sink {} failed, its lines are lost until it recovers: {}", name, e);
+                    }
+                    failing = true;
+                }
+            },
+            Message::Flush(done) => {
+                let _ = sink.flush();
+                let _ = done.send(());
+            }
+        }
+        let lost = dropped.swap(0, Ordering::Relaxed);
+        if lost > 0 {
+            tracing::warn!(sink = name, dropped = lost, "Log sink fell behind, lines were dropped");
+        }
+    }
+}
+
+//LETS A fmt LAYER WRITE INTO THE QUEUE, ONE LINE PER EVENT
+impl<'a> MakeWriter<'a> for Queue {
+    type Writer = QueueLine<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        QueueLine { queue: self, line: Vec::new() }
+    }
+}
+
+pub struct QueueLine<'a> {
+    queue: &'a Queue,
+    line: Vec<u8>,
+}
+
+impl Write for QueueLine<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.line.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for QueueLine<'_> {
+    fn drop(&mut self) {
+        if !self.line.is_empty() {
+            self.queue.push(std::mem::take(&mut self.line));
+        }
+    }
+}
+
+pub struct Stdout;
+
+impl Sink for Stdout {
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        io::stdout().lock().write_all(line)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().lock().flush()
+    }
+}
+
+//DATAGRAMS TO A LOCAL DAEMON, RECONNECTED AFTER A FAILURE SO A RESTARTED DAEMON IS PICKED UP AGAIN
+pub struct UnixDatagramSink {
+    path: PathBuf,
+    socket: Option<UnixDatagram>,
+}
+
+impl UnixDatagramSink {
+    pub fn connect(path: &str) -> io::Result<Self> {
+        let path = PathBuf::from(path);
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(&path)?;
+        Ok(UnixDatagramSink { path, socket: Some(socket) })
+    }
+}
+
+impl Sink for UnixDatagramSink {
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        let socket = match self.socket.take() {
+            Some(socket) => socket,
+            None => {
+                let socket = UnixDatagram::unbound()?;
+                socket.connect(&self.path)?;
+                socket
+            }
+        };
+        socket.send(line)?;
+        self.socket = Some(socket);
+        Ok(())
+    }
+}
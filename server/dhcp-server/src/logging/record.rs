@@ -0,0 +1,117 @@
+//EVENTS FLATTENED INTO A MESSAGE AND NAME/VALUE FIELDS FOR THE SYSLOG AND JOURNALD SINKS
+//THE FIELDS OF THE ENCLOSING SPANS (xid, mac, client_id...) COME FIRST, THEN THOSE OF THE EVENT
+use std::fmt::Debug;
+
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record as SpanRecord};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use super::queue::Queue;
+
+pub struct Record {
+    pub level: Level,
+    pub target: &'static str,
+    pub message: String,
+    pub fields: Vec<(&'static str, String)>,
+}
+
+//TURNS A RECORD INTO THE BYTES ONE DATAGRAM CARRIES
+pub trait Format: Send + Sync + 'static {
+    fn format(&self, record: &Record) -> Vec<u8>;
+}
+
+pub struct RecordLayer<F> {
+    format: F,
+    queue: Queue,
+}
+
+impl<F: Format> RecordLayer<F> {
+    pub fn new(format: F, queue: Queue) -> Self {
+        RecordLayer { format, queue }
+    }
+}
+
+//FIELDS OF A SPAN, KEPT IN ITS EXTENSIONS
+//BOTH RECORD LAYERS MAY STORE THE SAME VALUES, SO SETTING A FIELD REPLACES IT
+#[derive(Default)]
+struct SpanFields(Vec<(&'static str, String)>);
+
+impl Visit for SpanFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        set(&mut self.0, field.name(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        set(&mut self.0, field.name(), format!("{:?}", value));
+    }
+}
+
+fn set(fields: &mut Vec<(&'static str, String)>, name: &'static str, value: String) {
+    match fields.iter_mut().find(|(known, _)| *known == name) {
+        Some(field) => field.1 = value,
+        None => fields.push((name, value)),
+    }
+}
+
+struct EventFields<'a>(&'a mut Record);
+
+impl Visit for EventFields<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "message" => self.0.message = value.to_string(),
+            name => set(&mut self.0.fields, name, value.to_string()),
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        match field.name() {
+            "message" => self.0.message = format!("{:?}", value),
+            name => set(&mut self.0.fields, name, format!("{:?}", value)),
+        }
+    }
+}
+
+impl<S, F> Layer<S> for RecordLayer<F>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    F: Format,
+{
+    fn on_new_span(&self, attributes: &Attributes<'_>, id: &Id, context: Context<'_, S>) {
+        if let Some(span) = context.span(id) {
+            let mut extensions = span.extensions_mut();
+            if extensions.get_mut::<SpanFields>().is_none() {
+                extensions.insert(SpanFields::default());
+            }
+            if let Some(fields) = extensions.get_mut::<SpanFields>() {
+                attributes.record(fields);
+            }
+        }
+    }
+
+    //E.G. relay, RECORDED ONCE THE SPAN EXISTS
+    fn on_record(&self, id: &Id, values: &SpanRecord<'_>, context: Context<'_, S>) {
+        if let Some(span) = context.span(id) {
+            if let Some(fields) = span.extensions_mut().get_mut::<SpanFields>() {
+                values.record(fields);
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, context: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut record = Record { level: *metadata.level(), target: metadata.target(), message: String::new(), fields: Vec::new() };
+        if let Some(scope) = context.event_scope(event) {
+            for span in scope.from_root() {
+                if let Some(fields) = span.extensions().get::<SpanFields>() {
+                    for (name, value) in &fields.0 {
+                        set(&mut record.fields, name, value.clone());
+                    }
+                }
+            }
+        }
+        event.record(&mut EventFields(&mut record));
+        self.queue.push(self.format.format(&record));
+    }
+}
@@ -0,0 +1,105 @@
+//RFC 5424 SYSLOG, ONE MESSAGE PER DATAGRAM OVER UDP (RFC 5426) OR A LOCAL UNIX SOCKET
+//THE FIELDS ARE SENT AS STRUCTURED DATA: <30>1 2026-10-19T00:41:12.416503Z host dhcp-server 42 - [fields@32473 xid="0x3281538d" ...] DHCP Discover answered with an offer
+use std::io;
+use std::net::{Ipv4Addr, UdpSocket};
+
+use chrono::Utc;
+use tracing::Level;
+
+use super::queue::{Sink, UnixDatagramSink};
+use super::record::{Format, Record};
+use crate::server_config::server_config::{SyslogAddress, SyslogSink};
+
+//32473 IS THE ENTERPRISE NUMBER RFC 5612 SETS ASIDE FOR DOCUMENTATION, FINE FOR PRIVATE SD-IDS
+const SD_ID: &str = "fields@32473";
+//LONGEST SD-NAME RFC 5424 ALLOWS
+const MAX_PARAM_NAME: usize = 32;
+
+pub struct Syslog {
+    facility: u8,
+    hostname: String,
+    app_name: String,
+    pid: u32,
+}
+
+impl Syslog {
+    pub fn new(sink: &SyslogSink) -> Self {
+        Syslog { facility: sink.facility, hostname: hostname(), app_name: sink.app_name.clone(), pid: std::process::id() }
+    }
+}
+
+impl Format for Syslog {
+    fn format(&self, record: &Record) -> Vec<u8> {
+        let priority = self.facility as u32 * 8 + severity(record.level) as u32;
+        let timestamp = Utc::now().format("%Y-%m-%dT%H:%M:%S%.6fZ");
+        let mut line = format!("<{}>1 {} {} {} {} - ", priority, timestamp, self.hostname, self.app_name, self.pid);
+        line.push('[');
+        line.push_str(SD_ID);
+        for (name, value) in std::iter::once(&("target", record.target.to_string())).chain(&record.fields) {
+            line.push_str(&format!(" {}=\"{}\"", param_name(name), escape(value)));
+        }
+        line.push_str("] ");
+        line.push_str(&record.message);
+        line.into_bytes()
+    }
+}
+
+//NUMERIC SEVERITY, ALSO USED AS THE JOURNAL PRIORITY
+pub fn severity(level: Level) -> u8 {
+    match level {
+        Level::ERROR => 3,
+        Level::WARN => 4,
+        Level::INFO => 6,
+        Level::DEBUG | Level::TRACE => 7,
+    }
+}
+
+pub fn transport(address: &SyslogAddress) -> io::Result<Box<dyn Sink>> {
+    match address {
+        SyslogAddress::Udp(address) => {
+            let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+            socket.connect(address)?;
+            Ok(Box::new(Udp(socket)))
+        }
+        SyslogAddress::Unix(path) => Ok(Box::new(UnixDatagramSink::connect(path)?)),
+    }
+}
+
+struct Udp(UdpSocket);
+
+impl Sink for Udp {
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        self.0.send(line).map(|_| ())
+    }
+}
+
+//SD-NAMES ARE PRINTABLE ASCII WITHOUT '=', ' ', ']' AND '"'
+fn param_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_graphic() && !matches!(c, '=' | ']' | '"') { c } else { '_' })
+        .take(MAX_PARAM_NAME)
+        .collect()
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+//"-" IS THE NILVALUE WHEN THE NAME CANNOT BE READ
+fn hostname() -> String {
+    let mut buffer = [0u8; 256];
+    //gethostname NUL TERMINATES WITHIN THE BUFFER ON SUCCESS
+    let result = unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len()) };
+    let length = buffer.iter().position(|&b| b == 0).unwrap_or(0);
+    match std::str::from_utf8(&buffer[..length]) {
+        Ok(name) if result == 0 && !name.is_empty() && name.bytes().all(|b| b.is_ascii_graphic()) => name.to_string(),
+        _ => "-".to_string(),
+    }
+}
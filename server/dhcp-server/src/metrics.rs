@@ -0,0 +1,205 @@
+//PROMETHEUS METRICS, SERVED AT /metrics ON THEIR OWN PORT
+//COUNTERS ARE BUMPED WHILE HANDLING MESSAGES, THE POOL GAUGES ARE READ FROM THE STORE ON EVERY SCRAPE
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use chrono::{DateTime, Utc};
+use prometheus::{exponential_buckets, HistogramOpts, HistogramTimer, HistogramVec, IntCounter,
+                 IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder};
+use tokio::net::TcpListener;
+
+use dhcp_proto::options::message_type_name;
+
+use crate::server::{NakReason, Server};
+use crate::store::{AddressRecord, AddressState};
+
+pub struct Metrics {
+    registry: Registry,
+    received: IntCounterVec,
+    sent: IntCounterVec,
+    send_failures: IntCounter,
+    malformed: IntCounter,
+    naks: IntCounterVec,
+    declines: IntCounter,
+    releases: IntCounter,
+    store_errors: IntCounter,
+    pool_exhausted: IntCounter,
+    handler_duration: HistogramVec,
+    pool_addresses: IntGaugeVec,
+    hooks: HookMetrics,
+}
+
+//COUNTERS THE HOOK WORKERS BUMP FROM THEIR OWN TASKS
+#[derive(Clone)]
+pub struct HookMetrics {
+    dropped: IntCounterVec,
+    failed: IntCounterVec,
+}
+
+impl HookMetrics {
+    pub fn dropped(&self, hook: &str) {
+        self.dropped.with_label_values(&[hook]).inc();
+    }
+
+    pub fn failed(&self, hook: &str) {
+        self.failed.with_label_values(&[hook]).inc();
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+        let metrics = Metrics {
+            received: IntCounterVec::new(Opts::new("dhcp_messages_received_total",
+                "DHCP messages received, by message type"), &["type"]).unwrap(),
+            sent: IntCounterVec::new(Opts::new("dhcp_messages_sent_total",
+                "DHCP messages sent, by message type"), &["type"]).unwrap(),
+            send_failures: IntCounter::new("dhcp_send_failures_total",
+                "Replies the socket did not take, dropped like a lost datagram").unwrap(),
+            malformed: IntCounter::new("dhcp_malformed_packets_total",
+                "Packets that could not be parsed as DHCP messages").unwrap(),
+            naks: IntCounterVec::new(Opts::new("dhcp_naks_total",
+                "DHCPREQUESTs answered with a NAK, by reason"), &["reason"]).unwrap(),
+            declines: IntCounter::new("dhcp_declines_total", "Addresses declined by clients").unwrap(),
+            releases: IntCounter::new("dhcp_releases_total", "Addresses released by clients").unwrap(),
+            store_errors: IntCounter::new("dhcp_store_errors_total", "Failed lease store operations").unwrap(),
+            pool_exhausted: IntCounter::new("dhcp_pool_exhausted_total",
+                "DHCPDISCOVERs left unanswered because no address was free").unwrap(),
+            //0.5 MS UP TO ABOUT 4 SECONDS, MOST OF THE TIME IS SPENT IN THE LEASE STORE
+            handler_duration: HistogramVec::new(HistogramOpts::new("dhcp_handler_duration_seconds",
+                "Time spent handling a DHCP message, by message type")
+                .buckets(exponential_buckets(0.0005, 2.0, 14).unwrap()), &["type"]).unwrap(),
+            pool_addresses: IntGaugeVec::new(Opts::new("dhcp_pool_addresses",
+                "Addresses of the pool, by state (total, allocated, declined, blocked, free)"), &["pool", "state"]).unwrap(),
+            hooks: HookMetrics {
+                dropped: IntCounterVec::new(Opts::new("dhcp_hook_events_dropped_total",
+                    "Lease events not handed to a hook because its queue was full"), &["hook"]).unwrap(),
+                failed: IntCounterVec::new(Opts::new("dhcp_hook_failures_total",
+                    "Lease events a hook still failed on after its last retry"), &["hook"]).unwrap(),
+            },
+            registry,
+        };
+        metrics.registry.register(Box::new(metrics.received.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.sent.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.send_failures.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.malformed.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.naks.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.declines.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.releases.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.store_errors.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.pool_exhausted.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.handler_duration.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.pool_addresses.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.hooks.dropped.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.hooks.failed.clone())).unwrap();
+        metrics
+    }
+
+    pub fn received(&self, message_type: u8) {
+        self.received.with_label_values(&[message_type_name(message_type)]).inc();
+    }
+
+    pub fn sent(&self, message_type: u8) {
+        self.sent.with_label_values(&[message_type_name(message_type)]).inc();
+    }
+
+    pub fn send_failure(&self) {
+        self.send_failures.inc();
+    }
+
+    pub fn malformed(&self) {
+        self.malformed.inc();
+    }
+
+    pub fn nak(&self, reason: NakReason) {
+        self.naks.with_label_values(&[reason.as_str()]).inc();
+    }
+
+    pub fn decline(&self) {
+        self.declines.inc();
+    }
+
+    pub fn release(&self) {
+        self.releases.inc();
+    }
+
+    pub fn store_error(&self) {
+        self.store_errors.inc();
+    }
+
+    pub fn pool_exhausted(&self) {
+        self.pool_exhausted.inc();
+    }
+
+    pub fn hooks(&self) -> HookMetrics {
+        self.hooks.clone()
+    }
+
+    //OBSERVES THE HANDLING TIME WHEN DROPPED
+    pub fn handler_timer(&self, message_type: u8) -> HistogramTimer {
+        self.handler_duration.with_label_values(&[message_type_name(message_type)]).start_timer()
+    }
+
+    //RETIRED ADDRESSES ARE NO LONGER PART OF THE POOL AND NOT COUNTED
+    //DECLINED ALSO COVERS LEASES REVOKED THROUGH THE API, BOTH KEEP THE ADDRESS OUT UNTIL lease_end
+    pub fn set_pool(&self, pool: &str, addresses: &[AddressRecord], now: DateTime<Utc>) {
+        let (mut total, mut allocated, mut declined, mut blocked, mut free) = (0, 0, 0, 0, 0);
+        for record in addresses.iter().filter(|record| !record.retired) {
+            total += 1;
+            match AddressState::of(record, now) {
+                AddressState::Leased => allocated += 1,
+                AddressState::Held => declined += 1,
+                AddressState::Blocked => blocked += 1,
+                AddressState::Retired | AddressState::Free => free += 1,
+            }
+        }
+        //A RELOAD MAY HAVE CHANGED THE POOL, DROP THE SERIES OF THE OLD ONE
+        self.pool_addresses.reset();
+        for (state, count) in [("total", total), ("allocated", allocated), ("declined", declined),
+                               ("blocked", blocked), ("free", free)] {
+            self.pool_addresses.with_label_values(&[pool, state]).set(count);
+        }
+    }
+
+    //TEXT EXPOSITION FORMAT
+    pub fn render(&self) -> String {
+        TextEncoder::new().encode_to_string(&self.registry.gather()).unwrap_or_else(|e| {
+            tracing::error!(error = %e, "Failed to encode metrics");
+            String::new()
+        })
+    }
+}
+
+pub fn router(server: Arc<Server>) -> Router {
+    Router::new()
+        .route("/metrics", get(scrape))
+        .with_state(server)
+}
+
+pub async fn serve(listener: TcpListener, server: Arc<Server>) -> std::io::Result<()> {
+    axum::serve(listener, router(server)).await
+}
+
+async fn scrape(State(server): State<Arc<Server>>) -> Response {
+    let config = server.config();
+    let pool = format!("{}-{}", config.ip_pool.range_start, config.ip_pool.range_end);
+    match server.store().addresses().await {
+        Ok(addresses) => server.metrics().set_pool(&pool, &addresses, server.now()),
+        //STILL SERVE THE COUNTERS, THE STORE ERROR COUNTER SAYS WHAT HAPPENED
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to read the pool for metrics");
+            server.metrics().store_error();
+        }
+    }
+    ([(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], server.metrics().render()).into_response()
+}
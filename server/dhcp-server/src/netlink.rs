@@ -0,0 +1,50 @@
+use nix::errno::Errno;
+use nix::sys::socket::{bind, recv, socket, AddressFamily, MsgFlags, NetlinkAddr, SockFlag, SockProtocol, SockType};
+use std::io;
+use std::os::fd::{AsRawFd, OwnedFd};
+use tokio::io::unix::AsyncFd;
+use tokio::io::Interest;
+
+//NOTICES WHEN AN IPV4 ADDRESS IS ADDED TO OR REMOVED FROM ANY INTERFACE
+//THE MESSAGES ARE NOT PARSED, THE INTERFACE LIST IS SIMPLY READ AGAIN AFTERWARDS
+pub struct AddressWatcher {
+    socket: AsyncFd<OwnedFd>,
+}
+
+impl AddressWatcher {
+    pub fn new() -> io::Result<Self> {
+        let socket = socket(
+            AddressFamily::Netlink,
+            SockType::Raw,
+            SockFlag::SOCK_NONBLOCK | SockFlag::SOCK_CLOEXEC,
+            SockProtocol::NetlinkRoute,
+        )?;
+        bind(socket.as_raw_fd(), &NetlinkAddr::new(0, libc::RTMGRP_IPV4_IFADDR as u32))?;
+        //SAFETY: THE ASYNCFD OWNS THE DESCRIPTOR, SO IT STAYS OPEN FOR AS LONG AS IT IS REGISTERED
+        let socket = unsafe { AsyncFd::register_with_interest(socket, Interest::READABLE) }
+            .map_err(|e| e.into_parts().1)?;
+        Ok(AddressWatcher { socket })
+    }
+
+    //WAIT FOR A CHANGE, A BURST OF CHANGES COUNTS AS ONE
+    pub async fn changed(&self) -> io::Result<()> {
+        let mut buf = [0u8; 8192];
+        loop {
+            let mut guard = self.socket.readable().await?;
+            let mut changed = false;
+            loop {
+                match recv(self.socket.get_ref().as_raw_fd(), &mut buf, MsgFlags::empty()) {
+                    Ok(_) => changed = true,
+                    Err(Errno::EAGAIN) => break,
+                    //THE KERNEL DROPPED MESSAGES, SOMETHING CHANGED FOR SURE
+                    Err(Errno::ENOBUFS) => changed = true,
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            guard.clear_ready();
+            if changed {
+                return Ok(());
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::io;
+use std::mem;
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+const IPV4_HEADER_LEN: usize = 20;
+const UDP_HEADER_LEN: usize = 8;
+const DEFAULT_TTL: u8 = 64;
+
+//SENDS UDP DATAGRAMS STRAIGHT TO A HARDWARE ADDRESS THROUGH AN AF_PACKET SOCKET
+//USED TO UNICAST TO CLIENTS THAT DO NOT HAVE AN IP ADDRESS YET, SO THERE IS NO ARP ENTRY FOR THEM
+//THE KERNEL ADDS THE ETHERNET HEADER, THE IP AND UDP HEADERS ARE BUILT HERE
+//NEEDS CAP_NET_RAW
+//THE SOCKET IS NON-BLOCKING SO A FULL SEND BUFFER NEVER HOLDS UP A RUNTIME THREAD
+pub struct RawSender {
+    socket: OwnedFd,
+}
+
+impl RawSender {
+    pub fn new() -> io::Result<Self> {
+        let fd = unsafe {
+            libc::socket(libc::AF_PACKET, libc::SOCK_DGRAM | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC, (libc::ETH_P_IP as u16).to_be() as libc::c_int)
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(RawSender { socket: unsafe { OwnedFd::from_raw_fd(fd) } })
+    }
+
+    //PACKET SOCKETS DO NOT WAIT FOR ARP, BUT THE SEND BUFFER CAN FILL UP BEHIND A BUSY INTERFACE
+    //THEN THIS FAILS WITH WouldBlock INSTEAD OF WAITING, AND THE CALLER DROPS THE REPLY, THE CLIENT ASKS AGAIN
+    pub fn send(&self, payload: &[u8], source: SocketAddrV4, dest: SocketAddrV4,
+                interface_index: u32, hardware_address: &[u8]) -> io::Result<usize> {
+        if hardware_address.len() > 8 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Hardware address longer than 8 bytes"));
+        }
+        let packet = build_udp_packet(payload, source, dest)?;
+
+        let mut address: libc::sockaddr_ll = unsafe { mem::zeroed() };
+        address.sll_family = libc::AF_PACKET as u16;
+        address.sll_protocol = (libc::ETH_P_IP as u16).to_be();
+        address.sll_ifindex = interface_index as i32;
+        address.sll_halen = hardware_address.len() as u8;
+        address.sll_addr[..hardware_address.len()].copy_from_slice(hardware_address);
+
+        let sent = unsafe {
+            libc::sendto(
+                self.socket.as_raw_fd(),
+                packet.as_ptr() as *const libc::c_void,
+                packet.len(),
+                0,
+                &address as *const libc::sockaddr_ll as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+            )
+        };
+        if sent < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(payload.len())
+    }
+}
+
+//IPV4 + UDP HEADERS FOLLOWED BY THE PAYLOAD
+fn build_udp_packet(payload: &[u8], source: SocketAddrV4, dest: SocketAddrV4) -> io::Result<Vec<u8>> {
+    let udp_len = UDP_HEADER_LEN + payload.len();
+    let total_len = IPV4_HEADER_LEN + udp_len;
+    if total_len > u16::MAX as usize {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "Payload too large for one IPv4 packet"));
+    }
+
+    let mut packet = Vec::with_capacity(total_len);
+    //IPV4 HEADER, NO OPTIONS
+    packet.push(0x45);
+    packet.push(0);
+    packet.extend_from_slice(&(total_len as u16).to_be_bytes());
+    packet.extend_from_slice(&[0, 0]); //IDENTIFICATION
+    packet.extend_from_slice(&[0x40, 0]); //DON'T FRAGMENT
+    packet.push(DEFAULT_TTL);
+    packet.push(libc::IPPROTO_UDP as u8);
+    packet.extend_from_slice(&[0, 0]); //CHECKSUM, FILLED BELOW
+    packet.extend_from_slice(&source.ip().octets());
+    packet.extend_from_slice(&dest.ip().octets());
+    let header_checksum = checksum(&[&packet[..IPV4_HEADER_LEN]]);
+    packet[10..12].copy_from_slice(&header_checksum.to_be_bytes());
+
+    //UDP HEADER
+    let mut udp = Vec::with_capacity(udp_len);
+    udp.extend_from_slice(&source.port().to_be_bytes());
+    udp.extend_from_slice(&dest.port().to_be_bytes());
+    udp.extend_from_slice(&(udp_len as u16).to_be_bytes());
+    udp.extend_from_slice(&[0, 0]);
+    udp.extend_from_slice(payload);
+    let udp_checksum = match checksum(&[&pseudo_header(*source.ip(), *dest.ip(), udp_len as u16), &udp]) {
+        //ZERO MEANS "NO CHECKSUM" IN UDP, SO IT IS SENT AS ALL ONES
+        0 => 0xFFFF,
+        sum => sum,
+    };
+    udp[6..8].copy_from_slice(&udp_checksum.to_be_bytes());
+
+    packet.extend_from_slice(&udp);
+    Ok(packet)
+}
+
+fn pseudo_header(source: Ipv4Addr, dest: Ipv4Addr, udp_len: u16) -> [u8; 12] {
+    let mut header = [0; 12];
+    header[..4].copy_from_slice(&source.octets());
+    header[4..8].copy_from_slice(&dest.octets());
+    header[9] = libc::IPPROTO_UDP as u8;
+    header[10..].copy_from_slice(&udp_len.to_be_bytes());
+    header
+}
+
+//INTERNET CHECKSUM (RFC 1071) OVER THE CONCATENATED PARTS
+//EVERY PART BUT THE LAST MUST HAVE EVEN LENGTH
+fn checksum(parts: &[&[u8]]) -> u16 {
+    let mut sum: u32 = 0;
+    for part in parts {
+        let mut words = part.chunks_exact(2);
+        for word in &mut words {
+            sum += u16::from_be_bytes([word[0], word[1]]) as u32;
+        }
+        if let [last] = words.remainder() {
+            sum += (*last as u32) << 8;
+        }
+    }
+    while sum >> 16 != 0 {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    !(sum as u16)
+}
@@ -0,0 +1,1100 @@
+use crate::utility::types::DHCPMessage;
+use crate::utility::options::*;
+use crate::server_config::{Config, DEFAULT_CONFIG_FILE, generate_ip_pool, load_config};
+use crate::server_config::detect::{address_warnings, apply_detected_address, detect_server_address};
+use crate::set_up::create_db_tables::{CustomError, ReconcileSummary};
+use crate::store::{HistoryEntry, Lease, LeaseStore, LeaseType, ServerResponse};
+use crate::utility::lease::{LeaseTimes, DECLINE_HOLD_SECONDS};
+use crate::utility::hostname::{client_name, names_itself, push_name_options, requested_hostname, sanitize};
+use crate::clock::{Clock, SystemClock};
+use crate::metrics::Metrics;
+use crate::hooks::{Hooks, LeaseEvent, LeaseEventKind};
+use crate::failover::{Binding, Failover};
+use crate::ha::{Election, LeaderLock};
+
+use crate::listener::{Interface, InterfaceTable, Listener, Received};
+use crate::raw_sender::RawSender;
+
+use tokio::time::{timeout, Duration};
+use tokio::task;
+use tracing::{debug, error, info, info_span, warn, Instrument};
+
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use chrono::{DateTime, Utc};
+
+pub struct Server {
+    config: RwLock<Arc<Config>>,
+    config_path: String,
+    listeners: Vec<Listener>,
+    interfaces: InterfaceTable,
+    store: Arc<dyn LeaseStore>,
+    metrics: Metrics,
+    hooks: Hooks,
+    clock: Arc<dyn Clock>,
+    client_port: u16,
+    //UNICASTS REPLIES TO CLIENTS WITHOUT AN ADDRESS, NONE MEANS THOSE REPLIES ARE BROADCAST
+    raw_sender: Option<RawSender>,
+    //NONE WITHOUT A FAILOVER PARTNER
+    failover: Option<Arc<Failover>>,
+    //NONE FOR A SERVER THAT IS ALONE ON ITS DATABASE
+    election: Option<Arc<Election>>,
+}
+
+//WHY A SERVER COULD NOT BE BUILT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    MissingStore,
+    NoListeners,
+    //THE HTTP CLIENT FOR WEBHOOKS COULD NOT BE SET UP
+    Hooks(String),
+    //THE SECONDARY COULD NOT LISTEN FOR ITS FAILOVER PARTNER
+    Failover(String),
+    //THE CONFIG HAS AN ha SECTION BUT NOTHING TO ELECT THE LEADER WITH
+    MissingLeaderLock,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BuildError::MissingStore => write!(f, "no lease store given"),
+            BuildError::NoListeners => write!(f, "no listener given, the server would never receive anything"),
+            BuildError::Hooks(e) => write!(f, "failed to start the lease event hooks: {}", e),
+            BuildError::Failover(e) => write!(f, "failed to listen for the failover partner: {}", e),
+            BuildError::MissingLeaderLock => write!(f, "no leader lock given, the ha section needs one"),
+        }
+    }
+}
+
+impl Error for BuildError {}
+
+//WHY A DHCPREQUEST WAS ANSWERED WITH A NAK, ONE PER CHECK IN should_nak
+//Unavailable AND NotHeld ALSO COVER AN ADDRESS LOST BETWEEN THE CHECKS AND THE WRITE
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NakReason {
+    NoRequestedAddress,
+    OutsidePool,
+    Restricted,
+    HoldsOtherAddress,
+    Unavailable,
+    NotHeld,
+    OtherServer,
+    NoServerIdentifier,
+    StoreError,
+}
+
+impl NakReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NakReason::NoRequestedAddress => "no_requested_address",
+            NakReason::OutsidePool => "outside_pool",
+            NakReason::Restricted => "restricted",
+            NakReason::HoldsOtherAddress => "holds_other_address",
+            NakReason::Unavailable => "unavailable",
+            NakReason::NotHeld => "not_held",
+            NakReason::OtherServer => "other_server",
+            NakReason::NoServerIdentifier => "no_server_identifier",
+            NakReason::StoreError => "store_error",
+        }
+    }
+}
+
+//SETS UP A SERVER FOR EMBEDDING
+//A LEASE STORE AND AT LEAST ONE LISTENER ARE REQUIRED, EVERYTHING ELSE HAS A DEFAULT
+pub struct ServerBuilder {
+    config: Config,
+    config_path: String,
+    listeners: Vec<Listener>,
+    store: Option<Arc<dyn LeaseStore>>,
+    clock: Arc<dyn Clock>,
+    client_port: u16,
+    raw_sender: Option<RawSender>,
+    leader_lock: Option<Arc<dyn LeaderLock>>,
+}
+
+impl ServerBuilder {
+    //FILE RE-READ ON RELOAD
+    pub fn config_path(mut self, config_path: impl Into<String>) -> Self {
+        self.config_path = config_path.into();
+        self
+    }
+
+    pub fn listener(mut self, listener: Listener) -> Self {
+        self.listeners.push(listener);
+        self
+    }
+
+    pub fn listeners(mut self, listeners: impl IntoIterator<Item = Listener>) -> Self {
+        self.listeners.extend(listeners);
+        self
+    }
+
+    //WHERE LEASES ARE KEPT, A PostgresStore OR A MemoryStore
+    pub fn store(mut self, store: Arc<dyn LeaseStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    //PORT REPLIES ARE SENT TO, 68 BY DEFAULT
+    pub fn client_port(mut self, client_port: u16) -> Self {
+        self.client_port = client_port;
+        self
+    }
+
+    pub fn raw_sender(mut self, raw_sender: RawSender) -> Self {
+        self.raw_sender = Some(raw_sender);
+        self
+    }
+
+    //WHAT SERVERS SHARING A DATABASE ELECT THEIR LEADER WITH, A PostgresLock OR A MemoryLock
+    //ONLY USED WITH AN ha SECTION IN THE CONFIG
+    pub fn leader_lock(mut self, leader_lock: Arc<dyn LeaderLock>) -> Self {
+        self.leader_lock = Some(leader_lock);
+        self
+    }
+
+    pub async fn build(self) -> Result<Server, BuildError> {
+        let store = self.store.ok_or(BuildError::MissingStore)?;
+        if self.listeners.is_empty() {
+            return Err(BuildError::NoListeners);
+        }
+        let metrics = Metrics::new();
+        let hooks = Hooks::start(&self.config.hooks, metrics.hooks(), Arc::clone(&store)).map_err(|e| BuildError::Hooks(e.to_string()))?;
+        let failover = match &self.config.failover {
+            Some(failover) => Some(Arc::new(Failover::bind(failover.clone(), Arc::clone(&store), Arc::clone(&self.clock)).await
+                .map_err(|e| BuildError::Failover(e.to_string()))?)),
+            None => None,
+        };
+        let election = match &self.config.ha {
+            Some(ha) => Some(Arc::new(Election::new(ha.clone(), self.leader_lock.ok_or(BuildError::MissingLeaderLock)?))),
+            None => None,
+        };
+        let server = Server {
+            config: RwLock::new(Arc::new(self.config.clone())),
+            config_path: self.config_path,
+            listeners: self.listeners,
+            interfaces: InterfaceTable::new(),
+            store,
+            metrics,
+            hooks,
+            clock: self.clock,
+            client_port: self.client_port,
+            raw_sender: self.raw_sender,
+            failover,
+            election,
+        };
+        let mut config = self.config;
+        server.detect_addresses(&mut config).await;
+        *server.config.write().unwrap() = Arc::new(config);
+        Ok(server)
+    }
+}
+
+impl Server {
+    pub fn builder(config: Config) -> ServerBuilder {
+        ServerBuilder {
+            config,
+            config_path: DEFAULT_CONFIG_FILE.to_string(),
+            listeners: Vec::new(),
+            store: None,
+            clock: Arc::new(SystemClock),
+            client_port: 68,
+            raw_sender: None,
+            leader_lock: None,
+        }
+    }
+
+    //INTERFACES THE SERVER IS BOUND TO, EMPTY WHEN LISTENING ON ALL OF THEM
+    fn bound_interfaces(&self) -> Vec<String> {
+        self.listeners.iter().filter_map(|listener| listener.interface.clone()).collect()
+    }
+
+    //FILL IN THE SERVER IDENTITY FROM THE INTERFACES WHEN auto_detect IS SET
+    //AND WARN ABOUT A POOL NO INTERFACE CAN REACH
+    async fn detect_addresses(&self, config: &mut Config) {
+        let interfaces = self.interfaces.all();
+        if config.server.auto_detect {
+            match detect_server_address(config, &interfaces, &self.bound_interfaces()) {
+                Some(detected) => {
+                    apply_detected_address(config, &detected);
+                    info!(address = %detected.address, interface = %detected.interface, "Server address detected");
+                }
+                None => {
+                    warn!("No interface address to take the server identity from, replying from the receiving interface address");
+                }
+            }
+        }
+        for warning in address_warnings(config, &interfaces) {
+            warn!("{}", warning);
+        }
+    }
+
+    //CALLED WHEN AN INTERFACE ADDRESS WAS ADDED OR REMOVED
+    pub async fn refresh_addresses(&self) {
+        self.interfaces.refresh();
+        let current = self.config();
+        if !current.server.auto_detect {
+            return;
+        }
+        let mut config = (*current).clone();
+        self.detect_addresses(&mut config).await;
+        if config.server.ip_address != current.server.ip_address || config.server.subnet_mask != current.server.subnet_mask {
+            info!(from = %current.server.ip_address, to = %config.server.ip_address, "Server identity changed");
+            *self.config.write().unwrap() = Arc::new(config);
+        }
+    }
+
+    //CONFIG CURRENTLY IN SERVICE
+    //EACH MESSAGE IS HANDLED WITH THE CONFIG THAT WAS CURRENT WHEN IT ARRIVED
+    pub fn config(&self) -> Arc<Config> {
+        Arc::clone(&self.config.read().unwrap())
+    }
+
+    //LEASE STORAGE, SHARED WITH THE MANAGEMENT API
+    pub fn store(&self) -> Arc<dyn LeaseStore> {
+        Arc::clone(&self.store)
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    pub fn hooks(&self) -> &Hooks {
+        &self.hooks
+    }
+
+    pub fn failover(&self) -> Option<&Arc<Failover>> {
+        self.failover.as_ref()
+    }
+
+    pub fn election(&self) -> Option<&Arc<Election>> {
+        self.election.as_ref()
+    }
+
+    //WHETHER THIS SERVER ANSWERS CLIENTS, ALWAYS WITHOUT AN ELECTION
+    pub fn is_leader(&self) -> bool {
+        self.election.as_ref().is_none_or(|election| election.is_leader())
+    }
+
+    //RE-READ THE CONFIG FILE, RECONCILE THE IP POOL AND SWAP THE CONFIG IN
+    //ON ANY FAILURE THE OLD CONFIG STAYS IN SERVICE
+    pub async fn reload_config(&self) -> Result<ReconcileSummary, String> {
+        info!(path = %self.config_path, "Reloading configuration");
+        let result = self.try_reload_config().await;
+        match &result {
+            Ok(summary) => info!(added = summary.added, removed = summary.removed, retired = summary.retired,
+                reinstated = summary.reinstated, restricted = summary.restricted,
+                "Configuration reloaded, IP addresses table reconciled"),
+            Err(e) => error!(error = %e, "Configuration reload rejected, keeping previous configuration"),
+        }
+        result
+    }
+
+    async fn try_reload_config(&self) -> Result<ReconcileSummary, String> {
+        let mut config = load_config(&self.config_path).map_err(|e| e.to_string())?;
+        self.detect_addresses(&mut config).await;
+
+        let ip_pool = generate_ip_pool(config.ip_pool.range_start, config.ip_pool.range_end);
+        let summary = self.store.reconcile(&ip_pool, &config.restricted_ips, self.clock.now()).await
+            .map_err(|e| e.to_string())?;
+
+        if config.logging != self.config().logging {
+            warn!("Logging configuration changed - the new sinks are used after a restart");
+        }
+        if config.hooks != self.config().hooks {
+            warn!("Hooks changed - they are started again after a restart");
+        }
+        if config.failover != self.config().failover {
+            warn!("Failover configuration changed - it is used after a restart");
+        }
+        if config.ha != self.config().ha {
+            warn!("High availability configuration changed - it is used after a restart");
+        }
+        *self.config.write().unwrap() = Arc::new(config);
+        Ok(summary)
+    }
+
+    //STARTING THE SERVER
+    //EVERY LISTENER GETS ITS OWN RECEIVE LOOP
+    pub async fn start(self: Arc<Self>) {
+        info!(listeners = self.listeners.len(), "Server starting");
+        if let Some(failover) = &self.failover {
+            failover.start();
+        }
+        if let Some(election) = &self.election {
+            election.start();
+        }
+        let receivers: Vec<_> = (0..self.listeners.len())
+            .map(|listener| task::spawn(Arc::clone(&self).receive(listener)))
+            .collect();
+        for receiver in receivers {
+            if let Err(e) = receiver.await {
+                error!(error = %e, "Receive loop stopped");
+            }
+        }
+    }
+
+    async fn receive(self: Arc<Self>, listener: usize) {
+        let mut buf = vec![0; 1500];
+        loop {
+            //TRYING TO RECEIVE RESPONSE FOR 60 SECONDS
+            match timeout(Duration::from_secs(60), self.listeners[listener].recv(&mut buf)).await {
+                Ok(Ok((size, source, interface_index, local_address))) => {
+                    //INDEX 0 MEANS THE KERNEL DID NOT SAY
+                    let interface = self.interfaces.get(interface_index).unwrap_or_else(|| Interface {
+                        name: self.listeners[listener].interface.clone().unwrap_or_else(|| "unknown".to_string()),
+                        index: interface_index,
+                        addresses: Vec::new(),
+                    });
+                    let received = Received { source, interface, local_address, listener };
+                    debug!(size, source = %received.source, interface = %received.interface.name,
+                        local_address = %received.local_address, "Received datagram");
+                    match DHCPMessage::from_buffer(&buf[..size]) {
+                        Ok(dhcp_message) => {
+                            let span = transaction_span(&dhcp_message, &received);
+                            let this = Arc::clone(&self);
+                            task::spawn(
+                                async move {
+                                    this.handle_message(dhcp_message, received, this.config()).await
+                                }.instrument(span)
+                            );
+                        }
+                        Err(e) => {
+                            self.metrics.malformed();
+                            warn!(source = %received.source, error = %e, "Failed to parse DHCP message");
+                        }
+                    }
+                }
+                Ok(Err(e)) => error!(error = %e, "Failed to receive data"),
+                Err(_) => debug!("Receive timed out"),
+            }
+        }
+    }
+
+    //SERVER IDENTIFIER FOR A MESSAGE
+    //THE CONFIGURED ADDRESS IF IT IS ON THE RECEIVING INTERFACE OR THE MESSAGE CAME THROUGH A RELAY
+    //OTHERWISE THE ADDRESS OF THE RECEIVING INTERFACE SO CLIENTS CAN REACH US ON THAT LINK
+    fn server_identifier(config: &Config, message: &DHCPMessage, received: &Received) -> Ipv4Addr {
+        if received.interface.has_address(config.server.ip_address)
+            || message.giaddr != Ipv4Addr::UNSPECIFIED
+            || received.local_address == Ipv4Addr::UNSPECIFIED {
+            config.server.ip_address
+        } else {
+            received.local_address
+        }
+    }
+
+    async fn handle_message(&self, dhcp_message: DHCPMessage, received: Received, config: Arc<Config>) {
+        let server_id = Server::server_identifier(&config, &dhcp_message, &received);
+        let message_type = dhcp_message.options_map.get(&MESSAGE_TYPE).and_then(|v| v.first()).copied().unwrap_or(0);
+        self.metrics.received(message_type);
+        //A STANDBY LEAVES EVERY CLIENT TO THE LEADER, IT DOES NOT EVEN EXPIRE LEASES
+        if !self.is_leader() {
+            debug!("Standing by, message left to the leader");
+            return;
+        }
+        let _timer = self.metrics.handler_timer(message_type);
+        //UPDATE DATABASE BEFORE PROCESSING EVERY MESSAGE
+        self.update_db().await;
+        debug!(message = ?dhcp_message, "Handling message");
+        match message_type {
+            
+            //DHCPDISCOVER
+            DHCPDISCOVER => {
+                if self.left_to_partner(&dhcp_message) {
+                    return;
+                }
+                if let Some(response) = self.build_offer_response(&dhcp_message, &config, server_id).await {
+                    info!(yiaddr = %response.yiaddr, "DHCP Discover answered with an offer");
+                    self.send_response(response, &dhcp_message.ciaddr, &received, server_id).await;
+                }
+            }
+
+            //DHCPREQUEST
+            DHCPREQUEST => {
+                debug!("DHCP Request received");
+                if self.failover.is_some() && self.request_for_partner(&dhcp_message, server_id) {
+                    return;
+                }
+                if let Some(reason) = self.should_nak(&dhcp_message, &config, server_id).await {
+                    self.send_nak(reason, &dhcp_message, &received, &config, server_id).await;
+                    return;
+                }
+                match self.build_ack_response_request(&dhcp_message, &config, server_id).await {
+                    Ok(Some(response)) => {
+                        info!(yiaddr = %response.yiaddr, "DHCP Request answered with an ACK");
+                        self.send_response(response, &dhcp_message.ciaddr, &received, server_id).await;
+                    }
+                    Ok(None) => {}
+                    Err(reason) => self.send_nak(reason, &dhcp_message, &received, &config, server_id).await,
+                }
+            }
+
+            //DHCPDECLINE
+            DHCPDECLINE => {
+                if !Server::for_this_server(&dhcp_message, server_id) {return;} 
+                //STORE ERRORS AND DECLINES FROM OTHER CLIENTS ARE LOGGED WHERE THEY ARE FOUND
+                if self.handle_decline(dhcp_message).await {
+                    self.metrics.decline();
+                }
+            }
+
+            //DHCPRELEASE
+            DHCPRELEASE => {
+                if !Server::for_this_server(&dhcp_message, server_id) {return;} 
+                if self.handle_release(dhcp_message).await {
+                    self.metrics.release();
+                }
+            }
+
+            //DHCPINFORM
+            DHCPINFORM => {
+                 if let Some(response) = self.build_ack_response_inform(&dhcp_message, &config, server_id).await {
+                    info!(ciaddr = %dhcp_message.ciaddr, "DHCP Inform answered with an ACK");
+                    self.send_response(response, &dhcp_message.ciaddr, &received, server_id).await;
+                }
+            }
+
+            //DEFAULT
+            _ => {
+                warn!("Server received invalid DHCP message type");
+            }
+        }
+    }
+
+    async fn send_nak(&self, reason: NakReason, message: &DHCPMessage, received: &Received, config: &Config, server_id: Ipv4Addr) {
+        self.metrics.nak(reason);
+        if let Some(response) = self.build_nak_response(message, config, server_id).await {
+            info!(reason = reason.as_str(), "DHCP Request answered with a NAK");
+            self.send_response(response, &message.ciaddr, received, server_id).await;
+        }
+    }
+
+    //SENDING RESPONSE TO CLIENT
+    //DESTINATION FOLLOWS RFC 2131 SECTION 4.1:
+    //THROUGH A RELAY -> TO THE RELAY ON THE SERVER PORT (NAKS WITH THE BROADCAST FLAG SET)
+    //NAK -> BROADCAST
+    //CLIENT HAS AN ADDRESS -> UNICAST TO CIADDR
+    //BROADCAST FLAG SET -> BROADCAST
+    //OTHERWISE -> UNICAST TO YIADDR AT CHADDR, OR BROADCAST WHEN RAW SENDING IS OFF
+    async fn send_response(&self, mut response: DHCPMessage, addr: &Ipv4Addr, received: &Received, server_id: Ipv4Addr) {
+        let listener = &self.listeners[received.listener];
+        let message_type = response.options_map.get(&MESSAGE_TYPE).and_then(|v| v.first()).copied().unwrap_or(0);
+        let is_nak = message_type == DHCPNAK;
+        let broadcast = SocketAddrV4::new(Ipv4Addr::BROADCAST, self.client_port);
+        let mut hardware_address = None;
+
+        let dest_addr = if response.giaddr != Ipv4Addr::UNSPECIFIED {
+            if is_nak {
+                response.flags |= 0x8000;
+            }
+            SocketAddrV4::new(response.giaddr, listener.local_port())
+        } else if is_nak {
+            broadcast
+        } else if *addr != Ipv4Addr::UNSPECIFIED {
+            SocketAddrV4::new(*addr, self.client_port)
+        } else if response.flags & 0x8000 != 0 {
+            broadcast
+        } else if self.raw_sender.is_some() && response.htype == 1 && response.hlen == 6
+            && response.yiaddr != Ipv4Addr::UNSPECIFIED {
+            hardware_address = Some(&response.chaddr[..6]);
+            SocketAddrV4::new(response.yiaddr, self.client_port)
+        } else {
+            broadcast
+        };
+        //REPLY FROM THE SERVER IDENTIFIER WHEN IT IS ON THE RECEIVING INTERFACE
+        let source = if received.interface.has_address(server_id) {server_id} else {received.local_address};
+        debug!(response = ?response, "Sending response to client");
+
+        let mut response_buffer = response.to_buffer();
+
+        //BOOTP MESSAGES ARE AT LEAST 300 BYTES (236 BYTE HEADER + 64 BYTE VENDOR AREA)
+        //SOME RELAYS AND OLD CLIENTS DROP ANYTHING SHORTER, SO PAD WITH ZEROS (PAD OPTIONS) AFTER END
+        const BOOTP_MIN_MESSAGE_SIZE: usize = 300;
+        if response_buffer.len() < BOOTP_MIN_MESSAGE_SIZE {
+            response_buffer.resize(BOOTP_MIN_MESSAGE_SIZE, 0);
+        }
+        let result = match (&self.raw_sender, hardware_address) {
+            (Some(raw_sender), Some(hardware_address)) => raw_sender.send(
+                &response_buffer,
+                SocketAddrV4::new(source, listener.local_port()),
+                dest_addr,
+                received.interface.index,
+                hardware_address,
+            ),
+            _ => listener.send(&response_buffer, dest_addr, received.interface.index, source).await,
+        };
+        if let Err(e) = result {
+            self.metrics.send_failure();
+            error!(destination = %dest_addr, error = %e, "Failed to send DHCP message");
+            return;
+        }
+        self.metrics.sent(message_type);
+        debug!(reply_type = message_type_name(message_type), destination = %dest_addr, yiaddr = %response.yiaddr,
+            interface = %received.interface.name, source = %source, raw = hardware_address.is_some(), "Sent DHCP message");
+    }
+
+    //FIT THE REPLY OPTIONS INTO WHAT THE CLIENT ACCEPTS AND WARN ABOUT ANYTHING LEFT OUT
+    fn pack_reply_options(&self, options_buf: &[u8], message: &DHCPMessage) -> PackedOptions {
+        let max_message_size = max_reply_size(message);
+        let packed = pack_options(options_buf, max_message_size, message);
+        if !packed.dropped.is_empty() {
+            let dropped = packed.dropped.iter().map(|code| code.to_string()).collect::<Vec<_>>().join(", ");
+            warn!(dropped = %dropped, max_message_size, "Dropped options that did not fit in the reply");
+        }
+        packed
+    }
+
+    fn store_error(&self, e: CustomError) {
+        self.metrics.store_error();
+        error!(error = %e, "Lease store error");
+    }
+
+    //UPDATE ADDRESSES TO CHECK IF SOME LEASES HAVE EXPIRED
+    //ADDRESSES REMOVED FROM THE POOL ARE DROPPED ONCE NOBODY LEASES THEM
+    async fn update_db(&self) {
+        let now = self.clock.now();
+        let cutoff = self.failover.as_ref().map_or(now, |failover| failover.expiry_cutoff(now));
+        match self.store.expire_leases(cutoff).await {
+            Ok(expired) => {
+                for lease in expired.leases {
+                    info!(address = %lease.ip_address, client_id = %lease.client_id, "Lease expired");
+                    self.record(|| Binding::free(&lease));
+                    self.hooks.emit(LeaseEvent::new(LeaseEventKind::Expired, lease.ip_address, &lease.client_id, now)
+                        .lease(lease.lease_start, lease.lease_end)
+                        .name(&lease.name));
+                }
+                if expired.removed > 0 {
+                    info!(removed = expired.removed, "Removed retired IP addresses after their leases ended");
+                }
+            }
+            Err(e) => self.store_error(e),
+        }
+    }
+
+    //HANDLING BUILDING DHCPOFFER RESPONSE TO DHCPDISCOVER
+    //SEARCH FOR FIRST AVAILABLE IP ADDRESS IN DATABASE
+    //AND CREATE OFFER MESSAGE TO CLIENT
+    //RETURNS MESSAGE
+    async fn build_offer_response(&self, message: &DHCPMessage, config: &Config, server_id: Ipv4Addr) -> Option<DHCPMessage> {
+        
+        //CHECK IF CLIENT REQUESTED SPECIFIC IP ADDRESS
+        let requested_ip_address: Ipv4Addr = message.options_map.get(&REQUESTED_IP)
+        .and_then(|v| if v.len() == 4 { Some([v[0], v[1], v[2], v[3]]) } else { None })
+        .map(Ipv4Addr::from)
+        .unwrap_or(Ipv4Addr::new(0, 0, 0, 0));
+
+        let client_id: String = message.chaddr.iter().map(|&c| format!("{:02x}", c)).collect::<Vec<String>>().join("");
+        let found = async {
+            //CHECK IF CLIENT HAS ALREADY ALLOCATED IP ADDRESS
+            if let Some(ip) = self.store.address_of_client(&client_id).await? {
+                return Ok(Some(ip));
+            }
+            //THEN FOR AN ADDRESS RESERVED FOR IT
+            if let Some(ip) = self.store.reservation_for(&client_id).await? {
+                if self.store.is_available(ip, &client_id).await? {
+                    return Ok(Some(ip));
+                }
+            }
+            //IF NOT SEARCH FOR REQUESTED IP ADDRESS
+            if self.may_allocate(requested_ip_address, config)
+                && self.store.is_available(requested_ip_address, &client_id).await? {
+                return Ok(Some(requested_ip_address));
+            }
+            //OTHERWISE SEARCH FOR FIRST AVAILABLE IP ADDRESS, IN A FAILOVER PAIR THE FIRST OF OUR SHARE
+            match &self.failover {
+                Some(failover) => Ok(self.store.available().await?.into_iter().find(|ip| failover.may_allocate(*ip, config))),
+                None => self.store.first_available().await,
+            }
+        };
+        let ip_address = match found.await {
+            Ok(Some(ip_address)) => ip_address,
+            Ok(None) => {
+                self.metrics.pool_exhausted();
+                warn!("No available IP addresses");
+                return None;
+            }
+            Err(e) => {
+                self.store_error(e);
+                return None;
+            }
+        };
+        
+        let name = match client_name(self.store.as_ref(), message, &client_id, &config.hostnames).await {
+            Ok(name) => name,
+            Err(e) => {
+                self.store_error(e);
+                return None;
+            }
+        };
+        let lease = self.lease_times(ip_address, config);
+        let mut options_buf = create_options_buffer(message, config, DHCPOFFER, server_id, Some(&lease));
+        push_name_options(&mut options_buf, message, &name, &config.server.domain_name);
+
+        let bootstrap_server_ip = Ipv4Addr::new(0,0,0,0);
+
+        let packed = self.pack_reply_options(&options_buf, message);
+
+        Some(DHCPMessage::new(
+            2,
+            message.htype,
+            message.hlen,
+            message.hops,
+            message.xid,
+            message.secs,
+            message.flags,
+            message.ciaddr,
+            ip_address,
+            bootstrap_server_ip,
+            message.giaddr,
+            message.chaddr,
+            packed.sname,
+            packed.file,
+            packed.options,
+        ))
+    }
+
+    //HANDLING BUILDING DHCPACK RESPONSE TO DHCPREQUEST
+    //AN ERROR IS THE REASON TO ANSWER WITH A NAK INSTEAD
+    async fn build_ack_response_request(&self, message: &DHCPMessage, config: &Config, server_id: Ipv4Addr)
+        -> Result<Option<DHCPMessage>, NakReason> {
+        let mut ip_address: Ipv4Addr = message.options_map.get(&REQUESTED_IP)
+        .and_then(|v| if v.len() == 4 { Some([v[0], v[1], v[2], v[3]]) } else { None })
+        .map(Ipv4Addr::from)
+        .unwrap_or(Ipv4Addr::new(0, 0, 0, 0));
+        
+        let client_id: String = message.chaddr.iter().map(|&c| format!("{:02x}", c)).collect::<Vec<String>>().join("");
+
+        let renewing;
+        //CHECK IF CLIENT WANTS TO EXTEND LEASE
+        if ip_address == Ipv4Addr::new(0, 0, 0, 0) && message.ciaddr != Ipv4Addr::new(0, 0, 0, 0) {
+            ip_address = message.ciaddr;
+            renewing = true;
+        }   
+        //OTHERWISE LEASE THE REQUESTED IP ADDRESS
+        else{
+            if !Server::for_this_server(message, server_id) {return Ok(None);}
+            renewing = false;
+        }
+        let lease = self.lease_times(ip_address, config);
+        let mut options_buf = create_options_buffer(message, config, DHCPACK, server_id, Some(&lease));
+        //A RENEWING CLIENT THAT DOES NOT NAME ITSELF KEEPS THE NAME IT HAS
+        let name = if renewing && !names_itself(message) {
+            None
+        } else {
+            match client_name(self.store.as_ref(), message, &client_id, &config.hostnames).await {
+                Ok(name) => Some(name),
+                Err(e) => {
+                    self.store_error(e);
+                    return Ok(None);
+                }
+            }
+        };
+        //should_nak LOOKED BEFORE THIS WRITE, ANOTHER SERVER ON THE SAME DATABASE MAY HAVE TAKEN THE ADDRESS SINCE
+        //ONLY WHAT THE WRITE ITSELF FINDS IS TRUSTED, AND A LOST RACE IS ANSWERED WITH A NAK
+        let stored = if renewing {
+            self.store.renew(ip_address, &client_id, &lease, name.as_ref()).await
+                .map(|renewed| renewed.map(|(renewed, previous)| (renewed.name, previous)).ok_or(NakReason::NotHeld))
+        } else {
+            let name = name.unwrap_or_default();
+            match self.store.lease(ip_address, &client_id, &lease, &name).await {
+                Ok(true) => Ok(Ok((name.clone(), name))),
+                //NOT FREE BECAUSE THE CLIENT ALREADY HOLDS IT, A REBOOTING CLIENT ASKING AGAIN
+                Ok(false) => self.store.renew(ip_address, &client_id, &lease, Some(&name)).await
+                    .map(|renewed| renewed.map(|(renewed, previous)| (renewed.name, previous)).ok_or(NakReason::Unavailable)),
+                Err(e) => Err(e),
+            }
+        };
+        let history = HistoryEntry {
+            ip_address,
+            client_id: client_id.clone(),
+            lease_start: Some(lease.start),
+            lease_end: Some(lease.end),
+            server_response: ServerResponse::Ack,
+            lease_type: if renewing {LeaseType::Renewing} else {LeaseType::Initial},
+        };
+        let (name, previous) = match stored {
+            Ok(Ok(names)) => names,
+            Ok(Err(reason)) => {
+                info!(requested = %ip_address, "Address taken while the request was being handled");
+                return Err(reason);
+            }
+            Err(e) => {
+                self.store_error(e);
+                return Ok(None);
+            }
+        };
+        if let Err(e) = self.store.record_history(history).await {
+            self.store_error(e);
+            return Ok(None);
+        }
+        self.record(|| {
+            let granted = Lease { ip_address, client_id: client_id.clone(), lease_start: lease.start, lease_end: lease.end, name: name.clone() };
+            Binding::active(&granted, LeaseTimes::from_config(lease.start, config).end)
+        });
+        let kind = if renewing {LeaseEventKind::Renewed} else {LeaseEventKind::Granted};
+        self.hooks.emit(LeaseEvent::new(kind, ip_address, &client_id, lease.start)
+            .mac(hardware_address(message))
+            .lease(lease.start, lease.end)
+            .name(&name)
+            .previous_name(&previous));
+        push_name_options(&mut options_buf, message, &name, &config.server.domain_name);
+
+        info!(yiaddr = %ip_address, renewing, lease_end = %lease.end.to_rfc3339(), renewal = %lease.renewal.to_rfc3339(),
+            rebinding = %lease.rebinding.to_rfc3339(), "{}", if renewing {"Lease renewed"} else {"Lease granted"});
+        let packed = self.pack_reply_options(&options_buf, message);
+
+        Ok(Some(DHCPMessage::new(
+            2,
+            message.htype,
+            message.hlen,
+            message.hops,
+            message.xid,
+            message.secs,
+            message.flags,
+            message.ciaddr,
+            ip_address,
+            Ipv4Addr::new(0,0,0,0),
+            message.giaddr,
+            message.chaddr,
+            packed.sname,
+            packed.file,
+            packed.options,
+        )))
+    }
+
+    //BUILDING DHCPACK RESPONSE TO DHCPINFORM
+    async fn build_ack_response_inform(&self, message: &DHCPMessage, config: &Config, server_id: Ipv4Addr) -> Option<DHCPMessage> {
+        //IF CLIENT DID NOT REQUEST ANY PARAMETERS
+        //SEND SOME DEFAULT PARAMETERS
+        if !message.options_map.contains_key(&PARAMETER_REQUEST_LIST) {
+            let options_buf = create_options_buffer(message, config, DHCPACK, server_id, None);
+            let packed = self.pack_reply_options(&options_buf, message);
+            Some(DHCPMessage::new(
+                2,
+                message.htype,
+                message.hlen,
+                0,
+                message.xid,
+                0,
+                message.flags,
+                message.ciaddr,
+                message.yiaddr,
+                Ipv4Addr::new(0,0,0,0),
+                message.giaddr,
+                message.chaddr,
+                packed.sname,
+                packed.file,
+                packed.options,
+            ))
+        }
+        //OTHERWISE SEND THE REQUESTED PARAMETERS
+        else{
+            let client_id: String = message.chaddr.iter().map(|&c| format!("{:02x}", c)).collect::<Vec<String>>().join("");
+            let hostname = requested_hostname(message)
+                .and_then(|name| sanitize(&name, &config.hostnames))
+                .unwrap_or_else(|| host_name(&client_id));
+            let options_buf = inform_options_buf(
+                message.options_map.get(&PARAMETER_REQUEST_LIST).unwrap().to_vec(),
+                config,
+                &hostname,
+                server_id
+            );
+            let packed = self.pack_reply_options(&options_buf, message);
+
+            Some(DHCPMessage::new(
+                2,
+                message.htype,
+                message.hlen,
+                0,
+                message.xid,
+                0,
+                message.flags,
+                message.ciaddr,
+                message.yiaddr,
+                Ipv4Addr::new(0,0,0,0),
+                message.giaddr,
+                message.chaddr,
+                packed.sname,
+                packed.file,
+                packed.options,
+            ))
+        }
+    }
+
+    //BUILDING DHCPNAK RESPONSE TO DHCPREQUEST
+    async fn build_nak_response(&self, message: &DHCPMessage, config: &Config, server_id: Ipv4Addr) -> Option<DHCPMessage> {
+        let requested_ip = match message.options_map.get(&REQUESTED_IP) {
+            Some(v) if v.len() == 4 => {
+                Ipv4Addr::new(v[0], v[1], v[2], v[3])
+            },
+            _ => message.ciaddr,
+        };
+        let client_id: String = message.chaddr.iter().map(|&c| format!("{:02x}", c)).collect::<Vec<String>>().join("");
+        let history = HistoryEntry {
+            ip_address: requested_ip,
+            client_id,
+            lease_start: None,
+            lease_end: None,
+            server_response: ServerResponse::Nak,
+            lease_type: LeaseType::Declined,
+        };
+        if let Err(e) = self.store.record_history(history).await {
+            self.store_error(e);
+            return None;
+        }
+        let options_buf = create_options_buffer(message, config, DHCPNAK, server_id, None);
+        Some(DHCPMessage::new(
+            2,
+            message.htype,
+            message.hlen,
+            0,
+            message.xid,
+            0,
+            message.flags,
+            Ipv4Addr::new(0, 0, 0, 0),
+            Ipv4Addr::new(0, 0, 0, 0),
+            Ipv4Addr::new(0, 0, 0, 0),
+            message.giaddr,
+            message.chaddr,
+            [0u8; 64],
+            [0u8; 128],
+            options_buf,
+        ))
+    }
+
+    //HANDLING DECLINE MESSAGE
+    //NO RESPONSE NECESSARY
+    //TRUE WHEN THE SENDER HELD THE ADDRESS, ANYONE ELSE COULD OTHERWISE TAKE IT OUT OF THE POOL
+    async fn handle_decline(&self, message: DHCPMessage) -> bool {
+        let declined_ip_address: Ipv4Addr = message.options_map.get(&REQUESTED_IP)
+        .and_then(|v| if v.len() == 4 { Some([v[0], v[1], v[2], v[3]]) } else { None })
+        .map(Ipv4Addr::from)
+        .unwrap_or(Ipv4Addr::new(0, 0, 0, 0));
+        let client_id: String = message.chaddr.iter().map(|&c| format!("{:02x}", c)).collect::<Vec<String>>().join("");
+
+        let now = self.clock.now();
+        let hold_until = now + chrono::Duration::seconds(DECLINE_HOLD_SECONDS);
+        let declined = match self.store.decline(declined_ip_address, Some(&client_id), now, hold_until).await {
+            Ok(Some(declined)) => declined,
+            Ok(None) => {
+                warn!(address = %declined_ip_address, client_id = %client_id, "Ignoring decline from a client that does not hold the address");
+                return false;
+            }
+            Err(e) => {
+                self.store_error(e);
+                return false;
+            }
+        };
+        self.record(|| Binding::held(declined_ip_address, now, hold_until));
+        self.hooks.emit(LeaseEvent::new(LeaseEventKind::Declined, declined_ip_address, &declined.client_id, now)
+            .mac(hardware_address(&message))
+            .name(&declined.name));
+        warn!(address = %declined_ip_address, hold_until = %hold_until.to_rfc3339(), "IP address declined, held out of the pool");
+        true
+    }
+
+    //HANDLING RELEASE MESSAGE
+    //NO RESPONSE NECESSARY
+    //TRUE WHEN THE SENDER HELD THE ADDRESS, ANYONE ELSE COULD OTHERWISE END THE LEASE
+    async fn handle_release(&self, message: DHCPMessage) -> bool {
+        //RFC 2131 TABLE 5: A RELEASE CARRIES THE ADDRESS IN CIADDR AND MUST NOT HAVE OPTION 50
+        let released_ip_address = message.ciaddr;
+        let client_id: String = message.chaddr.iter().map(|&c| format!("{:02x}", c)).collect::<Vec<String>>().join("");
+
+        let released = match self.store.release(released_ip_address, Some(&client_id)).await {
+            Ok(Some(released)) => released,
+            Ok(None) => {
+                warn!(address = %released_ip_address, client_id = %client_id, "Ignoring release from a client that does not hold the address");
+                return false;
+            }
+            Err(e) => {
+                self.store_error(e);
+                return false;
+            }
+        };
+        self.record(|| Binding::free(&released));
+        self.hooks.emit(LeaseEvent::new(LeaseEventKind::Released, released_ip_address, &released.client_id, self.clock.now())
+            .mac(hardware_address(&message))
+            .name(&released.name));
+        info!(address = %released_ip_address, "IP address released");
+        true
+    }
+
+
+    /*
+     FUNCTIONS FOR CONTROLLING THE SERVER BEHAVIOR
+     */
+    async fn should_nak(&self, message: &DHCPMessage, config: &Config, server_id: Ipv4Addr) -> Option<NakReason> {
+        let mut requested_ip = match message.options_map.get(&REQUESTED_IP) {
+            Some(v) if v.len() == 4 => {
+                Ipv4Addr::new(v[0], v[1], v[2], v[3])
+            },
+            _ => Ipv4Addr::new(0, 0, 0, 0),
+        };
+
+        let renewing: bool = requested_ip == Ipv4Addr::new(0, 0, 0, 0) && message.ciaddr != Ipv4Addr::new(0, 0, 0, 0) ;
+        if requested_ip == Ipv4Addr::new(0, 0, 0, 0) && !renewing {
+            info!("Client requested lease of IP address without requested IP option");
+            return Some(NakReason::NoRequestedAddress);
+        }
+
+        requested_ip = if requested_ip == Ipv4Addr::new(0, 0, 0, 0) {message.ciaddr} else {requested_ip};
+
+        if !config.ip_pool.contains(requested_ip) {
+            info!(requested = %requested_ip, "Client requested lease of IP address outside the server's pool");
+            return Some(NakReason::OutsidePool);
+        }
+
+        if config.restricted_ips.contains(&requested_ip) {
+            info!(requested = %requested_ip, "Client requested lease of a restricted IP address");
+            return Some(NakReason::Restricted);
+        }
+
+        let client_id: String = message.chaddr.iter().map(|&c| format!("{:02x}", c)).collect::<Vec<String>>().join("");
+        
+        match self.store.holds_other_address(&client_id, requested_ip).await {
+            Ok(false) => {}
+            Ok(true) => {
+                info!(requested = %requested_ip, "Client requested lease of IP address while holding another one");
+                return Some(NakReason::HoldsOtherAddress);
+            }
+            Err(e) => {
+                self.store_error(e);
+                return Some(NakReason::StoreError);
+            }
+        };
+
+        match self.store.can_lease(requested_ip, &client_id).await {
+            Ok(true) => {}
+            Ok(false) => {
+                info!(requested = %requested_ip, "Client requested lease of IP address that is not available to it");
+                return Some(NakReason::Unavailable);
+            }
+            Err(e) => {
+                self.store_error(e);
+                return Some(NakReason::StoreError);
+            }
+        };
+
+        //A RENEWING CLIENT MUST STILL HOLD THE ADDRESS, IT MAY HAVE BEEN RELEASED OR REVOKED BY AN ADMINISTRATOR
+        //IN A FAILOVER PAIR A NEW LEASE ALSO HAS TO COME FROM THIS SERVER'S SHARE OF THE POOL
+        if renewing || !self.may_allocate(requested_ip, config) {
+            match self.store.address_of_client(&client_id).await {
+                Ok(Some(held)) if held == requested_ip => {}
+                Ok(_) if renewing => {
+                    info!(requested = %requested_ip, "Client tried to renew IP address it does not hold");
+                    return Some(NakReason::NotHeld);
+                }
+                Ok(_) => {
+                    info!(requested = %requested_ip, "Client requested lease of IP address the failover partner hands out");
+                    return Some(NakReason::Unavailable);
+                }
+                Err(e) => {
+                    self.store_error(e);
+                    return Some(NakReason::StoreError);
+                }
+            }
+        }
+
+        if let Some(server_identifier) = message.options_map.get(&SERVER_IDENTIFIER) {
+            let server_ip = &server_id.octets();
+            if server_identifier != server_ip {
+                info!("Client requested lease of IP from a different server");
+                return Some(NakReason::OtherServer);
+            }
+        } else if !renewing {
+            info!("Client requested lease of IP address without Server Identifier option");
+            return Some(NakReason::NoServerIdentifier);
+        }
+
+        None
+    }   
+
+    //THE LEASE A CLIENT IS GIVEN, CUT SHORT BY THE FAILOVER MCLT
+    fn lease_times(&self, ip: Ipv4Addr, config: &Config) -> LeaseTimes {
+        let lease = LeaseTimes::from_config(self.clock.now(), config);
+        match &self.failover {
+            Some(failover) => lease.capped(failover.lease_end(ip, lease.end, lease.start)),
+            None => lease,
+        }
+    }
+
+    fn may_allocate(&self, ip: Ipv4Addr, config: &Config) -> bool {
+        self.failover.as_ref().is_none_or(|failover| failover.may_allocate(ip, config))
+    }
+
+    //TELL THE FAILOVER PARTNER ABOUT A CHANGE
+    fn record(&self, binding: impl FnOnce() -> Binding) {
+        if let Some(failover) = &self.failover {
+            failover.record(binding());
+        }
+    }
+
+    //A CLIENT LOOKING FOR A SERVER THAT THE FAILOVER PARTNER ANSWERS
+    fn left_to_partner(&self, message: &DHCPMessage) -> bool {
+        let left = self.failover.as_ref().is_some_and(|failover| !failover.serves(message));
+        if left {
+            debug!("Client left to the failover partner");
+        }
+        left
+    }
+
+    //BOTH SERVERS OF A PAIR SEE A BROADCAST REQUEST, ONLY THE ONE IT IS MEANT FOR ANSWERS
+    //A CLIENT THAT CHOSE THE PARTNER IS NOT SENT A NAK, AND ONE REBOOTING IS ANSWERED BY THE SERVER ITS HASH BUCKET BELONGS TO
+    fn request_for_partner(&self, message: &DHCPMessage, server_id: Ipv4Addr) -> bool {
+        match message.options_map.get(&SERVER_IDENTIFIER) {
+            Some(server_identifier) => server_identifier.as_slice() != server_id.octets(),
+            None => message.ciaddr == Ipv4Addr::UNSPECIFIED && self.left_to_partner(message),
+        }
+    }
+
+    fn for_this_server(message: &DHCPMessage, server_id: Ipv4Addr) -> bool {
+        if let Some(server_identifier) = message.options_map.get(&SERVER_IDENTIFIER) {
+            let server_ip = &server_id.octets();
+            if server_identifier != server_ip {
+                debug!("Mismatched Server Identifier");
+                return false;
+            }
+            true
+        } else {
+            debug!("Server Identifier not present in options");
+            false
+        }
+    }
+}
+
+//SPAN EVERY EVENT OF ONE MESSAGE IS LOGGED IN
+//A CLIENT KEEPS ITS xid FOR A WHOLE EXCHANGE, SO THE DISCOVER AND REQUEST OF ONE DORA SHARE IT
+fn transaction_span(message: &DHCPMessage, received: &Received) -> tracing::Span {
+    let message_type = message.options_map.get(&MESSAGE_TYPE).and_then(|v| v.first()).copied().unwrap_or(0);
+    let span = info_span!("transaction",
+        xid = %format_args!("{:#010x}", message.xid),
+        message_type = message_type_name(message_type),
+        mac = %hardware_address(message),
+        client_id = %message.chaddr.iter().map(|&c| format!("{:02x}", c)).collect::<String>(),
+        interface = %received.interface.name,
+        relay = tracing::field::Empty,
+    );
+    if message.giaddr != Ipv4Addr::UNSPECIFIED {
+        span.record("relay", tracing::field::display(message.giaddr));
+    }
+    span
+}
+
+//CLIENT HARDWARE ADDRESS AS AA:BB:CC:DD:EE:FF, hlen BYTES OF chaddr
+fn hardware_address(message: &DHCPMessage) -> String {
+    let length = (message.hlen as usize).min(message.chaddr.len());
+    message.chaddr[..length].iter().map(|byte| format!("{:02X}", byte)).collect::<Vec<_>>().join(":")
+}
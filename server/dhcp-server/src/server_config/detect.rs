@@ -0,0 +1,60 @@
+use ipnet::Ipv4Net;
+
+use crate::listener::Interface;
+use crate::server_config::Config;
+
+//ADDRESS THE SERVER IDENTITY IS TAKEN FROM
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectedAddress {
+    pub interface: String,
+    pub address: Ipv4Net,
+}
+
+//PICK THE LOCAL ADDRESS THE SERVER SHOULD BE KNOWN BY
+//ONLY BOUND INTERFACES ARE CONSIDERED WHEN THE SERVER IS BOUND TO SOME
+//AN ADDRESS WHOSE SUBNET HOLDS THE POOL WINS, OTHERWISE THE FIRST NON LOOPBACK ADDRESS
+pub fn detect_server_address(config: &Config, interfaces: &[Interface], bound: &[String]) -> Option<DetectedAddress> {
+    let mut candidates: Vec<(&Interface, Ipv4Net)> = interfaces.iter()
+        .filter(|interface| bound.is_empty() || bound.contains(&interface.name))
+        .flat_map(|interface| interface.addresses.iter().map(move |address| (interface, *address)))
+        .collect();
+    //GETIFADDRS ORDER IS NOT STABLE, SORT SO THE SAME ADDRESSES ALWAYS GIVE THE SAME ANSWER
+    candidates.sort_by_key(|(interface, address)| (interface.index, *address));
+
+    candidates.iter()
+        .find(|(_, address)| address.contains(&config.ip_pool.range_start) && address.contains(&config.ip_pool.range_end))
+        .or_else(|| candidates.iter().find(|(_, address)| !address.addr().is_loopback()))
+        .or_else(|| candidates.first())
+        .map(|(interface, address)| DetectedAddress { interface: interface.name.clone(), address: *address })
+}
+
+//FILL THE SERVER IDENTITY, SUBNET AND BROADCAST ADDRESS FROM AN INTERFACE ADDRESS
+pub fn apply_detected_address(config: &mut Config, detected: &DetectedAddress) {
+    let address = detected.address;
+    config.server.ip_address = address.addr();
+    config.server.subnet_mask = address.netmask();
+    config.server.subnet = address.trunc();
+    config.options_extended.subnet_mask = address.netmask();
+    config.options_extended.broadcast_address = address.broadcast();
+}
+
+//THINGS THAT WORK BUT ARE PROBABLY NOT WHAT WAS MEANT
+//A POOL NO LOCAL INTERFACE IS ON CAN ONLY BE SERVED THROUGH A RELAY
+pub fn address_warnings(config: &Config, interfaces: &[Interface]) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if config.server.relay_expected {
+        return warnings;
+    }
+    let pool_is_local = interfaces.iter()
+        .flat_map(|interface| interface.addresses.iter())
+        .any(|address| address.contains(&config.ip_pool.range_start) && address.contains(&config.ip_pool.range_end));
+    if !pool_is_local {
+        warnings.push(format!("IP pool {}-{} is not inside any local subnet and no relay is expected (server.relay_expected)",
+            config.ip_pool.range_start, config.ip_pool.range_end));
+    }
+    if config.server.auto_detect && !config.server.subnet.contains(&config.server.default_gateway) {
+        warnings.push(format!("default_gateway {} is outside the detected subnet {}",
+            config.server.default_gateway, config.server.subnet));
+    }
+    warnings
+}
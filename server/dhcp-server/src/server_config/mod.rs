@@ -0,0 +1,8 @@
+pub mod detect;
+pub mod ip_pool;
+pub mod reload;
+#[allow(clippy::module_inception)]
+pub mod server_config;
+
+pub use server_config::{Config, ConfigError, LoggingConfig, DEFAULT_CONFIG_FILE, load_config};
+pub use ip_pool::generate_ip_pool;
\ No newline at end of file
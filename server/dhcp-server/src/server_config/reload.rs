@@ -0,0 +1,73 @@
+use std::sync::Arc;
+
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{info, warn};
+
+use crate::netlink::AddressWatcher;
+use crate::server::Server;
+use crate::set_up::create_db_tables::ReconcileSummary;
+
+type ReloadReply = oneshot::Sender<Result<ReconcileSummary, String>>;
+
+//TRIGGERS A CONFIGURATION RELOAD FROM OUTSIDE THE SIGNAL HANDLER, E.G. AN ADMIN API
+#[derive(Clone)]
+pub struct ReloadHandle {
+    sender: mpsc::Sender<ReloadReply>,
+}
+
+impl ReloadHandle {
+    pub async fn reload(&self) -> Result<ReconcileSummary, String> {
+        let (reply, response) = oneshot::channel();
+        self.sender.send(reply).await.map_err(|_| "Configuration reloader is not running".to_string())?;
+        response.await.map_err(|_| "Configuration reloader stopped during reload".to_string())?
+    }
+}
+
+//RELOAD THE CONFIGURATION ON SIGHUP OR WHEN ASKED THROUGH THE RETURNED HANDLE
+//RELOADS ARE HANDLED ONE AT A TIME
+//INTERFACE ADDRESS CHANGES ARE PICKED UP IN THE SAME LOOP SO THEY NEVER RACE A RELOAD
+pub fn spawn_config_reloader(server: Arc<Server>) -> std::io::Result<ReloadHandle> {
+    let mut hangup = signal(SignalKind::hangup())?;
+    let (sender, mut receiver) = mpsc::channel::<ReloadReply>(8);
+    let mut addresses = match AddressWatcher::new() {
+        Ok(watcher) => Some(watcher),
+        Err(e) => {
+            warn!(error = %e, "Not watching interface addresses");
+            None
+        }
+    };
+
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                Some(()) = hangup.recv() => {
+                    info!("Received SIGHUP");
+                    let _ = server.reload_config().await;
+                }
+                Some(reply) = receiver.recv() => {
+                    let _ = reply.send(server.reload_config().await);
+                }
+                changed = address_change(&addresses) => {
+                    match changed {
+                        Ok(()) => server.refresh_addresses().await,
+                        Err(e) => {
+                            warn!(error = %e, "Stopped watching interface addresses");
+                            addresses = None;
+                        }
+                    }
+                }
+                else => break,
+            }
+        }
+    });
+
+    Ok(ReloadHandle { sender })
+}
+
+async fn address_change(watcher: &Option<AddressWatcher>) -> std::io::Result<()> {
+    match watcher {
+        Some(watcher) => watcher.changed().await,
+        None => std::future::pending().await,
+    }
+}
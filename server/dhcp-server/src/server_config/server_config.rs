@@ -0,0 +1,1052 @@
+use ipnet::Ipv4Net;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::error::Error;
+use std::fmt;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, ToSocketAddrs};
+use std::time::Duration;
+
+use base64::Engine;
+
+use crate::hooks::LeaseEventKind;
+
+pub const DEFAULT_CONFIG_FILE: &str = "app/server-config.json";
+
+//LONGEST VALUE A SINGLE DHCP OPTION CAN CARRY
+const MAX_OPTION_LENGTH: usize = 255;
+
+//LINES A SINK MAY FALL BEHIND BEFORE NEW ONES ARE DROPPED
+const DEFAULT_LOG_QUEUE_SIZE: usize = 1024;
+//ROTATED FILES KEPT WHEN logging.sinks[].keep IS LEFT OUT
+const DEFAULT_LOG_KEEP: usize = 7;
+const DAEMON_FACILITY: u8 = 3;
+//EVENTS A HOOK MAY FALL BEHIND BEFORE NEW ONES ARE DROPPED
+const DEFAULT_HOOK_QUEUE_SIZE: usize = 1000;
+const DEFAULT_HOOK_TIMEOUT_SECONDS: u32 = 5;
+const DEFAULT_HOOK_RETRIES: u32 = 3;
+const DEFAULT_HOOK_RETRY_DELAY_SECONDS: u32 = 1;
+pub const DEFAULT_NOTIFY_CHANNEL: &str = "dhcp_lease_events";
+const DNS_PORT: u16 = 53;
+const DEFAULT_DDNS_TTL: u32 = 300;
+//PORT IANA ASSIGNED TO DHCP FAILOVER
+pub const FAILOVER_PORT: u16 = 647;
+const DEFAULT_MCLT_SECONDS: u32 = 3600;
+const DEFAULT_PEER_TIMEOUT_SECONDS: u32 = 30;
+//HASH BUCKETS OF RFC 3074, THE PRIMARY SERVES THOSE BELOW failover.split
+pub const HASH_BUCKETS: u16 = 256;
+//KEY OF THE ADVISORY LOCK THE SERVERS ON ONE DATABASE ELECT A LEADER WITH
+pub const DEFAULT_HA_LOCK_ID: i64 = 0x4448_4350_4c45_4144;
+const DEFAULT_HA_HEARTBEAT_SECONDS: u32 = 1;
+const DEFAULT_HA_TAKEOVER_SECONDS: u32 = 5;
+//LONGEST IDENTIFIER POSTGRESQL KEEPS
+const MAX_CHANNEL_LENGTH: usize = 63;
+pub const DEFAULT_SYSLOG_SOCKET: &str = "/dev/log";
+pub const DEFAULT_JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub ip_pool: IpPoolConfig,
+    pub restricted_ips: Vec<Ipv4Addr>,
+    pub options_extended: ExtendedConfig,
+    pub logging: LoggingConfig,
+    pub hooks: HooksConfig,
+    pub hostnames: HostnameConfig,
+    //NONE FOR A SERVER WITHOUT A PARTNER
+    pub failover: Option<FailoverConfig>,
+    //NONE FOR A SERVER THAT IS ALONE ON ITS DATABASE
+    pub ha: Option<HaConfig>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub lease_time: Duration,
+    pub renewal_time: Duration,
+    pub rebinding_time: Duration,
+    pub subnet_mask: Ipv4Addr,
+    pub default_gateway: Ipv4Addr,
+    pub dns_server: Ipv4Addr,
+    pub domain_name: String,
+    pub ip_address: Ipv4Addr,
+    //NETWORK OF ip_address AND subnet_mask
+    pub subnet: Ipv4Net,
+    //TAKE ip_address AND subnet_mask FROM THE INTERFACE ADDRESS INSTEAD OF THE FILE
+    pub auto_detect: bool,
+    //POOL IS ON A REMOTE SUBNET SERVED THROUGH A RELAY AGENT
+    pub relay_expected: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct IpPoolConfig {
+    pub range_start: Ipv4Addr,
+    pub range_end: Ipv4Addr,
+}
+
+impl IpPoolConfig {
+    pub fn contains(&self, ip: Ipv4Addr) -> bool {
+        self.range_start <= ip && ip <= self.range_end
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExtendedConfig {
+    pub subnet_mask: Ipv4Addr,
+    pub time_offset: i32,
+    pub router: Vec<Ipv4Addr>,
+    pub time_server: Vec<Ipv4Addr>,
+    pub name_server: Vec<Ipv4Addr>,
+    pub domain_name_server: Vec<Ipv4Addr>,
+    pub log_server: Vec<Ipv4Addr>,
+    pub cookie_server: Vec<Ipv4Addr>,
+    pub lpr_server: Vec<Ipv4Addr>,
+    pub impress_server: Vec<Ipv4Addr>,
+    pub resource_location_server: Vec<Ipv4Addr>,
+    pub boot_file_size: u16,
+    pub merit_dump_file: String,
+    pub domain_name: String,
+    pub swap_server: Ipv4Addr,
+    pub root_path: String,
+    pub extensions_path: String,
+    pub broadcast_address: Ipv4Addr,
+    pub network_time_protocol_servers: Vec<Ipv4Addr>,
+    //EXTEND HERE IF NEEDED
+}
+
+//WHERE LOG LINES GO, EVERY SINK IS WRITTEN FROM ITS OWN THREAD
+//WITHOUT A logging SECTION THIS IS STDOUT PLUS server.log_file WHEN GIVEN
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoggingConfig {
+    pub queue_size: usize,
+    pub sinks: Vec<LogSink>,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        LoggingConfig { queue_size: DEFAULT_LOG_QUEUE_SIZE, sinks: vec![LogSink::Stdout] }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogSink {
+    Stdout,
+    File(FileSink),
+    Syslog(SyslogSink),
+    //NATIVE JOURNAL PROTOCOL, FIELDS LIKE XID AND CLIENT_ID CAN BE MATCHED WITH journalctl
+    Journald { socket: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileSink {
+    pub path: String,
+    //ROTATE BEFORE THE FILE WOULD GROW PAST THIS MANY BYTES
+    pub max_size: Option<u64>,
+    pub rotate: Option<RotateEvery>,
+    //ROTATED FILES KEPT, OLDER ONES ARE DELETED
+    pub keep: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RotateEvery {
+    Hourly,
+    Daily,
+}
+
+//RFC 5424 MESSAGES
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyslogSink {
+    pub address: SyslogAddress,
+    pub facility: u8,
+    pub app_name: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyslogAddress {
+    Udp(SocketAddr),
+    Unix(String),
+}
+
+//HOW THE NAMES CLIENTS SEND IN OPTION 12 OR 81 ARE CLEANED UP BEFORE THEY ARE STORED, RETURNED AND REGISTERED IN DNS
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostnameConfig {
+    pub lowercase: bool,
+    //TAKES THE PLACE OF EVERY CHARACTER THAT IS NOT A LETTER, DIGIT OR HYPHEN, EMPTY DROPS THEM
+    pub replacement: String,
+    //A NAME ANOTHER CLIENT ALREADY HOLDS GETS -2, -3 AND SO ON
+    pub unique: bool,
+    //THE SERVER UPDATES THE A RECORD EVEN WHEN THE CLIENT ASKED TO DO IT ITSELF OR ASKED FOR NO UPDATES
+    pub override_client_updates: bool,
+}
+
+impl Default for HostnameConfig {
+    fn default() -> Self {
+        HostnameConfig { lowercase: true, replacement: "-".to_string(), unique: true, override_client_updates: false }
+    }
+}
+
+//A PAIR OF SERVERS SHARING ONE POOL, EACH KEEPING THE OTHER UP TO DATE ABOUT EVERY LEASE
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailoverConfig {
+    pub role: FailoverRole,
+    pub mode: FailoverMode,
+    //THE SECONDARY ACCEPTS THE PRIMARY'S CONNECTION HERE
+    pub listen: SocketAddr,
+    //THE PRIMARY CONNECTS HERE, THE SECONDARY ONLY ACCEPTS CONNECTIONS FROM ITS ADDRESS
+    pub peer: SocketAddr,
+    //MAXIMUM CLIENT LEAD TIME: HOW FAR A LEASE MAY RUN PAST WHAT THE PARTNER KNOWS ABOUT IT
+    pub mclt: Duration,
+    //HASH BUCKETS BELOW split ARE ANSWERED BY THE PRIMARY, IN load_balance THE SAME SHARE OF THE FREE ADDRESSES IS ITS TO HAND OUT
+    pub split: u16,
+    //THE PEER IS TAKEN FOR GONE AFTER THIS LONG WITHOUT A MESSAGE, HEARTBEATS GO OUT THREE TIMES AS OFTEN
+    pub peer_timeout: Duration,
+    //GO TO PARTNER-DOWN THIS LONG AFTER LOSING THE PEER, ONLY THROUGH THE API WITHOUT IT
+    pub partner_down_after: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FailoverRole {
+    Primary,
+    Secondary,
+}
+
+impl FailoverRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailoverRole::Primary => "primary",
+            FailoverRole::Secondary => "secondary",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailoverMode {
+    //BOTH ANSWER NEW CLIENTS, SPLIT BY HASH BUCKET
+    LoadBalance,
+    //THE PRIMARY ANSWERS EVERY NEW CLIENT WHILE THE PAIR IS TALKING
+    HotStandby,
+}
+
+impl FailoverMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailoverMode::LoadBalance => "load_balance",
+            FailoverMode::HotStandby => "hot_standby",
+        }
+    }
+}
+
+//SEVERAL SERVERS ON ONE DATABASE, THE ONE HOLDING THE LEADER LOCK ANSWERS CLIENTS AND THE OTHERS STAND BY
+#[derive(Debug, Clone, PartialEq)]
+pub struct HaConfig {
+    //KEY OF THE POSTGRESQL ADVISORY LOCK, THE SAME ON EVERY SERVER OF THE GROUP
+    pub lock_id: i64,
+    //WHAT THIS SERVER IS CALLED IN THE LOGS AND THE ha_leader TABLE, THE HOST NAME AND PROCESS ID WHEN LEFT OUT
+    pub node: String,
+    //HOW OFTEN THE LEADER RECORDS THAT IT IS ALIVE AND A STANDBY CHECKS ON IT
+    pub heartbeat: Duration,
+    //A STANDBY TAKES OVER FROM A LEADER WHOSE LAST HEARTBEAT IS OLDER THAN THIS
+    //A LEADER THAT COULD NOT RECORD ONE FOR HALF OF IT STOPS ANSWERING, SO IT IS SILENT BEFORE ANYBODY TAKES OVER
+    pub takeover_after: Duration,
+}
+
+//PROGRAMS AND WEBHOOKS TOLD ABOUT LEASE CHANGES, EACH TARGET IS FED FROM ITS OWN QUEUE
+#[derive(Debug, Clone, PartialEq)]
+pub struct HooksConfig {
+    pub queue_size: usize,
+    pub targets: Vec<HookConfig>,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        HooksConfig { queue_size: DEFAULT_HOOK_QUEUE_SIZE, targets: Vec::new() }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HookConfig {
+    //LABEL IN LOGS AND METRICS
+    pub name: String,
+    pub action: HookAction,
+    //EVENTS THE HOOK IS CALLED FOR, ALL OF THEM WHEN LEFT OUT
+    pub events: Vec<LeaseEventKind>,
+    //PER ATTEMPT
+    pub timeout: Duration,
+    //ATTEMPTS AFTER THE FIRST ONE FAILED, THE DELAY DOUBLES EVERY TIME
+    pub retries: u32,
+    pub retry_delay: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HookAction {
+    //RUN WITH THE EVENT IN DHCP_* ENVIRONMENT VARIABLES AND AS JSON ON STDIN, EXIT CODE 0 IS SUCCESS
+    Command { program: String, args: Vec<String> },
+    //POST THE EVENT AS JSON, ANY 2XX STATUS IS SUCCESS
+    Webhook { url: String, headers: Vec<(String, String)> },
+    //NOTIFY channel WITH THE EVENT AS JSON THROUGH THE LEASE STORE, WITH outbox ALSO KEPT IN THE lease_events TABLE
+    Notify { channel: String, outbox: bool },
+    //RFC 2136 UPDATES ADDING OR REMOVING THE CLIENT'S A, DHCID AND PTR RECORDS
+    Ddns(DdnsConfig),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DdnsConfig {
+    //PRIMARY SERVER OF THE ZONES
+    pub server: SocketAddr,
+    //FORWARD ZONE THE HOST NAMES ARE ADDED TO, server.domain_name WHEN LEFT OUT
+    pub zone: String,
+    //ZONE FOR PTR RECORDS, NONE ARE WRITTEN WITHOUT IT
+    pub reverse_zone: Option<String>,
+    pub ttl: u32,
+    //UPDATES ARE SENT UNSIGNED WITHOUT IT
+    pub key: Option<TsigKey>,
+    //REGISTER CLIENTS THAT SENT NO HOST NAME AS user<mac in hex>, OTHERWISE THEY GET NO RECORDS
+    pub generated_names: bool,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct TsigKey {
+    pub name: String,
+    pub algorithm: TsigAlgorithm,
+    pub secret: Vec<u8>,
+}
+
+//THE SECRET STAYS OUT OF LOGS
+impl fmt::Debug for TsigKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TsigKey").field("name", &self.name).field("algorithm", &self.algorithm).finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TsigAlgorithm {
+    HmacSha256,
+    HmacSha512,
+}
+
+impl TsigAlgorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TsigAlgorithm::HmacSha256 => "hmac-sha256",
+            TsigAlgorithm::HmacSha512 => "hmac-sha512",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        [TsigAlgorithm::HmacSha256, TsigAlgorithm::HmacSha512].into_iter().find(|known| known.name() == name)
+    }
+}
+
+//ONE THING WRONG WITH THE CONFIG FILE AND WHERE IT IS
+#[derive(Debug, Clone)]
+pub struct ConfigProblem {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    IoError(io::Error),
+    JsonError(serde_json::Error),
+    Invalid(Vec<ConfigProblem>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::IoError(e) => write!(f, "Failed to read configuration: {}", e),
+            ConfigError::JsonError(e) => write!(f, "Configuration is not valid JSON: {}", e),
+            ConfigError::Invalid(problems) => {
+                write!(f, "Configuration has {} problem(s): ", problems.len())?;
+                let problems: Vec<String> = problems.iter().map(|p| p.to_string()).collect();
+                write!(f, "{}", problems.join("; "))
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+pub fn load_config(path: &str) -> Result<Config, ConfigError> {
+    let file_content = fs::read_to_string(path).map_err(ConfigError::IoError)?;
+    let value: Value = serde_json::from_str(&file_content).map_err(ConfigError::JsonError)?;
+    parse_config(&value).map_err(ConfigError::Invalid)
+}
+
+//TURN THE JSON DOCUMENT INTO A TYPED CONFIG
+//EVERY PROBLEM IS COLLECTED INSTEAD OF STOPPING AT THE FIRST ONE
+pub fn parse_config(value: &Value) -> Result<Config, Vec<ConfigProblem>> {
+    let mut reader = ConfigReader { problems: Vec::new() };
+
+    let lease_time = reader.seconds(value, "server.lease_time");
+    let renewal_time = reader.seconds(value, "server.renewal_time");
+    let rebinding_time = match lookup(value, "server.rebinding_time") {
+        Some(_) => reader.seconds(value, "server.rebinding_time"),
+        //T2 DEFAULTS TO 7/8 OF THE LEASE TIME AS SUGGESTED BY RFC 2131
+        None => lease_time * 7 / 8,
+    };
+    let auto_detect = reader.flag(value, "server.auto_detect");
+    let relay_expected = reader.flag(value, "server.relay_expected");
+    let subnet_mask = reader.detectable_ip(value, "server.subnet_mask", auto_detect);
+    let ip_address = reader.detectable_ip(value, "server.ip_address", auto_detect);
+    let server = ServerConfig {
+        lease_time,
+        renewal_time,
+        rebinding_time,
+        subnet_mask,
+        default_gateway: reader.ip(value, "server.default_gateway"),
+        dns_server: reader.ip(value, "server.dns_server"),
+        domain_name: reader.option_string(value, "server.domain_name"),
+        ip_address,
+        subnet: reader.subnet(ip_address, subnet_mask, "server.subnet_mask"),
+        auto_detect,
+        relay_expected,
+    };
+
+    let ip_pool = IpPoolConfig {
+        range_start: reader.ip(value, "ip_pool.range_start"),
+        range_end: reader.ip(value, "ip_pool.range_end"),
+    };
+    let restricted_ips = reader.ip_list(value, "restricted_ips");
+
+    let options_extended = ExtendedConfig {
+        subnet_mask: reader.detectable_ip(value, "options_extended.subnet_mask", auto_detect),
+        time_offset: reader.integer(value, "options_extended.time_offset"),
+        router: reader.ip_list(value, "options_extended.router"),
+        time_server: reader.ip_list(value, "options_extended.time_server"),
+        name_server: reader.ip_list(value, "options_extended.name_server"),
+        domain_name_server: reader.ip_list(value, "options_extended.domain_name_server"),
+        log_server: reader.ip_list(value, "options_extended.log_server"),
+        cookie_server: reader.ip_list(value, "options_extended.cookie_server"),
+        lpr_server: reader.ip_list(value, "options_extended.lpr_server"),
+        impress_server: reader.ip_list(value, "options_extended.impress_server"),
+        resource_location_server: reader.ip_list(value, "options_extended.resource_location_server"),
+        boot_file_size: reader.integer(value, "options_extended.boot_file_size"),
+        merit_dump_file: reader.option_string(value, "options_extended.merit_dump_file"),
+        domain_name: reader.option_string(value, "options_extended.domain_name"),
+        swap_server: reader.ip(value, "options_extended.swap_server"),
+        root_path: reader.option_string(value, "options_extended.root_path"),
+        extensions_path: reader.option_string(value, "options_extended.extensions_path"),
+        broadcast_address: reader.detectable_ip(value, "options_extended.broadcast_address", auto_detect),
+        network_time_protocol_servers: reader.ip_list(value, "options_extended.network_time_protocol_servers"),
+    };
+
+    let logging = reader.logging(value);
+    let hooks = reader.hooks(value);
+    let hostnames = reader.hostnames(value);
+    let failover = lookup(value, "failover").map(|_| reader.failover(value));
+    let ha = lookup(value, "ha").map(|_| reader.ha(value));
+
+    let config = Config { server, ip_pool, restricted_ips, options_extended, logging, hooks, hostnames, failover, ha };
+    //CROSS CHECKS ONLY MAKE SENSE ONCE EVERY VALUE PARSED
+    if reader.problems.is_empty() {
+        cross_check(&config, &mut reader.problems);
+    }
+
+    if reader.problems.is_empty() {
+        Ok(config)
+    } else {
+        Err(reader.problems)
+    }
+}
+
+//CHECKS BETWEEN VALUES THAT ARE EACH VALID ON THEIR OWN
+fn cross_check(config: &Config, problems: &mut Vec<ConfigProblem>) {
+    let mut problem = |path: &str, message: String| {
+        problems.push(ConfigProblem { path: path.to_string(), message });
+    };
+    let subnet = config.server.subnet;
+
+    if config.ip_pool.range_start > config.ip_pool.range_end {
+        problem("ip_pool.range_start", format!("{} is after ip_pool.range_end {}",
+            config.ip_pool.range_start, config.ip_pool.range_end));
+    }
+    //A DETECTED SUBNET IS ONLY KNOWN AT RUNTIME AND A RELAYED POOL IS ON ANOTHER SUBNET
+    if !config.server.auto_detect && !config.server.relay_expected {
+        if !subnet.contains(&config.ip_pool.range_start) {
+            problem("ip_pool.range_start", format!("{} is outside the server subnet {}", config.ip_pool.range_start, subnet));
+        }
+        if !subnet.contains(&config.ip_pool.range_end) {
+            problem("ip_pool.range_end", format!("{} is outside the server subnet {}", config.ip_pool.range_end, subnet));
+        }
+        if !subnet.contains(&config.server.default_gateway) {
+            problem("server.default_gateway", format!("{} is outside the server subnet {}", config.server.default_gateway, subnet));
+        }
+    }
+    for (index, ip) in config.restricted_ips.iter().enumerate() {
+        if !config.ip_pool.contains(*ip) {
+            problem(&format!("restricted_ips[{}]", index), format!("{} is outside the IP pool {}-{}",
+                ip, config.ip_pool.range_start, config.ip_pool.range_end));
+        }
+    }
+
+    if config.server.lease_time.is_zero() {
+        problem("server.lease_time", "must be greater than 0".to_string());
+    }
+    if config.server.renewal_time >= config.server.rebinding_time {
+        problem("server.renewal_time", format!("must be shorter than the rebinding time of {} seconds",
+            config.server.rebinding_time.as_secs()));
+    }
+    if config.server.rebinding_time >= config.server.lease_time {
+        problem("server.rebinding_time", format!("must be shorter than the lease time of {} seconds",
+            config.server.lease_time.as_secs()));
+    }
+    //A FAILOVER PAIR KEEPS TWO DATABASES IN STEP, SERVERS ELECTING A LEADER SHARE ONE
+    if config.ha.is_some() && config.failover.is_some() {
+        problem("ha", "cannot be used together with failover".to_string());
+    }
+}
+
+//WALKS THE JSON DOCUMENT BY DOTTED PATH AND RECORDS WHAT IS WRONG
+//ON A PROBLEM A PLACEHOLDER VALUE IS RETURNED SO PARSING CAN CONTINUE
+struct ConfigReader {
+    problems: Vec<ConfigProblem>,
+}
+
+impl ConfigReader {
+    fn problem(&mut self, path: &str, message: impl Into<String>) {
+        self.problems.push(ConfigProblem { path: path.to_string(), message: message.into() });
+    }
+
+    fn get<'a>(&mut self, value: &'a Value, path: &str) -> Option<&'a Value> {
+        let found = lookup(value, path);
+        if found.is_none() {
+            self.problem(path, "missing");
+        }
+        found
+    }
+
+    fn string(&mut self, value: &Value, path: &str) -> String {
+        match self.get(value, path) {
+            Some(Value::String(s)) => s.clone(),
+            Some(other) => {
+                self.problem(path, format!("expected a string, found {}", other));
+                String::new()
+            }
+            None => String::new(),
+        }
+    }
+
+    //STRING SENT AS A DHCP OPTION SO IT HAS TO FIT ITS LENGTH BYTE
+    fn option_string(&mut self, value: &Value, path: &str) -> String {
+        let s = self.string(value, path);
+        if s.len() > MAX_OPTION_LENGTH {
+            self.problem(path, format!("is {} bytes long, a DHCP option holds at most {}", s.len(), MAX_OPTION_LENGTH));
+        }
+        s
+    }
+
+    fn parse_ip(&mut self, value: &Value, path: &str) -> Ipv4Addr {
+        match value {
+            Value::String(s) => match s.parse() {
+                Ok(ip) => ip,
+                Err(_) => {
+                    self.problem(path, format!("{:?} is not a valid IPv4 address", s));
+                    Ipv4Addr::UNSPECIFIED
+                }
+            },
+            other => {
+                self.problem(path, format!("expected an IPv4 address string, found {}", other));
+                Ipv4Addr::UNSPECIFIED
+            }
+        }
+    }
+
+    fn ip(&mut self, value: &Value, path: &str) -> Ipv4Addr {
+        match self.get(value, path) {
+            Some(found) => self.parse_ip(found, path),
+            None => Ipv4Addr::UNSPECIFIED,
+        }
+    }
+
+    //MAY BE LEFT OUT WHEN IT IS DETECTED FROM THE INTERFACE
+    fn detectable_ip(&mut self, value: &Value, path: &str, auto_detect: bool) -> Ipv4Addr {
+        if auto_detect && lookup(value, path).is_none() {
+            return Ipv4Addr::UNSPECIFIED;
+        }
+        self.ip(value, path)
+    }
+
+    fn ip_list(&mut self, value: &Value, path: &str) -> Vec<Ipv4Addr> {
+        match self.get(value, path) {
+            Some(Value::Array(items)) => {
+                if items.len() * 4 > MAX_OPTION_LENGTH {
+                    self.problem(path, format!("has {} addresses, a DHCP option holds at most {}",
+                        items.len(), MAX_OPTION_LENGTH / 4));
+                }
+                items.iter().enumerate()
+                    .map(|(index, item)| self.parse_ip(item, &format!("{}[{}]", path, index)))
+                    .collect()
+            }
+            Some(other) => {
+                self.problem(path, format!("expected a list of IPv4 addresses, found {}", other));
+                Vec::new()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    fn integer<T: TryFrom<i64> + Default>(&mut self, value: &Value, path: &str) -> T {
+        match self.get(value, path) {
+            Some(Value::Number(n)) => match n.as_i64().and_then(|n| T::try_from(n).ok()) {
+                Some(n) => n,
+                None => {
+                    self.problem(path, format!("{} is out of range", n));
+                    T::default()
+                }
+            },
+            Some(other) => {
+                self.problem(path, format!("expected a number, found {}", other));
+                T::default()
+            }
+            None => T::default(),
+        }
+    }
+
+    //OPTIONAL, FALSE WHEN LEFT OUT
+    fn flag(&mut self, value: &Value, path: &str) -> bool {
+        match lookup(value, path) {
+            Some(Value::Bool(b)) => *b,
+            Some(other) => {
+                self.problem(path, format!("expected true or false, found {}", other));
+                false
+            }
+            None => false,
+        }
+    }
+
+    //READ WITH read WHEN PRESENT, None WHEN LEFT OUT
+    fn optional<T>(&mut self, value: &Value, path: &str, read: fn(&mut Self, &Value, &str) -> T) -> Option<T> {
+        lookup(value, path).map(|_| read(self, value, path))
+    }
+
+    fn logging(&mut self, value: &Value) -> LoggingConfig {
+        let log_file = self.optional(value, "server.log_file", Self::string);
+        if lookup(value, "logging").is_none() {
+            let mut logging = LoggingConfig::default();
+            logging.sinks.extend(log_file.map(|path| LogSink::File(FileSink { path, max_size: None, rotate: None, keep: DEFAULT_LOG_KEEP })));
+            return logging;
+        }
+        if log_file.is_some() {
+            self.problem("server.log_file", "cannot be used together with a logging section, add a file sink instead");
+        }
+        let queue_size = self.optional(value, "logging.queue_size", Self::integer::<usize>).unwrap_or(DEFAULT_LOG_QUEUE_SIZE);
+        if queue_size == 0 {
+            self.problem("logging.queue_size", "must be at least 1");
+        }
+        let sinks = match self.get(value, "logging.sinks") {
+            Some(Value::Array(items)) => (0..items.len())
+                .filter_map(|index| self.log_sink(value, &format!("logging.sinks[{}]", index)))
+                .collect(),
+            Some(other) => {
+                self.problem("logging.sinks", format!("expected a list of sinks, found {}", other));
+                Vec::new()
+            }
+            None => Vec::new(),
+        };
+        LoggingConfig { queue_size, sinks }
+    }
+
+    fn log_sink(&mut self, value: &Value, path: &str) -> Option<LogSink> {
+        let field = |name: &str| format!("{}.{}", path, name);
+        match self.string(value, &field("type")).as_str() {
+            "stdout" => Some(LogSink::Stdout),
+            "file" => {
+                let path = self.string(value, &field("path"));
+                if path.is_empty() {
+                    self.problem(&field("path"), "must not be empty");
+                }
+                let max_size = self.optional(value, &field("max_size"), Self::integer::<u64>);
+                if max_size == Some(0) {
+                    self.problem(&field("max_size"), "must be at least 1 byte");
+                }
+                let rotate = match self.optional(value, &field("rotate"), Self::string).as_deref() {
+                    None => None,
+                    Some("hourly") => Some(RotateEvery::Hourly),
+                    Some("daily") => Some(RotateEvery::Daily),
+                    Some(other) => {
+                        self.problem(&field("rotate"), format!("{:?} is not hourly or daily", other));
+                        None
+                    }
+                };
+                let keep = self.optional(value, &field("keep"), Self::integer::<usize>).unwrap_or(DEFAULT_LOG_KEEP);
+                Some(LogSink::File(FileSink { path, max_size, rotate, keep }))
+            }
+            "syslog" => {
+                let address = self.optional(value, &field("address"), Self::string)
+                    .unwrap_or_else(|| format!("unix:{}", DEFAULT_SYSLOG_SOCKET));
+                let address = match (address.strip_prefix("udp:"), address.strip_prefix("unix:")) {
+                    (Some(host), _) => match resolve(host) {
+                        Some(address) => SyslogAddress::Udp(address),
+                        None => {
+                            self.problem(&field("address"), format!("{:?} is not a host:port that resolves", host));
+                            SyslogAddress::Unix(DEFAULT_SYSLOG_SOCKET.to_string())
+                        }
+                    },
+                    (_, Some(socket)) => SyslogAddress::Unix(socket.to_string()),
+                    _ => {
+                        self.problem(&field("address"), format!("{:?} does not start with udp: or unix:", address));
+                        SyslogAddress::Unix(DEFAULT_SYSLOG_SOCKET.to_string())
+                    }
+                };
+                let facility = match self.optional(value, &field("facility"), Self::string) {
+                    None => DAEMON_FACILITY,
+                    Some(name) => match FACILITIES.iter().find(|(known, _)| *known == name) {
+                        Some((_, code)) => *code,
+                        None => {
+                            self.problem(&field("facility"), format!("{:?} is not a syslog facility", name));
+                            0
+                        }
+                    },
+                };
+                let app_name = self.optional(value, &field("app_name"), Self::string)
+                    .unwrap_or_else(|| "dhcp-server".to_string());
+                //RFC 5424 APP-NAME IS 1 TO 48 PRINTABLE ASCII CHARACTERS
+                if app_name.is_empty() || app_name.len() > 48 || !app_name.bytes().all(|b| b.is_ascii_graphic()) {
+                    self.problem(&field("app_name"), "must be 1 to 48 printable ASCII characters without spaces");
+                }
+                Some(LogSink::Syslog(SyslogSink { address, facility, app_name }))
+            }
+            "journald" => {
+                let socket = self.optional(value, &field("socket"), Self::string)
+                    .unwrap_or_else(|| DEFAULT_JOURNALD_SOCKET.to_string());
+                Some(LogSink::Journald { socket })
+            }
+            //A MISSING TYPE IS ALREADY REPORTED
+            "" => None,
+            other => {
+                self.problem(&field("type"), format!("unknown sink type {:?}, expected stdout, file, syslog or journald", other));
+                None
+            }
+        }
+    }
+
+    //host OR host:port, THE PORT MAY BE LEFT OUT
+    fn socket_address(&mut self, value: &Value, path: &str, default_port: u16) -> SocketAddr {
+        let address = self.string(value, path);
+        let with_port = if address.parse::<SocketAddr>().is_ok() || address.rsplit_once(':').is_some_and(|(_, port)| port.parse::<u16>().is_ok()) {
+            address.clone()
+        } else {
+            format!("{}:{}", address, default_port)
+        };
+        match with_port.to_socket_addrs().ok().and_then(|mut addresses| addresses.next()) {
+            Some(address) => address,
+            None => {
+                self.problem(path, format!("{:?} is not a host or host:port that resolves", address));
+                SocketAddr::from((Ipv4Addr::LOCALHOST, default_port))
+            }
+        }
+    }
+
+    fn failover(&mut self, value: &Value) -> FailoverConfig {
+        let role = match self.string(value, "failover.role").as_str() {
+            "primary" => FailoverRole::Primary,
+            "secondary" => FailoverRole::Secondary,
+            other => {
+                self.problem("failover.role", format!("{:?} is not primary or secondary", other));
+                FailoverRole::Primary
+            }
+        };
+        let mode = match self.optional(value, "failover.mode", Self::string).as_deref() {
+            None | Some("load_balance") => FailoverMode::LoadBalance,
+            Some("hot_standby") => FailoverMode::HotStandby,
+            Some(other) => {
+                self.problem("failover.mode", format!("{:?} is not load_balance or hot_standby", other));
+                FailoverMode::LoadBalance
+            }
+        };
+        let listen = match lookup(value, "failover.listen") {
+            Some(_) => self.socket_address(value, "failover.listen", FAILOVER_PORT),
+            None => SocketAddr::from((Ipv4Addr::UNSPECIFIED, FAILOVER_PORT)),
+        };
+        let peer = self.socket_address(value, "failover.peer", FAILOVER_PORT);
+        let mclt = self.optional(value, "failover.mclt", Self::seconds)
+            .unwrap_or(Duration::from_secs(DEFAULT_MCLT_SECONDS as u64));
+        if mclt.is_zero() {
+            self.problem("failover.mclt", "must be greater than 0");
+        }
+        let split = self.optional(value, "failover.split", Self::integer::<u16>).unwrap_or(match mode {
+            FailoverMode::LoadBalance => HASH_BUCKETS / 2,
+            FailoverMode::HotStandby => HASH_BUCKETS,
+        });
+        if split > HASH_BUCKETS {
+            self.problem("failover.split", format!("{} is more than the {} hash buckets", split, HASH_BUCKETS));
+        }
+        let peer_timeout = self.optional(value, "failover.peer_timeout", Self::seconds)
+            .unwrap_or(Duration::from_secs(DEFAULT_PEER_TIMEOUT_SECONDS as u64));
+        if peer_timeout < Duration::from_secs(1) {
+            self.problem("failover.peer_timeout", "must be at least 1 second");
+        }
+        let partner_down_after = self.optional(value, "failover.partner_down_after", Self::seconds);
+        FailoverConfig { role, mode, listen, peer, mclt, split, peer_timeout, partner_down_after }
+    }
+
+    fn ha(&mut self, value: &Value) -> HaConfig {
+        let lock_id = self.optional(value, "ha.lock_id", Self::integer::<i64>).unwrap_or(DEFAULT_HA_LOCK_ID);
+        let node = self.optional(value, "ha.node", Self::string).unwrap_or_else(default_node);
+        if node.is_empty() {
+            self.problem("ha.node", "must not be empty");
+        }
+        let heartbeat = self.optional(value, "ha.heartbeat", Self::seconds)
+            .unwrap_or(Duration::from_secs(DEFAULT_HA_HEARTBEAT_SECONDS as u64));
+        if heartbeat < Duration::from_secs(1) {
+            self.problem("ha.heartbeat", "must be at least 1 second");
+        }
+        let takeover_after = self.optional(value, "ha.takeover_after", Self::seconds)
+            .unwrap_or(Duration::from_secs(DEFAULT_HA_TAKEOVER_SECONDS as u64));
+        //THE LEADER GIVES UP AFTER HALF OF IT, WHICH HAS TO LEAVE ROOM FOR AT LEAST ONE HEARTBEAT
+        if takeover_after < heartbeat * 2 {
+            self.problem("ha.takeover_after", format!("must be at least twice ha.heartbeat of {} seconds", heartbeat.as_secs()));
+        }
+        HaConfig { lock_id, node, heartbeat, takeover_after }
+    }
+
+    fn hostnames(&mut self, value: &Value) -> HostnameConfig {
+        let defaults = HostnameConfig::default();
+        let replacement = self.optional(value, "hostnames.replacement", Self::string).unwrap_or(defaults.replacement);
+        if !replacement.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            self.problem("hostnames.replacement", format!("{:?} may only hold letters, digits and hyphens", replacement));
+        }
+        HostnameConfig {
+            lowercase: self.optional(value, "hostnames.lowercase", Self::flag).unwrap_or(defaults.lowercase),
+            replacement,
+            unique: self.optional(value, "hostnames.unique", Self::flag).unwrap_or(defaults.unique),
+            override_client_updates: self.flag(value, "hostnames.override_client_updates"),
+        }
+    }
+
+    fn hooks(&mut self, value: &Value) -> HooksConfig {
+        if lookup(value, "hooks").is_none() {
+            return HooksConfig::default();
+        }
+        let queue_size = self.optional(value, "hooks.queue_size", Self::integer::<usize>).unwrap_or(DEFAULT_HOOK_QUEUE_SIZE);
+        if queue_size == 0 {
+            self.problem("hooks.queue_size", "must be at least 1");
+        }
+        let count = match self.get(value, "hooks.targets") {
+            Some(Value::Array(items)) => items.len(),
+            Some(other) => {
+                self.problem("hooks.targets", format!("expected a list of hooks, found {}", other));
+                0
+            }
+            None => 0,
+        };
+        let mut names = HashSet::new();
+        let mut targets = Vec::new();
+        for index in 0..count {
+            let path = format!("hooks.targets[{}]", index);
+            if let Some(target) = self.hook(value, &path, index) {
+                //THE NAME LABELS ITS METRICS, TWO HOOKS SHARING ONE WOULD BE COUNTED TOGETHER
+                if !names.insert(target.name.clone()) {
+                    self.problem(&format!("{}.name", path), format!("{:?} is used by another hook", target.name));
+                }
+                targets.push(target);
+            }
+        }
+        HooksConfig { queue_size, targets }
+    }
+
+    fn hook(&mut self, value: &Value, path: &str, index: usize) -> Option<HookConfig> {
+        let field = |name: &str| format!("{}.{}", path, name);
+        let kind = self.string(value, &field("type"));
+        let action = match kind.as_str() {
+            "command" => {
+                let program = self.string(value, &field("command"));
+                if program.is_empty() {
+                    self.problem(&field("command"), "must not be empty");
+                }
+                let args = self.optional(value, &field("args"), Self::string_list).unwrap_or_default();
+                HookAction::Command { program, args }
+            }
+            "webhook" => {
+                let url = self.string(value, &field("url"));
+                if !(url.starts_with("http://") || url.starts_with("https://")) || reqwest::Url::parse(&url).is_err() {
+                    self.problem(&field("url"), format!("{:?} is not an http:// or https:// URL", url));
+                }
+                let headers = self.optional(value, &field("headers"), Self::string_map).unwrap_or_default();
+                HookAction::Webhook { url, headers }
+            }
+            "notify" => {
+                let channel = self.optional(value, &field("channel"), Self::string).unwrap_or_else(|| DEFAULT_NOTIFY_CHANNEL.to_string());
+                //LISTEN TAKES AN UNQUOTED NAME, WHICH POSTGRESQL LOWERCASES
+                let valid = channel.len() <= MAX_CHANNEL_LENGTH
+                    && channel.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+                    && channel.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+                if !valid {
+                    self.problem(&field("channel"), format!("{:?} is not a lowercase identifier of at most {} characters", channel, MAX_CHANNEL_LENGTH));
+                }
+                let outbox = self.flag(value, &field("outbox"));
+                HookAction::Notify { channel, outbox }
+            }
+            "ddns" => HookAction::Ddns(self.ddns(value, path)),
+            //A MISSING TYPE IS ALREADY REPORTED
+            "" => return None,
+            other => {
+                self.problem(&field("type"), format!("unknown hook type {:?}, expected command, webhook, notify or ddns", other));
+                return None;
+            }
+        };
+        let name = self.optional(value, &field("name"), Self::string).unwrap_or_else(|| format!("{}-{}", kind, index));
+        let events = match self.optional(value, &field("events"), Self::string_list) {
+            None => LeaseEventKind::ALL.to_vec(),
+            Some(names) => names.iter().enumerate().filter_map(|(event, name)| {
+                let kind = LeaseEventKind::parse(name);
+                if kind.is_none() {
+                    self.problem(&format!("{}[{}]", field("events"), event), format!("{:?} is not granted, renewed, released, declined or expired", name));
+                }
+                kind
+            }).collect(),
+        };
+        let timeout = self.optional(value, &field("timeout"), Self::seconds)
+            .unwrap_or(Duration::from_secs(DEFAULT_HOOK_TIMEOUT_SECONDS as u64));
+        if timeout.is_zero() {
+            self.problem(&field("timeout"), "must be at least 1 second");
+        }
+        let retries = self.optional(value, &field("retries"), Self::integer::<u32>).unwrap_or(DEFAULT_HOOK_RETRIES);
+        let retry_delay = self.optional(value, &field("retry_delay"), Self::seconds)
+            .unwrap_or(Duration::from_secs(DEFAULT_HOOK_RETRY_DELAY_SECONDS as u64));
+        Some(HookConfig { name, action, events, timeout, retries, retry_delay })
+    }
+
+    fn ddns(&mut self, value: &Value, path: &str) -> DdnsConfig {
+        let field = |name: &str| format!("{}.{}", path, name);
+        let server = self.socket_address(value, &field("server"), DNS_PORT);
+        let zone = self.zone(value, &field("zone"), || lookup(value, "server.domain_name").and_then(Value::as_str).map(str::to_string));
+        let reverse_zone = match lookup(value, &field("reverse_zone")) {
+            None => None,
+            Some(_) => {
+                let zone = self.zone(value, &field("reverse_zone"), || None);
+                if !zone.ends_with("in-addr.arpa") {
+                    self.problem(&field("reverse_zone"), format!("{:?} is not under in-addr.arpa", zone));
+                }
+                Some(zone)
+            }
+        };
+        let ttl = self.optional(value, &field("ttl"), Self::integer::<u32>).unwrap_or(DEFAULT_DDNS_TTL);
+        let key = lookup(value, &field("tsig")).map(|_| {
+            let name = self.string(value, &field("tsig.name"));
+            if !crate::ddns::message::valid_name(&name) {
+                self.problem(&field("tsig.name"), format!("{:?} is not a DNS name", name));
+            }
+            let algorithm = self.optional(value, &field("tsig.algorithm"), Self::string).unwrap_or_else(|| TsigAlgorithm::HmacSha256.name().to_string());
+            let algorithm = TsigAlgorithm::parse(&algorithm).unwrap_or_else(|| {
+                self.problem(&field("tsig.algorithm"), format!("{:?} is not hmac-sha256 or hmac-sha512", algorithm));
+                TsigAlgorithm::HmacSha256
+            });
+            let secret = self.string(value, &field("tsig.secret"));
+            let secret = match base64::engine::general_purpose::STANDARD.decode(&secret) {
+                Ok(secret) if !secret.is_empty() => secret,
+                _ => {
+                    self.problem(&field("tsig.secret"), "expected the key in base64, as tsig-keygen prints it");
+                    Vec::new()
+                }
+            };
+            TsigKey { name: name.trim_end_matches('.').to_ascii_lowercase(), algorithm, secret }
+        });
+        let generated_names = self.flag(value, &field("generated_names"));
+        DdnsConfig { server, zone, reverse_zone, ttl, key, generated_names }
+    }
+
+    //LOWERCASE WITHOUT THE TRAILING DOT, fallback WHEN LEFT OUT
+    fn zone(&mut self, value: &Value, path: &str, fallback: impl FnOnce() -> Option<String>) -> String {
+        let zone = match lookup(value, path) {
+            Some(_) => self.string(value, path),
+            None => match fallback() {
+                Some(zone) => zone,
+                None => {
+                    self.problem(path, "missing");
+                    return String::new();
+                }
+            },
+        };
+        if !crate::ddns::message::valid_name(&zone) {
+            self.problem(path, format!("{:?} is not a DNS zone name", zone));
+        }
+        zone.trim_end_matches('.').to_ascii_lowercase()
+    }
+
+    fn string_list(&mut self, value: &Value, path: &str) -> Vec<String> {
+        match self.get(value, path) {
+            Some(Value::Array(items)) => (0..items.len())
+                .map(|index| self.string(value, &format!("{}[{}]", path, index)))
+                .collect(),
+            Some(other) => {
+                self.problem(path, format!("expected a list of strings, found {}", other));
+                Vec::new()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    fn string_map(&mut self, value: &Value, path: &str) -> Vec<(String, String)> {
+        match self.get(value, path) {
+            Some(Value::Object(entries)) => entries.iter().filter_map(|(key, entry)| match entry {
+                Value::String(entry) => Some((key.clone(), entry.clone())),
+                other => {
+                    self.problem(&format!("{}.{}", path, key), format!("expected a string, found {}", other));
+                    None
+                }
+            }).collect(),
+            Some(other) => {
+                self.problem(path, format!("expected an object of strings, found {}", other));
+                Vec::new()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    fn seconds(&mut self, value: &Value, path: &str) -> Duration {
+        Duration::from_secs(self.integer::<u32>(value, path) as u64)
+    }
+
+    fn subnet(&mut self, ip_address: Ipv4Addr, subnet_mask: Ipv4Addr, path: &str) -> Ipv4Net {
+        match Ipv4Net::with_netmask(ip_address, subnet_mask) {
+            Ok(net) => net.trunc(),
+            Err(_) => {
+                self.problem(path, format!("{} is not a contiguous subnet mask", subnet_mask));
+                Ipv4Net::default()
+            }
+        }
+    }
+}
+
+//HOST NAME AND PROCESS ID, TELLS SERVERS ON THE SAME HOST APART
+fn default_node() -> String {
+    let host = fs::read_to_string("/proc/sys/kernel/hostname").ok()
+        .map(|host| host.trim().to_string())
+        .filter(|host| !host.is_empty())
+        .unwrap_or_else(|| "dhcp-server".to_string());
+    format!("{}-{}", host, std::process::id())
+}
+
+//SYSLOG FACILITY NAMES AND CODES FROM RFC 5424
+const FACILITIES: [(&str, u8); 20] = [
+    ("kern", 0), ("user", 1), ("mail", 2), ("daemon", 3), ("auth", 4), ("syslog", 5), ("lpr", 6),
+    ("news", 7), ("uucp", 8), ("cron", 9), ("authpriv", 10), ("ftp", 11),
+    ("local0", 16), ("local1", 17), ("local2", 18), ("local3", 19),
+    ("local4", 20), ("local5", 21), ("local6", 22), ("local7", 23),
+];
+
+//THE SYSLOG HOST IS LOOKED UP ONCE, WHEN THE CONFIG IS LOADED
+fn resolve(host: &str) -> Option<SocketAddr> {
+    host.to_socket_addrs().ok()?.find(SocketAddr::is_ipv4)
+}
+
+//A SEGMENT LIKE sinks[2] INDEXES INTO AN ARRAY
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |value, segment| {
+        match segment.strip_suffix(']').and_then(|segment| segment.split_once('[')) {
+            Some((key, index)) => value.get(key)?.get(index.parse::<usize>().ok()?),
+            None => value.get(segment),
+        }
+    })
+}
@@ -0,0 +1,149 @@
+use tokio_postgres::Error as TokioError;
+use std::net::{IpAddr, Ipv4Addr};
+use std::collections::HashSet;
+use std::io::Error as StdError;
+use std::fmt;
+use chrono::{DateTime, Utc};
+
+use crate::server_config::{Config, generate_ip_pool};
+
+use crate::set_up::migrations;
+use crate::clock::{Clock, SystemClock};
+
+pub async fn create_db (client: &mut tokio_postgres::Client, config: &Config) -> Result<(), CustomError> {
+
+    let applied = migrations::run_migrations(client).await?;
+    if applied == 0 {
+        tracing::info!(version = migrations::latest_version(), "Database schema up to date");
+    }
+
+    //RECONCILING IS A DIFF AGAINST THE TABLE SO IT CHANGES NOTHING WHEN THE POOL DID NOT CHANGE
+    let summary = reconcile_with_config(client, config, SystemClock.now()).await?;
+    tracing::info!("IP addresses table reconciled: {}", summary);
+
+    Ok(())
+}
+
+//RECONCILE IP ADDRESSES TABLE WITH THE POOL OF A LOADED CONFIG
+pub async fn reconcile_with_config(client: &mut tokio_postgres::Client, config: &Config,
+                                   now: DateTime<Utc>) -> Result<ReconcileSummary, CustomError> {
+    let ip_pool = generate_ip_pool(config.ip_pool.range_start, config.ip_pool.range_end);
+    let summary = reconcile_ip_addresses_table(client, &ip_pool, &config.restricted_ips, now).await?;
+    Ok(summary)
+}
+
+//COUNTS OF WHAT RECONCILIATION CHANGED IN THE IP ADDRESSES TABLE
+#[derive(Debug, Default)]
+pub struct ReconcileSummary {
+    pub added: usize,
+    pub removed: usize,
+    pub retired: usize,
+    pub reinstated: usize,
+    pub restricted: usize,
+}
+
+impl fmt::Display for ReconcileSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} added, {} removed, {} retired until lease expiry ({} newly restricted), {} reinstated",
+            self.added, self.removed, self.retired, self.restricted, self.reinstated)
+    }
+}
+
+//BRING IP ADDRESSES TABLE IN LINE WITH THE CONFIGURED POOL WITHOUT TOUCHING ACTIVE LEASES
+//ADDRESSES NO LONGER IN THE POOL ARE DELETED IF FREE, OTHERWISE FLAGGED AS RETIRED
+//RETIRED ADDRESSES ARE DELETED BY THE SERVER ONCE THEIR LEASE IS OVER
+pub async fn reconcile_ip_addresses_table(client: &mut tokio_postgres::Client, ip_pool: &[Ipv4Addr],
+                                          restricted_ips: &[Ipv4Addr], now: DateTime<Utc>) -> Result<ReconcileSummary, TokioError> {
+    let mut summary = ReconcileSummary::default();
+    let restricted: HashSet<Ipv4Addr> = restricted_ips.iter().copied().collect();
+    let desired: HashSet<Ipv4Addr> = ip_pool.iter().copied().filter(|ip| !restricted.contains(ip)).collect();
+
+    let transaction = client.transaction().await?;
+
+    let existing_query = "SELECT ip_address,
+                                 allocated AND (lease_end IS NULL OR lease_end > $1) AS in_use,
+                                 retired
+                          FROM ip_addresses
+                          FOR UPDATE";
+    let mut existing: HashSet<Ipv4Addr> = HashSet::new();
+    for row in transaction.query(existing_query, &[&now]).await? {
+        let ip = match row.get::<usize, IpAddr>(0) {
+            IpAddr::V4(ip) => ip,
+            IpAddr::V6(_) => continue,
+        };
+        let in_use: bool = row.get(1);
+        let retired: bool = row.get(2);
+        existing.insert(ip);
+
+        if desired.contains(&ip) {
+            if retired {
+                transaction.execute("UPDATE ip_addresses SET retired = false WHERE ip_address = $1",
+                                    &[&IpAddr::V4(ip)]).await?;
+                summary.reinstated += 1;
+            }
+            continue;
+        }
+        if retired {
+            continue;
+        }
+        if in_use {
+            transaction.execute("UPDATE ip_addresses SET retired = true WHERE ip_address = $1",
+                                &[&IpAddr::V4(ip)]).await?;
+            summary.retired += 1;
+        } else {
+            transaction.execute("DELETE FROM ip_addresses WHERE ip_address = $1",
+                                &[&IpAddr::V4(ip)]).await?;
+            summary.removed += 1;
+        }
+        if restricted.contains(&ip) {
+            summary.restricted += 1;
+        }
+    }
+
+    let insert_ip_query = "
+        INSERT INTO ip_addresses (ip_address, allocated)
+        VALUES ($1, $2);
+    ";
+    for ip in ip_pool {
+        if desired.contains(ip) && !existing.contains(ip) {
+            transaction.execute(insert_ip_query, &[&IpAddr::V4(*ip), &false]).await?;
+            summary.added += 1;
+        }
+    }
+
+    transaction.commit().await?;
+    Ok(summary)
+}
+
+#[derive(Debug)]
+pub enum CustomError {
+    PostgresError(TokioError),
+    IoError(StdError),
+    SchemaTooNew { database: i32, binary: i32 },
+    //A CHANGE THE LEASE STORE REFUSED, E.G. A SECOND ADDRESS FOR ONE CLIENT
+    Conflict(String),
+}
+
+impl From<TokioError> for CustomError {
+    fn from(err: TokioError) -> Self {
+        CustomError::PostgresError(err)
+    }
+}
+
+impl From<StdError> for CustomError {
+    fn from(err: StdError) -> Self {
+        CustomError::IoError(err)
+    }
+}
+
+impl fmt::Display for CustomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CustomError::PostgresError(e) => write!(f, "Database error: {}", e),
+            CustomError::IoError(e) => write!(f, "I/O error: {}", e),
+            CustomError::SchemaTooNew { database, binary } => write!(f,
+                "Database schema version {} is newer than the latest version {} known to this server", database, binary),
+            CustomError::Conflict(e) => write!(f, "Lease store conflict: {}", e),
+        }
+    }
+}
\ No newline at end of file
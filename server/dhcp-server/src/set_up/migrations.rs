@@ -0,0 +1,111 @@
+use tokio_postgres::Client;
+
+use crate::set_up::create_db_tables::CustomError;
+
+//SCHEMA CHANGE APPLIED ONCE AND RECORDED IN schema_version
+pub struct Migration {
+    pub version: i32,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+//ALL MIGRATIONS KNOWN TO THIS BINARY IN ORDER
+//NEVER EDIT A MIGRATION THAT WAS RELEASED - ADD A NEW ONE INSTEAD
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "initial schema",
+        sql: include_str!("../../migrations/0001_initial_schema.sql"),
+    },
+    Migration {
+        version: 2,
+        description: "retired addresses",
+        sql: include_str!("../../migrations/0002_retired_addresses.sql"),
+    },
+    Migration {
+        version: 3,
+        description: "lease times as timestamptz",
+        sql: include_str!("../../migrations/0003_lease_times_timestamptz.sql"),
+    },
+    Migration {
+        version: 4,
+        description: "reservations and blocked addresses",
+        sql: include_str!("../../migrations/0004_reservations_and_blocked_addresses.sql"),
+    },
+    Migration {
+        version: 5,
+        description: "lease events outbox",
+        sql: include_str!("../../migrations/0005_lease_events_outbox.sql"),
+    },
+    Migration {
+        version: 6,
+        description: "client host names",
+        sql: include_str!("../../migrations/0006_client_host_names.sql"),
+    },
+    Migration {
+        version: 7,
+        description: "leader election",
+        sql: include_str!("../../migrations/0007_leader_election.sql"),
+    },
+];
+
+//ARBITRARY KEY SHARED BY ALL SERVERS SO ONLY ONE OF THEM MIGRATES AT A TIME
+const MIGRATION_LOCK_KEY: i64 = 0x4448_4350_5345_5256;
+
+pub fn latest_version() -> i32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+pub async fn current_version(client: &Client) -> Result<i32, CustomError> {
+    let row = client.query_one("SELECT COALESCE(MAX(version), 0) FROM schema_version", &[]).await?;
+    Ok(row.get(0))
+}
+
+//APPLY EVERY MIGRATION NEWER THAN THE DATABASE SCHEMA
+//EACH MIGRATION RUNS IN ITS OWN TRANSACTION TOGETHER WITH ITS schema_version ROW
+//RETURNS THE NUMBER OF MIGRATIONS APPLIED
+pub async fn run_migrations(client: &mut Client) -> Result<usize, CustomError> {
+    let create_schema_version_table = "
+        CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at TIMESTAMP NOT NULL DEFAULT NOW()
+        )
+    ";
+    //IF NOT EXISTS STILL FAILS WHEN TWO SERVERS CREATE THE TABLE AT THE SAME TIME, SO IT IS DONE UNDER THE LOCK
+    let transaction = client.transaction().await?;
+    transaction.execute("SELECT pg_advisory_xact_lock($1)", &[&MIGRATION_LOCK_KEY]).await?;
+    transaction.execute(create_schema_version_table, &[]).await?;
+    transaction.commit().await?;
+
+    let database_version = current_version(client).await?;
+    if database_version > latest_version() {
+        return Err(CustomError::SchemaTooNew {
+            database: database_version,
+            binary: latest_version(),
+        });
+    }
+
+    let mut applied = 0;
+    for migration in MIGRATIONS.iter().filter(|m| m.version > database_version) {
+        let transaction = client.transaction().await?;
+        transaction.execute("SELECT pg_advisory_xact_lock($1)", &[&MIGRATION_LOCK_KEY]).await?;
+
+        //ANOTHER SERVER MAY HAVE APPLIED IT WHILE WE WAITED FOR THE LOCK
+        let row = transaction.query_one("SELECT COALESCE(MAX(version), 0) FROM schema_version", &[]).await?;
+        let locked_version: i32 = row.get(0);
+        if locked_version >= migration.version {
+            continue;
+        }
+
+        transaction.batch_execute(migration.sql).await?;
+        transaction.execute("INSERT INTO schema_version (version, description) VALUES ($1, $2)",
+                            &[&migration.version, &migration.description]).await?;
+        transaction.commit().await?;
+
+        tracing::info!(version = migration.version, description = migration.description, "Applied migration");
+        applied += 1;
+    }
+
+    Ok(applied)
+}
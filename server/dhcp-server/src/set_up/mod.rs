@@ -0,0 +1,2 @@
+pub mod create_db_tables;
+pub mod migrations;
\ No newline at end of file
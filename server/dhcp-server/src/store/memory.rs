@@ -0,0 +1,386 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashSet};
+use std::net::Ipv4Addr;
+use std::sync::Mutex;
+
+use crate::hooks::LeaseEvent;
+use crate::set_up::create_db_tables::{CustomError, ReconcileSummary};
+use crate::store::{AddressRecord, ClientName, Expired, HistoryEntry, HistoryFilter, Lease, LeaseStore, Reservation};
+use crate::utility::lease::LeaseTimes;
+
+//ONE ROW OF THE ip_addresses TABLE
+#[derive(Debug, Clone, Default)]
+struct Address {
+    allocated: bool,
+    client_id: Option<String>,
+    lease_start: Option<DateTime<Utc>>,
+    lease_end: Option<DateTime<Utc>>,
+    retired: bool,
+    blocked: bool,
+    name: ClientName,
+}
+
+impl Address {
+    //THE LEASE HELD ON THE ADDRESS, EXPIRED OR NOT
+    fn lease(&self, ip: Ipv4Addr) -> Option<Lease> {
+        Some(Lease {
+            ip_address: ip,
+            client_id: self.client_id.clone()?,
+            lease_start: self.lease_start?,
+            lease_end: self.lease_end?,
+            name: self.name.clone(),
+        })
+    }
+
+    fn record(&self, ip: Ipv4Addr) -> AddressRecord {
+        AddressRecord {
+            ip_address: ip,
+            allocated: self.allocated,
+            client_id: self.client_id.clone(),
+            lease_start: self.lease_start,
+            lease_end: self.lease_end,
+            retired: self.retired,
+            blocked: self.blocked,
+            name: self.name.clone(),
+        }
+    }
+
+    //FREE AGAIN, POOL FLAGS KEPT
+    //ANY ROW WHEN client_id IS NONE
+    fn held_by(&self, client_id: Option<&str>) -> bool {
+        client_id.is_none() || self.client_id.as_deref() == client_id
+    }
+
+    fn clear(&mut self) {
+        *self = Address { retired: self.retired, blocked: self.blocked, ..Address::default() };
+    }
+}
+
+#[derive(Default)]
+struct Tables {
+    addresses: BTreeMap<Ipv4Addr, Address>,
+    history: Vec<HistoryEntry>,
+    //CLIENT ID TO RESERVED ADDRESS
+    reservations: BTreeMap<String, Ipv4Addr>,
+    //THE lease_events TABLE, THE id IS THE POSITION PLUS ONE
+    outbox: Vec<LeaseEvent>,
+    //CHANNEL AND PAYLOAD OF EVERY NOTIFY
+    notifications: Vec<(String, String)>,
+}
+
+impl Tables {
+    //client_id NONE MEANS RESERVED FOR ANY CLIENT
+    fn reserved_for_other(&self, ip: Ipv4Addr, client_id: Option<&str>) -> bool {
+        self.reservations.iter().any(|(holder, reserved)| *reserved == ip && Some(holder.as_str()) != client_id)
+    }
+
+    //FREE, IN THE POOL AND NOT BLOCKED, RESERVATIONS ASIDE
+    fn is_free(&self, ip: Ipv4Addr) -> bool {
+        self.addresses.get(&ip).is_some_and(|row| !row.allocated && !row.retired && !row.blocked)
+    }
+
+    //client_id IS UNIQUE IN THE DATABASE
+    fn check_unique(&self, ip: Ipv4Addr, client_id: &str) -> Result<(), CustomError> {
+        match self.addresses.iter().find(|(other, row)| **other != ip && row.client_id.as_deref() == Some(client_id)) {
+            Some((other, _)) => Err(CustomError::Conflict(format!("client {} already holds {}", client_id, other))),
+            None => Ok(()),
+        }
+    }
+}
+
+//LEASES KEPT IN MEMORY WITH THE SAME RULES AS THE POSTGRESQL TABLES
+//NOTHING SURVIVES A RESTART, MEANT FOR TESTS AND TRYING THE SERVER OUT
+#[derive(Default)]
+pub struct MemoryStore {
+    tables: Mutex<Tables>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        MemoryStore::default()
+    }
+
+    //EVERY LEASE HISTORY ROW WRITTEN SO FAR, OLDEST FIRST
+    pub fn history(&self) -> Vec<HistoryEntry> {
+        self.tables.lock().unwrap().history.clone()
+    }
+
+    //EVERY EVENT PUBLISHED SO FAR AS CHANNEL AND PAYLOAD, OLDEST FIRST
+    pub fn notifications(&self) -> Vec<(String, String)> {
+        self.tables.lock().unwrap().notifications.clone()
+    }
+
+    //EVERY EVENT KEPT IN THE OUTBOX, OLDEST FIRST
+    pub fn outbox(&self) -> Vec<LeaseEvent> {
+        self.tables.lock().unwrap().outbox.clone()
+    }
+}
+
+#[async_trait]
+impl LeaseStore for MemoryStore {
+    async fn expire_leases(&self, now: DateTime<Utc>) -> Result<Expired, CustomError> {
+        let mut tables = self.tables.lock().unwrap();
+        let mut expired = Expired::default();
+        for (ip, row) in tables.addresses.iter_mut() {
+            if row.lease_end.is_some_and(|end| end < now) {
+                expired.leases.extend(row.lease(*ip));
+                row.clear();
+            }
+        }
+        let before = tables.addresses.len();
+        tables.addresses.retain(|_, row| !row.retired || row.allocated);
+        expired.removed = (before - tables.addresses.len()) as u64;
+        Ok(expired)
+    }
+
+    async fn address_of_client(&self, client_id: &str) -> Result<Option<Ipv4Addr>, CustomError> {
+        let tables = self.tables.lock().unwrap();
+        Ok(tables.addresses.iter()
+            .find(|(_, row)| row.client_id.as_deref() == Some(client_id))
+            .map(|(ip, _)| *ip))
+    }
+
+    async fn reservation_for(&self, client_id: &str) -> Result<Option<Ipv4Addr>, CustomError> {
+        Ok(self.tables.lock().unwrap().reservations.get(client_id).copied())
+    }
+
+    async fn is_available(&self, ip: Ipv4Addr, client_id: &str) -> Result<bool, CustomError> {
+        let tables = self.tables.lock().unwrap();
+        Ok(tables.is_free(ip) && !tables.reserved_for_other(ip, Some(client_id)))
+    }
+
+    async fn first_available(&self) -> Result<Option<Ipv4Addr>, CustomError> {
+        let tables = self.tables.lock().unwrap();
+        Ok(tables.addresses.keys().copied().find(|ip| tables.is_free(*ip) && !tables.reserved_for_other(*ip, None)))
+    }
+
+    async fn available(&self) -> Result<Vec<Ipv4Addr>, CustomError> {
+        let tables = self.tables.lock().unwrap();
+        Ok(tables.addresses.keys().copied().filter(|ip| tables.is_free(*ip) && !tables.reserved_for_other(*ip, None)).collect())
+    }
+
+    async fn holds_other_address(&self, client_id: &str, ip: Ipv4Addr) -> Result<bool, CustomError> {
+        let tables = self.tables.lock().unwrap();
+        Ok(tables.addresses.iter().any(|(other, row)| *other != ip && row.client_id.as_deref() == Some(client_id)))
+    }
+
+    async fn can_lease(&self, ip: Ipv4Addr, client_id: &str) -> Result<bool, CustomError> {
+        let tables = self.tables.lock().unwrap();
+        Ok(tables.addresses.get(&ip).is_some_and(|row| (!row.allocated || row.client_id.as_deref() == Some(client_id)) && !row.blocked)
+            && !tables.reserved_for_other(ip, Some(client_id)))
+    }
+
+    async fn hostname_taken(&self, hostname: &str, client_id: &str) -> Result<bool, CustomError> {
+        let tables = self.tables.lock().unwrap();
+        Ok(tables.addresses.values().any(|row| {
+            row.name.hostname.as_deref() == Some(hostname) && row.client_id.as_deref().is_some_and(|holder| holder != client_id)
+        }))
+    }
+
+    async fn lease(&self, ip: Ipv4Addr, client_id: &str, lease: &LeaseTimes, name: &ClientName) -> Result<bool, CustomError> {
+        let mut tables = self.tables.lock().unwrap();
+        if !tables.is_free(ip) || tables.reserved_for_other(ip, Some(client_id)) {
+            return Ok(false);
+        }
+        tables.check_unique(ip, client_id)?;
+        let row = tables.addresses.get_mut(&ip).unwrap();
+        row.allocated = true;
+        row.client_id = Some(client_id.to_string());
+        row.lease_start = Some(lease.start);
+        row.lease_end = Some(lease.end);
+        row.name = name.clone();
+        Ok(true)
+    }
+
+    async fn renew(&self, ip: Ipv4Addr, client_id: &str, lease: &LeaseTimes,
+                   name: Option<&ClientName>) -> Result<Option<(Lease, ClientName)>, CustomError> {
+        let mut tables = self.tables.lock().unwrap();
+        Ok(tables.addresses.get_mut(&ip).filter(|row| row.client_id.as_deref() == Some(client_id)).and_then(|row| {
+            let previous = row.name.clone();
+            row.allocated = true;
+            row.lease_start = Some(lease.start);
+            row.lease_end = Some(lease.end);
+            if let Some(name) = name {
+                row.name = name.clone();
+            }
+            row.lease(ip).map(|lease| (lease, previous))
+        }))
+    }
+
+    async fn decline(&self, ip: Ipv4Addr, client_id: Option<&str>, now: DateTime<Utc>,
+                     hold_until: DateTime<Utc>) -> Result<Option<Lease>, CustomError> {
+        let mut tables = self.tables.lock().unwrap();
+        Ok(tables.addresses.get_mut(&ip).filter(|row| row.held_by(client_id)).and_then(|row| {
+            let declined = row.lease(ip);
+            row.allocated = true;
+            row.client_id = None;
+            row.lease_start = Some(now);
+            row.lease_end = Some(hold_until);
+            row.name = ClientName::default();
+            declined
+        }))
+    }
+
+    async fn release(&self, ip: Ipv4Addr, client_id: Option<&str>) -> Result<Option<Lease>, CustomError> {
+        let mut tables = self.tables.lock().unwrap();
+        Ok(tables.addresses.get_mut(&ip).filter(|row| row.held_by(client_id)).and_then(|row| {
+            let released = row.lease(ip);
+            row.clear();
+            released
+        }))
+    }
+
+    async fn record_history(&self, entry: HistoryEntry) -> Result<(), CustomError> {
+        self.tables.lock().unwrap().history.push(entry);
+        Ok(())
+    }
+
+    async fn reconcile(&self, ip_pool: &[Ipv4Addr], restricted_ips: &[Ipv4Addr],
+                       now: DateTime<Utc>) -> Result<ReconcileSummary, CustomError> {
+        let mut summary = ReconcileSummary::default();
+        let restricted: HashSet<Ipv4Addr> = restricted_ips.iter().copied().collect();
+        let desired: HashSet<Ipv4Addr> = ip_pool.iter().copied().filter(|ip| !restricted.contains(ip)).collect();
+
+        let mut tables = self.tables.lock().unwrap();
+        let mut removed = Vec::new();
+        for (ip, row) in tables.addresses.iter_mut() {
+            if desired.contains(ip) {
+                if row.retired {
+                    row.retired = false;
+                    summary.reinstated += 1;
+                }
+                continue;
+            }
+            if row.retired {
+                continue;
+            }
+            let in_use = row.allocated && row.lease_end.is_none_or(|end| end > now);
+            if in_use {
+                row.retired = true;
+                summary.retired += 1;
+            } else {
+                removed.push(*ip);
+                summary.removed += 1;
+            }
+            if restricted.contains(ip) {
+                summary.restricted += 1;
+            }
+        }
+        for ip in removed {
+            tables.addresses.remove(&ip);
+        }
+        for ip in ip_pool {
+            if desired.contains(ip) && !tables.addresses.contains_key(ip) {
+                tables.addresses.insert(*ip, Address::default());
+                summary.added += 1;
+            }
+        }
+        Ok(summary)
+    }
+
+    async fn active_leases(&self, now: DateTime<Utc>) -> Result<Vec<Lease>, CustomError> {
+        let tables = self.tables.lock().unwrap();
+        Ok(tables.addresses.iter().filter_map(|(ip, row)| {
+            let lease_end = row.lease_end.filter(|end| *end > now)?;
+            if !row.allocated {
+                return None;
+            }
+            Some(Lease { lease_end, ..row.lease(*ip)? })
+        }).collect())
+    }
+
+    async fn addresses(&self) -> Result<Vec<AddressRecord>, CustomError> {
+        let tables = self.tables.lock().unwrap();
+        Ok(tables.addresses.iter().map(|(ip, row)| row.record(*ip)).collect())
+    }
+
+    async fn address(&self, ip: Ipv4Addr) -> Result<Option<AddressRecord>, CustomError> {
+        Ok(self.tables.lock().unwrap().addresses.get(&ip).map(|row| row.record(ip)))
+    }
+
+    async fn put_lease(&self, lease: &Lease) -> Result<(), CustomError> {
+        let mut tables = self.tables.lock().unwrap();
+        if !tables.addresses.contains_key(&lease.ip_address) {
+            return Ok(());
+        }
+        for (ip, row) in tables.addresses.iter_mut() {
+            if *ip != lease.ip_address && row.client_id.as_deref() == Some(lease.client_id.as_str()) {
+                row.clear();
+            }
+        }
+        let row = tables.addresses.get_mut(&lease.ip_address).unwrap();
+        row.allocated = true;
+        row.client_id = Some(lease.client_id.clone());
+        row.lease_start = Some(lease.lease_start);
+        row.lease_end = Some(lease.lease_end);
+        row.name = lease.name.clone();
+        Ok(())
+    }
+
+    async fn history(&self, filter: &HistoryFilter) -> Result<Vec<HistoryEntry>, CustomError> {
+        let tables = self.tables.lock().unwrap();
+        Ok(tables.history.iter().rev()
+            .filter(|entry| filter.client_id.as_ref().is_none_or(|client_id| entry.client_id == *client_id))
+            .filter(|entry| filter.ip_address.is_none_or(|ip| entry.ip_address == ip))
+            .take(filter.limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn reservations(&self) -> Result<Vec<Reservation>, CustomError> {
+        let tables = self.tables.lock().unwrap();
+        let mut reservations: Vec<Reservation> = tables.reservations.iter()
+            .map(|(client_id, ip)| Reservation { client_id: client_id.clone(), ip_address: *ip })
+            .collect();
+        reservations.sort_by_key(|reservation| reservation.ip_address);
+        Ok(reservations)
+    }
+
+    async fn reserve(&self, reservation: &Reservation) -> Result<(), CustomError> {
+        let mut tables = self.tables.lock().unwrap();
+        if tables.reserved_for_other(reservation.ip_address, Some(&reservation.client_id)) {
+            return Err(CustomError::Conflict(format!("{} is reserved for another client", reservation.ip_address)));
+        }
+        tables.reservations.insert(reservation.client_id.clone(), reservation.ip_address);
+        Ok(())
+    }
+
+    async fn unreserve(&self, client_id: &str) -> Result<bool, CustomError> {
+        Ok(self.tables.lock().unwrap().reservations.remove(client_id).is_some())
+    }
+
+    async fn revoke(&self, ip: Ipv4Addr, now: DateTime<Utc>) -> Result<Option<Lease>, CustomError> {
+        let mut tables = self.tables.lock().unwrap();
+        Ok(tables.addresses.get_mut(&ip).filter(|row| row.allocated).and_then(|row| {
+            let revoked = row.lease(ip)?;
+            row.client_id = None;
+            row.lease_start = Some(now);
+            row.name = ClientName::default();
+            Some(revoked)
+        }))
+    }
+
+    async fn set_blocked(&self, ip: Ipv4Addr, blocked: bool) -> Result<bool, CustomError> {
+        let mut tables = self.tables.lock().unwrap();
+        match tables.addresses.get_mut(&ip) {
+            Some(row) => {
+                row.blocked = blocked;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn publish(&self, channel: &str, event: &LeaseEvent, outbox: bool) -> Result<(), CustomError> {
+        let mut tables = self.tables.lock().unwrap();
+        let id = if outbox {
+            tables.outbox.push(event.clone());
+            Some(tables.outbox.len() as i64)
+        } else {
+            None
+        };
+        tables.notifications.push((channel.to_string(), event.payload(id)));
+        Ok(())
+    }
+}
@@ -0,0 +1,278 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::net::Ipv4Addr;
+
+use crate::hooks::LeaseEvent;
+use crate::set_up::create_db_tables::{CustomError, ReconcileSummary};
+use crate::utility::lease::LeaseTimes;
+
+pub mod memory;
+pub mod postgres;
+
+pub use memory::MemoryStore;
+pub use postgres::PostgresStore;
+
+//SERVER ANSWER RECORDED IN THE LEASE HISTORY
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServerResponse {
+    Ack,
+    Nak,
+}
+
+impl ServerResponse {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ServerResponse::Ack => "ACK",
+            ServerResponse::Nak => "NAK",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeaseType {
+    Initial,
+    Renewing,
+    Declined,
+}
+
+impl LeaseType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LeaseType::Initial => "INITIAL",
+            LeaseType::Renewing => "RENEWING",
+            LeaseType::Declined => "DECLINED",
+        }
+    }
+}
+
+//ONE ROW OF THE LEASE HISTORY
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub ip_address: Ipv4Addr,
+    pub client_id: String,
+    pub lease_start: Option<DateTime<Utc>>,
+    pub lease_end: Option<DateTime<Utc>>,
+    pub server_response: ServerResponse,
+    pub lease_type: LeaseType,
+}
+
+//WHICH OF THE CLIENT'S DNS RECORDS THE SERVER KEEPS UP TO DATE, AGREED THROUGH OPTION 81 (RFC 4702)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DnsUpdate {
+    //A AND PTR, ALSO WHEN THE CLIENT SENT NO OPTION 81
+    #[default]
+    Both,
+    //THE CLIENT UPDATES ITS A RECORD ITSELF
+    Ptr,
+    //THE CLIENT ASKED FOR NO UPDATES AT ALL
+    Neither,
+}
+
+impl DnsUpdate {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DnsUpdate::Both => "both",
+            DnsUpdate::Ptr => "ptr",
+            DnsUpdate::Neither => "neither",
+        }
+    }
+
+    pub fn parse(update: &str) -> Option<Self> {
+        match update {
+            "both" => Some(DnsUpdate::Both),
+            "ptr" => Some(DnsUpdate::Ptr),
+            "neither" => Some(DnsUpdate::Neither),
+            _ => None,
+        }
+    }
+}
+
+//WHAT THE CLIENT IS CALLED, FROM OPTION 12 OR 81 AFTER THE HOST NAME POLICY
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientName {
+    //ONE LABEL, NONE WHEN THE CLIENT SENT NO USABLE NAME
+    pub hostname: Option<String>,
+    pub dns_update: DnsUpdate,
+}
+
+//ADDRESS LEASED TO A CLIENT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lease {
+    pub ip_address: Ipv4Addr,
+    pub client_id: String,
+    pub lease_start: DateTime<Utc>,
+    pub lease_end: DateTime<Utc>,
+    pub name: ClientName,
+}
+
+//WHAT expire_leases CLEARED UP
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Expired {
+    //LEASES THAT RAN OUT, DECLINED OR REVOKED ADDRESSES RETURNING TO THE POOL ARE NOT AMONG THEM
+    pub leases: Vec<Lease>,
+    //RETIRED ADDRESSES DROPPED
+    pub removed: u64,
+}
+
+//ONE ROW OF THE ADDRESS TABLE AS IT IS STORED
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressRecord {
+    pub ip_address: Ipv4Addr,
+    pub allocated: bool,
+    pub client_id: Option<String>,
+    pub lease_start: Option<DateTime<Utc>>,
+    pub lease_end: Option<DateTime<Utc>>,
+    pub retired: bool,
+    pub blocked: bool,
+    pub name: ClientName,
+}
+
+//WHAT AN ADDRESS IS DOING RIGHT NOW, A LIVE LEASE WINS OVER THE FLAGS
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressState {
+    //HELD BY A CLIENT
+    Leased,
+    //DECLINED OR REVOKED, OUT OF THE POOL UNTIL lease_end
+    Held,
+    Blocked,
+    //NO LONGER IN THE POOL, DROPPED ONCE NOBODY HOLDS IT
+    Retired,
+    Free,
+}
+
+impl AddressState {
+    pub fn of(record: &AddressRecord, now: DateTime<Utc>) -> Self {
+        let unexpired = record.allocated && record.lease_end.is_none_or(|end| end > now);
+        if unexpired && record.client_id.is_some() {
+            AddressState::Leased
+        } else if unexpired {
+            AddressState::Held
+        } else if record.blocked {
+            AddressState::Blocked
+        } else if record.retired {
+            AddressState::Retired
+        } else {
+            AddressState::Free
+        }
+    }
+
+    pub fn parse(state: &str) -> Option<Self> {
+        match state {
+            "leased" => Some(AddressState::Leased),
+            "held" => Some(AddressState::Held),
+            "blocked" => Some(AddressState::Blocked),
+            "retired" => Some(AddressState::Retired),
+            "free" => Some(AddressState::Free),
+            _ => None,
+        }
+    }
+}
+
+//ADDRESS KEPT FOR ONE CLIENT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reservation {
+    pub client_id: String,
+    pub ip_address: Ipv4Addr,
+}
+
+//WHICH LEASE HISTORY ROWS TO RETURN, NEWEST FIRST
+#[derive(Debug, Clone, Default)]
+pub struct HistoryFilter {
+    pub client_id: Option<String>,
+    pub ip_address: Option<Ipv4Addr>,
+    pub limit: u32,
+}
+
+//WHERE ADDRESSES AND LEASES ARE KEPT
+//THE SERVER ONLY TALKS TO STORAGE THROUGH THIS, SO IT CAN RUN ON POSTGRESQL OR IN MEMORY
+#[async_trait]
+pub trait LeaseStore: Send + Sync {
+    //FREE LEASES THAT ENDED BEFORE now AND DROP RETIRED ADDRESSES NOBODY HOLDS ANY MORE
+    //EVERY LEASE THAT RAN OUT IS RETURNED ONCE, EVEN WITH SEVERAL HANDLERS EXPIRING AT THE SAME TIME
+    async fn expire_leases(&self, now: DateTime<Utc>) -> Result<Expired, CustomError>;
+
+    //ADDRESS THE CLIENT ALREADY HOLDS
+    async fn address_of_client(&self, client_id: &str) -> Result<Option<Ipv4Addr>, CustomError>;
+
+    //ADDRESS RESERVED FOR THE CLIENT
+    async fn reservation_for(&self, client_id: &str) -> Result<Option<Ipv4Addr>, CustomError>;
+
+    //FREE, STILL IN THE POOL, NOT BLOCKED AND NOT RESERVED FOR ANOTHER CLIENT
+    async fn is_available(&self, ip: Ipv4Addr, client_id: &str) -> Result<bool, CustomError>;
+
+    //FREE, STILL IN THE POOL, NOT BLOCKED AND NOT RESERVED
+    async fn first_available(&self) -> Result<Option<Ipv4Addr>, CustomError>;
+
+    //EVERY ADDRESS first_available COULD RETURN, BY ADDRESS
+    async fn available(&self) -> Result<Vec<Ipv4Addr>, CustomError>;
+
+    //CLIENT HOLDS AN ADDRESS OTHER THAN ip
+    async fn holds_other_address(&self, client_id: &str, ip: Ipv4Addr) -> Result<bool, CustomError>;
+
+    //ip IS FREE OR ALREADY THE CLIENT'S, AND NEITHER BLOCKED NOR RESERVED FOR ANOTHER CLIENT
+    async fn can_lease(&self, ip: Ipv4Addr, client_id: &str) -> Result<bool, CustomError>;
+
+    //ANOTHER CLIENT HOLDING AN ADDRESS GOES BY hostname
+    async fn hostname_taken(&self, hostname: &str, client_id: &str) -> Result<bool, CustomError>;
+
+    //LEASE A FREE ADDRESS TO THE CLIENT, FALSE IF IT WAS NOT FREE BY THE TIME IT WAS WRITTEN
+    async fn lease(&self, ip: Ipv4Addr, client_id: &str, lease: &LeaseTimes, name: &ClientName) -> Result<bool, CustomError>;
+
+    //EXTEND A LEASE THE CLIENT ALREADY HOLDS, KEEPING ITS NAME WHEN name IS NONE
+    //RETURNS THE LEASE AS IT IS NOW AND THE NAME IT HAD BEFORE, NONE IF THE CLIENT DID NOT HOLD ip
+    async fn renew(&self, ip: Ipv4Addr, client_id: &str, lease: &LeaseTimes,
+                   name: Option<&ClientName>) -> Result<Option<(Lease, ClientName)>, CustomError>;
+
+    //KEEP A DECLINED ADDRESS OUT OF THE POOL UNTIL hold_until
+    //WITH A client_id NOTHING CHANGES UNLESS THAT CLIENT HOLDS ip, NONE IS FOR THE FAILOVER PARTNER
+    //RETURNS THE LEASE THE CLIENT HELD ON IT, IF ANY
+    async fn decline(&self, ip: Ipv4Addr, client_id: Option<&str>, now: DateTime<Utc>,
+                     hold_until: DateTime<Utc>) -> Result<Option<Lease>, CustomError>;
+
+    //WITH A client_id NOTHING CHANGES UNLESS THAT CLIENT HOLDS ip, NONE IS FOR THE API
+    //RETURNS THE LEASE THAT ENDED, IF ANY
+    async fn release(&self, ip: Ipv4Addr, client_id: Option<&str>) -> Result<Option<Lease>, CustomError>;
+
+    async fn record_history(&self, entry: HistoryEntry) -> Result<(), CustomError>;
+
+    //BRING THE ADDRESSES IN LINE WITH THE POOL WITHOUT TOUCHING ACTIVE LEASES
+    async fn reconcile(&self, ip_pool: &[Ipv4Addr], restricted_ips: &[Ipv4Addr],
+                       now: DateTime<Utc>) -> Result<ReconcileSummary, CustomError>;
+
+    //LEASES HELD BY CLIENTS THAT HAVE NOT ENDED AT now, BY ADDRESS
+    async fn active_leases(&self, now: DateTime<Utc>) -> Result<Vec<Lease>, CustomError>;
+
+    //EVERY ADDRESS IN THE TABLE, RETIRED ONES INCLUDED, BY ADDRESS
+    async fn addresses(&self) -> Result<Vec<AddressRecord>, CustomError>;
+
+    //NONE IF ip IS NOT IN THE TABLE
+    async fn address(&self, ip: Ipv4Addr) -> Result<Option<AddressRecord>, CustomError>;
+
+    //RECORD A LEASE THE FAILOVER PARTNER GRANTED, WHATEVER THE ADDRESS WAS DOING
+    //AN ADDRESS THE CLIENT HELD BEFORE IS FREED, A CLIENT HOLDS ONE ADDRESS AT A TIME
+    async fn put_lease(&self, lease: &Lease) -> Result<(), CustomError>;
+
+    async fn history(&self, filter: &HistoryFilter) -> Result<Vec<HistoryEntry>, CustomError>;
+
+    async fn reservations(&self) -> Result<Vec<Reservation>, CustomError>;
+
+    //RESERVE OR MOVE THE CLIENT'S RESERVATION, A CONFLICT IF THE ADDRESS IS RESERVED FOR ANOTHER CLIENT
+    async fn reserve(&self, reservation: &Reservation) -> Result<(), CustomError>;
+
+    //FALSE IF THE CLIENT HAD NO RESERVATION
+    async fn unreserve(&self, client_id: &str) -> Result<bool, CustomError>;
+
+    //TAKE A LEASE AWAY BUT KEEP THE ADDRESS OUT OF THE POOL UNTIL THE LEASE WOULD HAVE ENDED
+    //THE CLIENT GETS A NAK WHEN IT RENEWS, RETURNS THE LEASE THAT WAS TAKEN, NONE IF NO CLIENT HELD THE ADDRESS
+    async fn revoke(&self, ip: Ipv4Addr, now: DateTime<Utc>) -> Result<Option<Lease>, CustomError>;
+
+    //FALSE IF THE ADDRESS IS NOT IN THE TABLE
+    async fn set_blocked(&self, ip: Ipv4Addr, blocked: bool) -> Result<bool, CustomError>;
+
+    //SEND THE EVENT TO EVERY SESSION LISTENING ON channel
+    //WITH outbox IT IS ALSO KEPT IN lease_events, IN THE SAME TRANSACTION, AND THE PAYLOAD CARRIES ITS id
+    async fn publish(&self, channel: &str, event: &LeaseEvent, outbox: bool) -> Result<(), CustomError>;
+}
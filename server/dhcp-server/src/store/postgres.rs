@@ -0,0 +1,464 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::net::{IpAddr, Ipv4Addr};
+use tokio::sync::Mutex;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::{Client, Row};
+
+use crate::hooks::LeaseEvent;
+use crate::set_up::create_db_tables::{reconcile_ip_addresses_table, CustomError, ReconcileSummary};
+use crate::store::{AddressRecord, ClientName, DnsUpdate, Expired, HistoryEntry, HistoryFilter, Lease, LeaseStore, LeaseType,
+                   Reservation, ServerResponse};
+use crate::utility::lease::LeaseTimes;
+
+//LEASES IN THE ip_addresses AND lease_history TABLES
+//RECONCILIATION RUNS IN A TRANSACTION SO IT GETS ITS OWN CONNECTION, HANDLERS NEVER SHARE IT
+pub struct PostgresStore {
+    client: Client,
+    admin: Mutex<Client>,
+}
+
+impl PostgresStore {
+    pub fn new(client: Client, admin: Client) -> Self {
+        PostgresStore { client, admin: Mutex::new(admin) }
+    }
+}
+
+fn ip_of(row: &Row) -> Option<Ipv4Addr> {
+    match row.get::<usize, IpAddr>(0) {
+        IpAddr::V4(ip) => Some(ip),
+        IpAddr::V6(_) => None,
+    }
+}
+
+//FROM COLUMNS ip_address, client_id, lease_start, lease_end, hostname, dns_update
+//NONE WHEN NO CLIENT HELD THE ADDRESS
+fn lease_of(row: &Row) -> Option<Lease> {
+    Some(Lease {
+        ip_address: ip_of(row)?,
+        client_id: row.get::<usize, Option<String>>(1)?,
+        lease_start: row.get::<usize, Option<DateTime<Utc>>>(2)?,
+        lease_end: row.get::<usize, Option<DateTime<Utc>>>(3)?,
+        name: name_of(row, 4),
+    })
+}
+
+//FROM COLUMNS ip_address, allocated, client_id, lease_start, lease_end, retired, blocked, hostname, dns_update
+fn record_of(row: &Row) -> Option<AddressRecord> {
+    Some(AddressRecord {
+        ip_address: ip_of(row)?,
+        allocated: row.get(1),
+        client_id: row.get(2),
+        lease_start: row.get(3),
+        lease_end: row.get(4),
+        retired: row.get(5),
+        blocked: row.get(6),
+        name: name_of(row, 7),
+    })
+}
+
+//FROM COLUMNS hostname AND dns_update STARTING AT first
+fn name_of(row: &Row, first: usize) -> ClientName {
+    ClientName {
+        hostname: row.get(first),
+        dns_update: DnsUpdate::parse(row.get(first + 1)).unwrap_or_default(),
+    }
+}
+
+#[async_trait]
+impl LeaseStore for PostgresStore {
+    async fn expire_leases(&self, now: DateTime<Utc>) -> Result<Expired, CustomError> {
+        //THE ROWS ARE LOCKED, A CONCURRENT CALL WAITS AND THEN NO LONGER SEES THEM AS EXPIRED
+        let update_query = "WITH expired AS (
+                                SELECT ip_address, client_id, lease_start, lease_end, hostname, dns_update
+                                FROM ip_addresses
+                                WHERE lease_end < $1
+                                FOR UPDATE
+                            )
+                            UPDATE ip_addresses
+                            SET allocated = false,
+                                client_id = NULL,
+                                lease_start = NULL,
+                                lease_end = NULL,
+                                hostname = NULL,
+                                dns_update = 'both'
+                            FROM expired
+                            WHERE ip_addresses.ip_address = expired.ip_address
+                            RETURNING expired.ip_address, expired.client_id, expired.lease_start, expired.lease_end,
+                                      expired.hostname, expired.dns_update";
+        let rows = self.client.query(update_query, &[&now]).await?;
+        let leases = rows.iter().filter_map(lease_of).collect();
+
+        //DROP ADDRESSES REMOVED FROM THE POOL ONCE NOBODY LEASES THEM
+        let remove_retired_query = "DELETE FROM ip_addresses
+                                    WHERE retired = true
+                                    AND allocated = false";
+        let removed = self.client.execute(remove_retired_query, &[]).await?;
+        Ok(Expired { leases, removed })
+    }
+
+    async fn address_of_client(&self, client_id: &str) -> Result<Option<Ipv4Addr>, CustomError> {
+        let query = "SELECT ip_address
+                     FROM ip_addresses
+                     WHERE client_id = $1
+                     LIMIT 1";
+        Ok(self.client.query_opt(query, &[&client_id]).await?.as_ref().and_then(ip_of))
+    }
+
+    async fn reservation_for(&self, client_id: &str) -> Result<Option<Ipv4Addr>, CustomError> {
+        let query = "SELECT ip_address
+                     FROM reservations
+                     WHERE client_id = $1";
+        Ok(self.client.query_opt(query, &[&client_id]).await?.as_ref().and_then(ip_of))
+    }
+
+    async fn is_available(&self, ip: Ipv4Addr, client_id: &str) -> Result<bool, CustomError> {
+        let query = "SELECT ip_address
+                     FROM ip_addresses
+                     WHERE ip_address = $1
+                     AND allocated = false
+                     AND retired = false
+                     AND blocked = false
+                     AND NOT EXISTS (SELECT 1 FROM reservations r
+                                     WHERE r.ip_address = ip_addresses.ip_address
+                                     AND r.client_id <> $2)
+                     LIMIT 1";
+        Ok(self.client.query_opt(query, &[&IpAddr::V4(ip), &client_id]).await?.is_some())
+    }
+
+    async fn first_available(&self) -> Result<Option<Ipv4Addr>, CustomError> {
+        let query = "SELECT ip_address
+                     FROM ip_addresses
+                     WHERE allocated = false
+                     AND retired = false
+                     AND blocked = false
+                     AND NOT EXISTS (SELECT 1 FROM reservations r
+                                     WHERE r.ip_address = ip_addresses.ip_address)
+                     LIMIT 1
+                     FOR UPDATE";
+        Ok(self.client.query_opt(query, &[]).await?.as_ref().and_then(ip_of))
+    }
+
+    async fn available(&self) -> Result<Vec<Ipv4Addr>, CustomError> {
+        let query = "SELECT ip_address
+                     FROM ip_addresses
+                     WHERE allocated = false
+                     AND retired = false
+                     AND blocked = false
+                     AND NOT EXISTS (SELECT 1 FROM reservations r
+                                     WHERE r.ip_address = ip_addresses.ip_address)
+                     ORDER BY ip_address";
+        Ok(self.client.query(query, &[]).await?.iter().filter_map(ip_of).collect())
+    }
+
+    async fn holds_other_address(&self, client_id: &str, ip: Ipv4Addr) -> Result<bool, CustomError> {
+        let query = "SELECT ip_address
+                     FROM ip_addresses
+                     WHERE client_id = $1
+                     AND NOT ip_address = $2
+                     LIMIT 1";
+        Ok(self.client.query_opt(query, &[&client_id, &IpAddr::V4(ip)]).await?.is_some())
+    }
+
+    async fn can_lease(&self, ip: Ipv4Addr, client_id: &str) -> Result<bool, CustomError> {
+        let query = "SELECT ip_address
+                     FROM ip_addresses
+                     WHERE ip_address = $1
+                     AND (allocated = false
+                     OR client_id = $2)
+                     AND blocked = false
+                     AND NOT EXISTS (SELECT 1 FROM reservations r
+                                     WHERE r.ip_address = ip_addresses.ip_address
+                                     AND r.client_id <> $2)
+                     LIMIT 1";
+        Ok(self.client.query_opt(query, &[&IpAddr::V4(ip), &client_id]).await?.is_some())
+    }
+
+    async fn hostname_taken(&self, hostname: &str, client_id: &str) -> Result<bool, CustomError> {
+        let query = "SELECT ip_address
+                     FROM ip_addresses
+                     WHERE hostname = $1
+                     AND client_id <> $2
+                     LIMIT 1";
+        Ok(self.client.query_opt(query, &[&hostname, &client_id]).await?.is_some())
+    }
+
+    async fn lease(&self, ip: Ipv4Addr, client_id: &str, lease: &LeaseTimes, name: &ClientName) -> Result<bool, CustomError> {
+        let query = "UPDATE ip_addresses
+                     SET allocated = true,
+                         client_id = $2,
+                         lease_start = $3,
+                         lease_end = $4,
+                         hostname = $5,
+                         dns_update = $6
+                     WHERE ip_address = $1
+                     AND allocated = false
+                     AND retired = false
+                     AND blocked = false
+                     AND NOT EXISTS (SELECT 1 FROM reservations r
+                                     WHERE r.ip_address = ip_addresses.ip_address
+                                     AND r.client_id <> $2)";
+        //ONE STATEMENT, SO OF TWO SERVERS RACING FOR ip THE SECOND FINDS IT TAKEN AND UPDATES NOTHING
+        let updated = self.client.execute(query, &[&IpAddr::V4(ip), &client_id, &lease.start, &lease.end,
+            &name.hostname, &name.dns_update.as_str()]).await?;
+        Ok(updated == 1)
+    }
+
+    async fn renew(&self, ip: Ipv4Addr, client_id: &str, lease: &LeaseTimes,
+                   name: Option<&ClientName>) -> Result<Option<(Lease, ClientName)>, CustomError> {
+        //THE ROW IS LOCKED SO THE NAME READ IS THE ONE THIS UPDATE REPLACES
+        let query = "WITH previous AS (
+                         SELECT ip_address, hostname, dns_update
+                         FROM ip_addresses
+                         WHERE ip_address = $1
+                         AND client_id = $2
+                         FOR UPDATE
+                     )
+                     UPDATE ip_addresses
+                     SET allocated = true,
+                         client_id = $2,
+                         lease_start = $3,
+                         lease_end = $4,
+                         hostname = CASE WHEN $5 THEN $6::VARCHAR ELSE ip_addresses.hostname END,
+                         dns_update = CASE WHEN $5 THEN $7::VARCHAR ELSE ip_addresses.dns_update END
+                     FROM previous
+                     WHERE ip_addresses.ip_address = previous.ip_address
+                     RETURNING ip_addresses.ip_address, ip_addresses.client_id, ip_addresses.lease_start, ip_addresses.lease_end,
+                               ip_addresses.hostname, ip_addresses.dns_update, previous.hostname, previous.dns_update";
+        let hostname = name.and_then(|name| name.hostname.as_deref());
+        let dns_update = name.map(|name| name.dns_update).unwrap_or_default();
+        let row = self.client.query_opt(query, &[&IpAddr::V4(ip), &client_id, &lease.start, &lease.end,
+            &name.is_some(), &hostname, &dns_update.as_str()]).await?;
+        Ok(row.and_then(|row| lease_of(&row).map(|lease| (lease, name_of(&row, 6)))))
+    }
+
+    async fn decline(&self, ip: Ipv4Addr, client_id: Option<&str>, now: DateTime<Utc>,
+                     hold_until: DateTime<Utc>) -> Result<Option<Lease>, CustomError> {
+        let query = "WITH declined AS (
+                         SELECT ip_address, client_id, lease_start, lease_end, hostname, dns_update
+                         FROM ip_addresses
+                         WHERE ip_address = $1
+                         AND ($4::TEXT IS NULL OR client_id = $4)
+                         FOR UPDATE
+                     )
+                     UPDATE ip_addresses
+                     SET allocated = true,
+                         client_id = NULL,
+                         lease_start = $2,
+                         lease_end = $3,
+                         hostname = NULL,
+                         dns_update = 'both'
+                     FROM declined
+                     WHERE ip_addresses.ip_address = declined.ip_address
+                     RETURNING declined.ip_address, declined.client_id, declined.lease_start, declined.lease_end,
+                               declined.hostname, declined.dns_update";
+        let row = self.client.query_opt(query, &[&IpAddr::V4(ip), &now, &hold_until, &client_id]).await?;
+        Ok(row.as_ref().and_then(lease_of))
+    }
+
+    async fn release(&self, ip: Ipv4Addr, client_id: Option<&str>) -> Result<Option<Lease>, CustomError> {
+        let query = "WITH released AS (
+                         SELECT ip_address, client_id, lease_start, lease_end, hostname, dns_update
+                         FROM ip_addresses
+                         WHERE ip_address = $1
+                         AND ($2::TEXT IS NULL OR client_id = $2)
+                         FOR UPDATE
+                     )
+                     UPDATE ip_addresses
+                     SET allocated = false,
+                         client_id = NULL,
+                         lease_start = NULL,
+                         lease_end = NULL,
+                         hostname = NULL,
+                         dns_update = 'both'
+                     FROM released
+                     WHERE ip_addresses.ip_address = released.ip_address
+                     RETURNING released.ip_address, released.client_id, released.lease_start, released.lease_end,
+                               released.hostname, released.dns_update";
+        let row = self.client.query_opt(query, &[&IpAddr::V4(ip), &client_id]).await?;
+        Ok(row.as_ref().and_then(lease_of))
+    }
+
+    async fn record_history(&self, entry: HistoryEntry) -> Result<(), CustomError> {
+        //ENUMS ARE SENT AS TEXT AND CAST SO NO CUSTOM TYPE MAPPING IS NEEDED
+        let query = "INSERT INTO lease_history (ip_address,
+                                                client_id,
+                                                lease_start,
+                                                lease_end,
+                                                server_response,
+                                                lease_type)
+                     VALUES ($1,
+                             $2,
+                             $3,
+                             $4,
+                             CAST($5::TEXT AS server_response),
+                             CAST($6::TEXT AS lease_type))";
+        self.client.execute(query, &[&IpAddr::V4(entry.ip_address), &entry.client_id,
+            &entry.lease_start, &entry.lease_end,
+            &entry.server_response.as_str(), &entry.lease_type.as_str()]).await?;
+        Ok(())
+    }
+
+    async fn reconcile(&self, ip_pool: &[Ipv4Addr], restricted_ips: &[Ipv4Addr],
+                       now: DateTime<Utc>) -> Result<ReconcileSummary, CustomError> {
+        let mut admin = self.admin.lock().await;
+        Ok(reconcile_ip_addresses_table(&mut admin, ip_pool, restricted_ips, now).await?)
+    }
+
+    async fn active_leases(&self, now: DateTime<Utc>) -> Result<Vec<Lease>, CustomError> {
+        let query = "SELECT ip_address, client_id, lease_start, lease_end, hostname, dns_update
+                     FROM ip_addresses
+                     WHERE allocated = true
+                     AND client_id IS NOT NULL
+                     AND lease_end > $1
+                     ORDER BY ip_address";
+        let rows = self.client.query(query, &[&now]).await?;
+        Ok(rows.iter().filter_map(lease_of).collect())
+    }
+
+    async fn addresses(&self) -> Result<Vec<AddressRecord>, CustomError> {
+        let query = "SELECT ip_address, allocated, client_id, lease_start, lease_end, retired, blocked, hostname, dns_update
+                     FROM ip_addresses
+                     ORDER BY ip_address";
+        let rows = self.client.query(query, &[]).await?;
+        Ok(rows.iter().filter_map(record_of).collect())
+    }
+
+    async fn address(&self, ip: Ipv4Addr) -> Result<Option<AddressRecord>, CustomError> {
+        let query = "SELECT ip_address, allocated, client_id, lease_start, lease_end, retired, blocked, hostname, dns_update
+                     FROM ip_addresses
+                     WHERE ip_address = $1";
+        Ok(self.client.query_opt(query, &[&IpAddr::V4(ip)]).await?.as_ref().and_then(record_of))
+    }
+
+    async fn put_lease(&self, lease: &Lease) -> Result<(), CustomError> {
+        //client_id IS UNIQUE, SO THE CLIENT'S OLD ADDRESS IS FREED FIRST IN THE SAME TRANSACTION
+        let mut admin = self.admin.lock().await;
+        let transaction = admin.transaction().await?;
+        let free_query = "UPDATE ip_addresses
+                          SET allocated = false,
+                              client_id = NULL,
+                              lease_start = NULL,
+                              lease_end = NULL,
+                              hostname = NULL,
+                              dns_update = 'both'
+                          WHERE client_id = $1
+                          AND ip_address <> $2";
+        let ip = IpAddr::V4(lease.ip_address);
+        transaction.execute(free_query, &[&lease.client_id, &ip]).await?;
+        let lease_query = "UPDATE ip_addresses
+                           SET allocated = true,
+                               client_id = $2,
+                               lease_start = $3,
+                               lease_end = $4,
+                               hostname = $5,
+                               dns_update = $6
+                           WHERE ip_address = $1";
+        transaction.execute(lease_query, &[&ip, &lease.client_id, &lease.lease_start, &lease.lease_end,
+            &lease.name.hostname, &lease.name.dns_update.as_str()]).await?;
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn history(&self, filter: &HistoryFilter) -> Result<Vec<HistoryEntry>, CustomError> {
+        let query = "SELECT ip_address, client_id, lease_start, lease_end,
+                            server_response::TEXT, lease_type::TEXT
+                     FROM lease_history
+                     WHERE ($1::VARCHAR IS NULL OR client_id = $1)
+                     AND ($2::INET IS NULL OR ip_address = $2)
+                     ORDER BY id DESC
+                     LIMIT $3";
+        let ip_address = filter.ip_address.map(IpAddr::V4);
+        let rows = self.client.query(query, &[&filter.client_id, &ip_address, &(filter.limit as i64)]).await?;
+        Ok(rows.iter().filter_map(|row| Some(HistoryEntry {
+            ip_address: ip_of(row)?,
+            client_id: row.get(1),
+            lease_start: row.get(2),
+            lease_end: row.get(3),
+            server_response: match row.get::<usize, &str>(4) {
+                "NAK" => ServerResponse::Nak,
+                _ => ServerResponse::Ack,
+            },
+            lease_type: match row.get::<usize, &str>(5) {
+                "RENEWING" => LeaseType::Renewing,
+                "DECLINED" => LeaseType::Declined,
+                _ => LeaseType::Initial,
+            },
+        })).collect())
+    }
+
+    async fn reservations(&self) -> Result<Vec<Reservation>, CustomError> {
+        let query = "SELECT ip_address, client_id
+                     FROM reservations
+                     ORDER BY ip_address";
+        let rows = self.client.query(query, &[]).await?;
+        Ok(rows.iter().filter_map(|row| Some(Reservation {
+            ip_address: ip_of(row)?,
+            client_id: row.get(1),
+        })).collect())
+    }
+
+    async fn reserve(&self, reservation: &Reservation) -> Result<(), CustomError> {
+        let query = "INSERT INTO reservations (client_id, ip_address)
+                     VALUES ($1, $2)
+                     ON CONFLICT (client_id) DO UPDATE SET ip_address = EXCLUDED.ip_address";
+        match self.client.execute(query, &[&reservation.client_id, &IpAddr::V4(reservation.ip_address)]).await {
+            Ok(_) => Ok(()),
+            Err(e) if e.code() == Some(&SqlState::UNIQUE_VIOLATION) => Err(CustomError::Conflict(
+                format!("{} is reserved for another client", reservation.ip_address))),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn unreserve(&self, client_id: &str) -> Result<bool, CustomError> {
+        let query = "DELETE FROM reservations
+                     WHERE client_id = $1";
+        Ok(self.client.execute(query, &[&client_id]).await? > 0)
+    }
+
+    async fn revoke(&self, ip: Ipv4Addr, now: DateTime<Utc>) -> Result<Option<Lease>, CustomError> {
+        let query = "WITH revoked AS (
+                         SELECT ip_address, client_id, lease_start, lease_end, hostname, dns_update
+                         FROM ip_addresses
+                         WHERE ip_address = $1
+                         AND allocated = true
+                         AND client_id IS NOT NULL
+                         FOR UPDATE
+                     )
+                     UPDATE ip_addresses
+                     SET client_id = NULL,
+                         lease_start = $2,
+                         hostname = NULL,
+                         dns_update = 'both'
+                     FROM revoked
+                     WHERE ip_addresses.ip_address = revoked.ip_address
+                     RETURNING revoked.ip_address, revoked.client_id, revoked.lease_start, revoked.lease_end,
+                               revoked.hostname, revoked.dns_update";
+        let row = self.client.query_opt(query, &[&IpAddr::V4(ip), &now]).await?;
+        Ok(row.as_ref().and_then(lease_of))
+    }
+
+    async fn set_blocked(&self, ip: Ipv4Addr, blocked: bool) -> Result<bool, CustomError> {
+        let query = "UPDATE ip_addresses
+                     SET blocked = $2
+                     WHERE ip_address = $1";
+        Ok(self.client.execute(query, &[&IpAddr::V4(ip), &blocked]).await? > 0)
+    }
+
+    async fn publish(&self, channel: &str, event: &LeaseEvent, outbox: bool) -> Result<(), CustomError> {
+        if !outbox {
+            self.client.execute("SELECT pg_notify($1, $2)", &[&channel, &event.payload(None)]).await?;
+            return Ok(());
+        }
+        //ONE STATEMENT, SO THE NOTIFICATION IS ONLY DELIVERED WHEN THE ROW IS COMMITTED
+        let query = "WITH kept AS (
+                         INSERT INTO lease_events (event, ip_address, client_id, payload)
+                         VALUES ($1, $2, $3, $4::TEXT::JSONB)
+                         RETURNING id, payload
+                     )
+                     SELECT pg_notify($5, (payload || jsonb_build_object('id', id))::TEXT) FROM kept";
+        self.client.execute(query, &[&event.event.as_str(), &IpAddr::V4(event.ip_address), &event.client_id,
+            &event.payload(None), &channel]).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+//THE NAME A CLIENT ASKS FOR IN OPTION 12 (RFC 2132) OR OPTION 81 (RFC 4702), THE HOST NAME POLICY APPLIED TO IT
+//AND THE OPTIONS THAT TELL THE CLIENT WHAT IT ENDED UP WITH
+//OPTION 81 ALSO SETTLES WHO UPDATES THE CLIENT'S A RECORD: THE SERVER (S) OR THE CLIENT ITSELF, OR NOBODY (N)
+use dhcp_proto::types::DHCPMessage;
+
+use crate::server_config::server_config::HostnameConfig;
+use crate::set_up::create_db_tables::CustomError;
+use crate::store::{ClientName, DnsUpdate, LeaseStore};
+use crate::utility::options::*;
+
+//OPTION 81 FLAGS
+pub const FQDN_SERVER_UPDATES: u8 = 0x01;
+pub const FQDN_OVERRIDE: u8 = 0x02;
+pub const FQDN_ENCODED: u8 = 0x04;
+pub const FQDN_NO_UPDATES: u8 = 0x08;
+//RCODE1 AND RCODE2 A SERVER SENDS, RFC 4702 SECTION 2.2
+const FQDN_RCODE: u8 = 255;
+
+const MAX_LABEL_LENGTH: usize = 63;
+
+//OPTION 81 AS THE CLIENT SENT IT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientFqdn {
+    pub flags: u8,
+    //DOTTED, WITHOUT THE TRAILING DOT, EMPTY WHEN THE CLIENT LEAVES THE NAME TO THE SERVER
+    pub name: String,
+}
+
+pub fn client_fqdn(message: &DHCPMessage) -> Option<ClientFqdn> {
+    let data = message.options_map.get(&CLIENT_FQDN)?;
+    let flags = *data.first()?;
+    let name = data.get(3..)?;
+    let name = if flags & FQDN_ENCODED != 0 {
+        //LABELS ON THE WIRE, A PARTIAL NAME LACKS THE CLOSING ZERO
+        let mut labels = Vec::new();
+        let mut position = 0;
+        while let Some(&length) = name.get(position) {
+            if length == 0 {
+                break;
+            }
+            let label = name.get(position + 1..position + 1 + length as usize)?;
+            labels.push(String::from_utf8_lossy(label).into_owned());
+            position += 1 + length as usize;
+        }
+        labels.join(".")
+    } else {
+        String::from_utf8_lossy(name).trim_end_matches(['.', '\0']).to_string()
+    };
+    Some(ClientFqdn { flags, name })
+}
+
+//FIRST LABEL OF OPTION 81, OR OPTION 12 WHEN OPTION 81 CARRIES NO NAME
+pub fn requested_hostname(message: &DHCPMessage) -> Option<String> {
+    let from_fqdn = client_fqdn(message).map(|fqdn| fqdn.name).filter(|name| !name.is_empty());
+    let name = match from_fqdn {
+        Some(name) => name,
+        None => String::from_utf8_lossy(message.options_map.get(&HOST_NAME)?).into_owned(),
+    };
+    name.split('.').next().map(str::to_string)
+}
+
+//A LABEL THAT IS SAFE TO STORE AND REGISTER IN DNS, NONE WHEN NOTHING USABLE IS LEFT
+pub fn sanitize(name: &str, policy: &HostnameConfig) -> Option<String> {
+    let mut clean = String::with_capacity(name.len());
+    for c in name.trim_end_matches('\0').chars() {
+        match c {
+            c if c.is_ascii_alphanumeric() || c == '-' => clean.push(if policy.lowercase { c.to_ascii_lowercase() } else { c }),
+            _ => clean.push_str(&policy.replacement),
+        }
+    }
+    let clean = label(&clean, MAX_LABEL_LENGTH);
+    (!clean.is_empty()).then_some(clean)
+}
+
+//AT MOST length BYTES, A LABEL NEITHER STARTS NOR ENDS WITH A HYPHEN
+fn label(name: &str, length: usize) -> String {
+    let name = name.trim_matches('-');
+    name[..name.len().min(length)].trim_end_matches('-').to_string()
+}
+
+//WHICH RECORDS THE SERVER UPDATES, BOTH WHEN THE CLIENT SENT NO OPTION 81
+pub fn dns_update(fqdn: Option<&ClientFqdn>, policy: &HostnameConfig) -> DnsUpdate {
+    match fqdn {
+        None => DnsUpdate::Both,
+        Some(_) if policy.override_client_updates => DnsUpdate::Both,
+        Some(fqdn) if fqdn.flags & FQDN_NO_UPDATES != 0 => DnsUpdate::Neither,
+        Some(fqdn) if fqdn.flags & FQDN_SERVER_UPDATES != 0 => DnsUpdate::Both,
+        Some(_) => DnsUpdate::Ptr,
+    }
+}
+
+//THE CLIENT SENT OPTION 12 OR 81, SO A RENEWAL REPLACES THE STORED NAME
+pub fn names_itself(message: &DHCPMessage) -> bool {
+    message.options_map.contains_key(&HOST_NAME) || message.options_map.contains_key(&CLIENT_FQDN)
+}
+
+//THE NAME THE CLIENT GETS, WITH -2, -3 AND SO ON ADDED WHILE ANOTHER CLIENT HOLDS IT AND THE POLICY ASKS FOR UNIQUE NAMES
+pub async fn client_name(store: &dyn LeaseStore, message: &DHCPMessage, client_id: &str,
+                         policy: &HostnameConfig) -> Result<ClientName, CustomError> {
+    let dns_update = dns_update(client_fqdn(message).as_ref(), policy);
+    let Some(requested) = requested_hostname(message).and_then(|name| sanitize(&name, policy)) else {
+        return Ok(ClientName { hostname: None, dns_update });
+    };
+    let mut hostname = requested.clone();
+    let mut suffix = 2;
+    while policy.unique && store.hostname_taken(&hostname, client_id).await? {
+        let ending = format!("-{}", suffix);
+        hostname = format!("{}{}", label(&requested, MAX_LABEL_LENGTH - ending.len()), ending);
+        suffix += 1;
+    }
+    Ok(ClientName { hostname: Some(hostname), dns_update })
+}
+
+//OPTION 12 WHEN THE CLIENT SENT OR ASKED FOR IT, OPTION 81 WHEN THE CLIENT SENT IT
+pub fn push_name_options(options_buf: &mut Vec<u8>, message: &DHCPMessage, name: &ClientName, domain_name: &str) {
+    let Some(hostname) = &name.hostname else { return };
+    let requested = message.options_map.get(&PARAMETER_REQUEST_LIST).is_some_and(|list| list.contains(&HOST_NAME));
+    if message.options_map.contains_key(&HOST_NAME) || requested {
+        push_bytes(options_buf, HOST_NAME, hostname.as_bytes());
+    }
+    let Some(fqdn) = client_fqdn(message) else { return };
+    let mut flags = fqdn.flags & FQDN_ENCODED;
+    match name.dns_update {
+        DnsUpdate::Both => {
+            flags |= FQDN_SERVER_UPDATES;
+            //THE CLIENT WANTED TO UPDATE THE A RECORD ITSELF, OR NOT AT ALL
+            if fqdn.flags & FQDN_SERVER_UPDATES == 0 {
+                flags |= FQDN_OVERRIDE;
+            }
+        }
+        DnsUpdate::Ptr => {}
+        DnsUpdate::Neither => flags |= FQDN_NO_UPDATES,
+    }
+    let mut full = hostname.clone();
+    if !domain_name.is_empty() {
+        full = format!("{}.{}", hostname, domain_name.trim_end_matches('.'));
+    }
+    let mut data = vec![flags, FQDN_RCODE, FQDN_RCODE];
+    if flags & FQDN_ENCODED != 0 {
+        for part in full.split('.') {
+            data.push(part.len() as u8);
+            data.extend_from_slice(part.as_bytes());
+        }
+        data.push(0);
+    } else {
+        data.extend_from_slice(full.as_bytes());
+    }
+    //A DOMAIN TOO LONG TO FIT IS LEFT OUT, THE CLIENT STILL LEARNS ITS HOST NAME
+    if data.len() > u8::MAX as usize {
+        data.truncate(3);
+        if flags & FQDN_ENCODED != 0 {
+            data.push(hostname.len() as u8);
+            data.extend_from_slice(hostname.as_bytes());
+        } else {
+            data.extend_from_slice(hostname.as_bytes());
+        }
+    }
+    push_bytes(options_buf, CLIENT_FQDN, &data);
+}
@@ -0,0 +1,52 @@
+use chrono::{DateTime, Duration, Utc};
+use std::time::Duration as StdDuration;
+
+use crate::server_config::Config;
+
+//HOW LONG A DECLINED ADDRESS IS KEPT OUT OF THE POOL
+pub const DECLINE_HOLD_SECONDS: i64 = 3600;
+
+//INSTANTS OF ONE LEASE GRANT
+//RENEWAL (T1) AND REBINDING (T2) ARE WHEN THE CLIENT STARTS RENEWING AND REBINDING
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeaseTimes {
+    pub start: DateTime<Utc>,
+    pub renewal: DateTime<Utc>,
+    pub rebinding: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl LeaseTimes {
+    pub fn new(start: DateTime<Utc>, lease_time: StdDuration, renewal_time: StdDuration, rebinding_time: StdDuration) -> Self {
+        LeaseTimes {
+            start,
+            renewal: start + Duration::seconds(renewal_time.as_secs() as i64),
+            rebinding: start + Duration::seconds(rebinding_time.as_secs() as i64),
+            end: start + Duration::seconds(lease_time.as_secs() as i64),
+        }
+    }
+
+    pub fn from_config(start: DateTime<Utc>, config: &Config) -> Self {
+        LeaseTimes::new(start, config.server.lease_time, config.server.renewal_time, config.server.rebinding_time)
+    }
+
+    //THE SAME GRANT ENDING AT end IF THAT IS EARLIER, RENEWAL AND REBINDING MOVE TO HALF AND SEVEN EIGHTHS OF IT
+    pub fn capped(self, end: DateTime<Utc>) -> Self {
+        if end >= self.end {
+            return self;
+        }
+        let length = end - self.start;
+        LeaseTimes {
+            start: self.start,
+            renewal: self.start + length / 2,
+            rebinding: self.start + length * 7 / 8,
+            end,
+        }
+    }
+
+    //LEASE, RENEWAL AND REBINDING TIME AS OPTIONS 51, 58 AND 59 CARRY THEM
+    pub fn durations(&self) -> (StdDuration, StdDuration, StdDuration) {
+        let since_start = |instant: DateTime<Utc>| (instant - self.start).to_std().unwrap_or_default();
+        (since_start(self.end), since_start(self.renewal), since_start(self.rebinding))
+    }
+}
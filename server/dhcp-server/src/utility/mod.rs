@@ -0,0 +1,4 @@
+pub use dhcp_proto::types;
+pub mod options;
+pub mod lease;
+pub mod hostname;
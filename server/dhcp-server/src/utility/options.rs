@@ -0,0 +1,84 @@
+//REPLY OPTIONS BUILT FROM THE SERVER CONFIGURATION
+//THE OPTION CODES AND ENCODERS THEMSELVES LIVE IN DHCP-PROTO
+pub use dhcp_proto::options::*;
+
+use dhcp_proto::types::DHCPMessage;
+use crate::server_config::Config;
+use crate::utility::lease::LeaseTimes;
+use std::net::Ipv4Addr;
+
+//lease IS THE GRANT BEING MADE, NONE FOR THE CONFIGURED TIMES
+pub fn create_options_buffer(message: &DHCPMessage, config: &Config, message_type: u8, server_id: Ipv4Addr,
+                             lease: Option<&LeaseTimes>) -> Vec<u8> {
+    let mut options_buf = Vec::new();
+    options_buf.push(MESSAGE_TYPE);
+    options_buf.push(1);
+    options_buf.push(message_type);
+
+    if message_type != DHCPNAK{
+        push_ip(&mut options_buf, SUBNET_MASK, config.server.subnet_mask);
+        push_ip(&mut options_buf, ROUTER, config.server.default_gateway);
+        push_ip(&mut options_buf, DNS_SERVER, config.server.dns_server);
+        push_bytes(&mut options_buf, DOMAIN_NAME, config.server.domain_name.as_bytes());
+
+        if message.options_map.get(&MESSAGE_TYPE).and_then(|v| v.first()) != Some(&DHCPINFORM){
+            let (lease_time, renewal_time, rebinding_time) = match lease {
+                Some(lease) => lease.durations(),
+                None => (config.server.lease_time, config.server.renewal_time, config.server.rebinding_time),
+            };
+            push_seconds(&mut options_buf, LEASE_TIME, lease_time);
+            push_seconds(&mut options_buf, RENEWAL_TIME, renewal_time);
+            push_seconds(&mut options_buf, REBINDING_TIME, rebinding_time);
+        }
+    }
+
+    push_ip(&mut options_buf, SERVER_IDENTIFIER, server_id);
+
+    options_buf
+}
+
+//hostname IS WHAT THE CLIENT IS CALLED, SEE utility::hostname
+pub fn inform_options_buf(parameter_request_list: Vec<u8>, config: &Config, hostname: &str, server_id: Ipv4Addr) -> Vec<u8> {
+    let mut options_buf = Vec::new();
+    push_ip(&mut options_buf, SERVER_IDENTIFIER, server_id);
+    options_buf.push(MESSAGE_TYPE);
+    options_buf.push(1);
+    options_buf.push(DHCPACK);
+
+    let extended = &config.options_extended;
+    for id in parameter_request_list {
+        match id {
+            SUBNET_MASK => push_ip(&mut options_buf, SUBNET_MASK, extended.subnet_mask),
+            TIME_OFFSET => push_bytes(&mut options_buf, TIME_OFFSET, &extended.time_offset.to_be_bytes()),
+            ROUTER => push_ip_list(&mut options_buf, ROUTER, &extended.router),
+            TIME_SERVER => push_ip_list(&mut options_buf, TIME_SERVER, &extended.time_server),
+            NAME_SERVER => push_ip_list(&mut options_buf, NAME_SERVER, &extended.name_server),
+            DNS_SERVER => push_ip_list(&mut options_buf, DNS_SERVER, &extended.domain_name_server),
+            LOG_SERVER => push_ip_list(&mut options_buf, LOG_SERVER, &extended.log_server),
+            COOKIE_SERVER => push_ip_list(&mut options_buf, COOKIE_SERVER, &extended.cookie_server),
+            LPR_SERVER => push_ip_list(&mut options_buf, LPR_SERVER, &extended.lpr_server),
+            IMPRESS_SERVER => push_ip_list(&mut options_buf, IMPRESS_SERVER, &extended.impress_server),
+            RESOURCE_LOCATION_SERVER => push_ip_list(&mut options_buf, RESOURCE_LOCATION_SERVER, &extended.resource_location_server),
+            BOOT_FILE_SIZE => push_bytes(&mut options_buf, BOOT_FILE_SIZE, &extended.boot_file_size.to_be_bytes()),
+            MERIT_DUMP_FILE => push_bytes(&mut options_buf, MERIT_DUMP_FILE, extended.merit_dump_file.as_bytes()),
+            DOMAIN_NAME => push_bytes(&mut options_buf, DOMAIN_NAME, extended.domain_name.as_bytes()),
+            SWAP_SERVER => push_ip(&mut options_buf, SWAP_SERVER, extended.swap_server),
+            ROOT_PATH => push_bytes(&mut options_buf, ROOT_PATH, extended.root_path.as_bytes()),
+            EXTENSIONS_PATH => push_bytes(&mut options_buf, EXTENSIONS_PATH, extended.extensions_path.as_bytes()),
+            BROADCAST_ADDRESS => push_ip(&mut options_buf, BROADCAST_ADDRESS, extended.broadcast_address),
+            NETWORK_TIME_PROTOCOL_SERVERS => push_ip_list(&mut options_buf, NETWORK_TIME_PROTOCOL_SERVERS, &extended.network_time_protocol_servers),
+            HOST_NAME => push_bytes(&mut options_buf, HOST_NAME, hostname.as_bytes()),
+            /*
+             * This match can be expanded to include other options as desired
+             * To do so would require adding the option to the config.json, config type and here
+             */
+            _ => {},
+        }
+    }
+    options_buf
+}
+
+//NAME FOR A CLIENT THAT SENT NONE OF ITS OWN: user FOLLOWED BY THE ETHERNET ADDRESS IN HEX
+pub fn host_name(client_id: &str) -> String {
+    format!("user{}", client_id.get(..12).unwrap_or(client_id))
+}
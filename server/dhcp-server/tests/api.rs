@@ -0,0 +1,227 @@
+mod common;
+
+use std::net::Ipv4Addr;
+
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+use serde_json::json;
+use tower::ServiceExt;
+
+use common::*;
+use dhcp_proto::options::*;
+use dhcp_server::hooks::LeaseEventKind;
+use dhcp_server::store::LeaseStore;
+
+const CLIENT_A: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x0a];
+const CLIENT_B: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x0b];
+
+#[tokio::test]
+async fn requests_without_the_token_are_refused() {
+    let harness = Harness::start().await;
+    let router = router(&harness);
+    for authorization in [None, Some("Bearer wrong-token"), Some(TOKEN)] {
+        let mut request = Request::builder().uri("/api/pool");
+        if let Some(authorization) = authorization {
+            request = request.header("authorization", authorization);
+        }
+        let response = router.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()["www-authenticate"], "Bearer");
+    }
+}
+
+#[tokio::test]
+async fn leases_are_listed_and_searched() {
+    let mut harness = Harness::start().await;
+    let router = router(&harness);
+    let client = harness.client(CLIENT_A);
+    let address = client.dora().await.yiaddr;
+    harness.give_back(client);
+
+    let (status, leases) = call(&router, Method::GET, "/api/leases", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(leases, json!([{
+        "ip_address": address.to_string(),
+        "state": "leased",
+        "client_id": client_id(CLIENT_A),
+        "hostname": null,
+        "lease_start": "2024-01-01T12:00:00Z",
+        "lease_end": "2024-01-01T13:00:00Z",
+        "blocked": false,
+        "retired": false,
+        "reserved_for": null,
+    }]));
+
+    let (_, found) = call(&router, Method::GET, "/api/leases?search=02:00:00:00:00:0A", None).await;
+    assert_eq!(found.as_array().unwrap().len(), 1);
+    let (_, found) = call(&router, Method::GET, "/api/leases?search=00:0b", None).await;
+    assert!(found.as_array().unwrap().is_empty());
+
+    let (status, history) = call(&router, Method::GET,
+        &format!("/api/history?client_id=02-00-00-00-00-0a&ip_address={}", address), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(history[0]["server_response"], "ACK");
+    assert_eq!(history[0]["lease_type"], "INITIAL");
+
+    let (status, _) = call(&router, Method::GET, "/api/history?limit=5000", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, error) = call(&router, Method::GET, "/api/leases/not-an-address", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(error["error"].is_string());
+}
+
+#[tokio::test]
+async fn reserved_address_is_offered_to_its_client_only() {
+    let mut harness = Harness::start().await;
+    let router = router(&harness);
+    let reserved = Ipv4Addr::new(127, 0, 0, 108);
+
+    let (status, reservation) = call(&router, Method::PUT, "/api/reservations/02:00:00:00:00:0a",
+        Some(json!({ "ip_address": reserved.to_string() }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(reservation["client_id"], client_id(CLIENT_A));
+
+    //THE SAME ADDRESS CANNOT BE RESERVED TWICE, NOR ONE OUTSIDE THE POOL
+    let (status, _) = call(&router, Method::PUT, "/api/reservations/02:00:00:00:00:0b",
+        Some(json!({ "ip_address": reserved.to_string() }))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = call(&router, Method::PUT, "/api/reservations/02:00:00:00:00:0b",
+        Some(json!({ "ip_address": RESTRICTED.to_string() }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let other = harness.client(CLIENT_B);
+    assert_ne!(other.discover(Some(reserved)).await.yiaddr, reserved);
+    harness.give_back(other);
+    let client = harness.client(CLIENT_A);
+    assert_eq!(client.dora().await.yiaddr, reserved);
+    harness.give_back(client);
+
+    let (status, _) = call(&router, Method::DELETE, "/api/reservations/02:00:00:00:00:0a", None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, reservations) = call(&router, Method::GET, "/api/reservations", None).await;
+    assert_eq!(reservations, json!([]));
+}
+
+#[tokio::test]
+async fn revoked_lease_is_naked_and_held() {
+    let mut harness = Harness::start().await;
+    let router = router(&harness);
+    let client = harness.client(CLIENT_A);
+    let address = client.dora().await.yiaddr;
+
+    let (status, revoked) = call(&router, Method::POST, &format!("/api/leases/{}/revoke", address), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(revoked["state"], "held");
+
+    assert_eq!(message_type(&client.renew(address).await), Some(DHCPNAK));
+    //THE ADDRESS STAYS OUT OF THE POOL UNTIL THE OLD LEASE WOULD HAVE ENDED
+    assert_ne!(client.discover(Some(address)).await.yiaddr, address);
+    harness.advance(LEASE_SECONDS + 1);
+    assert_eq!(client.discover(Some(address)).await.yiaddr, address);
+    harness.give_back(client);
+
+    let (status, _) = call(&router, Method::POST, &format!("/api/leases/{}/revoke", address), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn released_lease_is_free_at_once() {
+    let mut harness = Harness::start().await;
+    let router = router(&harness);
+    let client = harness.client(CLIENT_A);
+    let address = client.dora().await.yiaddr;
+    harness.give_back(client);
+
+    let (status, released) = call(&router, Method::POST, &format!("/api/leases/{}/release", address), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(released["state"], "free");
+
+    let other = harness.client(CLIENT_B);
+    assert_eq!(other.discover(Some(address)).await.yiaddr, address);
+    harness.give_back(other);
+}
+
+//THE EVENT COMES FROM THE LEASE THE STORE ENDED, AND NOTHING IS SENT WHEN NO LEASE ENDED
+#[tokio::test]
+async fn api_releases_tell_hooks_about_the_lease_that_ended() {
+    let mut harness = Harness::start_with(config_with("hooks", json!({
+        "targets": [{"type": "notify", "outbox": true, "events": ["released"]}],
+    }))).await;
+    let router = router(&harness);
+    let mut addresses = Vec::new();
+    for (mac, name) in [(CLIENT_A, "printer"), (CLIENT_B, "scanner")] {
+        let mut client = harness.client(mac);
+        push_bytes(&mut client.options, HOST_NAME, name.as_bytes());
+        addresses.push(client.dora().await.yiaddr);
+        harness.give_back(client);
+    }
+    let leases = harness.store.active_leases(harness.now()).await.unwrap();
+
+    for (address, action) in addresses.iter().zip(["release", "revoke"]) {
+        let (status, _) = call(&router, Method::POST, &format!("/api/leases/{}/{}", address, action), None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = call(&router, Method::POST, &format!("/api/leases/{}/{}", address, action), None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    let store = harness.store.clone();
+    harness.eventually("both events were kept", || async { store.outbox().len() >= 2 }).await;
+    let events: Vec<_> = harness.store.outbox().into_iter()
+        .map(|event| (event.event, event.ip_address, event.client_id, event.hostname))
+        .collect();
+    let expected: Vec<_> = leases.into_iter()
+        .map(|lease| (LeaseEventKind::Released, lease.ip_address, lease.client_id, lease.name.hostname))
+        .collect();
+    assert_eq!(events, expected);
+    assert!(events.iter().all(|event| event.3.is_some()));
+}
+
+#[tokio::test]
+async fn blocked_address_is_not_offered() {
+    let mut harness = Harness::start().await;
+    let router = router(&harness);
+    let address = Ipv4Addr::new(127, 0, 0, 107);
+
+    let (status, blocked) = call(&router, Method::PUT, &format!("/api/addresses/{}/block", address), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(blocked["state"], "blocked");
+    let (_, listed) = call(&router, Method::GET, "/api/addresses?state=blocked", None).await;
+    assert_eq!(listed.as_array().unwrap().len(), 1);
+
+    let client = harness.client(CLIENT_A);
+    assert_ne!(client.discover(Some(address)).await.yiaddr, address);
+    let (status, _) = call(&router, Method::DELETE, &format!("/api/addresses/{}/block", address), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(client.discover(Some(address)).await.yiaddr, address);
+    harness.give_back(client);
+
+    let (status, _) = call(&router, Method::PUT, "/api/addresses/127.0.0.200/block", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn pool_utilization_counts_every_state() {
+    let mut harness = Harness::start().await;
+    let router = router(&harness);
+    let client = harness.client(CLIENT_A);
+    let address = client.dora().await.yiaddr;
+    harness.give_back(client);
+    call(&router, Method::PUT, "/api/addresses/127.0.0.110/block", None).await;
+
+    let (status, pool) = call(&router, Method::GET, "/api/pool", None).await;
+    assert_eq!(status, StatusCode::OK);
+    //ELEVEN ADDRESSES, ONE OF THEM RESTRICTED
+    assert_eq!(pool, json!({
+        "range_start": POOL_START.to_string(),
+        "range_end": POOL_END.to_string(),
+        "size": 10,
+        "leased": 1,
+        "held": 0,
+        "blocked": 1,
+        "free": 8,
+        "reserved": 0,
+        "retired": 0,
+        "utilization": 0.1,
+    }));
+    assert_ne!(address, Ipv4Addr::new(127, 0, 0, 110));
+}
@@ -0,0 +1,443 @@
+//IN-PROCESS DHCP CLIENT AND SERVER FOR INTEGRATION TESTS
+//EVERYTHING RUNS ON THE LOOPBACK INTERFACE ON EPHEMERAL PORTS SO NO PRIVILEGES ARE NEEDED
+#![allow(dead_code)]
+
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+use axum::Router;
+use chrono::{DateTime, TimeZone, Utc};
+use http_body_util::BodyExt;
+use serde_json::Value;
+use tokio::net::UdpSocket;
+use tokio::time::{timeout, Duration};
+use tower::ServiceExt;
+
+use dhcp_proto::options::*;
+use dhcp_proto::DHCPMessage;
+use dhcp_server::api::{self, ApiState};
+use dhcp_server::clock::FakeClock;
+use dhcp_server::ha::LeaderLock;
+use dhcp_server::listener::Listener;
+use dhcp_server::server_config::server_config::{parse_config, ConfigProblem};
+use dhcp_server::server_config::{generate_ip_pool, load_config, Config};
+use dhcp_server::set_up::migrations::run_migrations;
+use dhcp_server::store::{LeaseStore, MemoryStore, PostgresStore};
+use dhcp_server::Server;
+
+pub const SERVER_ID: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 1);
+pub const POOL_START: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 100);
+pub const POOL_END: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 110);
+pub const RESTRICTED: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 105);
+pub const LEASE_SECONDS: i64 = 3600;
+pub const RENEWAL_SECONDS: i64 = 1800;
+pub const REBINDING_SECONDS: i64 = 3150;
+
+//BEARER TOKEN OF THE MANAGEMENT API IN router
+pub const TOKEN: &str = "test-token";
+
+//HOW LONG TO WAIT FOR A REPLY OR FOR THE STORE TO CHANGE
+const WAIT: Duration = Duration::from_secs(5);
+//HOW LONG A SERVER THAT IS NOT GOING TO ANSWER IS GIVEN
+const SILENCE: Duration = Duration::from_secs(1);
+
+pub fn test_config() -> Config {
+    parse_config(&test_config_value()).expect("test config is valid")
+}
+
+//THE TEST CONFIG WITH ONE TOP-LEVEL SECTION SET, SUCH AS hooks OR failover
+pub fn config_with(section: &str, value: serde_json::Value) -> Config {
+    let mut config = test_config_value();
+    config[section] = value;
+    parse_config(&config).unwrap_or_else(|problems| panic!("config with {} is invalid: {:?}", section, problems))
+}
+
+//PROBLEMS REPORTED FOR THE TEST CONFIG AFTER change, AS PATH AND MESSAGE
+pub fn problems(change: impl FnOnce(&mut serde_json::Value)) -> Vec<ConfigProblem> {
+    let mut value = test_config_value();
+    change(&mut value);
+    parse_config(&value).err().unwrap_or_default()
+}
+
+pub fn paths(problems: &[ConfigProblem]) -> Vec<&str> {
+    problems.iter().map(|problem| problem.path.as_str()).collect()
+}
+
+//VALUE OF ONE METRICS SAMPLE, ZERO WHILE THE SERVER HAS NOT WRITTEN IT
+pub fn counter(harness: &Harness, sample: &str) -> u64 {
+    harness.server.metrics().render().lines()
+        .find_map(|line| line.strip_prefix(sample)?.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+pub fn test_config_value() -> serde_json::Value {
+    let log_file = std::env::temp_dir().join(format!("dhcp-simulator-{}.log", std::process::id()));
+    serde_json::json!({
+        "server": {
+            "lease_time": LEASE_SECONDS,
+            "renewal_time": RENEWAL_SECONDS,
+            "rebinding_time": REBINDING_SECONDS,
+            "subnet_mask": "255.0.0.0",
+            "default_gateway": "127.0.0.1",
+            "dns_server": "127.0.0.53",
+            "domain_name": "simulator.test",
+            "ip_address": SERVER_ID.to_string(),
+            "log_file": log_file.to_string_lossy(),
+        },
+        "ip_pool": {
+            "range_start": POOL_START.to_string(),
+            "range_end": POOL_END.to_string(),
+        },
+        "options_extended": {
+            "subnet_mask": "255.0.0.0",
+            "time_offset": 0,
+            "router": ["127.0.0.1"],
+            "time_server": [],
+            "name_server": [],
+            "domain_name_server": ["127.0.0.53"],
+            "log_server": [],
+            "cookie_server": [],
+            "lpr_server": [],
+            "impress_server": [],
+            "resource_location_server": [],
+            "boot_file_size": 0,
+            "merit_dump_file": "",
+            "domain_name": "simulator.test",
+            "swap_server": "0.0.0.0",
+            "root_path": "",
+            "extensions_path": "",
+            "broadcast_address": "127.255.255.255",
+            "network_time_protocol_servers": [],
+        },
+        "restricted_ips": [RESTRICTED.to_string()],
+    })
+}
+
+//A RUNNING SERVER WITH AN IN-MEMORY STORE AND A CLOCK THE TEST CONTROLS
+pub struct Harness {
+    pub server: Arc<Server>,
+    pub store: Arc<MemoryStore>,
+    pub clock: Arc<FakeClock>,
+    pub server_address: SocketAddrV4,
+    client_socket: Option<UdpSocket>,
+}
+
+impl Harness {
+    pub async fn start() -> Harness {
+        Harness::start_with(test_config()).await
+    }
+
+    pub async fn start_with(config: Config) -> Harness {
+        Harness::start_bound(config, Ipv4Addr::LOCALHOST).await
+    }
+
+    //LISTENING ON ANOTHER LOOPBACK ADDRESS, FOR A SECOND SERVER NEXT TO THE FIRST
+    pub async fn start_bound(config: Config, address: Ipv4Addr) -> Harness {
+        Harness::start_sharing(config, address, Arc::new(MemoryStore::new()), None).await
+    }
+
+    //ON A STORE ANOTHER SERVER MAY ALSO BE USING, ELECTING A LEADER WITH leader_lock WHEN THE CONFIG HAS AN ha SECTION
+    pub async fn start_sharing(config: Config, address: Ipv4Addr, store: Arc<MemoryStore>,
+                               leader_lock: Option<Arc<dyn LeaderLock>>) -> Harness {
+        Harness::launch(config, None, address, store, leader_lock).await
+    }
+
+    //WITH THE CONFIG READ FROM path, WHICH IS ALSO WHERE A RELOAD READS IT FROM
+    pub async fn start_from_file(path: &std::path::Path) -> Harness {
+        let path = path.to_str().expect("config path is UTF-8");
+        let config = load_config(path).expect("config file is valid");
+        Harness::launch(config, Some(path), Ipv4Addr::LOCALHOST, Arc::new(MemoryStore::new()), None).await
+    }
+
+    async fn launch(config: Config, config_path: Option<&str>, address: Ipv4Addr, store: Arc<MemoryStore>,
+                    leader_lock: Option<Arc<dyn LeaderLock>>) -> Harness {
+        let clock = Arc::new(FakeClock::new(start_time()));
+        store.reconcile(&generate_ip_pool(config.ip_pool.range_start, config.ip_pool.range_end),
+            &config.restricted_ips, start_time()).await.unwrap();
+
+        //THE CLIENT PORT IS PICKED FIRST SO THE SERVER KNOWS WHERE TO REPLY
+        let client_socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await.unwrap();
+        client_socket.set_broadcast(true).unwrap();
+        let client_port = client_socket.local_addr().unwrap().port();
+
+        let listener = Listener::bind(address, 0, None).unwrap();
+        let server_address = SocketAddrV4::new(address, listener.local_port());
+        let mut builder = Server::builder(config)
+            .listener(listener)
+            .store(store.clone())
+            .clock(clock.clone())
+            .client_port(client_port);
+        if let Some(leader_lock) = leader_lock {
+            builder = builder.leader_lock(leader_lock);
+        }
+        if let Some(config_path) = config_path {
+            builder = builder.config_path(config_path);
+        }
+        let server = Arc::new(builder.build().await.unwrap());
+        tokio::spawn(Arc::clone(&server).start());
+
+        Harness { server, store, clock, server_address, client_socket: Some(client_socket) }
+    }
+
+    //THE SIMULATED CLIENTS SHARE THE ONE CLIENT PORT, SO ONLY ONE CAN BE TALKING AT A TIME
+    pub fn client(&mut self, mac: [u8; 6]) -> SimulatedClient {
+        SimulatedClient {
+            socket: self.client_socket.take().expect("one client at a time, hand the socket back with give_back"),
+            server: self.server_address,
+            mac,
+            xid: u32::from_be_bytes([mac[2], mac[3], mac[4], mac[5]]),
+            options: Vec::new(),
+        }
+    }
+
+    pub fn give_back(&mut self, client: SimulatedClient) {
+        self.client_socket = Some(client.socket);
+    }
+
+    pub fn advance(&self, seconds: i64) {
+        self.clock.advance(chrono::Duration::seconds(seconds));
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        use dhcp_server::clock::Clock;
+        self.clock.now()
+    }
+
+    //WAIT FOR A MESSAGE THAT GETS NO REPLY TO BE HANDLED
+    pub async fn eventually<F, Fut>(&self, what: &str, check: F)
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = bool>,
+    {
+        eventually(what, check).await
+    }
+}
+
+//WAIT FOR SOMETHING DONE IN THE BACKGROUND, A SERVER TASK OR THE DATABASE
+pub async fn eventually<F, Fut>(what: &str, mut check: F)
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = bool>,
+{
+    let deadline = tokio::time::Instant::now() + WAIT;
+    while tokio::time::Instant::now() < deadline {
+        if check().await {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("timed out waiting until {}", what);
+}
+
+//THE MANAGEMENT API OF A HARNESS SERVER
+pub fn router(harness: &Harness) -> Router {
+    api::router(ApiState::new(harness.server.clone(), TOKEN))
+}
+
+//ONE CALL WITH THE TOKEN, THE ANSWER AS JSON OR NULL
+pub async fn call(router: &Router, method: Method, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("authorization", format!("Bearer {}", TOKEN))
+        .header("content-type", "application/json")
+        .body(body.map(|body| Body::from(body.to_string())).unwrap_or_default())
+        .unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+//A DATABASE OF ITS OWN ON THE SERVER AT TEST_POSTGRES_URI, NONE WHEN THAT IS NOT SET
+//TESTS THAT NEED POSTGRESQL RETURN EARLY WITHOUT IT, SO THE SUITE STILL RUNS WITHOUT ONE
+pub async fn test_database(name: &str) -> Option<tokio_postgres::Config> {
+    static CREATED: AtomicUsize = AtomicUsize::new(0);
+    let Ok(uri) = std::env::var("TEST_POSTGRES_URI") else {
+        eprintln!("TEST_POSTGRES_URI is not set, skipping {}", name);
+        return None;
+    };
+    let database = format!("dhcp_test_{}_{}_{}", name, std::process::id(), CREATED.fetch_add(1, Ordering::Relaxed));
+    let admin = connect(&uri.parse().expect("TEST_POSTGRES_URI is a connection string")).await;
+    admin.batch_execute(&format!("DROP DATABASE IF EXISTS {}", database)).await.unwrap();
+    admin.batch_execute(&format!("CREATE DATABASE {}", database)).await.unwrap();
+    let mut config: tokio_postgres::Config = uri.parse().unwrap();
+    config.dbname(&database);
+    Some(config)
+}
+
+//CONNECT AND DRIVE THE CONNECTION IN THE BACKGROUND
+pub async fn connect(config: &tokio_postgres::Config) -> tokio_postgres::Client {
+    let (client, connection) = config.connect(tokio_postgres::NoTls).await.expect("test database is reachable");
+    tokio::spawn(connection);
+    client
+}
+
+//A STORE ON A MIGRATED DATABASE OF ITS OWN, NONE WITHOUT TEST_POSTGRES_URI
+pub async fn postgres_store(name: &str) -> Option<PostgresStore> {
+    let database = test_database(name).await?;
+    let mut client = connect(&database).await;
+    run_migrations(&mut client).await.unwrap();
+    Some(PostgresStore::new(client, connect(&database).await))
+}
+
+//THE SAME DATABASE AS A CONNECTION STRING, FOR WHAT MAKES ITS OWN CONNECTIONS
+pub fn connection_string(config: &tokio_postgres::Config) -> String {
+    let mut settings = Vec::new();
+    if let Some(tokio_postgres::config::Host::Tcp(host)) = config.get_hosts().first() {
+        settings.push(format!("host={}", host));
+    }
+    if let Some(port) = config.get_ports().first() {
+        settings.push(format!("port={}", port));
+    }
+    if let Some(user) = config.get_user() {
+        settings.push(format!("user={}", user));
+    }
+    if let Some(password) = config.get_password() {
+        settings.push(format!("password={}", String::from_utf8_lossy(password)));
+    }
+    if let Some(dbname) = config.get_dbname() {
+        settings.push(format!("dbname={}", dbname));
+    }
+    settings.join(" ")
+}
+
+pub fn start_time() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()
+}
+
+pub fn client_id(mac: [u8; 6]) -> String {
+    let mut chaddr = [0u8; 16];
+    chaddr[..6].copy_from_slice(&mac);
+    chaddr.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn message_type(message: &DHCPMessage) -> Option<u8> {
+    message.options_map.get(&MESSAGE_TYPE).and_then(|v| v.first().copied())
+}
+
+pub fn ip_option(message: &DHCPMessage, code: u8) -> Option<Ipv4Addr> {
+    message.options_map.get(&code)
+        .and_then(|v| <[u8; 4]>::try_from(v.as_slice()).ok())
+        .map(Ipv4Addr::from)
+}
+
+pub fn seconds_option(message: &DHCPMessage, code: u8) -> Option<u32> {
+    message.options_map.get(&code)
+        .and_then(|v| <[u8; 4]>::try_from(v.as_slice()).ok())
+        .map(u32::from_be_bytes)
+}
+
+//CLIENT SIDE OF THE PROTOCOL, ONE MESSAGE AT A TIME
+//EVERY MESSAGE GOES STRAIGHT TO THE SERVER SOCKET, THE BROADCAST OF A REAL CLIENT ENDS UP THERE TOO
+pub struct SimulatedClient {
+    socket: UdpSocket,
+    server: SocketAddrV4,
+    pub mac: [u8; 6],
+    pub xid: u32,
+    //ADDED TO EVERY MESSAGE, LIKE THE HOST NAME A REAL CLIENT KEEPS SENDING
+    pub options: Vec<u8>,
+}
+
+impl SimulatedClient {
+    fn message(&self, message_type: u8, ciaddr: Ipv4Addr, mut options: Vec<u8>) -> DHCPMessage {
+        let mut chaddr = [0u8; 16];
+        chaddr[..6].copy_from_slice(&self.mac);
+        let mut options_buf = vec![MESSAGE_TYPE, 1, message_type];
+        options_buf.append(&mut options);
+        options_buf.extend_from_slice(&self.options);
+        DHCPMessage::new(1, 1, 6, 0, self.xid, 0, 0, ciaddr, Ipv4Addr::UNSPECIFIED,
+            Ipv4Addr::UNSPECIFIED, Ipv4Addr::UNSPECIFIED, chaddr, [0; 64], [0; 128], options_buf)
+    }
+
+    async fn send(&self, message: DHCPMessage) {
+        self.socket.send_to(&message.to_buffer(), SocketAddr::V4(self.server)).await.unwrap();
+    }
+
+    //NEXT REPLY FOR THIS CLIENT, NONE IF THE SERVER STAYS SILENT
+    pub async fn reply(&self) -> Option<DHCPMessage> {
+        self.reply_within(WAIT).await
+    }
+
+    async fn reply_within(&self, wait: Duration) -> Option<DHCPMessage> {
+        let mut buf = vec![0; 1500];
+        let deadline = tokio::time::Instant::now() + wait;
+        loop {
+            let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+            let (size, _) = timeout(remaining, self.socket.recv_from(&mut buf)).await.ok()?.unwrap();
+            let message = DHCPMessage::from_buffer(&buf[..size]).expect("server reply parses");
+            if message.op == 2 && message.xid == self.xid && message.chaddr[..6] == self.mac {
+                return Some(message);
+            }
+        }
+    }
+
+    pub async fn exchange(&self, message: DHCPMessage) -> DHCPMessage {
+        self.send(message).await;
+        self.reply().await.expect("server replied")
+    }
+
+    pub async fn discover(&self, requested: Option<Ipv4Addr>) -> DHCPMessage {
+        let mut options = Vec::new();
+        if let Some(requested) = requested {
+            push_ip(&mut options, REQUESTED_IP, requested);
+        }
+        self.exchange(self.message(DHCPDISCOVER, Ipv4Addr::UNSPECIFIED, options)).await
+    }
+
+    //A DISCOVER THE SERVER MAY LEAVE TO ANOTHER ONE
+    pub async fn try_discover(&self) -> Option<DHCPMessage> {
+        self.send(self.message(DHCPDISCOVER, Ipv4Addr::UNSPECIFIED, Vec::new())).await;
+        self.reply_within(SILENCE).await
+    }
+
+    //REQUEST IN SELECTING STATE, ANSWERING AN OFFER
+    pub async fn request(&self, address: Ipv4Addr, server_id: Ipv4Addr) -> DHCPMessage {
+        let mut options = Vec::new();
+        push_ip(&mut options, REQUESTED_IP, address);
+        push_ip(&mut options, SERVER_IDENTIFIER, server_id);
+        self.exchange(self.message(DHCPREQUEST, Ipv4Addr::UNSPECIFIED, options)).await
+    }
+
+    //DISCOVER, OFFER, REQUEST, ACK - RETURNS THE ACK
+    pub async fn dora(&self) -> DHCPMessage {
+        let offer = self.discover(None).await;
+        assert_eq!(message_type(&offer), Some(DHCPOFFER));
+        let server_id = ip_option(&offer, SERVER_IDENTIFIER).expect("offer has a server identifier");
+        self.request(offer.yiaddr, server_id).await
+    }
+
+    //REQUEST IN RENEWING STATE, UNICAST WITH THE ADDRESS IN CIADDR
+    pub async fn renew(&self, address: Ipv4Addr) -> DHCPMessage {
+        self.exchange(self.message(DHCPREQUEST, address, Vec::new())).await
+    }
+
+    //REQUEST IN REBINDING STATE
+    //THE MESSAGE IS THE SAME AS WHEN RENEWING, ONLY A REAL CLIENT BROADCASTS IT
+    pub async fn rebind(&self, address: Ipv4Addr) -> DHCPMessage {
+        self.exchange(self.message(DHCPREQUEST, address, Vec::new())).await
+    }
+
+    pub async fn decline(&self, address: Ipv4Addr, server_id: Ipv4Addr) {
+        let mut options = Vec::new();
+        push_ip(&mut options, REQUESTED_IP, address);
+        push_ip(&mut options, SERVER_IDENTIFIER, server_id);
+        self.send(self.message(DHCPDECLINE, Ipv4Addr::UNSPECIFIED, options)).await;
+    }
+
+    //THE RELEASED ADDRESS GOES IN CIADDR ONLY, RFC 2131 FORBIDS OPTION 50 HERE
+    pub async fn release(&self, address: Ipv4Addr, server_id: Ipv4Addr) {
+        let mut options = Vec::new();
+        push_ip(&mut options, SERVER_IDENTIFIER, server_id);
+        self.send(self.message(DHCPRELEASE, address, options)).await;
+    }
+
+    pub async fn inform(&self, address: Ipv4Addr, parameter_request_list: &[u8]) -> DHCPMessage {
+        let mut options = Vec::new();
+        push_bytes(&mut options, PARAMETER_REQUEST_LIST, parameter_request_list);
+        self.exchange(self.message(DHCPINFORM, address, options)).await
+    }
+}
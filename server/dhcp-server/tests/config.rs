@@ -0,0 +1,51 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn the_test_config_is_valid() {
+    assert!(problems(|_| {}).is_empty());
+}
+
+#[test]
+fn every_problem_is_reported_with_its_path() {
+    let found = problems(|value| {
+        value["server"]["lease_time"] = "an hour".into();
+        value["server"]["dns_server"] = "127.0.0.300".into();
+        value["options_extended"]["router"] = serde_json::json!(["127.0.0.1", 7]);
+        value["server"].as_object_mut().unwrap().remove("subnet_mask");
+    });
+    assert_eq!(paths(&found), ["server.lease_time", "server.subnet_mask", "server.dns_server", "options_extended.router[1]"]);
+    assert_eq!(found[0].message, "expected a number, found \"an hour\"");
+    assert_eq!(found[1].message, "missing");
+    assert_eq!(found[2].message, "\"127.0.0.300\" is not a valid IPv4 address");
+}
+
+#[test]
+fn values_are_checked_against_each_other() {
+    let found = problems(|value| {
+        value["ip_pool"]["range_start"] = "127.0.0.120".into();
+        value["server"]["renewal_time"] = 3200.into();
+    });
+    assert_eq!(paths(&found), ["ip_pool.range_start", "restricted_ips[0]", "server.renewal_time"]);
+    assert_eq!(found[0].message, "127.0.0.120 is after ip_pool.range_end 127.0.0.110");
+    assert_eq!(found[2].message, "must be shorter than the rebinding time of 3150 seconds");
+}
+
+#[test]
+fn the_pool_must_be_on_the_server_subnet() {
+    let found = problems(|value| {
+        value["server"]["subnet_mask"] = "255.255.255.0".into();
+        value["ip_pool"]["range_end"] = "127.0.1.10".into();
+    });
+    assert_eq!(paths(&found), ["ip_pool.range_end"]);
+    assert_eq!(found[0].to_string(), "ip_pool.range_end: 127.0.1.10 is outside the server subnet 127.0.0.0/24");
+}
+
+#[test]
+fn options_that_do_not_fit_in_one_option_are_refused() {
+    let found = problems(|value| {
+        value["options_extended"]["domain_name"] = "a".repeat(256).into();
+    });
+    assert_eq!(paths(&found), ["options_extended.domain_name"]);
+}
@@ -0,0 +1,356 @@
+mod common;
+
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::Engine;
+use tokio::net::UdpSocket;
+
+use common::*;
+use dhcp_proto::options::{push_bytes, CLIENT_FQDN, HOST_NAME};
+use dhcp_server::ddns::message::{encode_name, Message, Record, CLASS_ANY, CLASS_IN, CLASS_NONE, NOERROR, NOTAUTH,
+                                 NXRRSET, TYPE_A, TYPE_ANY, TYPE_DHCID, TYPE_PTR, YXDOMAIN, YXRRSET};
+use dhcp_server::ddns::{dhcid, tsig};
+use dhcp_server::server_config::server_config::{HookAction, TsigAlgorithm, TsigKey};
+
+const CLIENT_A: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x0a];
+const CLIENT_B: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x0b];
+const ZONE: &str = "example.test";
+const REVERSE_ZONE: &str = "0.0.127.in-addr.arpa";
+const KEY_NAME: &str = "dhcp-update";
+const SECRET: &[u8] = b"secret shared with the dns server";
+
+fn key(secret: &[u8]) -> TsigKey {
+    TsigKey { name: KEY_NAME.into(), algorithm: TsigAlgorithm::HmacSha256, secret: secret.to_vec() }
+}
+
+//THE hooks SECTION FOR ONE DDNS TARGET
+fn ddns_hooks(server: SocketAddr, secret: &[u8]) -> serde_json::Value {
+    serde_json::json!({
+        "targets": [{
+            "type": "ddns",
+            "server": server.to_string(),
+            "zone": ZONE,
+            "reverse_zone": REVERSE_ZONE,
+            "ttl": 600,
+            "tsig": {"name": KEY_NAME, "secret": base64::engine::general_purpose::STANDARD.encode(secret)},
+            "retries": 0,
+        }],
+    })
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+//AUTHORITATIVE SERVER STAND-IN FOR BOTH ZONES: CHECKS THE SIGNATURE AND THE RFC 2136 PREREQUISITES, THEN APPLIES THE UPDATES
+#[derive(Clone, Default)]
+struct Zones {
+    records: Arc<Mutex<Vec<Record>>>,
+    updates: Arc<Mutex<usize>>,
+}
+
+impl Zones {
+    fn records(&self) -> Vec<(String, u16, Vec<u8>)> {
+        let mut records: Vec<_> = self.records.lock().unwrap().iter()
+            .map(|record| (record.name.clone(), record.rtype, record.data.clone()))
+            .collect();
+        records.sort();
+        records
+    }
+
+    fn updates(&self) -> usize {
+        *self.updates.lock().unwrap()
+    }
+
+    fn answer(&self, wire: &[u8], key: &TsigKey) -> Vec<u8> {
+        *self.updates.lock().unwrap() += 1;
+        let request = Message::decode(wire).unwrap();
+        let request_mac = match tsig::verify(wire, key, now(), None) {
+            Ok(mac) => mac,
+            Err(_) => return tsig::sign_error(&Message::reply(&request, NOTAUTH).encode(), key, now(), tsig::BADSIG),
+        };
+        let mut records = self.records.lock().unwrap();
+        let rcode = check(&records, &request.prerequisites);
+        if rcode == NOERROR {
+            for update in &request.updates {
+                let same_set = |record: &Record| record.name == update.name && (update.rtype == TYPE_ANY || record.rtype == update.rtype);
+                match update.class {
+                    CLASS_IN if !records.iter().any(|record| same_set(record) && record.data == update.data) => records.push(update.clone()),
+                    CLASS_ANY => records.retain(|record| !same_set(record)),
+                    CLASS_NONE => records.retain(|record| !(same_set(record) && record.data == update.data)),
+                    _ => {}
+                }
+            }
+        }
+        tsig::sign(&Message::reply(&request, rcode).encode(), key, now(), Some(&request_mac)).0
+    }
+}
+
+fn check(records: &[Record], prerequisites: &[Record]) -> u8 {
+    for prerequisite in prerequisites {
+        let matching: Vec<&Record> = records.iter()
+            .filter(|record| record.name == prerequisite.name && (prerequisite.rtype == TYPE_ANY || record.rtype == prerequisite.rtype))
+            .collect();
+        match prerequisite.class {
+            CLASS_NONE if prerequisite.rtype == TYPE_ANY && !matching.is_empty() => return YXDOMAIN,
+            CLASS_NONE if !matching.is_empty() => return YXRRSET,
+            CLASS_IN => {
+                let mut wanted: Vec<&[u8]> = prerequisites.iter()
+                    .filter(|other| other.class == CLASS_IN && other.name == prerequisite.name && other.rtype == prerequisite.rtype)
+                    .map(|other| other.data.as_slice())
+                    .collect();
+                let mut found: Vec<&[u8]> = matching.iter().map(|record| record.data.as_slice()).collect();
+                wanted.sort();
+                found.sort();
+                if wanted != found {
+                    return NXRRSET;
+                }
+            }
+            _ => {}
+        }
+    }
+    NOERROR
+}
+
+async fn dns_stand_in() -> (SocketAddr, Zones) {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let address = socket.local_addr().unwrap();
+    let zones = Zones::default();
+    let serving = zones.clone();
+    tokio::spawn(async move {
+        let mut buffer = [0u8; 4096];
+        let key = key(SECRET);
+        loop {
+            let (length, peer) = socket.recv_from(&mut buffer).await.unwrap();
+            let answer = serving.answer(&buffer[..length], &key);
+            socket.send_to(&answer, peer).await.unwrap();
+        }
+    });
+    (address, zones)
+}
+
+const HOST: &str = "laptop";
+
+fn fqdn(host: &str) -> String {
+    format!("{}.{}", host, ZONE)
+}
+
+//A CLIENT THAT SENDS ITS HOST NAME IN OPTION 12
+fn named_client(harness: &mut Harness, mac: [u8; 6], host: &str) -> SimulatedClient {
+    let mut client = harness.client(mac);
+    push_bytes(&mut client.options, HOST_NAME, host.as_bytes());
+    client
+}
+
+fn reverse(ip: Ipv4Addr) -> String {
+    let [a, b, c, d] = ip.octets();
+    format!("{}.{}.{}.{}.in-addr.arpa", d, c, b, a)
+}
+
+#[tokio::test]
+async fn records_follow_the_lease() {
+    let (server, zones) = dns_stand_in().await;
+    let mut harness = Harness::start_with(config_with("hooks", ddns_hooks(server, SECRET))).await;
+
+    let client = named_client(&mut harness, CLIENT_A, HOST);
+    let address = client.dora().await.yiaddr;
+    let name = fqdn(HOST);
+    let expected = vec![
+        (reverse(address), TYPE_PTR, encode_name(&name)),
+        (name.clone(), TYPE_A, address.octets().to_vec()),
+        (name.clone(), TYPE_DHCID, dhcid(1, &CLIENT_A, &name)),
+    ];
+    harness.eventually("the records were added", || async { zones.records() == expected }).await;
+
+    //A RENEWAL FINDS THE NAME ALREADY OURS AND LEAVES THE RECORDS AS THEY ARE
+    client.renew(address).await;
+    harness.eventually("the renewal was sent", || async { zones.updates() >= 5 }).await;
+    assert_eq!(zones.records(), expected);
+
+    client.release(address, SERVER_ID).await;
+    harness.give_back(client);
+    harness.eventually("the records were removed", || async { zones.records().is_empty() }).await;
+}
+
+//THE OLD NAME'S RECORDS GO BEFORE THE NEW ONES ARE ADDED, NONE ARE LEFT BEHIND
+#[tokio::test]
+async fn a_renamed_client_loses_its_old_records() {
+    let (server, zones) = dns_stand_in().await;
+    let mut harness = Harness::start_with(config_with("hooks", ddns_hooks(server, SECRET))).await;
+
+    let client = named_client(&mut harness, CLIENT_A, HOST);
+    let address = client.dora().await.yiaddr;
+    harness.give_back(client);
+    harness.eventually("the records were added", || async { zones.records().len() == 3 }).await;
+
+    let client = named_client(&mut harness, CLIENT_A, "desk");
+    client.renew(address).await;
+    harness.give_back(client);
+    let name = fqdn("desk");
+    harness.eventually("the records were moved", || async {
+        zones.records() == vec![
+            (reverse(address), TYPE_PTR, encode_name(&name)),
+            (name.clone(), TYPE_A, address.octets().to_vec()),
+            (name.clone(), TYPE_DHCID, dhcid(1, &CLIENT_A, &name)),
+        ]
+    }).await;
+}
+
+#[tokio::test]
+async fn a_name_held_by_another_client_is_left_alone() {
+    let (server, zones) = dns_stand_in().await;
+    let name = fqdn(HOST);
+    let elsewhere = Ipv4Addr::new(192, 0, 2, 7);
+    let theirs = dhcid(1, &CLIENT_B, &name);
+    zones.records.lock().unwrap().extend([
+        Record::a(&name, 600, elsewhere),
+        Record::new(&name, TYPE_DHCID, CLASS_IN, 600, theirs.clone()),
+    ]);
+    let mut harness = Harness::start_with(config_with("hooks", ddns_hooks(server, SECRET))).await;
+
+    let client = named_client(&mut harness, CLIENT_A, HOST);
+    client.dora().await;
+    harness.give_back(client);
+    //THE CLAIM AND THE REPLACEMENT BOTH FAIL THEIR PREREQUISITE, SO NO PTR IS WRITTEN EITHER
+    harness.eventually("both updates were tried", || async { zones.updates() >= 2 }).await;
+    harness.advance(LEASE_SECONDS + 1);
+    let client = harness.client(CLIENT_B);
+    client.discover(None).await;
+    harness.give_back(client);
+    //ON EXPIRY THE ADDRESS DELETE FAILS ITS PREREQUISITE, THE PTR DELETE FINDS NOTHING
+    harness.eventually("the expiry was handled", || async { zones.updates() >= 4 }).await;
+
+    assert_eq!(zones.records(), vec![
+        (name.clone(), TYPE_A, elsewhere.octets().to_vec()),
+        (name.clone(), TYPE_DHCID, theirs),
+    ]);
+    assert_eq!(counter(&harness, r#"dhcp_hook_failures_total{hook="ddns-0"}"#), 0);
+}
+
+#[tokio::test]
+async fn a_client_updating_its_own_name_only_gets_a_ptr() {
+    let (server, zones) = dns_stand_in().await;
+    let mut harness = Harness::start_with(config_with("hooks", ddns_hooks(server, SECRET))).await;
+
+    //OPTION 81 WITH S=0 AND THE NAME AS PLAIN TEXT
+    let mut client = harness.client(CLIENT_A);
+    push_bytes(&mut client.options, CLIENT_FQDN, b"\x00\x00\x00Desk");
+    let address = client.dora().await.yiaddr;
+    let name = format!("desk.{}", ZONE);
+    harness.eventually("the PTR was added", || async {
+        zones.records() == vec![(reverse(address), TYPE_PTR, encode_name(&name))]
+    }).await;
+
+    client.release(address, SERVER_ID).await;
+    harness.give_back(client);
+    harness.eventually("the PTR was removed", || async { zones.records().is_empty() }).await;
+    assert_eq!(zones.updates(), 2);
+}
+
+//NO MADE-UP NAMES IN DNS UNLESS generated_names ASKS FOR THEM
+#[tokio::test]
+async fn a_client_without_a_host_name_gets_no_records() {
+    let (server, zones) = dns_stand_in().await;
+    let mut harness = Harness::start_with(config_with("hooks", ddns_hooks(server, SECRET))).await;
+
+    let client = harness.client(CLIENT_A);
+    let unnamed = client.dora().await.yiaddr;
+    harness.give_back(client);
+    let client = named_client(&mut harness, CLIENT_B, HOST);
+    let named = client.dora().await.yiaddr;
+    harness.give_back(client);
+    //HOOK EVENTS ARE HANDLED IN ORDER, SO THE FIRST LEASE WAS DEALT WITH ONCE THE SECOND ONE'S RECORDS ARE IN
+    harness.eventually("the named client's records were added", || async { zones.records().len() == 3 }).await;
+    assert_eq!(zones.updates(), 2);
+    assert!(zones.records().iter().all(|(name, _, _)| *name != reverse(unnamed)));
+    assert!(zones.records().contains(&(reverse(named), TYPE_PTR, encode_name(&fqdn(HOST)))));
+}
+
+#[tokio::test]
+async fn generated_names_are_used_when_turned_on() {
+    let (server, zones) = dns_stand_in().await;
+    let mut hooks = ddns_hooks(server, SECRET);
+    hooks["targets"][0]["generated_names"] = true.into();
+    let mut harness = Harness::start_with(config_with("hooks", hooks)).await;
+
+    let client = harness.client(CLIENT_A);
+    let address = client.dora().await.yiaddr;
+    harness.give_back(client);
+    let name = fqdn(&format!("user{}", &client_id(CLIENT_A)[..12]));
+    harness.eventually("the records were added", || async {
+        zones.records() == vec![
+            (reverse(address), TYPE_PTR, encode_name(&name)),
+            (name.clone(), TYPE_A, address.octets().to_vec()),
+            (name.clone(), TYPE_DHCID, dhcid(1, &CLIENT_A, &name)),
+        ]
+    }).await;
+}
+
+#[tokio::test]
+async fn a_wrong_secret_is_refused_and_counted() {
+    let (server, zones) = dns_stand_in().await;
+    let mut harness = Harness::start_with(config_with("hooks", ddns_hooks(server, b"not the shared secret"))).await;
+
+    let client = named_client(&mut harness, CLIENT_A, HOST);
+    client.dora().await;
+    harness.give_back(client);
+    harness.eventually("the failure was counted", || async {
+        counter(&harness, r#"dhcp_hook_failures_total{hook="ddns-0"}"#) == 1
+    }).await;
+    assert!(zones.records().is_empty());
+}
+
+#[test]
+fn dhcid_matches_rfc_4701() {
+    //THE chaddr EXAMPLE OF RFC 4701 SECTION 3.6
+    let rdata = dhcid(1, &[0x01, 0x02, 0x03, 0x04, 0x05, 0x06], "client.example.com");
+    assert_eq!(base64::engine::general_purpose::STANDARD.encode(rdata), "AAABxLmlskllE0MVjd57zHcWmEH3pCQ6VytcKD//7es/deY=");
+}
+
+#[test]
+fn signatures_cover_the_whole_message() {
+    let key = key(SECRET);
+    let mut update = Message::update(0x1234, ZONE);
+    update.updates.push(Record::a(&fqdn(HOST), 600, Ipv4Addr::new(127, 0, 0, 100)));
+    let unsigned = update.encode();
+    let (signed, mac) = tsig::sign(&unsigned, &key, now(), None);
+    assert_eq!(tsig::verify(&signed, &key, now(), None), Ok(mac));
+    assert_eq!(Message::decode(&signed).unwrap().additional.len(), 1);
+
+    //THE LAST BYTE OF THE ADDRESS
+    let mut tampered = signed.clone();
+    tampered[unsigned.len() - 1] = 101;
+    assert!(tsig::verify(&tampered, &key, now(), None).is_err());
+    assert!(tsig::verify(&signed, &key, now() + 3600, None).is_err());
+    assert!(tsig::verify(&signed, &self::key(b"another secret"), now(), None).is_err());
+}
+
+#[test]
+fn ddns_problems_name_the_setting() {
+    let found = problems(|value| {
+        value["hooks"] = serde_json::json!({
+            "targets": [{
+                "type": "ddns",
+                "server": "dns.invalid:53",
+                "reverse_zone": "example.test",
+                "tsig": {"name": KEY_NAME, "algorithm": "hmac-md5", "secret": "not base64!"},
+            }],
+        });
+    });
+    assert_eq!(paths(&found), [
+        "hooks.targets[0].server",
+        "hooks.targets[0].reverse_zone",
+        "hooks.targets[0].tsig.algorithm",
+        "hooks.targets[0].tsig.secret",
+    ]);
+
+    //THE ZONE DEFAULTS TO server.domain_name AND THE PORT TO 53
+    let config = config_with("hooks", serde_json::json!({"targets": [{"type": "ddns", "server": "127.0.0.1"}]}));
+    let HookAction::Ddns(ddns) = &config.hooks.targets[0].action else { panic!("not a ddns hook") };
+    assert_eq!(ddns.server, "127.0.0.1:53".parse().unwrap());
+    assert_eq!(ddns.zone, "simulator.test");
+    assert_eq!((ddns.reverse_zone.as_deref(), ddns.ttl, ddns.key.is_none()), (None, 300, true));
+    assert!(!ddns.generated_names);
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use ipnet::Ipv4Net;
+use std::net::Ipv4Addr;
+
+use common::*;
+use dhcp_server::listener::Interface;
+use dhcp_server::server_config::detect::{address_warnings, apply_detected_address, detect_server_address, DetectedAddress};
+use dhcp_server::server_config::server_config::parse_config;
+use dhcp_server::server_config::Config;
+
+fn interface(name: &str, index: u32, addresses: &[&str]) -> Interface {
+    Interface {
+        name: name.to_string(),
+        index,
+        addresses: addresses.iter().map(|address| address.parse().unwrap()).collect(),
+    }
+}
+
+//A HOST WITH LOOPBACK, AN UPLINK AND THE LAN THE POOL 192.168.10.100-192.168.10.150 IS ON
+fn host() -> Vec<Interface> {
+    vec![
+        interface("lan0", 3, &["192.168.10.30/24"]),
+        interface("lo", 1, &["127.0.0.1/8"]),
+        interface("wan0", 2, &["203.0.113.7/24"]),
+    ]
+}
+
+//THE TEST CONFIG WITHOUT A SERVER ADDRESS OR SUBNET, SERVING 192.168.10.100-192.168.10.150
+fn detecting_config() -> Config {
+    let mut value = test_config_value();
+    let server = value["server"].as_object_mut().unwrap();
+    server.remove("ip_address");
+    server.remove("subnet_mask");
+    server.insert("auto_detect".to_string(), true.into());
+    server.insert("default_gateway".to_string(), "192.168.10.1".into());
+    let options = value["options_extended"].as_object_mut().unwrap();
+    options.remove("subnet_mask");
+    options.remove("broadcast_address");
+    value["ip_pool"] = serde_json::json!({"range_start": "192.168.10.100", "range_end": "192.168.10.150"});
+    value["restricted_ips"] = serde_json::json!([]);
+    parse_config(&value).expect("detecting config is valid")
+}
+
+fn detected(interface: &str, address: &str) -> Option<DetectedAddress> {
+    Some(DetectedAddress { interface: interface.to_string(), address: address.parse().unwrap() })
+}
+
+#[test]
+fn the_address_on_the_pool_subnet_is_picked() {
+    assert_eq!(detect_server_address(&detecting_config(), &host(), &[]), detected("lan0", "192.168.10.30/24"));
+}
+
+#[test]
+fn without_a_pool_subnet_the_first_non_loopback_address_is_picked() {
+    let mut interfaces = host();
+    interfaces.retain(|interface| interface.name != "lan0");
+    assert_eq!(detect_server_address(&detecting_config(), &interfaces, &[]), detected("wan0", "203.0.113.7/24"));
+    //THE SAME ANSWER WHATEVER ORDER THE KERNEL LISTS THEM IN
+    interfaces.reverse();
+    assert_eq!(detect_server_address(&detecting_config(), &interfaces, &[]), detected("wan0", "203.0.113.7/24"));
+
+    interfaces.retain(|interface| interface.name == "lo");
+    assert_eq!(detect_server_address(&detecting_config(), &interfaces, &[]), detected("lo", "127.0.0.1/8"));
+    assert_eq!(detect_server_address(&detecting_config(), &[], &[]), None);
+}
+
+#[test]
+fn only_bound_interfaces_are_considered() {
+    let bound = ["wan0".to_string()];
+    assert_eq!(detect_server_address(&detecting_config(), &host(), &bound), detected("wan0", "203.0.113.7/24"));
+}
+
+#[test]
+fn the_detected_address_sets_identity_subnet_and_broadcast() {
+    let mut config = detecting_config();
+    apply_detected_address(&mut config, &detected("lan0", "192.168.10.30/24").unwrap());
+    assert_eq!(config.server.ip_address, Ipv4Addr::new(192, 168, 10, 30));
+    assert_eq!(config.server.subnet_mask, Ipv4Addr::new(255, 255, 255, 0));
+    assert_eq!(config.server.subnet, "192.168.10.0/24".parse::<Ipv4Net>().unwrap());
+    assert_eq!(config.options_extended.subnet_mask, Ipv4Addr::new(255, 255, 255, 0));
+    assert_eq!(config.options_extended.broadcast_address, Ipv4Addr::new(192, 168, 10, 255));
+    assert!(address_warnings(&config, &host()).is_empty());
+}
+
+#[test]
+fn a_pool_on_no_local_subnet_is_warned_about_unless_relayed() {
+    let mut config = detecting_config();
+    apply_detected_address(&mut config, &detected("wan0", "203.0.113.7/24").unwrap());
+    let interfaces = vec![interface("wan0", 2, &["203.0.113.7/24"])];
+    let warnings = address_warnings(&config, &interfaces);
+    assert_eq!(warnings.len(), 2, "{:?}", warnings);
+    assert!(warnings[0].starts_with("IP pool 192.168.10.100-192.168.10.150 is not inside any local subnet"));
+    assert_eq!(warnings[1], "default_gateway 192.168.10.1 is outside the detected subnet 203.0.113.0/24");
+
+    config.server.relay_expected = true;
+    assert!(address_warnings(&config, &interfaces).is_empty());
+}
@@ -0,0 +1,288 @@
+mod common;
+
+use std::net::{Ipv4Addr, SocketAddr};
+
+use axum::http::{Method, StatusCode};
+use serde_json::{json, Value};
+use tokio::io::copy_bidirectional;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+
+use common::*;
+use dhcp_proto::options::*;
+use dhcp_server::failover::hash::{pearson, MIX_TABLE};
+use dhcp_server::failover::FailoverState;
+use dhcp_server::server_config::server_config::{FailoverMode, FailoverRole, FAILOVER_PORT};
+use dhcp_server::server_config::Config;
+use dhcp_server::store::LeaseStore;
+
+const SECONDARY_ID: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 2);
+const MCLT: i64 = 600;
+//.100 TO .105 ARE THE PRIMARY'S TO HAND OUT, .106 TO .110 THE SECONDARY'S
+const SECONDARY_SHARE_START: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 106);
+
+//A CLIENT WHOSE HASH BUCKET THE PRIMARY OR THE SECONDARY ANSWERS
+fn mac_for(role: FailoverRole) -> [u8; 6] {
+    (0..=255).map(|last| [0x02, 0x00, 0x00, 0x00, 0x01, last])
+        .find(|mac| (pearson(mac) < 128) == (role == FailoverRole::Primary))
+        .unwrap()
+}
+
+//A SERVER OF THE PAIR, IDENTIFIED BY address
+fn pair_member(address: Ipv4Addr, failover: Value) -> Config {
+    let mut config = config_with("failover", failover);
+    config.server.ip_address = address;
+    config
+}
+
+//TCP BETWEEN THE PRIMARY AND THE SECONDARY THAT A TEST CAN CUT AND HEAL
+struct Link {
+    address: SocketAddr,
+    up: watch::Sender<bool>,
+}
+
+impl Link {
+    async fn start(target: SocketAddr) -> Link {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (up, watcher) = watch::channel(true);
+        tokio::spawn(async move {
+            loop {
+                let (mut inbound, _) = listener.accept().await.unwrap();
+                if !*watcher.borrow() {
+                    continue;
+                }
+                let mut cut = watcher.clone();
+                tokio::spawn(async move {
+                    let Ok(mut outbound) = TcpStream::connect(target).await else { return };
+                    tokio::select! {
+                        _ = copy_bidirectional(&mut inbound, &mut outbound) => {}
+                        _ = cut.wait_for(|up| !up) => {}
+                    }
+                });
+            }
+        });
+        Link { address, up }
+    }
+
+    fn cut(&self) {
+        self.up.send_replace(false);
+    }
+
+    fn heal(&self) {
+        self.up.send_replace(true);
+    }
+}
+
+struct Pair {
+    primary: Harness,
+    secondary: Harness,
+    link: Link,
+}
+
+impl Pair {
+    //extra GOES INTO THE SECONDARY'S failover SECTION
+    async fn start(extra: Value) -> Pair {
+        let mut failover = json!({"role": "secondary", "listen": "127.0.0.1:0", "peer": "127.0.0.1", "mclt": MCLT, "peer_timeout": 1});
+        for (key, value) in extra.as_object().unwrap() {
+            failover[key] = value.clone();
+        }
+        let secondary = Harness::start_bound(pair_member(SECONDARY_ID, failover), SECONDARY_ID).await;
+        let link = Link::start(secondary.server.failover().unwrap().listen_address().unwrap()).await;
+        let primary = Harness::start_with(pair_member(SERVER_ID,
+            json!({"role": "primary", "peer": link.address.to_string(), "mclt": MCLT, "peer_timeout": 1}))).await;
+        let pair = Pair { primary, secondary, link };
+        pair.both_in(FailoverState::Normal).await;
+        pair
+    }
+
+    async fn both_in(&self, state: FailoverState) {
+        for harness in [&self.primary, &self.secondary] {
+            harness.eventually(state.as_str(), || async { harness.server.failover().unwrap().state().0 == state }).await;
+        }
+    }
+
+    //BOTH SERVERS HEARD BACK ABOUT EVERY CHANGE THEY SENT
+    async fn settled(&self) {
+        for harness in [&self.primary, &self.secondary] {
+            harness.eventually("every update was acknowledged", || async {
+                harness.server.failover().unwrap().pending_updates() == 0
+            }).await;
+        }
+    }
+
+    fn advance(&self, seconds: i64) {
+        self.primary.advance(seconds);
+        self.secondary.advance(seconds);
+    }
+}
+
+async fn holder(harness: &Harness, address: Ipv4Addr) -> Option<String> {
+    harness.store.active_leases(harness.now()).await.unwrap().into_iter()
+        .find(|lease| lease.ip_address == address)
+        .map(|lease| lease.client_id)
+}
+
+#[tokio::test]
+async fn leases_reach_the_partner_and_start_at_the_mclt() {
+    let mut pair = Pair::start(json!({})).await;
+    let client = pair.primary.client(mac_for(FailoverRole::Primary));
+    let ack = client.dora().await;
+    assert!(ack.yiaddr < SECONDARY_SHARE_START);
+    //THE SECONDARY KNOWS NOTHING OF THE LEASE YET, SO THE CLIENT GETS NO MORE THAN THE MCLT
+    assert_eq!(seconds_option(&ack, LEASE_TIME), Some(MCLT as u32));
+    assert_eq!(seconds_option(&ack, RENEWAL_TIME), Some(MCLT as u32 / 2));
+    assert_eq!(seconds_option(&ack, REBINDING_TIME), Some(MCLT as u32 * 7 / 8));
+    pair.settled().await;
+    assert_eq!(holder(&pair.secondary, ack.yiaddr).await, Some(client_id(client.mac)));
+
+    //NOW IT DOES, THE RENEWAL GETS THE WHOLE LEASE
+    let renewed = client.renew(ack.yiaddr).await;
+    assert_eq!(seconds_option(&renewed, LEASE_TIME), Some(LEASE_SECONDS as u32));
+    pair.primary.give_back(client);
+
+    //THE SECONDARY LEAVES THE CLIENT TO THE PRIMARY AND ANSWERS ITS OWN FROM ITS SHARE OF THE POOL
+    let client = pair.secondary.client(mac_for(FailoverRole::Primary));
+    assert!(client.try_discover().await.is_none());
+    pair.secondary.give_back(client);
+    let client = pair.secondary.client(mac_for(FailoverRole::Secondary));
+    let offer = client.try_discover().await.expect("the secondary answers its own clients");
+    assert_eq!(offer.yiaddr, SECONDARY_SHARE_START);
+    assert_eq!(ip_option(&offer, SERVER_IDENTIFIER), Some(SECONDARY_ID));
+    //A REQUEST MEANT FOR THE SECONDARY IS NOT ANSWERED WITH A NAK BY THE PRIMARY
+    pair.secondary.give_back(client);
+    let client = pair.primary.client(mac_for(FailoverRole::Secondary));
+    let ack = client.request(offer.yiaddr, SECONDARY_ID);
+    assert!(tokio::time::timeout(std::time::Duration::from_secs(1), ack).await.is_err());
+    pair.primary.give_back(client);
+}
+
+#[tokio::test]
+async fn the_partner_renews_while_the_other_is_cut_off() {
+    let mut pair = Pair::start(json!({})).await;
+    let client = pair.primary.client(mac_for(FailoverRole::Primary));
+    let address = client.dora().await.yiaddr;
+    pair.primary.give_back(client);
+    pair.settled().await;
+
+    pair.link.cut();
+    pair.both_in(FailoverState::CommunicationsInterrupted).await;
+    pair.advance(1800);
+    let client = pair.secondary.client(mac_for(FailoverRole::Primary));
+    let ack = client.renew(address).await;
+    assert_eq!(message_type(&ack), Some(DHCPACK));
+    //THE PRIMARY KNOWS THE LEASE TO RUN AN HOUR FROM THE START, THE RENEWAL ENDS ONE MCLT AFTER THAT
+    assert_eq!(seconds_option(&ack, LEASE_TIME), Some((LEASE_SECONDS - 1800 + MCLT) as u32));
+    pair.secondary.give_back(client);
+    assert_eq!(pair.secondary.server.failover().unwrap().pending_updates(), 1);
+
+    pair.link.heal();
+    pair.both_in(FailoverState::Normal).await;
+    pair.settled().await;
+    let lease = pair.primary.store.active_leases(pair.primary.now()).await.unwrap().into_iter()
+        .find(|lease| lease.ip_address == address)
+        .unwrap();
+    assert_eq!(lease.lease_start, pair.secondary.now());
+}
+
+#[tokio::test]
+async fn partner_down_takes_over_the_partners_addresses_after_the_mclt() {
+    let mut pair = Pair::start(json!({"partner_down_after": 60})).await;
+    let (status, _) = call(&router(&pair.primary), Method::POST, "/api/failover/partner-down", None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    pair.link.cut();
+    pair.both_in(FailoverState::CommunicationsInterrupted).await;
+    //CUT OFF, THE SECONDARY ANSWERS EVERY CLIENT BUT ONLY FROM ITS OWN SHARE
+    let client = pair.secondary.client(mac_for(FailoverRole::Primary));
+    assert_eq!(client.discover(Some(POOL_START)).await.yiaddr, SECONDARY_SHARE_START);
+
+    pair.secondary.advance(60);
+    let (status, view) = call(&router(&pair.secondary), Method::GET, "/api/failover", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!((view["state"].as_str(), view["role"].as_str()), (Some("partner_down"), Some("secondary")));
+    assert_eq!(client.discover(Some(POOL_START)).await.yiaddr, SECONDARY_SHARE_START);
+    pair.secondary.advance(MCLT);
+    assert_eq!(client.discover(Some(POOL_START)).await.yiaddr, POOL_START);
+    pair.secondary.give_back(client);
+
+    //WITHOUT partner_down_after AN ADMINISTRATOR SAYS SO
+    let (status, view) = call(&router(&pair.primary), Method::POST, "/api/failover/partner-down", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(view["state"], "partner_down");
+    assert_eq!(view["since"], json!(dhcp_server::api::views::timestamp(pair.primary.now())));
+}
+
+#[tokio::test]
+async fn the_pair_agrees_again_after_a_partition() {
+    let mut pair = Pair::start(json!({})).await;
+    let first = pair.primary.client(mac_for(FailoverRole::Primary));
+    let first_address = first.dora().await.yiaddr;
+    pair.settled().await;
+
+    pair.link.cut();
+    pair.both_in(FailoverState::CommunicationsInterrupted).await;
+    first.release(first_address, SERVER_ID).await;
+    pair.primary.give_back(first);
+    pair.primary.eventually("the lease was released", || async { holder(&pair.primary, first_address).await.is_none() }).await;
+    let second = pair.secondary.client(mac_for(FailoverRole::Secondary));
+    let second_address = second.dora().await.yiaddr;
+    pair.secondary.give_back(second);
+    assert_eq!(holder(&pair.secondary, first_address).await, Some(client_id(mac_for(FailoverRole::Primary))));
+    assert_eq!(holder(&pair.primary, second_address).await, None);
+
+    pair.link.heal();
+    pair.both_in(FailoverState::Normal).await;
+    pair.settled().await;
+    assert_eq!(holder(&pair.secondary, first_address).await, None);
+    assert_eq!(holder(&pair.primary, second_address).await, Some(client_id(mac_for(FailoverRole::Secondary))));
+}
+
+//A RELEASE FROM A CLIENT THAT DOES NOT HOLD THE ADDRESS IS NOT PASSED ON TO THE PARTNER
+#[tokio::test]
+async fn a_forged_release_frees_the_address_on_neither_server() {
+    let mut pair = Pair::start(json!({})).await;
+    let client = pair.primary.client(mac_for(FailoverRole::Primary));
+    let address = client.dora().await.yiaddr;
+    pair.primary.give_back(client);
+    pair.settled().await;
+
+    let forger = pair.primary.client([0x02, 0x00, 0x00, 0x00, 0x02, 0x01]);
+    forger.release(address, SERVER_ID).await;
+    //THE INFORM IS HANDLED AFTER THE RELEASE, SO ONCE IT IS ANSWERED THE RELEASE WAS SEEN
+    forger.inform(Ipv4Addr::new(127, 0, 0, 109), &[]).await;
+    pair.primary.give_back(forger);
+    pair.settled().await;
+
+    let holder_id = Some(client_id(mac_for(FailoverRole::Primary)));
+    assert_eq!(holder(&pair.primary, address).await, holder_id);
+    assert_eq!(holder(&pair.secondary, address).await, holder_id);
+}
+
+#[test]
+fn the_load_balancing_hash_follows_rfc_3074() {
+    let mut seen = [false; 256];
+    for value in MIX_TABLE {
+        assert!(!seen[value as usize], "{} is in the table twice", value);
+        seen[value as usize] = true;
+    }
+    //THE LENGTH IS THE STARTING VALUE, THE LAST BYTE IS MIXED IN FIRST
+    assert_eq!(pearson(&[]), 0);
+    assert_eq!(pearson(&[0]), MIX_TABLE[1]);
+    assert_eq!(pearson(&[1, 2]), MIX_TABLE[(MIX_TABLE[2 ^ 2] ^ 1) as usize]);
+}
+
+#[test]
+fn failover_config_is_checked() {
+    let failover = config_with("failover", json!({"role": "primary", "peer": "10.0.0.2"})).failover.unwrap();
+    assert_eq!(failover.peer, SocketAddr::from(([10, 0, 0, 2], FAILOVER_PORT)));
+    assert_eq!((failover.mode, failover.split, failover.mclt.as_secs()), (FailoverMode::LoadBalance, 128, 3600));
+    let hot_standby = config_with("failover", json!({"role": "primary", "peer": "10.0.0.2", "mode": "hot_standby"}));
+    assert_eq!(hot_standby.failover.unwrap().split, 256);
+    assert_eq!(test_config().failover, None);
+
+    let found = problems(|value| {
+        value["failover"] = json!({"role": "tertiary", "mode": "active", "peer": "10.0.0.2", "mclt": 0, "split": 300, "peer_timeout": 0});
+    });
+    assert_eq!(paths(&found), ["failover.role", "failover.mode", "failover.mclt", "failover.split", "failover.peer_timeout"]);
+}
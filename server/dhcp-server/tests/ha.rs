@@ -0,0 +1,211 @@
+mod common;
+
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use common::*;
+use dhcp_proto::options::*;
+use dhcp_server::ha::{LeaderLock, MemoryLock, PostgresLock};
+use dhcp_server::server_config::server_config::DEFAULT_HA_LOCK_ID;
+use dhcp_server::server_config::Config;
+use dhcp_server::set_up::migrations::run_migrations;
+use dhcp_server::store::{ClientName, LeaseStore, MemoryStore};
+use dhcp_server::utility::lease::LeaseTimes;
+
+const SECOND_ID: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 2);
+const HEARTBEAT: Duration = Duration::from_millis(50);
+const TAKEOVER_AFTER: Duration = Duration::from_millis(300);
+
+//THE CONFIG TAKES WHOLE SECONDS, THE TESTS RUN THE ELECTION A LOT FASTER
+//A SERVER OF THE GROUP, IDENTIFIED BY address, WITH TIMINGS SHORT ENOUGH FOR A TEST
+fn group_member(address: Ipv4Addr, node: &str) -> Config {
+    let mut config = config_with("ha", json!({"node": node}));
+    config.server.ip_address = address;
+    let ha = config.ha.as_mut().unwrap();
+    ha.heartbeat = HEARTBEAT;
+    ha.takeover_after = TAKEOVER_AFTER;
+    config
+}
+
+//TWO SERVERS ON ONE STORE, EACH WITH ITS OWN SESSION ON THE LEADER LOCK
+struct Group {
+    first: Harness,
+    second: Harness,
+    first_lock: Arc<MemoryLock>,
+    second_lock: Arc<MemoryLock>,
+}
+
+impl Group {
+    //THE FIRST SERVER IS GIVEN A HEAD START SO IT IS THE ONE ELECTED
+    async fn start() -> Group {
+        let store = Arc::new(MemoryStore::new());
+        let first_lock = Arc::new(MemoryLock::new());
+        let second_lock = Arc::new(first_lock.session());
+        let first = Harness::start_sharing(group_member(SERVER_ID, "first"), SERVER_ID, store.clone(),
+            Some(first_lock.clone() as Arc<dyn LeaderLock>)).await;
+        first.eventually("the first server leads", || async { first.server.is_leader() }).await;
+        let second = Harness::start_sharing(group_member(SECOND_ID, "second"), SECOND_ID, store,
+            Some(second_lock.clone() as Arc<dyn LeaderLock>)).await;
+        first.eventually("the second server saw the leader", || async {
+            second.server.election().unwrap().leader().as_deref() == Some("first")
+        }).await;
+        Group { first, second, first_lock, second_lock }
+    }
+}
+
+async fn ha_view(harness: &Harness) -> (StatusCode, Value) {
+    call(&router(harness), Method::GET, "/api/ha", None).await
+}
+
+#[tokio::test]
+async fn only_the_leader_answers() {
+    let mut group = Group::start().await;
+    let client = group.second.client([0x02, 0, 0, 0, 0x03, 0x01]);
+    assert!(client.try_discover().await.is_none());
+    group.second.give_back(client);
+
+    let client = group.first.client([0x02, 0, 0, 0, 0x03, 0x01]);
+    let ack = client.dora().await;
+    assert_eq!(message_type(&ack), Some(DHCPACK));
+    assert_eq!(ip_option(&ack, SERVER_IDENTIFIER), Some(SERVER_ID));
+
+    let (status, first) = ha_view(&group.first).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!((first["role"].as_str(), first["term"].as_i64(), first["leader"].as_str()), (Some("leader"), Some(1), Some("first")));
+    let (_, second) = ha_view(&group.second).await;
+    assert_eq!((second["role"].as_str(), &second["term"], second["leader"].as_str()), (Some("standby"), &Value::Null, Some("first")));
+    assert_eq!(ha_view(&Harness::start_bound(test_config(), Ipv4Addr::new(127, 0, 0, 3)).await).await.0, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn a_standby_takes_over_when_the_leader_heartbeat_lapses() {
+    let mut group = Group::start().await;
+    let mac = [0x02, 0, 0, 0, 0x03, 0x02];
+    let client = group.first.client(mac);
+    let address = client.dora().await.yiaddr;
+    group.first.give_back(client);
+
+    //THE LEADER LOSES THE DATABASE BUT NOTHING TELLS THE LOCK, IT STAYS HELD UNTIL THE STANDBY ENDS THE SESSION
+    group.first_lock.cut();
+    group.first.eventually("the leader went silent", || async { !group.first.server.is_leader() }).await;
+    group.second.eventually("the standby took over", || async { group.second.server.is_leader() }).await;
+    assert_eq!(group.second.server.election().unwrap().term(), Some(2));
+
+    //THE LEASE IS IN THE SHARED STORE, SO THE REBINDING CLIENT KEEPS ITS ADDRESS
+    let client = group.second.client(mac);
+    let ack = client.rebind(address).await;
+    assert_eq!((message_type(&ack), ack.yiaddr), (Some(DHCPACK), address));
+    assert_eq!(ip_option(&ack, SERVER_IDENTIFIER), Some(SECOND_ID));
+
+    //BACK ON THE DATABASE THE OLD LEADER FINDS ITSELF DEPOSED AND STANDS BY
+    group.first_lock.heal();
+    let client = group.first.client([0x02, 0, 0, 0, 0x03, 0x03]);
+    assert!(client.try_discover().await.is_none());
+    assert!(!group.first.server.is_leader());
+    assert!(group.second_lock.heartbeat().await.unwrap());
+}
+
+#[tokio::test]
+async fn an_address_taken_by_another_server_is_never_acked() {
+    //NO ELECTION, SO BOTH SERVERS ANSWER AND ONLY THE STORE KEEPS THEM FROM GIVING OUT THE SAME ADDRESS
+    let store = Arc::new(MemoryStore::new());
+    let mut first = Harness::start_sharing(test_config(), SERVER_ID, store.clone(), None).await;
+    let mut second_config = test_config();
+    second_config.server.ip_address = SECOND_ID;
+    let mut second = Harness::start_sharing(second_config, SECOND_ID, store.clone(), None).await;
+    let wanted = Ipv4Addr::new(127, 0, 0, 101);
+
+    let first_client = first.client([0x02, 0, 0, 0, 0x04, 0x01]);
+    let second_client = second.client([0x02, 0, 0, 0, 0x04, 0x02]);
+    let (first_reply, second_reply) = tokio::join!(first_client.request(wanted, SERVER_ID), second_client.request(wanted, SECOND_ID));
+    let mut replies = [message_type(&first_reply), message_type(&second_reply)];
+    replies.sort();
+    assert_eq!(replies, [Some(DHCPACK), Some(DHCPNAK)]);
+    let holders: Vec<_> = store.active_leases(first.now()).await.unwrap().into_iter()
+        .filter(|lease| lease.ip_address == wanted).collect();
+    assert_eq!(holders.len(), 1);
+
+    //A WRITE THAT FINDS THE ADDRESS TAKEN SAYS SO INSTEAD OF GOING THROUGH
+    let lease = LeaseTimes::from_config(first.now(), &test_config());
+    assert!(!store.lease(wanted, &client_id([0x02, 0, 0, 0, 0x04, 0x03]), &lease, &ClientName::default()).await.unwrap());
+    first.give_back(first_client);
+    second.give_back(second_client);
+}
+
+//TWO SERVERS' LOCKS ON ONE MIGRATED DATABASE, NONE WITHOUT TEST_POSTGRES_URI
+async fn postgres_locks(name: &str) -> Option<(String, PostgresLock, PostgresLock)> {
+    let database = test_database(name).await?;
+    run_migrations(&mut connect(&database).await).await.unwrap();
+    let url = connection_string(&database);
+    Some((url.clone(), PostgresLock::new(url.clone(), DEFAULT_HA_LOCK_ID), PostgresLock::new(url, DEFAULT_HA_LOCK_ID)))
+}
+
+#[tokio::test]
+async fn postgres_lock_elects_one_leader_and_deposes_it() {
+    let Some((url, first, second)) = postgres_locks("ha_depose").await else { return };
+    let (first_term, second_term) = tokio::join!(first.try_acquire("first"), second.try_acquire("second"));
+    let (leader, standby, node) = match (first_term.unwrap(), second_term.unwrap()) {
+        (Some(1), None) => (first, second, "first"),
+        (None, Some(1)) => (second, first, "second"),
+        terms => panic!("expected exactly one server elected for term 1, got {:?}", terms),
+    };
+    assert!(leader.heartbeat().await.unwrap());
+    assert!(!standby.heartbeat().await.unwrap());
+    let record = standby.leader().await.unwrap().expect("a leader was recorded");
+    assert_eq!((record.node.as_str(), record.term), (node, 1));
+    assert!(record.heartbeat_age < Duration::from_secs(5));
+
+    //THE LOCK ID IS SPLIT OVER classid AND objid, A KEY DIFFERING IN EITHER HALF DOES NOT MATCH THE LEADER'S SESSION
+    for other in [DEFAULT_HA_LOCK_ID ^ (1 << 32), DEFAULT_HA_LOCK_ID ^ 1] {
+        PostgresLock::new(url.clone(), other).depose(&record).await.unwrap();
+        assert!(leader.heartbeat().await.unwrap());
+    }
+
+    standby.depose(&record).await.unwrap();
+    eventually("the standby was elected", || async { standby.try_acquire("standby").await.unwrap().is_some() }).await;
+    assert_eq!(standby.leader().await.unwrap().map(|leader| (leader.node, leader.term)), Some(("standby".to_string(), 2)));
+    //THE OLD LEADER'S SESSION IS GONE, ON A NEW ONE IT IS NO LONGER THE RECORDED LEADER
+    assert!(!matches!(leader.heartbeat().await, Ok(true)));
+    assert!(!leader.heartbeat().await.unwrap());
+    assert_eq!(leader.try_acquire("first").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn postgres_lock_is_freed_with_its_connection() {
+    let Some((_, first, second)) = postgres_locks("ha_release").await else { return };
+    assert_eq!(first.try_acquire("first").await.unwrap(), Some(1));
+    assert_eq!(second.try_acquire("second").await.unwrap(), None);
+
+    first.release().await;
+    eventually("the released lock was taken", || async { second.try_acquire("second").await.unwrap().is_some() }).await;
+    assert_eq!(second.leader().await.unwrap().map(|leader| (leader.node, leader.term)), Some(("second".to_string(), 2)));
+
+    //A LOCK THAT GOES AWAY CLOSES ITS CONNECTION TOO
+    drop(second);
+    eventually("the dropped lock was taken", || async { first.try_acquire("first").await.unwrap().is_some() }).await;
+    assert_eq!(first.leader().await.unwrap().map(|leader| leader.term), Some(3));
+}
+
+#[test]
+fn ha_config_is_checked() {
+    let ha = config_with("ha", json!({})).ha.unwrap();
+    assert_eq!((ha.lock_id, ha.heartbeat.as_secs(), ha.takeover_after.as_secs()), (DEFAULT_HA_LOCK_ID, 1, 5));
+    assert!(ha.node.ends_with(&format!("-{}", std::process::id())));
+    assert_eq!(test_config().ha, None);
+
+    let found = problems(|value| {
+        value["ha"] = json!({"node": "", "heartbeat": 3, "takeover_after": 5});
+        value["failover"] = json!({"role": "primary", "peer": "10.0.0.2"});
+    });
+    assert_eq!(paths(&found), ["ha.node", "ha.takeover_after"]);
+
+    let found = problems(|value| {
+        value["ha"] = json!({"node": "a"});
+        value["failover"] = json!({"role": "primary", "peer": "10.0.0.2"});
+    });
+    assert_eq!(paths(&found), ["ha"]);
+}
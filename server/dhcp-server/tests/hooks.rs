@@ -0,0 +1,242 @@
+mod common;
+
+use std::fs;
+use std::net::Ipv4Addr;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::post;
+use axum::{Json, Router};
+
+use common::*;
+use dhcp_server::hooks::{LeaseEvent, LeaseEventKind};
+use dhcp_server::server_config::server_config::HookAction;
+
+const CLIENT_A: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x0a];
+const CLIENT_B: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x0b];
+
+//WEBHOOK RECEIVER THAT FAILS ITS FIRST REQUEST, SO EVERY TEST OF IT ALSO COVERS A RETRY
+#[derive(Clone, Default)]
+struct Receiver {
+    events: Arc<Mutex<Vec<LeaseEvent>>>,
+    requests: Arc<Mutex<usize>>,
+}
+
+async fn receive(State(receiver): State<Receiver>, Json(event): Json<LeaseEvent>) -> StatusCode {
+    let mut requests = receiver.requests.lock().unwrap();
+    *requests += 1;
+    if *requests == 1 {
+        return StatusCode::SERVICE_UNAVAILABLE;
+    }
+    receiver.events.lock().unwrap().push(event);
+    StatusCode::NO_CONTENT
+}
+
+async fn webhook_receiver() -> (String, Receiver) {
+    let receiver = Receiver::default();
+    let router = Router::new().route("/events", post(receive)).with_state(receiver.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/events", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    (url, receiver)
+}
+
+#[tokio::test]
+async fn webhook_hears_every_lease_change_in_order() {
+    let (url, receiver) = webhook_receiver().await;
+    let mut harness = Harness::start_with(config_with("hooks", serde_json::json!({
+        "targets": [{"type": "webhook", "name": "ipam", "url": url, "retry_delay": 0}],
+    }))).await;
+
+    let client = harness.client(CLIENT_A);
+    let address_a = client.dora().await.yiaddr;
+    client.renew(address_a).await;
+    client.release(address_a, SERVER_ID).await;
+    harness.give_back(client);
+    let client = harness.client(CLIENT_B);
+    let address_b = client.dora().await.yiaddr;
+    harness.give_back(client);
+    //EXPIRY IS NOTICED WHILE HANDLING THE NEXT MESSAGE
+    harness.advance(LEASE_SECONDS + 1);
+    let client = harness.client(CLIENT_A);
+    client.discover(None).await;
+    harness.give_back(client);
+
+    let events = receiver.events.clone();
+    harness.eventually("the webhook got every event", || async {
+        events.lock().unwrap().len() == 5
+    }).await;
+    let events = receiver.events.lock().unwrap().clone();
+    let seen: Vec<_> = events.iter().map(|event| (event.event, event.ip_address, event.client_id.clone())).collect();
+    assert_eq!(seen, vec![
+        (LeaseEventKind::Granted, address_a, client_id(CLIENT_A)),
+        (LeaseEventKind::Renewed, address_a, client_id(CLIENT_A)),
+        (LeaseEventKind::Released, address_a, client_id(CLIENT_A)),
+        (LeaseEventKind::Granted, address_b, client_id(CLIENT_B)),
+        (LeaseEventKind::Expired, address_b, client_id(CLIENT_B)),
+    ]);
+    assert_eq!(events[0].mac.as_deref(), Some("02:00:00:00:00:0A"));
+    assert_eq!(events[0].lease_start.as_deref(), Some("2024-01-01T12:00:00Z"));
+    assert_eq!(events[0].lease_end.as_deref(), Some("2024-01-01T13:00:00Z"));
+    assert_eq!(events[4].mac, None);
+    assert_eq!(*receiver.requests.lock().unwrap(), 6);
+}
+
+#[tokio::test]
+async fn command_gets_the_event_in_its_environment_and_on_stdin() {
+    let directory = std::env::temp_dir().join(format!("dhcp-hooks-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    let output = directory.join("events");
+    //THE ENVIRONMENT IS MOVED INTO PLACE SO THE TEST NEVER READS IT HALF WRITTEN
+    let script = r#"cat >> "$1"; echo >> "$1"; env | grep '^DHCP_' | sort > "$1.tmp"; mv "$1.tmp" "$1.env""#;
+    let mut harness = Harness::start_with(config_with("hooks", serde_json::json!({
+        "targets": [{
+            "type": "command",
+            "command": "sh",
+            "args": ["-c", script, "hook", output.to_string_lossy()],
+            "events": ["declined"],
+        }],
+    }))).await;
+
+    let client = harness.client(CLIENT_A);
+    let address = client.dora().await.yiaddr;
+    client.decline(address, SERVER_ID).await;
+    harness.give_back(client);
+
+    let environment = output.with_extension("env");
+    harness.eventually("the command ran", || async { environment.exists() }).await;
+    //ONLY THE DECLINE, THE GRANT IS FILTERED OUT
+    let lines: Vec<LeaseEvent> = fs::read_to_string(&output).unwrap().lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 1);
+    assert_eq!((lines[0].event, lines[0].ip_address), (LeaseEventKind::Declined, address));
+    let environment = fs::read_to_string(&environment).unwrap();
+    assert!(environment.contains("DHCP_EVENT=declined\n"), "{}", environment);
+    assert!(environment.contains(&format!("DHCP_IP_ADDRESS={}\n", address)), "{}", environment);
+    assert!(environment.contains(&format!("DHCP_CLIENT_ID={}\n", client_id(CLIENT_A))), "{}", environment);
+    assert!(environment.contains("DHCP_MAC=02:00:00:00:00:0A\n"), "{}", environment);
+    assert!(!environment.contains("DHCP_LEASE_END"), "{}", environment);
+}
+
+#[tokio::test]
+async fn slow_hooks_do_not_delay_replies() {
+    let mut harness = Harness::start_with(config_with("hooks", serde_json::json!({
+        "queue_size": 1,
+        "targets": [{"type": "command", "name": "slow", "command": "sleep", "args": ["30"], "timeout": 1, "retries": 0}],
+    }))).await;
+
+    let started = Instant::now();
+    let client = harness.client(CLIENT_A);
+    let address = client.dora().await.yiaddr;
+    client.renew(address).await;
+    client.renew(address).await;
+    harness.give_back(client);
+    assert!(started.elapsed().as_millis() < 1000, "replies took {:?}", started.elapsed());
+
+    //THE FIRST EVENT IS BEING HANDLED, THE SECOND WAITS IN THE QUEUE, THE THIRD DOES NOT FIT
+    assert!(counter(&harness, r#"dhcp_hook_events_dropped_total{hook="slow"}"#) >= 1);
+    //THE HUNG COMMAND IS KILLED AT THE TIMEOUT
+    harness.eventually("the timeout is counted as a failure", || async {
+        counter(&harness, r#"dhcp_hook_failures_total{hook="slow"}"#) >= 1
+    }).await;
+}
+
+#[tokio::test]
+async fn notify_hook_publishes_through_the_store() {
+    let mut harness = Harness::start_with(config_with("hooks", serde_json::json!({
+        "targets": [
+            {"type": "notify", "name": "stream"},
+            {"type": "notify", "name": "outbox", "channel": "lease_outbox", "outbox": true, "events": ["granted", "released"]},
+        ],
+    }))).await;
+
+    let client = harness.client(CLIENT_A);
+    let address = client.dora().await.yiaddr;
+    client.renew(address).await;
+    client.release(address, SERVER_ID).await;
+    harness.give_back(client);
+
+    let store = harness.store.clone();
+    harness.eventually("every notification was sent", || async { store.notifications().len() == 5 }).await;
+    let mut notifications = harness.store.notifications();
+    notifications.sort_by(|a, b| a.0.cmp(&b.0));
+    let seen: Vec<_> = notifications.iter().map(|(channel, payload)| {
+        let payload: serde_json::Value = serde_json::from_str(payload).unwrap();
+        (channel.as_str(), payload["event"].as_str().unwrap().to_string(), payload["id"].as_i64())
+    }).collect();
+    assert_eq!(seen, vec![
+        ("dhcp_lease_events", "granted".to_string(), None),
+        ("dhcp_lease_events", "renewed".to_string(), None),
+        ("dhcp_lease_events", "released".to_string(), None),
+        ("lease_outbox", "granted".to_string(), Some(1)),
+        ("lease_outbox", "released".to_string(), Some(2)),
+    ]);
+    let outbox: Vec<_> = harness.store.outbox().iter().map(|event| (event.event, event.ip_address)).collect();
+    assert_eq!(outbox, vec![(LeaseEventKind::Granted, address), (LeaseEventKind::Released, address)]);
+}
+
+//A RELEASE OR DECLINE THAT ENDED NO LEASE IS NOT AN EVENT
+#[tokio::test]
+async fn forged_releases_and_declines_are_not_reported() {
+    let mut harness = Harness::start_with(config_with("hooks", serde_json::json!({
+        "targets": [{"type": "notify", "outbox": true, "events": ["released", "declined"]}],
+    }))).await;
+    let client = harness.client(CLIENT_A);
+    let address = client.dora().await.yiaddr;
+    harness.give_back(client);
+    let other = harness.client(CLIENT_B);
+    other.release(address, SERVER_ID).await;
+    other.decline(address, SERVER_ID).await;
+    other.release(Ipv4Addr::new(127, 0, 0, 109), SERVER_ID).await;
+    harness.give_back(other);
+    let client = harness.client(CLIENT_A);
+    client.release(address, SERVER_ID).await;
+    harness.give_back(client);
+
+    let store = harness.store.clone();
+    harness.eventually("the release was kept", || async { !store.outbox().is_empty() }).await;
+    let outbox: Vec<_> = harness.store.outbox().into_iter()
+        .map(|event| (event.event, event.ip_address, event.client_id))
+        .collect();
+    assert_eq!(outbox, vec![(LeaseEventKind::Released, address, client_id(CLIENT_A))]);
+}
+
+#[test]
+fn hook_problems_name_the_target() {
+    let found = problems(|value| {
+        value["hooks"] = serde_json::json!({
+            "targets": [
+                {"type": "webhook", "url": "ftp://ipam.example"},
+                {"type": "command", "command": "/bin/true", "events": ["granted", "offered"]},
+                {"type": "carrier-pigeon"},
+                {"type": "command", "name": "command-1", "command": "/bin/true"},
+                {"type": "notify", "channel": "Lease-Events"},
+            ],
+        });
+    });
+    assert_eq!(paths(&found), [
+        "hooks.targets[0].url",
+        "hooks.targets[1].events[1]",
+        "hooks.targets[2].type",
+        "hooks.targets[3].name",
+        "hooks.targets[4].channel",
+    ]);
+
+    let config = config_with("hooks", serde_json::json!({
+        "targets": [{"type": "webhook", "url": "https://ipam.example/dhcp", "headers": {"Authorization": "Bearer secret"}}],
+    }));
+    let hook = &config.hooks.targets[0];
+    assert_eq!(hook.name, "webhook-0");
+    assert_eq!(hook.events, LeaseEventKind::ALL.to_vec());
+    assert_eq!(hook.action, HookAction::Webhook {
+        url: "https://ipam.example/dhcp".into(),
+        headers: vec![("Authorization".into(), "Bearer secret".into())],
+    });
+
+    let config = config_with("hooks", serde_json::json!({"targets": [{"type": "notify"}]}));
+    assert_eq!(config.hooks.targets[0].action, HookAction::Notify { channel: "dhcp_lease_events".into(), outbox: false });
+}
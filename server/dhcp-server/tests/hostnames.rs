@@ -0,0 +1,152 @@
+mod common;
+
+use common::*;
+use dhcp_proto::options::*;
+use dhcp_server::server_config::server_config::HostnameConfig;
+use dhcp_server::store::{ClientName, DnsUpdate, LeaseStore};
+use dhcp_server::utility::hostname::{sanitize, FQDN_ENCODED, FQDN_NO_UPDATES, FQDN_OVERRIDE, FQDN_SERVER_UPDATES};
+
+const CLIENT_A: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x0a];
+const CLIENT_B: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x0b];
+
+fn host_name_option(name: &str) -> Vec<u8> {
+    let mut options = Vec::new();
+    push_bytes(&mut options, HOST_NAME, name.as_bytes());
+    options
+}
+
+//OPTION 81 WITH THE NAME IN WIRE FORMAT
+fn fqdn_option(flags: u8, name: &str) -> Vec<u8> {
+    let mut data = vec![flags | FQDN_ENCODED, 0, 0];
+    for label in name.split('.') {
+        data.push(label.len() as u8);
+        data.extend_from_slice(label.as_bytes());
+    }
+    data.push(0);
+    let mut options = Vec::new();
+    push_bytes(&mut options, CLIENT_FQDN, &data);
+    options
+}
+
+async fn stored_name(harness: &Harness, mac: [u8; 6]) -> ClientName {
+    harness.store.active_leases(harness.now()).await.unwrap().into_iter()
+        .find(|lease| lease.client_id == client_id(mac))
+        .expect("the client holds a lease")
+        .name
+}
+
+#[tokio::test]
+async fn requested_names_are_cleaned_up_and_returned() {
+    let mut harness = Harness::start().await;
+    let mut client = harness.client(CLIENT_A);
+    client.options = host_name_option("Anna's_Laptop.home");
+
+    let offer = client.discover(None).await;
+    assert_eq!(offer.options_map.get(&HOST_NAME).map(Vec::as_slice), Some(&b"anna-s-laptop"[..]));
+    let ack = client.request(offer.yiaddr, SERVER_ID).await;
+    assert_eq!(ack.options_map.get(&HOST_NAME).map(Vec::as_slice), Some(&b"anna-s-laptop"[..]));
+    assert_eq!(ack.options_map.get(&CLIENT_FQDN), None);
+    assert_eq!(stored_name(&harness, CLIENT_A).await,
+        ClientName { hostname: Some("anna-s-laptop".into()), dns_update: DnsUpdate::Both });
+
+    //AN INFORM GETS THE SAME NAME BACK INSTEAD OF ONE MADE UP FROM THE HARDWARE ADDRESS
+    let inform = client.inform(ack.yiaddr, &[HOST_NAME]).await;
+    assert_eq!(inform.options_map.get(&HOST_NAME).map(Vec::as_slice), Some(&b"anna-s-laptop"[..]));
+    harness.give_back(client);
+}
+
+#[tokio::test]
+async fn a_taken_name_gets_a_suffix_and_renewals_keep_theirs() {
+    let mut harness = Harness::start().await;
+    let mut first = harness.client(CLIENT_A);
+    first.options = host_name_option("printer");
+    let first_address = first.dora().await.yiaddr;
+    harness.give_back(first);
+
+    let mut second = harness.client(CLIENT_B);
+    second.options = host_name_option("PRINTER");
+    let ack = second.dora().await;
+    let second_address = ack.yiaddr;
+    assert_eq!(ack.options_map.get(&HOST_NAME).map(Vec::as_slice), Some(&b"printer-2"[..]));
+    harness.give_back(second);
+
+    //A RENEWAL WITHOUT A NAME KEEPS THE STORED ONE, ONE WITH ITS OWN NAME IS NOT TOLD TO TAKE A SUFFIX
+    let mut first = harness.client(CLIENT_A);
+    first.renew(first_address).await;
+    assert_eq!(stored_name(&harness, CLIENT_A).await.hostname.as_deref(), Some("printer"));
+    first.options = host_name_option("printer");
+    let ack = first.renew(first_address).await;
+    assert_eq!(ack.options_map.get(&HOST_NAME).map(Vec::as_slice), Some(&b"printer"[..]));
+
+    //ONCE RELEASED THE NAME IS FREE AGAIN
+    first.release(first_address, SERVER_ID).await;
+    harness.give_back(first);
+    harness.eventually("the lease was released", || async {
+        harness.store.active_leases(harness.now()).await.unwrap().len() == 1
+    }).await;
+    let mut second = harness.client(CLIENT_B);
+    second.options = host_name_option("printer");
+    let ack = second.renew(second_address).await;
+    assert_eq!(ack.options_map.get(&HOST_NAME).map(Vec::as_slice), Some(&b"printer"[..]));
+    harness.give_back(second);
+}
+
+#[tokio::test]
+async fn client_fqdn_flags_decide_who_updates() {
+    let mut harness = Harness::start().await;
+    let mut client = harness.client(CLIENT_A);
+
+    //S=0: THE CLIENT UPDATES ITS A RECORD, THE SERVER ONLY THE PTR
+    client.options = fqdn_option(0, "Desk.example.com");
+    let ack = client.dora().await;
+    let mut expected = vec![FQDN_ENCODED, 255, 255, 4];
+    expected.extend_from_slice(b"desk");
+    expected.push(9);
+    expected.extend_from_slice(b"simulator");
+    expected.push(4);
+    expected.extend_from_slice(b"test");
+    expected.push(0);
+    assert_eq!(ack.options_map.get(&CLIENT_FQDN), Some(&expected));
+    //OPTION 12 ONLY GOES TO CLIENTS THAT SENT OR ASKED FOR IT
+    assert_eq!(ack.options_map.get(&HOST_NAME), None);
+    assert_eq!(stored_name(&harness, CLIENT_A).await,
+        ClientName { hostname: Some("desk".into()), dns_update: DnsUpdate::Ptr });
+
+    //N=1: NO UPDATES AT ALL
+    client.options = fqdn_option(FQDN_NO_UPDATES, "desk");
+    let ack = client.renew(ack.yiaddr).await;
+    assert_eq!(ack.options_map.get(&CLIENT_FQDN).map(|data| data[0]), Some(FQDN_NO_UPDATES | FQDN_ENCODED));
+    assert_eq!(stored_name(&harness, CLIENT_A).await.dns_update, DnsUpdate::Neither);
+
+    //S=1: THE SERVER DOES BOTH
+    client.options = fqdn_option(FQDN_SERVER_UPDATES, "desk");
+    let ack = client.renew(ack.yiaddr).await;
+    assert_eq!(ack.options_map.get(&CLIENT_FQDN).map(|data| data[0]), Some(FQDN_SERVER_UPDATES | FQDN_ENCODED));
+    assert_eq!(stored_name(&harness, CLIENT_A).await.dns_update, DnsUpdate::Both);
+    harness.give_back(client);
+}
+
+#[tokio::test]
+async fn the_server_can_override_client_updates() {
+    let mut harness = Harness::start_with(config_with("hostnames", serde_json::json!({"override_client_updates": true}))).await;
+    let mut client = harness.client(CLIENT_A);
+    client.options = fqdn_option(0, "desk");
+    let ack = client.dora().await;
+    assert_eq!(ack.options_map.get(&CLIENT_FQDN).map(|data| data[0]),
+        Some(FQDN_SERVER_UPDATES | FQDN_OVERRIDE | FQDN_ENCODED));
+    assert_eq!(stored_name(&harness, CLIENT_A).await.dns_update, DnsUpdate::Both);
+    harness.give_back(client);
+}
+
+#[test]
+fn the_policy_is_configurable() {
+    let policy = HostnameConfig { lowercase: false, replacement: String::new(), ..HostnameConfig::default() };
+    assert_eq!(sanitize("Anna's Laptop", &policy).as_deref(), Some("AnnasLaptop"));
+    assert_eq!(sanitize("--édition--", &HostnameConfig::default()).as_deref(), Some("dition"));
+    assert_eq!(sanitize("___", &HostnameConfig::default()), None);
+    assert_eq!(sanitize(&"a".repeat(100), &policy).map(|name| name.len()), Some(63));
+
+    let found = problems(|value| value["hostnames"] = serde_json::json!({"lowercase": "yes", "replacement": "_"}));
+    assert_eq!(paths(&found), ["hostnames.replacement", "hostnames.lowercase"]);
+    assert_eq!(test_config().hostnames, HostnameConfig::default());
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use common::*;
+use dhcp_server::set_up::migrations::{run_migrations, MIGRATIONS};
+use dhcp_server::store::{ClientName, LeaseStore, PostgresStore};
+use dhcp_server::utility::lease::LeaseTimes;
+
+const ADDRESS: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 10);
+const CLIENT: &str = "02000000000a";
+
+#[test]
+fn lease_times_follow_the_configured_durations() {
+    let start = start_time();
+    let times = LeaseTimes::from_config(start, &test_config());
+    assert_eq!(times.renewal - start, chrono::Duration::seconds(RENEWAL_SECONDS));
+    assert_eq!(times.rebinding - start, chrono::Duration::seconds(REBINDING_SECONDS));
+    assert_eq!(times.end - start, chrono::Duration::seconds(LEASE_SECONDS));
+    assert_eq!(times.durations(), (Duration::from_secs(LEASE_SECONDS as u64),
+        Duration::from_secs(RENEWAL_SECONDS as u64), Duration::from_secs(REBINDING_SECONDS as u64)));
+}
+
+#[test]
+fn a_capped_lease_moves_renewal_and_rebinding_with_its_end() {
+    let start = start_time();
+    let times = LeaseTimes::from_config(start, &test_config());
+    assert_eq!(times.capped(times.end + chrono::Duration::hours(1)), times);
+
+    let capped = times.capped(start + chrono::Duration::seconds(800));
+    assert_eq!(capped.durations(), (Duration::from_secs(800), Duration::from_secs(400), Duration::from_secs(700)));
+}
+
+//A SERVER IN ANOTHER TIME ZONE THAN THE DATABASE STILL SEES THE SAME INSTANTS
+#[tokio::test]
+async fn lease_instants_do_not_depend_on_the_session_time_zone() {
+    let Some(database) = test_database("lease_time_zone").await else { return };
+    let mut client = connect(&database).await;
+    run_migrations(&mut client).await.unwrap();
+    client.batch_execute("SET TimeZone = 'Pacific/Kiritimati'").await.unwrap();
+    let admin = connect(&database).await;
+    admin.batch_execute("SET TimeZone = 'America/Los_Angeles'").await.unwrap();
+    let store = PostgresStore::new(client, admin);
+
+    let start = start_time();
+    store.reconcile(&[ADDRESS], &[], start).await.unwrap();
+    let times = LeaseTimes::new(start, Duration::from_secs(3600), Duration::from_secs(1800), Duration::from_secs(3150));
+    assert!(store.lease(ADDRESS, CLIENT, &times, &ClientName::default()).await.unwrap());
+
+    let lease = &store.active_leases(start).await.unwrap()[0];
+    assert_eq!((lease.lease_start, lease.lease_end), (times.start, times.end));
+    //EXPIRY IS DECIDED BY THE CLOCK PASSED IN, NOT BY NOW() IN THE DATABASE
+    assert!(store.expire_leases(times.end - chrono::Duration::seconds(1)).await.unwrap().leases.is_empty());
+    assert_eq!(store.expire_leases(times.end + chrono::Duration::seconds(1)).await.unwrap().leases.len(), 1);
+}
+
+#[tokio::test]
+async fn local_lease_times_are_converted_to_instants() {
+    let Some(database) = test_database("lease_time_migration").await else { return };
+    let mut client = connect(&database).await;
+    client.batch_execute("SET TimeZone = 'Europe/Warsaw'").await.unwrap();
+    //THE SCHEMA AS IT WAS BEFORE LEASE TIMES WERE TIMESTAMPTZ, WITH A LEASE WRITTEN IN LOCAL TIME
+    client.batch_execute("CREATE TABLE schema_version (
+                              version INTEGER PRIMARY KEY,
+                              description TEXT NOT NULL,
+                              applied_at TIMESTAMP NOT NULL DEFAULT NOW()
+                          )").await.unwrap();
+    for migration in MIGRATIONS.iter().filter(|migration| migration.version < 3) {
+        client.batch_execute(migration.sql).await.unwrap();
+        client.execute("INSERT INTO schema_version (version, description) VALUES ($1, $2)",
+            &[&migration.version, &migration.description]).await.unwrap();
+    }
+    client.execute("INSERT INTO ip_addresses (ip_address, allocated, client_id, lease_start, lease_end)
+                    VALUES ($1, true, $2, '2024-01-01 13:00:00', '2024-01-01 14:00:00')",
+        &[&IpAddr::V4(ADDRESS), &CLIENT]).await.unwrap();
+
+    run_migrations(&mut client).await.unwrap();
+    let row = client.query_one("SELECT lease_start, lease_end FROM ip_addresses", &[]).await.unwrap();
+    //WARSAW IS ONE HOUR AHEAD OF UTC IN JANUARY
+    assert_eq!(row.get::<usize, DateTime<Utc>>(0), Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap());
+    assert_eq!(row.get::<usize, DateTime<Utc>>(1), Utc.with_ymd_and_hms(2024, 1, 1, 13, 0, 0).unwrap());
+}
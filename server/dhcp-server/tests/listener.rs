@@ -0,0 +1,70 @@
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+
+use tokio::net::UdpSocket;
+use tokio::time::{timeout, Duration};
+
+use dhcp_server::listener::{interfaces, InterfaceTable, Listener};
+
+const WAIT: Duration = Duration::from_secs(5);
+
+fn loopback_index() -> u32 {
+    nix::net::if_::if_nametoindex("lo").expect("the loopback interface is lo")
+}
+
+async fn peer() -> UdpSocket {
+    UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap()
+}
+
+#[tokio::test]
+async fn received_packets_carry_the_interface_and_local_address() {
+    let listener = Listener::bind(Ipv4Addr::UNSPECIFIED, 0, None).unwrap();
+    let peer = peer().await;
+    peer.send_to(b"discover", (Ipv4Addr::LOCALHOST, listener.local_port())).await.unwrap();
+
+    let mut buf = [0u8; 64];
+    let (size, source, interface_index, local_address) = timeout(WAIT, listener.recv(&mut buf)).await.unwrap().unwrap();
+    assert_eq!(&buf[..size], b"discover");
+    assert_eq!(source, peer.local_addr().unwrap());
+    assert_eq!(interface_index, loopback_index());
+    assert_eq!(local_address, Ipv4Addr::LOCALHOST);
+}
+
+#[tokio::test]
+async fn replies_leave_from_the_given_source_address() {
+    let listener = Listener::bind(Ipv4Addr::UNSPECIFIED, 0, None).unwrap();
+    let peer = peer().await;
+    let SocketAddr::V4(destination) = peer.local_addr().unwrap() else { unreachable!() };
+
+    let source = Ipv4Addr::new(127, 0, 0, 3);
+    listener.send(b"offer", destination, loopback_index(), source).await.unwrap();
+    let mut buf = [0u8; 64];
+    let (size, from) = timeout(WAIT, peer.recv_from(&mut buf)).await.unwrap().unwrap();
+    assert_eq!(&buf[..size], b"offer");
+    assert_eq!(from, SocketAddr::V4(SocketAddrV4::new(source, listener.local_port())));
+}
+
+#[tokio::test]
+async fn a_listener_bound_to_an_interface_hears_only_that_interface() {
+    let listener = Listener::bind(Ipv4Addr::UNSPECIFIED, 0, Some("lo")).unwrap();
+    assert_eq!(listener.interface.as_deref(), Some("lo"));
+    let peer = peer().await;
+    peer.send_to(b"request", (Ipv4Addr::LOCALHOST, listener.local_port())).await.unwrap();
+    let mut buf = [0u8; 64];
+    let (_, _, interface_index, _) = timeout(WAIT, listener.recv(&mut buf)).await.unwrap().unwrap();
+    assert_eq!(interface_index, loopback_index());
+
+    assert!(Listener::bind(Ipv4Addr::UNSPECIFIED, 0, Some("no-such-if0")).is_err());
+}
+
+#[test]
+fn interfaces_are_listed_with_their_addresses() {
+    let loopback = interfaces().unwrap().into_iter().find(|interface| interface.name == "lo").expect("lo is listed");
+    assert_eq!(loopback.index, loopback_index());
+    assert!(loopback.has_address(Ipv4Addr::LOCALHOST));
+    assert!(loopback.addresses.iter().any(|net| net.prefix_len() == 8));
+
+    let table = InterfaceTable::new();
+    assert_eq!(table.get(loopback_index()).map(|interface| interface.name), Some("lo".to_string()));
+    assert!(table.get(u32::MAX).is_none());
+    assert!(table.get(0).is_none());
+}
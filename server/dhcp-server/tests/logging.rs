@@ -0,0 +1,169 @@
+mod common;
+
+use std::fs;
+use std::net::UdpSocket;
+use std::os::unix::net::UnixDatagram;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use tracing::{info, info_span};
+
+use common::*;
+use dhcp_server::logging::{self, LogFormat};
+use dhcp_server::server_config::server_config::{parse_config, FileSink, LogSink, LoggingConfig, RotateEvery,
+                                                SyslogAddress, SyslogSink};
+
+//A DIRECTORY OF ITS OWN FOR EVERY TEST
+fn scratch(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("dhcp-logging-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+//RUN log WITH ONLY THESE SINKS AND WAIT FOR THEM TO WRITE EVERYTHING
+fn log_to(sinks: Vec<LogSink>, queue_size: usize, log: impl FnOnce()) {
+    let config = LoggingConfig { queue_size, sinks };
+    let (subscriber, guard) = logging::subscriber(LogFormat::Text, &config).unwrap();
+    tracing::subscriber::with_default(subscriber, log);
+    guard.flush();
+}
+
+#[test]
+fn file_sink_rotates_by_size_and_keeps_the_newest() {
+    let directory = scratch("rotate");
+    let path = directory.join("server.log");
+    let sink = FileSink { path: path.to_string_lossy().into(), max_size: Some(300), rotate: None, keep: 2 };
+    log_to(vec![LogSink::File(sink)], 64, || {
+        for line in 0..20 {
+            info!(line, "Filling the log file");
+        }
+    });
+
+    let mut files: Vec<_> = fs::read_dir(&directory).unwrap().map(|entry| entry.unwrap().path()).collect();
+    files.sort();
+    assert_eq!(files.len(), 3, "expected the log file and two rotated ones, found {:?}", files);
+    for file in &files {
+        assert!(fs::metadata(file).unwrap().len() <= 300, "{:?} grew past max_size", file);
+    }
+    //THE NEWEST LINE IS IN THE CURRENT FILE, THE OLDEST ROTATED FILES ARE GONE
+    assert!(fs::read_to_string(&path).unwrap().trim_end().ends_with("line=19"));
+    let kept = files.iter().map(|file| fs::read_to_string(file).unwrap()).collect::<String>();
+    assert!(!kept.lines().any(|line| line.ends_with(" line=0")));
+}
+
+#[test]
+fn syslog_messages_carry_the_transaction_fields() {
+    let collector = UdpSocket::bind("127.0.0.1:0").unwrap();
+    collector.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let sink = SyslogSink {
+        address: SyslogAddress::Udp(collector.local_addr().unwrap()),
+        facility: 3,
+        app_name: "dhcp-test".into(),
+    };
+    log_to(vec![LogSink::Syslog(sink)], 64, || {
+        let span = info_span!("transaction", xid = "0x00000001", mac = "02:00:00:00:00:0A");
+        let _entered = span.enter();
+        info!(yiaddr = "127.0.0.100", note = "a \"quoted\" value", "DHCP Discover answered with an offer");
+    });
+
+    let mut buffer = [0u8; 2048];
+    let length = collector.recv(&mut buffer).unwrap();
+    let message = String::from_utf8_lossy(&buffer[..length]);
+    //FACILITY daemon (3) AND SEVERITY info (6)
+    assert!(message.starts_with("<30>1 "), "{}", message);
+    assert!(message.contains(&format!(" dhcp-test {} - [fields@32473 ", std::process::id())), "{}", message);
+    assert!(message.contains(r#" xid="0x00000001" mac="02:00:00:00:00:0A" yiaddr="127.0.0.100""#), "{}", message);
+    assert!(message.contains(r#" note="a \"quoted\" value""#), "{}", message);
+    assert!(message.ends_with("] DHCP Discover answered with an offer"), "{}", message);
+}
+
+#[test]
+fn journald_datagrams_carry_the_fields() {
+    let socket = scratch("journald").join("journal.socket");
+    let journal = UnixDatagram::bind(&socket).unwrap();
+    journal.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    log_to(vec![LogSink::Journald { socket: socket.to_string_lossy().into() }], 64, || {
+        let span = info_span!("transaction", client_id = "0200000000");
+        let _entered = span.enter();
+        tracing::warn!(reason = "unavailable", "DHCP Request answered with a NAK\nsecond line");
+    });
+
+    let mut buffer = [0u8; 2048];
+    let length = journal.recv(&mut buffer).unwrap();
+    let datagram = &buffer[..length];
+    let text = String::from_utf8_lossy(datagram);
+    for field in ["PRIORITY=4\n", "SYSLOG_IDENTIFIER=dhcp-server\n", "CLIENT_ID=0200000000\n", "REASON=unavailable\n"] {
+        assert!(text.contains(field), "missing {:?} in {:?}", field, text);
+    }
+    //A VALUE SPANNING LINES IS SENT WITH ITS LENGTH
+    let message = "DHCP Request answered with a NAK\nsecond line";
+    let mut expected = b"MESSAGE\n".to_vec();
+    expected.extend_from_slice(&(message.len() as u64).to_le_bytes());
+    expected.extend_from_slice(message.as_bytes());
+    assert!(datagram.windows(expected.len()).any(|window| window == expected.as_slice()));
+}
+
+#[test]
+fn a_stalled_sink_does_not_hold_up_logging() {
+    //NOBODY READS THIS SOCKET, SO SENDING TO IT BLOCKS ONCE ITS BUFFER IS FULL
+    let socket = scratch("stalled").join("syslog.socket");
+    let _stalled = UnixDatagram::bind(&socket).unwrap();
+    let sink = SyslogSink { address: SyslogAddress::Unix(socket.to_string_lossy().into()), facility: 3, app_name: "dhcp-test".into() };
+    let started = Instant::now();
+    log_to(vec![LogSink::Syslog(sink)], 8, || {
+        for line in 0..5000 {
+            info!(line, "Logging faster than the sink takes it");
+        }
+    });
+    //THE FLUSH GIVES UP AFTER A SECOND, LOGGING ITSELF NEVER WAITED
+    assert!(started.elapsed() < Duration::from_secs(5), "logging took {:?}", started.elapsed());
+}
+
+#[test]
+fn logging_section_is_parsed() {
+    let mut value = test_config_value();
+    value["server"].as_object_mut().unwrap().remove("log_file");
+    value["logging"] = serde_json::json!({
+        "queue_size": 50,
+        "sinks": [
+            {"type": "stdout"},
+            {"type": "file", "path": "/var/log/dhcp.log", "max_size": 1048576, "rotate": "daily"},
+            {"type": "syslog", "address": "udp:127.0.0.1:514", "facility": "local3"},
+            {"type": "journald"},
+        ],
+    });
+    let logging = parse_config(&value).unwrap().logging;
+    assert_eq!(logging.queue_size, 50);
+    assert_eq!(logging.sinks, vec![
+        LogSink::Stdout,
+        LogSink::File(FileSink { path: "/var/log/dhcp.log".into(), max_size: Some(1048576), rotate: Some(RotateEvery::Daily), keep: 7 }),
+        LogSink::Syslog(SyslogSink { address: SyslogAddress::Udp("127.0.0.1:514".parse().unwrap()), facility: 19, app_name: "dhcp-server".into() }),
+        LogSink::Journald { socket: "/run/systemd/journal/socket".into() },
+    ]);
+
+    //WITHOUT A logging SECTION server.log_file IS STILL WRITTEN NEXT TO STDOUT
+    let logging = test_config().logging;
+    assert_eq!(logging.sinks.len(), 2);
+    assert!(matches!(&logging.sinks[1], LogSink::File(FileSink { max_size: None, rotate: None, .. })));
+}
+
+#[test]
+fn logging_problems_name_the_sink() {
+    let found = problems(|value| {
+        value["logging"] = serde_json::json!({
+            "sinks": [
+                {"type": "file", "path": "dhcp.log", "rotate": "weekly"},
+                {"type": "kafka"},
+                {"type": "syslog", "address": "tcp:127.0.0.1:514", "facility": "local9"},
+            ],
+        });
+    });
+    assert_eq!(paths(&found), [
+        "server.log_file",
+        "logging.sinks[0].rotate",
+        "logging.sinks[1].type",
+        "logging.sinks[2].address",
+        "logging.sinks[2].facility",
+    ]);
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use std::net::Ipv4Addr;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use http_body_util::BodyExt;
+use tower::ServiceExt;
+
+use common::*;
+use dhcp_server::metrics;
+
+const CLIENT_A: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x0a];
+const CLIENT_B: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x0b];
+
+//SCRAPE THE WAY PROMETHEUS WOULD
+async fn scrape(harness: &Harness) -> String {
+    let request = Request::builder().uri("/metrics").body(Body::empty()).unwrap();
+    let response = metrics::router(harness.server.clone()).oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    String::from_utf8(bytes.to_vec()).unwrap()
+}
+
+fn has_sample(scraped: &str, sample: &str) -> bool {
+    scraped.lines().any(|line| line == sample)
+}
+
+#[tokio::test]
+async fn messages_are_counted_by_type() {
+    let mut harness = Harness::start().await;
+    let client = harness.client(CLIENT_A);
+    let address = client.dora().await.yiaddr;
+    client.release(address, SERVER_ID).await;
+    harness.give_back(client);
+    let server = harness.server.clone();
+    harness.eventually("the release is counted", || async {
+        server.metrics().render().contains("dhcp_releases_total 1")
+    }).await;
+
+    let scraped = scrape(&harness).await;
+    assert!(has_sample(&scraped, r#"dhcp_messages_received_total{type="discover"} 1"#));
+    assert!(has_sample(&scraped, r#"dhcp_messages_received_total{type="request"} 1"#));
+    assert!(has_sample(&scraped, r#"dhcp_messages_received_total{type="release"} 1"#));
+    assert!(has_sample(&scraped, r#"dhcp_messages_sent_total{type="offer"} 1"#));
+    assert!(has_sample(&scraped, r#"dhcp_messages_sent_total{type="ack"} 1"#));
+    assert!(has_sample(&scraped, r#"dhcp_handler_duration_seconds_count{type="request"} 1"#));
+    assert!(has_sample(&scraped, "dhcp_store_errors_total 0"));
+    assert!(has_sample(&scraped, "dhcp_send_failures_total 0"));
+}
+
+#[tokio::test]
+async fn naks_are_counted_by_reason() {
+    let mut harness = Harness::start().await;
+    let client = harness.client(CLIENT_A);
+    let address = client.dora().await.yiaddr;
+    client.request(Ipv4Addr::new(127, 0, 0, 200), SERVER_ID).await;
+    client.request(RESTRICTED, SERVER_ID).await;
+    harness.give_back(client);
+    let other = harness.client(CLIENT_B);
+    other.request(address, SERVER_ID).await;
+    other.renew(address).await;
+    harness.give_back(other);
+
+    let scraped = scrape(&harness).await;
+    assert!(has_sample(&scraped, r#"dhcp_naks_total{reason="outside_pool"} 1"#));
+    assert!(has_sample(&scraped, r#"dhcp_naks_total{reason="restricted"} 1"#));
+    assert!(has_sample(&scraped, r#"dhcp_naks_total{reason="unavailable"} 2"#));
+    assert!(has_sample(&scraped, r#"dhcp_messages_sent_total{type="nak"} 4"#));
+}
+
+#[tokio::test]
+async fn pool_gauges_follow_the_store() {
+    let mut harness = Harness::start().await;
+    let client = harness.client(CLIENT_A);
+    let address = client.dora().await.yiaddr;
+    client.decline(address, SERVER_ID).await;
+    harness.give_back(client);
+    let server = harness.server.clone();
+    harness.eventually("the decline is counted", || async {
+        server.metrics().render().contains("dhcp_declines_total 1")
+    }).await;
+    let client = harness.client(CLIENT_A);
+    client.dora().await;
+    harness.give_back(client);
+
+    let scraped = scrape(&harness).await;
+    let pool = format!("{}-{}", POOL_START, POOL_END);
+    for (state, count) in [("total", 10), ("allocated", 1), ("declined", 1), ("blocked", 0), ("free", 8)] {
+        let sample = format!(r#"dhcp_pool_addresses{{pool="{}",state="{}"}} {}"#, pool, state, count);
+        assert!(has_sample(&scraped, &sample), "missing {} in\n{}", sample, scraped);
+    }
+}
@@ -0,0 +1,52 @@
+mod common;
+
+use common::*;
+use dhcp_server::set_up::create_db_tables::CustomError;
+use dhcp_server::set_up::migrations::{current_version, latest_version, run_migrations, MIGRATIONS};
+
+#[test]
+fn versions_count_up_from_one_without_gaps() {
+    for (position, migration) in MIGRATIONS.iter().enumerate() {
+        assert_eq!(migration.version, position as i32 + 1, "{}", migration.description);
+    }
+    assert_eq!(latest_version(), MIGRATIONS.len() as i32);
+}
+
+#[tokio::test]
+async fn migrations_are_applied_once() {
+    let Some(database) = test_database("migrations_once").await else { return };
+    let mut client = connect(&database).await;
+
+    assert_eq!(run_migrations(&mut client).await.unwrap(), MIGRATIONS.len());
+    assert_eq!(current_version(&client).await.unwrap(), latest_version());
+    //A SECOND START FINDS NOTHING TO DO
+    assert_eq!(run_migrations(&mut client).await.unwrap(), 0);
+    let recorded = client.query_one("SELECT COUNT(*) FROM schema_version", &[]).await.unwrap();
+    assert_eq!(recorded.get::<usize, i64>(0), MIGRATIONS.len() as i64);
+}
+
+#[tokio::test]
+async fn servers_starting_together_apply_each_migration_once() {
+    let Some(database) = test_database("migrations_together").await else { return };
+    let mut first = connect(&database).await;
+    let mut second = connect(&database).await;
+
+    let (first, second) = tokio::join!(run_migrations(&mut first), run_migrations(&mut second));
+    assert_eq!(first.unwrap() + second.unwrap(), MIGRATIONS.len());
+}
+
+#[tokio::test]
+async fn a_schema_from_a_newer_server_is_refused() {
+    let Some(database) = test_database("migrations_newer").await else { return };
+    let mut client = connect(&database).await;
+    run_migrations(&mut client).await.unwrap();
+    client.execute("INSERT INTO schema_version (version, description) VALUES ($1, 'from the future')",
+        &[&(latest_version() + 1)]).await.unwrap();
+
+    match run_migrations(&mut client).await {
+        Err(CustomError::SchemaTooNew { database, binary }) => {
+            assert_eq!((database, binary), (latest_version() + 1, latest_version()));
+        }
+        other => panic!("expected SchemaTooNew, got {:?}", other.map_err(|e| e.to_string())),
+    }
+}
@@ -0,0 +1,21 @@
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+
+use proptest::prelude::*;
+
+use dhcp_proto::types::{parse_option_field, OptionField};
+use dhcp_server::server_config::server_config::parse_config;
+use dhcp_server::utility::options::*;
+
+proptest! {
+    #[test]
+    fn inform_options_are_well_formed(parameter_request_list in proptest::collection::vec(any::<u8>(), 0..64)) {
+        let config = parse_config(&serde_json::from_str(include_str!("../../../server-config.json")).unwrap()).unwrap();
+        let options_buf = inform_options_buf(parameter_request_list.clone(), &config, "client", Ipv4Addr::new(10, 0, 0, 1));
+        let mut options_map = HashMap::new();
+        prop_assert!(parse_option_field(&options_buf, OptionField::Options, &mut options_map).is_ok());
+        for code in options_map.keys() {
+            prop_assert!(*code == MESSAGE_TYPE || *code == SERVER_IDENTIFIER || parameter_request_list.contains(code));
+        }
+    }
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use common::*;
+use dhcp_server::server_config::generate_ip_pool;
+use dhcp_server::store::{AddressState, ClientName, LeaseStore, MemoryStore};
+use dhcp_server::utility::lease::LeaseTimes;
+
+const FIRST: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 10);
+const LAST: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 19);
+const CLIENT: &str = "02000000000a";
+
+fn lease_from(start: DateTime<Utc>) -> LeaseTimes {
+    LeaseTimes::new(start, Duration::from_secs(3600), Duration::from_secs(1800), Duration::from_secs(3150))
+}
+
+async fn state_of(store: &dyn LeaseStore, ip: Ipv4Addr, now: DateTime<Utc>) -> Option<AddressState> {
+    store.address(ip).await.unwrap().map(|record| AddressState::of(&record, now))
+}
+
+async fn shrinking_keeps_live_leases_and_drops_free_addresses(store: &dyn LeaseStore) {
+    let now = start_time();
+    let summary = store.reconcile(&generate_ip_pool(FIRST, LAST), &[], now).await.unwrap();
+    assert_eq!(summary.added, 10);
+
+    let leased = Ipv4Addr::new(10, 0, 0, 18);
+    assert!(store.lease(leased, CLIENT, &lease_from(now), &ClientName::default()).await.unwrap());
+
+    //THE LAST TWO ADDRESSES LEAVE THE POOL AND 10.0.0.15 BECOMES RESTRICTED
+    let restricted = Ipv4Addr::new(10, 0, 0, 15);
+    let summary = store.reconcile(&generate_ip_pool(FIRST, Ipv4Addr::new(10, 0, 0, 17)), &[restricted], now).await.unwrap();
+    assert_eq!((summary.added, summary.removed, summary.retired, summary.restricted), (0, 2, 1, 1));
+
+    //THE LEASED ADDRESS STAYS WITH ITS CLIENT BUT CANNOT BE HANDED OUT AGAIN
+    assert_eq!(state_of(store, leased, now).await, Some(AddressState::Leased));
+    assert!(store.address(leased).await.unwrap().unwrap().retired);
+    assert_eq!(store.address_of_client(CLIENT).await.unwrap(), Some(leased));
+    assert_eq!(store.address(LAST).await.unwrap(), None);
+    assert_eq!(store.address(restricted).await.unwrap(), None);
+    assert!(!store.available().await.unwrap().contains(&leased));
+
+    //ONCE THE LEASE RUNS OUT THE RETIRED ADDRESS GOES AWAY
+    let later = now + chrono::Duration::seconds(3601);
+    let expired = store.expire_leases(later).await.unwrap();
+    assert_eq!(expired.leases.len(), 1);
+    assert_eq!(expired.removed, 1);
+    assert_eq!(store.address(leased).await.unwrap(), None);
+}
+
+async fn growing_adds_addresses_and_reinstates_retired_ones(store: &dyn LeaseStore) {
+    let now = start_time();
+    store.reconcile(&generate_ip_pool(FIRST, LAST), &[], now).await.unwrap();
+    assert!(store.lease(LAST, CLIENT, &lease_from(now), &ClientName::default()).await.unwrap());
+    store.reconcile(&generate_ip_pool(FIRST, Ipv4Addr::new(10, 0, 0, 15)), &[], now).await.unwrap();
+    assert!(store.address(LAST).await.unwrap().unwrap().retired);
+
+    let summary = store.reconcile(&generate_ip_pool(FIRST, Ipv4Addr::new(10, 0, 0, 21)), &[], now).await.unwrap();
+    //10.0.0.16-10.0.0.18 AND 10.0.0.20-10.0.0.21 ARE NEW, 10.0.0.19 NEVER LEFT THE TABLE
+    assert_eq!((summary.added, summary.removed, summary.reinstated), (5, 0, 1));
+    let record = store.address(LAST).await.unwrap().unwrap();
+    assert!(!record.retired);
+    assert_eq!(record.client_id.as_deref(), Some(CLIENT));
+    assert_eq!(state_of(store, Ipv4Addr::new(10, 0, 0, 21), now).await, Some(AddressState::Free));
+
+    //RECONCILING THE SAME POOL AGAIN CHANGES NOTHING
+    let summary = store.reconcile(&generate_ip_pool(FIRST, Ipv4Addr::new(10, 0, 0, 21)), &[], now).await.unwrap();
+    assert_eq!((summary.added, summary.removed, summary.retired, summary.reinstated), (0, 0, 0, 0));
+}
+
+//ONLY ADDRESSES THAT WERE IN THE TABLE COUNT AS NEWLY RESTRICTED, A LEASED ONE IS RETIRED AND A FREE ONE REMOVED
+async fn restricting_takes_addresses_out_of_the_pool(store: &dyn LeaseStore) {
+    let now = start_time();
+    store.reconcile(&generate_ip_pool(FIRST, LAST), &[], now).await.unwrap();
+    let (leased, free, outside) = (Ipv4Addr::new(10, 0, 0, 12), Ipv4Addr::new(10, 0, 0, 13), Ipv4Addr::new(192, 0, 2, 1));
+    assert!(store.lease(leased, CLIENT, &lease_from(now), &ClientName::default()).await.unwrap());
+
+    let summary = store.reconcile(&generate_ip_pool(FIRST, LAST), &[leased, free, outside], now).await.unwrap();
+    assert_eq!((summary.added, summary.removed, summary.retired, summary.restricted), (0, 1, 1, 2));
+    assert_eq!(store.address(free).await.unwrap(), None);
+    assert!(store.address(leased).await.unwrap().unwrap().retired);
+    assert_eq!(store.available().await.unwrap().len(), 8);
+
+    //LIFTING THE RESTRICTION PUTS BOTH BACK, THE LEASED ONE STILL WITH ITS CLIENT
+    let summary = store.reconcile(&generate_ip_pool(FIRST, LAST), &[outside], now).await.unwrap();
+    assert_eq!((summary.added, summary.removed, summary.restricted, summary.reinstated), (1, 0, 0, 1));
+    assert_eq!(state_of(store, leased, now).await, Some(AddressState::Leased));
+    assert_eq!(state_of(store, free, now).await, Some(AddressState::Free));
+}
+
+#[tokio::test]
+async fn memory_store_reconciles_the_pool() {
+    shrinking_keeps_live_leases_and_drops_free_addresses(&MemoryStore::new()).await;
+    growing_adds_addresses_and_reinstates_retired_ones(&MemoryStore::new()).await;
+    restricting_takes_addresses_out_of_the_pool(&MemoryStore::new()).await;
+}
+
+//THE SAME SCENARIOS AGAINST reconcile_ip_addresses_table, THE SQL THAT SHIPS
+#[tokio::test]
+async fn postgres_store_reconciles_the_pool() {
+    let Some(store) = postgres_store("reconcile_shrink").await else { return };
+    shrinking_keeps_live_leases_and_drops_free_addresses(&store).await;
+    growing_adds_addresses_and_reinstates_retired_ones(&postgres_store("reconcile_grow").await.unwrap()).await;
+    restricting_takes_addresses_out_of_the_pool(&postgres_store("reconcile_restricted").await.unwrap()).await;
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use common::*;
+use dhcp_proto::options::*;
+use dhcp_server::server_config::reload::spawn_config_reloader;
+use dhcp_server::store::LeaseStore;
+
+const CLIENT_A: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x0a];
+const CLIENT_B: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x0b];
+
+fn config_file(name: &str, value: &serde_json::Value) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("dhcp-reload-{}-{}.json", std::process::id(), name));
+    std::fs::write(&path, value.to_string()).unwrap();
+    path
+}
+
+//THE TEST CONFIG WITH THE POOL MOVED TO 127.0.0.106-127.0.0.110 AND TWO HOUR LEASES
+fn smaller_pool() -> serde_json::Value {
+    let mut value = test_config_value();
+    value["ip_pool"]["range_start"] = "127.0.0.106".into();
+    value["server"]["lease_time"] = 7200.into();
+    value["restricted_ips"] = serde_json::json!([]);
+    value
+}
+
+#[tokio::test]
+async fn reload_applies_the_new_pool_without_dropping_leases() {
+    let path = config_file("pool", &test_config_value());
+    let mut harness = Harness::start_from_file(&path).await;
+    let client = harness.client(CLIENT_A);
+    let address = client.dora().await.yiaddr;
+    assert_eq!(address, POOL_START);
+    harness.give_back(client);
+
+    std::fs::write(&path, smaller_pool().to_string()).unwrap();
+    let summary = harness.server.reload_config().await.unwrap();
+    assert_eq!(summary.retired, 1);
+
+    //THE LEASE OUTSIDE THE NEW POOL RUNS ON UNTIL IT ENDS
+    let leases = harness.store.active_leases(harness.now()).await.unwrap();
+    assert_eq!((leases[0].ip_address, leases[0].client_id.as_str()), (address, client_id(CLIENT_A).as_str()));
+
+    //NEW CLIENTS ARE SERVED FROM THE NEW POOL WITH THE NEW LEASE TIME
+    let client = harness.client(CLIENT_B);
+    let ack = client.dora().await;
+    assert_eq!(ack.yiaddr, Ipv4Addr::new(127, 0, 0, 106));
+    assert_eq!(seconds_option(&ack, LEASE_TIME), Some(7200));
+    harness.give_back(client);
+}
+
+#[tokio::test]
+async fn an_invalid_config_is_rejected_and_the_old_one_kept() {
+    let path = config_file("invalid", &test_config_value());
+    let mut harness = Harness::start_from_file(&path).await;
+
+    let mut broken = smaller_pool();
+    broken["ip_pool"]["range_end"] = "127.0.0.50".into();
+    std::fs::write(&path, broken.to_string()).unwrap();
+    let error = harness.server.reload_config().await.unwrap_err();
+    assert!(error.contains("ip_pool"), "{}", error);
+    assert_eq!(harness.server.config().server.lease_time, Duration::from_secs(LEASE_SECONDS as u64));
+
+    let client = harness.client(CLIENT_A);
+    let ack = client.dora().await;
+    assert_eq!(ack.yiaddr, POOL_START);
+    assert_eq!(seconds_option(&ack, LEASE_TIME), Some(LEASE_SECONDS as u32));
+    harness.give_back(client);
+}
+
+#[tokio::test]
+async fn sighup_reloads_the_config() {
+    let path = config_file("sighup", &test_config_value());
+    let harness = Harness::start_from_file(&path).await;
+    spawn_config_reloader(harness.server.clone()).unwrap();
+
+    std::fs::write(&path, smaller_pool().to_string()).unwrap();
+    //SAFETY: raise only sends a signal to this process, which handles SIGHUP from here on
+    assert_eq!(unsafe { libc::raise(libc::SIGHUP) }, 0);
+    let server = harness.server.clone();
+    harness.eventually("the new config is in service", || async {
+        server.config().server.lease_time == Duration::from_secs(7200)
+    }).await;
+    assert_eq!(harness.server.config().ip_pool.range_start, Ipv4Addr::new(127, 0, 0, 106));
+}
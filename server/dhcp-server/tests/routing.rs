@@ -0,0 +1,149 @@
+mod common;
+
+use std::net::{Ipv4Addr, SocketAddrV4};
+
+use tokio::net::UdpSocket;
+use tokio::time::{timeout, Duration};
+
+use common::*;
+use dhcp_proto::options::*;
+use dhcp_proto::DHCPMessage;
+use dhcp_server::raw_sender::RawSender;
+
+const WAIT: Duration = Duration::from_secs(5);
+const RELAY: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 2);
+const CLIENT_A: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x0a];
+
+//A MESSAGE FROM CLIENT_A AS THE RELAY AT giaddr PASSES IT ON
+fn relayed(message_type: u8, giaddr: Ipv4Addr, mut options: Vec<u8>) -> DHCPMessage {
+    let mut chaddr = [0u8; 16];
+    chaddr[..6].copy_from_slice(&CLIENT_A);
+    let mut options_buf = vec![MESSAGE_TYPE, 1, message_type];
+    options_buf.append(&mut options);
+    DHCPMessage::new(1, 1, 6, 1, 0x0a0a, 0, 0, Ipv4Addr::UNSPECIFIED, Ipv4Addr::UNSPECIFIED,
+        Ipv4Addr::UNSPECIFIED, giaddr, chaddr, [0; 64], [0; 128], options_buf)
+}
+
+async fn exchange(relay: &UdpSocket, server: SocketAddrV4, message: DHCPMessage) -> DHCPMessage {
+    relay.send_to(&message.to_buffer(), server).await.unwrap();
+    let mut buf = vec![0; 1500];
+    let size = timeout(WAIT, relay.recv(&mut buf)).await.expect("the relay got a reply").unwrap();
+    DHCPMessage::from_buffer(&buf[..size]).unwrap()
+}
+
+#[tokio::test]
+async fn relayed_replies_go_back_to_the_relay_on_the_server_port() {
+    let harness = Harness::start().await;
+    let relay = UdpSocket::bind((RELAY, harness.server_address.port())).await.unwrap();
+
+    let offer = exchange(&relay, harness.server_address, relayed(DHCPDISCOVER, RELAY, Vec::new())).await;
+    assert_eq!(message_type(&offer), Some(DHCPOFFER));
+    assert_eq!(offer.giaddr, RELAY);
+    assert_eq!(offer.yiaddr, POOL_START);
+
+    //A NAK THROUGH A RELAY ASKS IT TO BROADCAST, THE CLIENT MAY HAVE AN ADDRESS THAT DOES NOT WORK
+    let mut options = Vec::new();
+    push_ip(&mut options, REQUESTED_IP, Ipv4Addr::new(127, 0, 0, 200));
+    push_ip(&mut options, SERVER_IDENTIFIER, SERVER_ID);
+    let nak = exchange(&relay, harness.server_address, relayed(DHCPREQUEST, RELAY, options)).await;
+    assert_eq!(message_type(&nak), Some(DHCPNAK));
+    assert_ne!(nak.flags & 0x8000, 0);
+}
+
+//THE PACKET SOCKET BUILDS THE IP AND UDP HEADERS ITSELF, SO THE DATAGRAM IS CAUGHT ON lo AND THEY ARE CHECKED
+#[test]
+fn raw_datagrams_carry_valid_ip_and_udp_headers() {
+    let (raw_sender, capture) = match (RawSender::new(), Capture::loopback()) {
+        (Ok(raw_sender), Ok(capture)) => (raw_sender, capture),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("cannot open a packet socket ({}), skipping raw_datagrams_carry_valid_ip_and_udp_headers", e);
+            return;
+        }
+    };
+    let source = SocketAddrV4::new(Ipv4Addr::LOCALHOST, 6767);
+    let destination = SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 100), 6868);
+    //AN ODD LENGTH ALSO COVERS THE CHECKSUM OF A TRAILING BYTE
+    let payload = b"offer for a client without an address";
+    assert_eq!(raw_sender.send(payload, source, destination, capture.index, &[0; 6]).unwrap(), payload.len());
+
+    let packet = capture.next_containing(payload).expect("the datagram went out on lo");
+    let (ip, udp) = packet.split_at(20);
+    assert_eq!(ip[0], 0x45);
+    assert_eq!(u16::from_be_bytes([ip[2], ip[3]]) as usize, packet.len());
+    assert_eq!(ip[9], 17);
+    assert_eq!(ip[12..16], source.ip().octets());
+    assert_eq!(ip[16..20], destination.ip().octets());
+    assert_eq!(checksum(&[ip]), 0);
+
+    assert_eq!(u16::from_be_bytes([udp[0], udp[1]]), source.port());
+    assert_eq!(u16::from_be_bytes([udp[2], udp[3]]), destination.port());
+    assert_eq!(u16::from_be_bytes([udp[4], udp[5]]) as usize, udp.len());
+    assert_eq!(&udp[8..], payload);
+    let mut pseudo_header = Vec::from(&ip[12..20]);
+    pseudo_header.extend_from_slice(&[0, 17]);
+    pseudo_header.extend_from_slice(&(udp.len() as u16).to_be_bytes());
+    assert_eq!(checksum(&[&pseudo_header, udp]), 0);
+}
+
+//RFC 1071, ZERO OVER DATA THAT CARRIES A CORRECT CHECKSUM
+fn checksum(parts: &[&[u8]]) -> u16 {
+    let bytes: Vec<u8> = parts.concat();
+    let mut sum: u32 = bytes.chunks(2)
+        .map(|word| u16::from_be_bytes([word[0], word.get(1).copied().unwrap_or(0)]) as u32)
+        .sum();
+    while sum >> 16 != 0 {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+//IPV4 PACKETS SEEN ON THE LOOPBACK INTERFACE, THE WAY tcpdump GETS THEM
+struct Capture {
+    socket: std::os::fd::OwnedFd,
+    index: u32,
+}
+
+impl Capture {
+    fn loopback() -> std::io::Result<Capture> {
+        use std::os::fd::{AsRawFd, FromRawFd};
+        let index = nix::net::if_::if_nametoindex("lo")?;
+        let protocol = (libc::ETH_P_IP as u16).to_be();
+        //SAFETY: PLAIN SOCKET CALLS, THE DESCRIPTOR IS OWNED BY THE RETURNED VALUE
+        unsafe {
+            let fd = libc::socket(libc::AF_PACKET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, protocol as libc::c_int);
+            if fd < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            let socket = std::os::fd::OwnedFd::from_raw_fd(fd);
+            let mut address: libc::sockaddr_ll = std::mem::zeroed();
+            address.sll_family = libc::AF_PACKET as u16;
+            address.sll_protocol = protocol;
+            address.sll_ifindex = index as i32;
+            if libc::bind(socket.as_raw_fd(), &address as *const libc::sockaddr_ll as *const libc::sockaddr,
+                          std::mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t) < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            let wait = libc::timeval { tv_sec: WAIT.as_secs() as libc::time_t, tv_usec: 0 };
+            libc::setsockopt(socket.as_raw_fd(), libc::SOL_SOCKET, libc::SO_RCVTIMEO,
+                &wait as *const libc::timeval as *const libc::c_void, std::mem::size_of::<libc::timeval>() as libc::socklen_t);
+            Ok(Capture { socket, index })
+        }
+    }
+
+    //THE FIRST IPV4 PACKET WITH payload IN IT, NONE IF NOTHING TURNS UP IN TIME
+    fn next_containing(&self, payload: &[u8]) -> Option<Vec<u8>> {
+        use std::os::fd::AsRawFd;
+        let mut buf = vec![0u8; 65536];
+        loop {
+            //SAFETY: buf IS VALID FOR ITS WHOLE LENGTH
+            let size = unsafe { libc::recv(self.socket.as_raw_fd(), buf.as_mut_ptr() as *mut libc::c_void, buf.len(), 0) };
+            if size < 0 {
+                return None;
+            }
+            let packet = &buf[..size as usize];
+            if packet.windows(payload.len()).any(|window| window == payload) {
+                return Some(packet.to_vec());
+            }
+        }
+    }
+}
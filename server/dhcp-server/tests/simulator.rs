@@ -0,0 +1,243 @@
+mod common;
+
+use std::net::Ipv4Addr;
+
+use common::*;
+use dhcp_proto::options::*;
+use dhcp_server::store::{LeaseStore, LeaseType, ServerResponse};
+
+const CLIENT_A: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x0a];
+const CLIENT_B: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x0b];
+
+#[tokio::test]
+async fn dora_leases_an_address() {
+    let mut harness = Harness::start().await;
+    let client = harness.client(CLIENT_A);
+
+    let offer = client.discover(None).await;
+    assert_eq!(message_type(&offer), Some(DHCPOFFER));
+    assert!((POOL_START..=POOL_END).contains(&offer.yiaddr));
+    assert_ne!(offer.yiaddr, RESTRICTED);
+    assert_eq!(ip_option(&offer, SERVER_IDENTIFIER), Some(SERVER_ID));
+    //AN OFFER RESERVES NOTHING
+    assert!(harness.store.active_leases(harness.now()).await.unwrap().is_empty());
+
+    let ack = client.request(offer.yiaddr, SERVER_ID).await;
+    assert_eq!(message_type(&ack), Some(DHCPACK));
+    assert_eq!(ack.yiaddr, offer.yiaddr);
+    assert_eq!(seconds_option(&ack, LEASE_TIME), Some(LEASE_SECONDS as u32));
+    assert_eq!(seconds_option(&ack, RENEWAL_TIME), Some(RENEWAL_SECONDS as u32));
+    assert_eq!(seconds_option(&ack, REBINDING_TIME), Some(REBINDING_SECONDS as u32));
+
+    let leases = harness.store.active_leases(harness.now()).await.unwrap();
+    assert_eq!(leases.len(), 1);
+    assert_eq!(leases[0].ip_address, ack.yiaddr);
+    assert_eq!(leases[0].client_id, client_id(CLIENT_A));
+    assert_eq!(leases[0].lease_start, start_time());
+    assert_eq!(leases[0].lease_end, start_time() + chrono::Duration::seconds(LEASE_SECONDS));
+
+    let history = harness.store.history();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].server_response, ServerResponse::Ack);
+    assert_eq!(history[0].lease_type, LeaseType::Initial);
+
+    //ASKING AGAIN GETS THE SAME ADDRESS
+    assert_eq!(client.discover(None).await.yiaddr, ack.yiaddr);
+    harness.give_back(client);
+}
+
+#[tokio::test]
+async fn requested_address_is_offered_when_free() {
+    let mut harness = Harness::start().await;
+    let client = harness.client(CLIENT_A);
+    let wanted = Ipv4Addr::new(127, 0, 0, 107);
+
+    assert_eq!(client.discover(Some(wanted)).await.yiaddr, wanted);
+    //A RESTRICTED ADDRESS IS NEVER HANDED OUT
+    assert_ne!(client.discover(Some(RESTRICTED)).await.yiaddr, RESTRICTED);
+    harness.give_back(client);
+}
+
+#[tokio::test]
+async fn renewing_extends_the_lease() {
+    let mut harness = Harness::start().await;
+    let client = harness.client(CLIENT_A);
+    let address = client.dora().await.yiaddr;
+
+    harness.advance(RENEWAL_SECONDS);
+    let ack = client.renew(address).await;
+    assert_eq!(message_type(&ack), Some(DHCPACK));
+    assert_eq!(ack.yiaddr, address);
+
+    let leases = harness.store.active_leases(harness.now()).await.unwrap();
+    assert_eq!(leases.len(), 1);
+    assert_eq!(leases[0].lease_start, harness.now());
+    assert_eq!(leases[0].lease_end, harness.now() + chrono::Duration::seconds(LEASE_SECONDS));
+    assert_eq!(harness.store.history().last().unwrap().lease_type, LeaseType::Renewing);
+    harness.give_back(client);
+}
+
+#[tokio::test]
+async fn rebinding_keeps_the_address() {
+    let mut harness = Harness::start().await;
+    let client = harness.client(CLIENT_A);
+    let address = client.dora().await.yiaddr;
+
+    harness.advance(REBINDING_SECONDS);
+    let ack = client.rebind(address).await;
+    assert_eq!(message_type(&ack), Some(DHCPACK));
+    assert_eq!(ack.yiaddr, address);
+    let leases = harness.store.active_leases(harness.now()).await.unwrap();
+    assert_eq!(leases[0].lease_end, harness.now() + chrono::Duration::seconds(LEASE_SECONDS));
+    harness.give_back(client);
+}
+
+#[tokio::test]
+async fn expired_lease_goes_back_to_the_pool() {
+    let mut harness = Harness::start().await;
+    let client = harness.client(CLIENT_A);
+    let address = client.dora().await.yiaddr;
+    harness.give_back(client);
+
+    let other = harness.client(CLIENT_B);
+    assert_ne!(other.discover(Some(address)).await.yiaddr, address);
+    harness.advance(LEASE_SECONDS + 1);
+    assert_eq!(other.discover(Some(address)).await.yiaddr, address);
+    harness.give_back(other);
+}
+
+#[tokio::test]
+async fn request_for_a_leased_address_is_naked() {
+    let mut harness = Harness::start().await;
+    let client = harness.client(CLIENT_A);
+    let address = client.dora().await.yiaddr;
+    harness.give_back(client);
+
+    let other = harness.client(CLIENT_B);
+    let nak = other.request(address, SERVER_ID).await;
+    assert_eq!(message_type(&nak), Some(DHCPNAK));
+    assert_eq!(nak.yiaddr, Ipv4Addr::UNSPECIFIED);
+
+    let history = harness.store.history();
+    let last = history.last().unwrap();
+    assert_eq!(last.server_response, ServerResponse::Nak);
+    assert_eq!(last.client_id, client_id(CLIENT_B));
+    //THE FIRST CLIENT STILL HOLDS IT
+    let leases = harness.store.active_leases(harness.now()).await.unwrap();
+    assert_eq!(leases[0].client_id, client_id(CLIENT_A));
+    harness.give_back(other);
+}
+
+#[tokio::test]
+async fn request_outside_the_pool_is_naked() {
+    let mut harness = Harness::start().await;
+    let client = harness.client(CLIENT_A);
+    let nak = client.request(Ipv4Addr::new(127, 0, 0, 200), SERVER_ID).await;
+    assert_eq!(message_type(&nak), Some(DHCPNAK));
+    harness.give_back(client);
+}
+
+#[tokio::test]
+async fn declined_address_is_held_out_of_the_pool() {
+    let mut harness = Harness::start().await;
+    let client = harness.client(CLIENT_A);
+    let address = client.dora().await.yiaddr;
+
+    client.decline(address, SERVER_ID).await;
+    let store = harness.store.clone();
+    harness.eventually("the declined address has no client", || async {
+        store.active_leases(harness.now()).await.unwrap().is_empty()
+    }).await;
+    assert!(!harness.store.is_available(address, &client_id(CLIENT_B)).await.unwrap());
+
+    //THE CLIENT STARTS OVER AND GETS ANOTHER ADDRESS
+    assert_ne!(client.discover(Some(address)).await.yiaddr, address);
+
+    //AFTER THE HOLD THE ADDRESS CAN BE HANDED OUT AGAIN
+    harness.advance(3601);
+    assert_eq!(client.discover(Some(address)).await.yiaddr, address);
+    harness.give_back(client);
+}
+
+#[tokio::test]
+async fn released_address_is_free_again() {
+    let mut harness = Harness::start().await;
+    let client = harness.client(CLIENT_A);
+    let address = client.dora().await.yiaddr;
+
+    client.release(address, SERVER_ID).await;
+    let store = harness.store.clone();
+    harness.eventually("the released address is available", || async {
+        store.is_available(address, &client_id(CLIENT_B)).await.unwrap()
+    }).await;
+    assert!(harness.store.active_leases(harness.now()).await.unwrap().is_empty());
+    harness.give_back(client);
+}
+
+#[tokio::test]
+async fn release_for_another_server_is_ignored() {
+    let mut harness = Harness::start().await;
+    let client = harness.client(CLIENT_A);
+    let address = client.dora().await.yiaddr;
+
+    client.release(address, Ipv4Addr::new(127, 0, 0, 2)).await;
+    //A DISCOVER IS HANDLED AFTER THE RELEASE, SO ONCE IT IS ANSWERED THE RELEASE WAS SEEN
+    assert_eq!(client.discover(None).await.yiaddr, address);
+    assert_eq!(harness.store.active_leases(harness.now()).await.unwrap().len(), 1);
+    harness.give_back(client);
+}
+
+//ANOTHER CLIENT CANNOT END OR BLOCK A LEASE IT DOES NOT HOLD
+#[tokio::test]
+async fn release_and_decline_from_another_client_are_ignored() {
+    let mut harness = Harness::start().await;
+    let client = harness.client(CLIENT_A);
+    let address = client.dora().await.yiaddr;
+    harness.give_back(client);
+
+    let other = harness.client(CLIENT_B);
+    other.release(address, SERVER_ID).await;
+    other.decline(address, SERVER_ID).await;
+    //THE DISCOVER IS HANDLED AFTER BOTH, SO ONCE IT IS ANSWERED THEY WERE SEEN
+    assert_ne!(other.discover(Some(address)).await.yiaddr, address);
+    harness.give_back(other);
+    let leases = harness.store.active_leases(harness.now()).await.unwrap();
+    assert_eq!(leases.len(), 1);
+    assert_eq!((leases[0].ip_address, leases[0].client_id.as_str()), (address, client_id(CLIENT_A).as_str()));
+    let client = harness.client(CLIENT_A);
+    assert_eq!(message_type(&client.renew(address).await), Some(DHCPACK));
+    harness.give_back(client);
+}
+
+#[tokio::test]
+async fn inform_returns_requested_parameters_only() {
+    let mut harness = Harness::start().await;
+    let client = harness.client(CLIENT_A);
+    let address = Ipv4Addr::new(127, 0, 0, 200);
+
+    let ack = client.inform(address, &[SUBNET_MASK, ROUTER, DNS_SERVER]).await;
+    assert_eq!(message_type(&ack), Some(DHCPACK));
+    assert_eq!(ack.ciaddr, address);
+    assert_eq!(ack.yiaddr, Ipv4Addr::UNSPECIFIED);
+    assert_eq!(ip_option(&ack, SUBNET_MASK), Some(Ipv4Addr::new(255, 0, 0, 0)));
+    assert_eq!(ip_option(&ack, ROUTER), Some(Ipv4Addr::new(127, 0, 0, 1)));
+    assert_eq!(ip_option(&ack, DNS_SERVER), Some(Ipv4Addr::new(127, 0, 0, 53)));
+    //NO LEASE PARAMETERS FOR A CLIENT THAT CONFIGURED ITSELF
+    assert!(!ack.options_map.contains_key(&LEASE_TIME));
+    assert!(harness.store.active_leases(harness.now()).await.unwrap().is_empty());
+    harness.give_back(client);
+}
+
+//A NAK IS FAR SHORTER THAN THE 300 BYTES OLD BOOTP RELAYS AND CLIENTS EXPECT
+#[tokio::test]
+async fn short_replies_are_padded_to_the_bootp_minimum() {
+    let mut harness = Harness::start().await;
+    let client = harness.client(CLIENT_A);
+
+    let nak = client.request(Ipv4Addr::new(127, 0, 0, 200), SERVER_ID).await;
+    assert_eq!(message_type(&nak), Some(DHCPNAK));
+    let buf = nak.to_buffer();
+    assert_eq!(buf.len(), 300);
+    let end = buf[240..].iter().position(|&byte| byte == END).unwrap() + 240;
+    assert!(buf[end + 1..].iter().all(|&byte| byte == 0));
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use common::*;
+use dhcp_server::store::{ClientName, LeaseStore, MemoryStore};
+use dhcp_server::utility::lease::LeaseTimes;
+
+const ADDRESS: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 10);
+const HOLDER: &str = "02000000000a";
+const OTHER: &str = "02000000000b";
+
+//A STORE WITH ADDRESS LEASED TO HOLDER UNDER THE NAME printer
+async fn leased(store: &dyn LeaseStore) -> LeaseTimes {
+    store.reconcile(&[ADDRESS], &[], start_time()).await.unwrap();
+    let times = LeaseTimes::new(start_time(), Duration::from_secs(3600), Duration::from_secs(1800), Duration::from_secs(3150));
+    let name = ClientName { hostname: Some("printer".into()), ..ClientName::default() };
+    assert!(store.lease(ADDRESS, HOLDER, &times, &name).await.unwrap());
+    times
+}
+
+//ONLY THE HOLDER, OR A CALLER NAMING NO CLIENT, ENDS A LEASE
+async fn release_is_scoped_to_the_holder(store: &dyn LeaseStore) {
+    leased(store).await;
+    assert_eq!(store.release(ADDRESS, Some(OTHER)).await.unwrap(), None);
+    assert_eq!(store.active_leases(start_time()).await.unwrap().len(), 1);
+
+    let released = store.release(ADDRESS, Some(HOLDER)).await.unwrap().expect("the holder released it");
+    assert_eq!((released.client_id.as_str(), released.name.hostname.as_deref()), (HOLDER, Some("printer")));
+    assert!(store.active_leases(start_time()).await.unwrap().is_empty());
+    assert_eq!(store.release(ADDRESS, Some(HOLDER)).await.unwrap(), None);
+
+    leased(store).await;
+    assert_eq!(store.release(ADDRESS, None).await.unwrap().map(|lease| lease.client_id), Some(HOLDER.to_string()));
+}
+
+async fn decline_is_scoped_to_the_holder(store: &dyn LeaseStore) {
+    let times = leased(store).await;
+    let hold_until = times.end + chrono::Duration::hours(1);
+    assert_eq!(store.decline(ADDRESS, Some(OTHER), start_time(), hold_until).await.unwrap(), None);
+    assert!(!store.is_available(ADDRESS, OTHER).await.unwrap());
+    assert_eq!(store.active_leases(start_time()).await.unwrap()[0].lease_end, times.end);
+
+    let declined = store.decline(ADDRESS, Some(HOLDER), start_time(), hold_until).await.unwrap();
+    assert_eq!(declined.map(|lease| lease.client_id), Some(HOLDER.to_string()));
+    assert!(store.active_leases(start_time()).await.unwrap().is_empty());
+    assert!(!store.is_available(ADDRESS, HOLDER).await.unwrap());
+}
+
+async fn revoke_returns_the_lease_taken(store: &dyn LeaseStore) {
+    let times = leased(store).await;
+    let revoked = store.revoke(ADDRESS, start_time()).await.unwrap().expect("a lease was revoked");
+    assert_eq!((revoked.client_id.as_str(), revoked.lease_end), (HOLDER, times.end));
+    assert_eq!(store.revoke(ADDRESS, start_time()).await.unwrap(), None);
+}
+
+//A RENEWAL WITHOUT A NAME KEEPS THE ONE THE LEASE HAS
+async fn renew_returns_the_name_it_replaced(store: &dyn LeaseStore) {
+    let times = leased(store).await;
+    assert_eq!(store.renew(ADDRESS, OTHER, &times, None).await.unwrap(), None);
+
+    let desk = ClientName { hostname: Some("desk".into()), ..ClientName::default() };
+    let (renewed, previous) = store.renew(ADDRESS, HOLDER, &times, Some(&desk)).await.unwrap().expect("the holder renewed it");
+    assert_eq!((renewed.name.hostname.as_deref(), previous.hostname.as_deref()), (Some("desk"), Some("printer")));
+    let (renewed, previous) = store.renew(ADDRESS, HOLDER, &times, None).await.unwrap().expect("the holder renewed it");
+    assert_eq!((renewed.name, previous), (desk.clone(), desk));
+}
+
+#[tokio::test]
+async fn memory_store_scopes_client_changes() {
+    release_is_scoped_to_the_holder(&MemoryStore::new()).await;
+    decline_is_scoped_to_the_holder(&MemoryStore::new()).await;
+    revoke_returns_the_lease_taken(&MemoryStore::new()).await;
+    renew_returns_the_name_it_replaced(&MemoryStore::new()).await;
+}
+
+#[tokio::test]
+async fn postgres_store_scopes_client_changes() {
+    let Some(store) = postgres_store("store_release").await else { return };
+    release_is_scoped_to_the_holder(&store).await;
+    decline_is_scoped_to_the_holder(&postgres_store("store_decline").await.unwrap()).await;
+    revoke_returns_the_lease_taken(&postgres_store("store_revoke").await.unwrap()).await;
+    renew_returns_the_name_it_replaced(&postgres_store("store_renew").await.unwrap()).await;
+}
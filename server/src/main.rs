@@ -1,61 +1,287 @@
-use tokio_postgres::NoTls;
+use tokio_postgres::{Client, NoTls};
+use clap::{Args, Parser, Subcommand};
 use dotenvy::dotenv;
-use std::env;
 use std::error::Error;
+use std::net::{Ipv4Addr, SocketAddr};
 use std::sync::Arc;
-use tokio::net::{UdpSocket};
 
-mod utility;
-mod server_config;
-mod set_up;
+use dhcp_server::set_up::create_db_tables::create_db;
+use dhcp_server::set_up::migrations::run_migrations;
+use dhcp_server::server_config::{load_config, ConfigError, LoggingConfig, DEFAULT_CONFIG_FILE};
+use dhcp_server::server_config::reload::spawn_config_reloader;
+use dhcp_server::Server;
+use dhcp_server::listener::Listener;
+use dhcp_server::raw_sender::RawSender;
+use dhcp_server::clock::{Clock, SystemClock};
+use dhcp_server::store::{LeaseStore, PostgresStore};
+use dhcp_server::ha::PostgresLock;
+use dhcp_server::api::{self, ApiState};
+use dhcp_server::metrics;
+use dhcp_server::logging::{self, LogFormat};
+use tracing::{error, info};
 
-use crate::set_up::create_db_tables::create_db;
-use crate::server_config::{load_config};
+#[derive(Parser)]
+#[command(name = "server", version, about = "DHCP server backed by PostgreSQL")]
+struct Cli {
+    #[command(flatten)]
+    options: GlobalOptions,
 
-mod logger;
-mod server;
-use server::Server;
+    //WITHOUT A SUBCOMMAND THE SERVER IS STARTED
+    #[command(subcommand)]
+    command: Option<Command>,
 
-const DHCP_SERVER_PORT: u16 = 67;
+    //KEPT FOR SCRIPTS WRITTEN BEFORE THE migrate SUBCOMMAND EXISTED
+    #[arg(long, hide = true)]
+    migrate_only: bool,
+}
+
+#[derive(Args)]
+struct GlobalOptions {
+    /// Path to the JSON configuration file
+    #[arg(long, short, global = true, env = "DHCP_CONFIG", default_value = DEFAULT_CONFIG_FILE)]
+    config: String,
+
+    /// PostgreSQL connection string
+    #[arg(long, global = true, env = "POSTGRES_URI", hide_env_values = true)]
+    db_uri: Option<String>,
+
+    /// Address to listen on for DHCP requests
+    #[arg(long, global = true, env = "DHCP_LISTEN_ADDRESS", default_value_t = Ipv4Addr::UNSPECIFIED)]
+    listen_address: Ipv4Addr,
+
+    /// UDP port the server listens on
+    #[arg(long, global = true, env = "DHCP_SERVER_PORT", default_value_t = 67)]
+    server_port: u16,
+
+    /// UDP port replies are sent to
+    #[arg(long, global = true, env = "DHCP_CLIENT_PORT", default_value_t = 68)]
+    client_port: u16,
+
+    /// Only serve requests arriving on this network interface (repeatable)
+    #[arg(long, global = true, env = "DHCP_INTERFACE", value_delimiter = ',')]
+    interface: Vec<String>,
+
+    /// Unicast replies to clients without an address straight to their hardware address
+    /// instead of broadcasting them (needs CAP_NET_RAW)
+    #[arg(long, global = true, env = "DHCP_RAW_UNICAST")]
+    raw_unicast: bool,
+
+    /// Serve the HTTP management API on this address, e.g. 127.0.0.1:8067
+    #[arg(long, global = true, env = "DHCP_API_LISTEN")]
+    api_listen: Option<SocketAddr>,
+
+    /// Bearer token the management API requires, needed with --api-listen
+    #[arg(long, global = true, env = "DHCP_API_TOKEN", hide_env_values = true)]
+    api_token: Option<String>,
+
+    /// Log output format, text or json (levels are set with RUST_LOG)
+    #[arg(long, global = true, env = "DHCP_LOG_FORMAT", default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// Serve Prometheus metrics at /metrics on this address, e.g. 0.0.0.0:9167
+    #[arg(long, global = true, env = "DHCP_METRICS_LISTEN")]
+    metrics_listen: Option<SocketAddr>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the DHCP server (default)
+    Serve,
+    /// Validate a configuration file and print every problem found
+    CheckConfig {
+        /// Configuration file to check, defaults to --config
+        path: Option<String>,
+    },
+    /// Apply pending database migrations and exit
+    Migrate,
+    /// Print the addresses currently leased
+    Leases,
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     //SET UP ENV
     dotenv().ok();
-    let db_url = env::var("POSTGRES_URI").expect("Failed to find POSTGRES_URL");
-    let (client, connection) = tokio_postgres::connect(&db_url, NoTls).await?;
-    
-    println!("Detected local timezone: {}", chrono::Local::now());
-    
-    //SET UP DATABASE CONNECTION
-    tokio::spawn(async move{
-        if let Err(e) = connection.await {
-            eprintln!("Connection error: {}", e);
+    let cli = Cli::parse();
+    let options = cli.options;
+
+    match cli.command {
+        Some(Command::CheckConfig { path }) => {
+            std::process::exit(check_config(path.as_deref().unwrap_or(&options.config)));
         }
-    });
-    println!("Connected to database");
+        Some(Command::Migrate) => {
+            let _log_guard = logging::init(options.log_format, &LoggingConfig::default())?;
+            migrate(&options).await
+        }
+        Some(Command::Leases) => print_leases(&options).await,
+        Some(Command::Serve) | None if cli.migrate_only => {
+            let _log_guard = logging::init(options.log_format, &LoggingConfig::default())?;
+            migrate(&options).await
+        }
+        Some(Command::Serve) | None => serve(&options).await,
+    }
+}
+
+async fn serve(options: &GlobalOptions) -> Result<(), Box<dyn Error>> {
+    //LOAD CONFIG
+    //A BROKEN CONFIG EXITS NON-ZERO SO SERVICE MANAGERS DO NOT TAKE IT FOR A CLEAN STOP
+    let config = match load_config(&options.config) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    //THE LOG SINKS ARE NAMED IN THE CONFIG, SO LOGGING STARTS ONCE IT IS LOADED
+    let _log_guard = logging::init(options.log_format, &config.logging)?;
+
+    let api_token = match (&options.api_listen, &options.api_token) {
+        (Some(_), token) if token.as_deref().is_none_or(str::is_empty) => return Err("The management API needs a token, set --api-token or DHCP_API_TOKEN".into()),
+        (_, token) => token.clone(),
+    };
+
+    let db_url = db_url(options)?;
+    let mut client = connect_db(&db_url).await?;
+    
+    info!(local_time = %chrono::Local::now(), "Connected to database");
 
     //CREATE DATABASE TABLES
-    if let Err(e) = create_db(&client).await {
-        eprintln!("Error creating db: {}", e);
-        return Ok(());
+    if let Err(e) = create_db(&mut client, &config).await {
+        error!(error = %e, "Error creating db");
+        std::process::exit(1);
     }
 
-    println!("Database created successfully");
-    println!("Starting DHCP server");
+    info!("Database created successfully");
 
-    //OPEN SOCKET
-    //ON WINDOWS USE THE INTERFACE IP ADDRESS
-    let dhcp_socket = UdpSocket::bind(("0.0.0.0", DHCP_SERVER_PORT)).await?;
-    dhcp_socket.set_broadcast(true)?;
-    println!("DHCP server listening on port {}", DHCP_SERVER_PORT);
-    
-    //LOAD CONFIG
-    let config = load_config("app/server-config.json").expect("Failed to load configuration");
+    //OPEN SOCKETS, ONE PER INTERFACE OR A SINGLE ONE FOR ALL INTERFACES
+    let mut listeners = Vec::new();
+    if options.interface.is_empty() {
+        listeners.push(Listener::bind(options.listen_address, options.server_port, None)?);
+        info!(address = %options.listen_address, port = options.server_port, "DHCP server listening");
+    }
+    for interface in &options.interface {
+        listeners.push(Listener::bind(options.listen_address, options.server_port, Some(interface))?);
+        info!(address = %options.listen_address, port = options.server_port, interface = %interface, "DHCP server listening");
+    }
+
+    let raw_sender = if options.raw_unicast {
+        let raw_sender = RawSender::new().map_err(|e| format!("Failed to open raw socket for unicast replies: {}", e))?;
+        info!("Unicasting replies to clients without an address");
+        Some(raw_sender)
+    } else {
+        None
+    };
 
     //START SERVER
-    let server = Server::new(config, dhcp_socket, client).await;
-    Arc::new(server).start().await;
+    let server_ha = config.ha.clone();
+    let mut builder = Server::builder(config)
+        .config_path(&options.config)
+        .listeners(listeners)
+        //RECONCILIATION ON RELOAD GETS ITS OWN DATABASE CONNECTION
+        .store(Arc::new(PostgresStore::new(client, connect_db(&db_url).await?)))
+        .client_port(options.client_port);
+    if let Some(raw_sender) = raw_sender {
+        builder = builder.raw_sender(raw_sender);
+    }
+    //THE LEADER LOCK IS HELD ON A CONNECTION OF ITS OWN, IT IS LET GO BY CLOSING THAT CONNECTION
+    if let Some(ha) = &server_ha {
+        builder = builder.leader_lock(Arc::new(PostgresLock::new(db_url.clone(), ha.lock_id)));
+        info!(node = %ha.node, "Standing by until elected leader");
+    }
+    let server = Arc::new(builder.build().await?);
+
+    //RELOAD CONFIG ON SIGHUP
+    let reload_handle = spawn_config_reloader(Arc::clone(&server))?;
+
+    //MANAGEMENT API ON ITS OWN PORT
+    if let (Some(api_listen), Some(api_token)) = (options.api_listen, api_token) {
+        let api_listener = tokio::net::TcpListener::bind(api_listen).await
+            .map_err(|e| format!("Failed to bind the management API to {}: {}", api_listen, e))?;
+        info!(address = %api_listen, "Management API listening");
+        let state = ApiState::new(Arc::clone(&server), api_token).reload_handle(reload_handle);
+        tokio::spawn(async move {
+            if let Err(e) = api::serve(api_listener, state).await {
+                error!(error = %e, "Management API stopped");
+            }
+        });
+    }
+
+    //PROMETHEUS METRICS, UNAUTHENTICATED LIKE MOST EXPORTERS
+    if let Some(metrics_listen) = options.metrics_listen {
+        let metrics_listener = tokio::net::TcpListener::bind(metrics_listen).await
+            .map_err(|e| format!("Failed to bind the metrics endpoint to {}: {}", metrics_listen, e))?;
+        info!(address = %metrics_listen, "Metrics served at /metrics");
+        let server = Arc::clone(&server);
+        tokio::spawn(async move {
+            if let Err(e) = metrics::serve(metrics_listener, server).await {
+                error!(error = %e, "Metrics endpoint stopped");
+            }
+        });
+    }
+
+    server.start().await;
+
+    Ok(())
+}
+
+//ONLY BRING THE SCHEMA UP TO DATE
+async fn migrate(options: &GlobalOptions) -> Result<(), Box<dyn Error>> {
+    let mut client = connect_db(&db_url(options)?).await?;
+    match run_migrations(&mut client).await {
+        Ok(applied) => info!(applied, "Migrations applied"),
+        Err(e) => {
+            error!(error = %e, "Error migrating db");
+            std::process::exit(1);
+        }
+    }
+    Ok(())
+}
+
+async fn print_leases(options: &GlobalOptions) -> Result<(), Box<dyn Error>> {
+    let db_url = db_url(options)?;
+    let store = PostgresStore::new(connect_db(&db_url).await?, connect_db(&db_url).await?);
+    let leases = store.active_leases(SystemClock.now()).await.map_err(|e| e.to_string())?;
+    println!("{:<16} {:<34} {:<26} {:<33} {:<33}", "IP ADDRESS", "CLIENT ID", "HOSTNAME", "LEASE START", "LEASE END");
+    for lease in &leases {
+        println!("{:<16} {:<34} {:<26} {:<33} {:<33}", lease.ip_address.to_string(), lease.client_id,
+            lease.name.hostname.as_deref().unwrap_or("-"), lease.lease_start.to_rfc3339(), lease.lease_end.to_rfc3339());
+    }
+    println!("{} active leases", leases.len());
+    Ok(())
+}
+
+fn db_url(options: &GlobalOptions) -> Result<String, Box<dyn Error>> {
+    options.db_uri.clone().ok_or_else(|| "No database given, set --db-uri or POSTGRES_URI".into())
+}
+
+//CONNECT TO DATABASE AND DRIVE THE CONNECTION IN THE BACKGROUND
+async fn connect_db(db_url: &str) -> Result<Client, tokio_postgres::Error> {
+    let (client, connection) = tokio_postgres::connect(db_url, NoTls).await?;
+    tokio::spawn(async move{
+        if let Err(e) = connection.await {
+            error!(error = %e, "Database connection error");
+        }
+    });
+    Ok(client)
+}
 
-    return Ok(());
-}
\ No newline at end of file
+//PRINT EVERY PROBLEM IN THE CONFIG FILE, RETURNS THE PROCESS EXIT CODE
+fn check_config(path: &str) -> i32 {
+    match load_config(path) {
+        Ok(_) => {
+            println!("{}: configuration is valid", path);
+            0
+        }
+        Err(ConfigError::Invalid(problems)) => {
+            for problem in &problems {
+                println!("{}: {}", path, problem);
+            }
+            println!("{}: {} problem(s) found", path, problems.len());
+            1
+        }
+        Err(e) => {
+            println!("{}: {}", path, e);
+            1
+        }
+    }
+}
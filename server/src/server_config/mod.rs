@@ -1,5 +0,0 @@
-pub mod ip_pool;
-pub mod server_config;
-
-pub use server_config::{Config, load_config};
-pub use ip_pool::generate_ip_pool;
\ No newline at end of file
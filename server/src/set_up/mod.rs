@@ -1,2 +0,0 @@
-pub mod config_hash;
-pub mod create_db_tables;
\ No newline at end of file
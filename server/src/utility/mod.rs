@@ -1,2 +0,0 @@
-pub mod types;
-pub mod options;
\ No newline at end of file
@@ -0,0 +1,116 @@
+//RUNS THE server BINARY AND CHECKS WHAT SERVICE MANAGERS SEE: THE EXIT CODE AND THE OUTPUT
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+//THE EXAMPLE CONFIG AT THE ROOT OF THE REPOSITORY
+fn example_config() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../server-config.json")
+}
+
+fn broken_config(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("dhcp-cli-{}-{}.json", std::process::id(), name));
+    let config = std::fs::read_to_string(example_config()).unwrap()
+        .replace("\"lease_time\": 60", "\"lease_time\": \"a minute\"");
+    std::fs::write(&path, config).unwrap();
+    path
+}
+
+fn server(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_server"))
+        .args(args)
+        //THE LOG FILE NAMED IN THE CONFIG IS CREATED IN THE WORKING DIRECTORY
+        .current_dir(std::env::temp_dir())
+        .env_remove("POSTGRES_URI")
+        .env_remove("DHCP_CONFIG")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn check_config_accepts_the_example_config() {
+    let output = server(&["check-config", example_config().to_str().unwrap()]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with("configuration is valid\n"));
+}
+
+#[test]
+fn check_config_lists_the_problems_and_fails() {
+    let path = broken_config("check");
+    let output = server(&["check-config", path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("server.lease_time: expected a number, found \"a minute\""), "{}", stdout);
+}
+
+#[test]
+fn serve_with_an_invalid_config_fails() {
+    let path = broken_config("serve");
+    let output = server(&["--config", path.to_str().unwrap(), "serve"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("server.lease_time"));
+}
+
+#[test]
+fn serve_without_a_database_fails() {
+    let output = server(&["--config", example_config().to_str().unwrap(), "serve"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No database given"));
+}
+
+//A DATABASE LEFT BY A NEWER SERVER CANNOT BE SET UP, WHICH MUST NOT LOOK LIKE A CLEAN STOP
+#[tokio::test]
+async fn serve_fails_when_the_database_cannot_be_set_up() {
+    let Some((uri, client)) = test_database("cli_setup").await else { return };
+    client.batch_execute("CREATE TABLE schema_version (version INTEGER PRIMARY KEY, description TEXT NOT NULL);
+                          INSERT INTO schema_version VALUES (1000, 'from a newer server')").await.unwrap();
+
+    let output = server(&["--config", example_config().to_str().unwrap(), "--db-uri", &uri, "serve"]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[tokio::test]
+async fn leases_lists_the_active_leases() {
+    let Some((uri, client)) = test_database("cli_leases").await else { return };
+    assert!(server(&["--db-uri", &uri, "migrate"]).status.success());
+    client.batch_execute("INSERT INTO ip_addresses (ip_address, allocated, client_id, lease_start, lease_end, hostname)
+                          VALUES ('192.168.10.100', true, '02000000000a', NOW(), NOW() + INTERVAL '1 hour', 'printer'),
+                                 ('192.168.10.101', true, '02000000000b', NOW() - INTERVAL '2 hours', NOW() - INTERVAL '1 hour', NULL),
+                                 ('192.168.10.102', false, NULL, NULL, NULL, NULL)").await.unwrap();
+
+    let output = server(&["--db-uri", &uri, "leases"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 3, "{}", stdout);
+    assert!(lines[1].starts_with("192.168.10.100"), "{}", stdout);
+    assert!(lines[1].contains("02000000000a") && lines[1].contains("printer"), "{}", stdout);
+    assert_eq!(lines[2], "1 active leases");
+}
+
+//A FRESH DATABASE ON THE SERVER AT TEST_POSTGRES_URI, AS A CONNECTION STRING AND A CLIENT
+//NONE WHEN THAT IS NOT SET, SO THE SUITE STILL RUNS WITHOUT POSTGRESQL
+async fn test_database(name: &str) -> Option<(String, tokio_postgres::Client)> {
+    let Ok(uri) = std::env::var("TEST_POSTGRES_URI") else {
+        eprintln!("TEST_POSTGRES_URI is not set, skipping {}", name);
+        return None;
+    };
+    let database = format!("dhcp_test_{}_{}", name, std::process::id());
+    let (admin, connection) = tokio_postgres::connect(&uri, tokio_postgres::NoTls).await.unwrap();
+    tokio::spawn(connection);
+    admin.batch_execute(&format!("DROP DATABASE IF EXISTS {}", database)).await.unwrap();
+    admin.batch_execute(&format!("CREATE DATABASE {}", database)).await.unwrap();
+    let uri = with_database(&uri, &database);
+    let (client, connection) = tokio_postgres::connect(&uri, tokio_postgres::NoTls).await.unwrap();
+    tokio::spawn(connection);
+    Some((uri, client))
+}
+
+//THE CONNECTION STRING uri POINTING AT ANOTHER DATABASE, IN EITHER FORM tokio_postgres ACCEPTS
+fn with_database(uri: &str, database: &str) -> String {
+    let Some(scheme_end) = uri.find("://") else {
+        return format!("{} dbname={}", uri, database);
+    };
+    let authority_end = uri[scheme_end + 3..].find(['/', '?']).map_or(uri.len(), |end| scheme_end + 3 + end);
+    let query = uri[authority_end..].find('?').map_or("", |start| &uri[authority_end + start..]);
+    format!("{}/{}{}", &uri[..authority_end], database, query)
+}